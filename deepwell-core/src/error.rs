@@ -19,7 +19,7 @@
  */
 
 use crate::roles::Role;
-use crate::types::UserId;
+use crate::types::{UserId, WikiId};
 use diesel::result::{ConnectionError, Error as DieselError};
 use std::fmt::{self, Display};
 use std::io;
@@ -62,12 +62,21 @@ pub enum Error {
     #[error("invalid username or password")]
     AuthenticationFailed,
 
+    #[error("too many failed login attempts, try again later")]
+    TooManyLoginAttempts,
+
     #[error("not logged in, expired session, or invalid token")]
     InvalidSession,
 
+    #[error("session has expired due to inactivity")]
+    SessionExpired,
+
     #[error("invalid password: {0}")]
     NewPasswordInvalid(&'static str),
 
+    #[error("password is insecure: {0}")]
+    PasswordInsecure(String),
+
     #[error("invalid verification token")]
     InvalidVerificationToken,
 
@@ -77,6 +86,15 @@ pub enum Error {
     #[error("the given wiki was not found")]
     WikiNotFound,
 
+    #[error("a wiki with the given slug already exists")]
+    WikiExists,
+
+    #[error("the given wiki is inactive")]
+    WikiInactive,
+
+    #[error("the revision store for wiki ID {0} is unavailable")]
+    WikiStoreUnavailable(WikiId),
+
     #[error("the given page was not found")]
     PageNotFound,
 
@@ -103,6 +121,36 @@ pub enum Error {
 
     #[error("the given revision does not correspond to the specified page")]
     RevisionPageMismatch,
+
+    #[error("the given revision is not part of the page's current history")]
+    RevisionNotInHistory,
+
+    #[error("the current state does not match what was expected")]
+    Conflict,
+
+    #[error("invalid value for profile field: {0}")]
+    InvalidProfileField(&'static str),
+
+    #[error("page content cannot be empty")]
+    EmptyPage,
+
+    #[error("page content was rejected: {0}")]
+    ContentRejected(String),
+
+    #[error("page content was too large, {0} > {1} bytes")]
+    ContentTooLarge(usize, usize),
+
+    #[error("renaming pages into a different category is not permitted")]
+    CategoryChangeForbidden,
+
+    #[error("the slug '{0}' is reserved")]
+    SlugReserved(String),
+
+    #[error("the slug '{0}' is not valid")]
+    InvalidSlug(String),
+
+    #[error("unable to apply patch: {0}")]
+    PatchFailed(String),
 }
 
 impl Error {
@@ -120,11 +168,17 @@ impl Error {
             ServiceTransport(_) => "service-transport",
             RequestTooLarge(_, _) => "request-too-large",
             AuthenticationFailed => "authentication-failed",
+            TooManyLoginAttempts => "too-many-login-attempts",
             InvalidSession => "invalid-session",
+            SessionExpired => "session-expired",
             NewPasswordInvalid(_) => "invalid-password",
+            PasswordInsecure(_) => "password-insecure",
             InvalidVerificationToken => "invalid-verification-token",
             InsufficientPermissions(_, _) => "insufficient-permissions",
             WikiNotFound => "wiki-not-found",
+            WikiExists => "wiki-exists",
+            WikiInactive => "wiki-inactive",
+            WikiStoreUnavailable(_) => "wiki-store-unavailable",
             PageNotFound => "page-not-found",
             PageExists => "page-exists",
             PageLocked(_) => "page-locked",
@@ -134,6 +188,16 @@ impl Error {
             UserEmailExists => "user-email-exists",
             RevisionNotFound => "revision-not-found",
             RevisionPageMismatch => "revision-page-mismatch",
+            RevisionNotInHistory => "revision-not-in-history",
+            Conflict => "conflict",
+            InvalidProfileField(_) => "invalid-profile-field",
+            EmptyPage => "empty-page",
+            ContentRejected(_) => "content-rejected",
+            ContentTooLarge(_, _) => "content-too-large",
+            CategoryChangeForbidden => "category-change-forbidden",
+            SlugReserved(_) => "slug-reserved",
+            InvalidSlug(_) => "invalid-slug",
+            PatchFailed(_) => "patch-failed",
         }
     }
 
@@ -42,6 +42,7 @@ extern crate serde;
 
 #[macro_use]
 extern crate thiserror;
+extern crate wikidot_normalize;
 
 #[macro_use]
 mod macros;
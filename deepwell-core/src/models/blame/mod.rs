@@ -22,3 +22,4 @@ mod object;
 mod parse;
 
 pub use self::object::*;
+pub use self::parse::BlameParser;
@@ -89,13 +89,46 @@ impl Into<BlameAuthor> for Author {
     }
 }
 
-// Blame implementation
+// Incremental parser
+
+const BLAME_ERROR: Error = Error::StaticMsg("unexpected or mismatched input line in blame data");
+
+/// Parses `git blame --porcelain` output one line at a time,
+/// producing each group as soon as it is complete.
+#[derive(Debug)]
+pub struct BlameParser {
+    // FSM state
+    state: State,
+    new_group: bool,
+
+    // Temporary state to build next item
+    author: Author,
+    committer: Author,
+    summary: String,
+    previous_commit: Option<GitHash>,
+    commit_info: Option<(GitHash, u32, u32)>,
+
+    // In-progress result data
+    blame_lines: Vec<BlameLine>,
+}
 
-impl Blame {
-    pub fn from_porcelain(raw_bytes: &[u8]) -> Result<Self> {
-        const BLAME_ERROR: Error =
-            Error::StaticMsg("unexpected or mismatched input line in blame data");
+impl BlameParser {
+    pub fn new() -> Self {
+        BlameParser {
+            state: State::Commit,
+            new_group: false,
+            author: Author::default(),
+            committer: Author::default(),
+            summary: String::new(),
+            previous_commit: None,
+            commit_info: None,
+            blame_lines: Vec::new(),
+        }
+    }
 
+    /// Consumes the next line of porcelain output, without its trailing newline.
+    /// Returns a group if this line completed one.
+    pub fn push_line(&mut self, line: &[u8]) -> Result<Option<BlameGroup>> {
         macro_rules! utf {
             ($captures:expr, $name:expr) => {
                 str::from_utf8(&$captures[$name]).unwrap()
@@ -109,186 +142,211 @@ impl Blame {
             }};
         }
 
-        debug!("Parsing git blame porcelain ({} bytes)", raw_bytes.len());
+        if line.is_empty() {
+            trace!("Skipping empty line");
+            return Ok(None);
+        }
 
-        // FSM state
-        let lines = raw_bytes.split(|&b| b == b'\n');
-        let mut state = State::Commit;
-        let mut new_group = false;
+        if line.starts_with(b"\t") {
+            trace!("Line starts with tab");
+            self.state = State::Content;
+        }
 
-        // Temporary state to build next item
-        let mut author = Author::default();
-        let mut committer = Author::default();
-        let mut summary = String::new();
-        let mut previous_commit = None;
-        let mut commit_info = None;
+        trace!("State: {:?}", self.state);
+
+        match self.state {
+            State::Commit => {
+                let captures = match GIT_HASH_REGEX.captures(line) {
+                    Some(captures) => captures,
+                    None => return Err(BLAME_ERROR),
+                };
+
+                // Unwraps are safe because the values are regex-verified
+                let old_lineno = utf!(captures, "old_line").parse().unwrap();
+                let new_lineno = utf!(captures, "new_line").parse().unwrap();
+                let commit = {
+                    let sha1 = utf!(captures, "sha1");
+
+                    GitHash::from_checked(sha1)
+                };
+
+                trace!(
+                    "commit: {}, old_lineno: {}, new_lineno: {}",
+                    commit,
+                    old_lineno,
+                    new_lineno,
+                );
+                self.commit_info = Some((commit, old_lineno, new_lineno));
+                self.state = State::Headers;
+            }
+            State::Headers => {
+                let captures = match METADATA_REGEX.captures(line) {
+                    Some(captures) => captures,
+                    None => {
+                        self.new_group = true;
+                        self.state = State::Content;
+                        return Ok(None);
+                    }
+                };
 
-        // In-progress result data
-        let mut blame_groups = Vec::new();
-        let mut blame_lines = Vec::new();
+                let key = utf!(captures, "key");
+                let value = captures
+                    .name("value")
+                    .map(|mtch| str::from_utf8(mtch.as_bytes()).unwrap());
 
-        for line in lines {
-            if line.is_empty() {
-                trace!("Skipping empty line");
-                continue;
-            }
+                trace!("Got blame key '{}' -> {:?}", key, value);
 
-            if line.starts_with(b"\t") {
-                trace!("Line starts with tab");
-                state = State::Content;
-            }
+                let author = &mut self.author;
+                let committer = &mut self.committer;
 
-            trace!("State: {:?}", state);
-
-            match state {
-                State::Commit => {
-                    let captures = match GIT_HASH_REGEX.captures(line) {
-                        Some(captures) => captures,
-                        None => return Err(BLAME_ERROR),
-                    };
-
-                    // Unwraps are safe because the values are regex-verified
-                    let old_lineno = utf!(captures, "old_line").parse().unwrap();
-                    let new_lineno = utf!(captures, "new_line").parse().unwrap();
-                    let commit = {
-                        let sha1 = utf!(captures, "sha1");
-
-                        GitHash::from_checked(sha1)
-                    };
-
-                    trace!(
-                        "commit: {}, old_lineno: {}, new_lineno: {}",
-                        commit,
-                        old_lineno,
-                        new_lineno,
-                    );
-                    commit_info = Some((commit, old_lineno, new_lineno));
-                    state = State::Headers;
-                }
-                State::Headers => {
-                    let captures = match METADATA_REGEX.captures(line) {
-                        Some(captures) => captures,
-                        None => {
-                            new_group = true;
-                            state = State::Content;
-                            continue;
-                        }
-                    };
-
-                    let key = utf!(captures, "key");
-                    let value = captures
-                        .name("value")
-                        .map(|mtch| str::from_utf8(mtch.as_bytes()).unwrap());
-
-                    trace!("Got blame key '{}' -> {:?}", key, value);
-
-                    match key {
-                        "author" => {
-                            let value = value.expect("No value for key author");
-                            set_string!(&mut author.name, value);
-                        }
-                        "author-mail" => {
-                            let value = value.expect("No value for key author-mail");
-                            set_string!(&mut author.email, value);
-                        }
-                        "author-time" => {
-                            let value = value.expect("No value for key author-time");
-                            author.timestamp = value.parse().unwrap();
-                        }
-                        "author-tz" => {
-                            let value = value.expect("No value for key author-tz");
-                            author.tz = value.parse().unwrap();
-                        }
-                        "committer" => {
-                            let value = value.expect("No value for key author");
-                            set_string!(&mut committer.name, value);
-                        }
-                        "committer-mail" => {
-                            let value = value.expect("No value for key author-mail");
-                            set_string!(&mut committer.email, value);
-                        }
-                        "committer-time" => {
-                            let value = value.expect("No value for key author-time");
-                            committer.timestamp = value.parse().unwrap();
-                        }
-                        "committer-tz" => {
-                            let value = value.expect("No value for key author-tz");
-                            committer.tz = value.parse().unwrap();
-                        }
-                        "summary" => {
-                            let value = value.expect("No value for key summary");
-                            set_string!(summary, value);
-                        }
-                        "previous" => {
-                            let (value, _) = value.expect("No value for key previous").split_at(40);
-                            let hash = GitHash::from_checked(value);
-                            previous_commit = Some(hash);
-                        }
-                        "boundary" => trace!("Hit metadata boundary"),
-                        "filename" => {
-                            trace!("Hit filename, moving to State::Content");
-                            state = State::Content;
-                        }
-                        _ => debug!("Unknown blame key '{}' -> {:?}", key, value),
+                match key {
+                    "author" => {
+                        let value = value.expect("No value for key author");
+                        set_string!(&mut author.name, value);
                     }
-                }
-                State::Content => {
-                    let (first, line) = line.split_at(1);
-                    assert_eq!(first, b"\t", "In content state but doesn't start with tab");
-
-                    // Push new blame line
-                    let line = line.into();
-                    let (commit, old_lineno, new_lineno) = match commit_info.take() {
-                        Some(values) => values,
-                        None => return Err(BLAME_ERROR),
-                    };
-
-                    trace!("Creating new blame line");
-
-                    blame_lines.push(BlameLine {
-                        commit,
-                        old_lineno,
-                        new_lineno,
-                        line,
-                    });
-
-                    // Push new blame group
-                    if new_group {
-                        trace!("Creating new blame group");
-
-                        let author = mem::replace(&mut author, Author::default());
-                        let committer = mem::replace(&mut committer, Author::default());
-                        let summary = mem::replace(&mut summary, String::new());
-                        let previous = previous_commit.take();
-                        let blame_lines = mem::replace(&mut blame_lines, Vec::new());
-
-                        blame_groups.push(BlameGroup {
-                            author: author.into(),
-                            committer: committer.into(),
-                            summary,
-                            previous,
-                            lines: blame_lines,
-                        });
+                    "author-mail" => {
+                        let value = value.expect("No value for key author-mail");
+                        set_string!(&mut author.email, value);
                     }
-
-                    state = State::Commit;
+                    "author-time" => {
+                        let value = value.expect("No value for key author-time");
+                        author.timestamp = value.parse().unwrap();
+                    }
+                    "author-tz" => {
+                        let value = value.expect("No value for key author-tz");
+                        author.tz = value.parse().unwrap();
+                    }
+                    "committer" => {
+                        let value = value.expect("No value for key author");
+                        set_string!(&mut committer.name, value);
+                    }
+                    "committer-mail" => {
+                        let value = value.expect("No value for key author-mail");
+                        set_string!(&mut committer.email, value);
+                    }
+                    "committer-time" => {
+                        let value = value.expect("No value for key author-time");
+                        committer.timestamp = value.parse().unwrap();
+                    }
+                    "committer-tz" => {
+                        let value = value.expect("No value for key author-tz");
+                        committer.tz = value.parse().unwrap();
+                    }
+                    "summary" => {
+                        let value = value.expect("No value for key summary");
+                        set_string!(self.summary, value);
+                    }
+                    "previous" => {
+                        let (value, _) = value.expect("No value for key previous").split_at(40);
+                        let hash = GitHash::from_checked(value);
+                        self.previous_commit = Some(hash);
+                    }
+                    "boundary" => trace!("Hit metadata boundary"),
+                    "filename" => {
+                        trace!("Hit filename, moving to State::Content");
+                        self.state = State::Content;
+                    }
+                    _ => debug!("Unknown blame key '{}' -> {:?}", key, value),
+                }
+            }
+            State::Content => {
+                let (first, line) = line.split_at(1);
+                assert_eq!(first, b"\t", "In content state but doesn't start with tab");
+
+                // Push new blame line
+                let line = line.into();
+                let (commit, old_lineno, new_lineno) = match self.commit_info.take() {
+                    Some(values) => values,
+                    None => return Err(BLAME_ERROR),
+                };
+
+                trace!("Creating new blame line");
+
+                self.blame_lines.push(BlameLine {
+                    commit,
+                    old_lineno,
+                    new_lineno,
+                    line,
+                });
+
+                self.state = State::Commit;
+
+                // Push new blame group
+                if self.new_group {
+                    trace!("Creating new blame group");
+
+                    let author = mem::replace(&mut self.author, Author::default());
+                    let committer = mem::replace(&mut self.committer, Author::default());
+                    let summary = mem::replace(&mut self.summary, String::new());
+                    let previous = self.previous_commit.take();
+                    let blame_lines = mem::replace(&mut self.blame_lines, Vec::new());
+
+                    return Ok(Some(BlameGroup {
+                        author: author.into(),
+                        committer: committer.into(),
+                        summary,
+                        previous,
+                        lines: blame_lines,
+                    }));
                 }
             }
         }
 
-        // Final blame group
-        if !blame_lines.is_empty() {
-            trace!("Moving remaining blame lines to final blame group");
-
-            blame_groups.push(BlameGroup {
-                author: author.into(),
-                committer: committer.into(),
-                summary,
-                previous: previous_commit,
-                lines: blame_lines,
-            });
+        Ok(None)
+    }
+
+    /// Finishes parsing, returning the final group if any lines remain.
+    pub fn finish(self) -> Option<BlameGroup> {
+        let BlameParser {
+            author,
+            committer,
+            summary,
+            previous_commit,
+            blame_lines,
+            ..
+        } = self;
+
+        if blame_lines.is_empty() {
+            return None;
         }
 
+        trace!("Moving remaining blame lines to final blame group");
+
+        Some(BlameGroup {
+            author: author.into(),
+            committer: committer.into(),
+            summary,
+            previous: previous_commit,
+            lines: blame_lines,
+        })
+    }
+}
+
+impl Default for BlameParser {
+    #[inline]
+    fn default() -> Self {
+        BlameParser::new()
+    }
+}
+
+// Blame implementation
+
+impl Blame {
+    pub fn from_porcelain(raw_bytes: &[u8]) -> Result<Self> {
+        debug!("Parsing git blame porcelain ({} bytes)", raw_bytes.len());
+
+        let mut parser = BlameParser::new();
+        let mut blame_groups = Vec::new();
+
+        for line in raw_bytes.split(|&b| b == b'\n') {
+            if let Some(group) = parser.push_line(line)? {
+                blame_groups.push(group);
+            }
+        }
+
+        // Final blame group
+        blame_groups.extend(parser.finish());
+
         Ok(Blame {
             groups: blame_groups,
         })
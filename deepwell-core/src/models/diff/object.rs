@@ -0,0 +1,62 @@
+/*
+ * models/diff/object.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/// A single piece of a word diff hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffSpan {
+    /// Text present in both versions.
+    Context(String),
+
+    /// Text only present in the new version.
+    Added(String),
+
+    /// Text only present in the old version.
+    Removed(String),
+
+    /// The end of a line in the original file.
+    Newline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub spans: Vec<DiffSpan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffFile {
+    /// The path before the change, or `None` if the file was created.
+    pub old_path: Option<String>,
+
+    /// The path after the change, or `None` if the file was deleted.
+    pub new_path: Option<String>,
+
+    /// Whether git considered the file binary, in which case there are no hunks.
+    pub binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub files: Vec<DiffFile>,
+}
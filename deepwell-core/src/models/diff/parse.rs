@@ -0,0 +1,394 @@
+/*
+ * models/diff/parse.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::*;
+use crate::error::{Error, Result};
+use regex::Regex;
+use std::mem;
+
+lazy_static! {
+    static ref HUNK_REGEX: Regex = Regex::new(
+        r"(?x)
+        ^@@\s
+        -(?P<old_start>[0-9]+)(,(?P<old_lines>[0-9]+))?
+        \s
+        \+(?P<new_start>[0-9]+)(,(?P<new_lines>[0-9]+))?
+        \s@@
+    "
+    )
+    .unwrap();
+}
+
+const DIFF_ERROR: Error = Error::StaticMsg("unexpected or mismatched input line in diff data");
+
+/// Removes the `a/` or `b/` prefix git adds to paths, mapping `/dev/null` to `None`.
+fn strip_path(path: &str) -> Option<String> {
+    if path == "/dev/null" {
+        return None;
+    }
+
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+
+    Some(String::from(path))
+}
+
+/// Gets both paths from a `diff --git a/X b/Y` line.
+fn parse_diff_line(line: &str) -> Result<(Option<String>, Option<String>)> {
+    let paths = line.strip_prefix("diff --git ").ok_or(DIFF_ERROR)?;
+
+    match paths.rfind(" b/") {
+        Some(idx) => Ok((strip_path(&paths[..idx]), strip_path(&paths[idx + 1..]))),
+        None => Err(DIFF_ERROR),
+    }
+}
+
+fn parse_hunk_line(line: &str) -> Result<DiffHunk> {
+    let captures = HUNK_REGEX.captures(line).ok_or(DIFF_ERROR)?;
+
+    macro_rules! number {
+        ($name:expr) => {
+            match captures.name($name) {
+                Some(mtch) => mtch.as_str().parse().map_err(|_| DIFF_ERROR)?,
+                None => 1,
+            }
+        };
+    }
+
+    Ok(DiffHunk {
+        old_start: number!("old_start"),
+        old_lines: number!("old_lines"),
+        new_start: number!("new_start"),
+        new_lines: number!("new_lines"),
+        spans: Vec::new(),
+    })
+}
+
+// Diff implementation
+
+impl Diff {
+    pub fn from_porcelain(raw: &str) -> Result<Self> {
+        debug!("Parsing git word diff porcelain ({} bytes)", raw.len());
+
+        let mut files = Vec::new();
+        let mut file: Option<DiffFile> = None;
+        let mut hunk: Option<DiffHunk> = None;
+
+        macro_rules! finish_hunk {
+            () => {
+                if let Some(hunk) = hunk.take() {
+                    file.as_mut().ok_or(DIFF_ERROR)?.hunks.push(hunk);
+                }
+            };
+        }
+
+        for line in raw.lines() {
+            trace!("Parsing diff line: {:?}", line);
+
+            if line.starts_with("diff --git ") {
+                finish_hunk!();
+                files.extend(file.take());
+
+                let (old_path, new_path) = parse_diff_line(line)?;
+                file = Some(DiffFile {
+                    old_path,
+                    new_path,
+                    binary: false,
+                    hunks: Vec::new(),
+                });
+
+                continue;
+            }
+
+            // Content of the current hunk
+            if let Some(ref mut hunk) = hunk {
+                let span = match line.chars().next() {
+                    Some(' ') => Some(DiffSpan::Context(String::from(&line[1..]))),
+                    Some('+') => Some(DiffSpan::Added(String::from(&line[1..]))),
+                    Some('-') => Some(DiffSpan::Removed(String::from(&line[1..]))),
+                    Some('~') => Some(DiffSpan::Newline),
+                    Some('\\') => None, // "No newline at end of file"
+                    Some('@') => {
+                        // Start of the next hunk in the same file
+                        let next = parse_hunk_line(line)?;
+                        let prev = mem::replace(hunk, next);
+                        file.as_mut().ok_or(DIFF_ERROR)?.hunks.push(prev);
+                        continue;
+                    }
+                    _ => return Err(DIFF_ERROR),
+                };
+
+                hunk.spans.extend(span);
+                continue;
+            }
+
+            // Extended header lines for the current file
+            let current = file.as_mut().ok_or(DIFF_ERROR)?;
+
+            if line.starts_with("@@ ") {
+                hunk = Some(parse_hunk_line(line)?);
+            } else if let Some(path) = line.strip_prefix("--- ") {
+                current.old_path = strip_path(path);
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                current.new_path = strip_path(path);
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                current.old_path = Some(String::from(path));
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                current.new_path = Some(String::from(path));
+            } else if line.starts_with("new file mode ") {
+                current.old_path = None;
+            } else if line.starts_with("deleted file mode ") {
+                current.new_path = None;
+            } else if line.starts_with("Binary files ") {
+                current.binary = true;
+            } else if line.starts_with("index ")
+                || line.starts_with("old mode ")
+                || line.starts_with("new mode ")
+                || line.starts_with("similarity index ")
+                || line.starts_with("dissimilarity index ")
+                || line.starts_with("copy from ")
+                || line.starts_with("copy to ")
+            {
+                // Nothing to record
+            } else {
+                return Err(DIFF_ERROR);
+            }
+        }
+
+        finish_hunk!();
+        files.extend(file);
+
+        Ok(Diff { files })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MODIFY_DIFF: &str = "\
+diff --git a/page b/page
+index 7bd984c..0c18f0c 100644
+--- a/page
++++ b/page
+@@ -1,3 +1,4 @@
+ The quick\x20
+-brown
++red
+  fox
+~
+ jumps over
+~
+ the\x20
+-lazy
++sleepy
+  dog.
+~
++New line here
+~
+";
+
+    const MULTIPLE_DIFF: &str = "\
+diff --git a/image.bin b/image.bin
+new file mode 100644
+index 0000000..8352675
+Binary files /dev/null and b/image.bin differ
+diff --git a/old-name b/new-name
+similarity index 100%
+rename from old-name
+rename to new-name
+diff --git a/new-name b/new-name
+deleted file mode 100644
+index e0808fa..0000000
+--- a/new-name
++++ /dev/null
+@@ -1 +0,0 @@
+-keep me
+~
+diff --git a/nonl b/nonl
+new file mode 100644
+index 0000000..3946807
+--- /dev/null
++++ b/nonl
+@@ -0,0 +1,3 @@
++a
+~
++b
+~
++x
+~
+";
+
+    const MULTIPLE_HUNKS_DIFF: &str = "\
+diff --git a/scp-xxxx b/scp-xxxx
+index 1111111..2222222 100644
+--- a/scp-xxxx
++++ b/scp-xxxx
+@@ -2 +2 @@ Item #: SCP-XXXX
+-Safe
++Euclid
+~
+@@ -40,2 +40,2 @@ Description
+ ends\x20
+-here.
++there.
+~
+\\ No newline at end of file
+";
+
+    fn context(s: &str) -> DiffSpan {
+        DiffSpan::Context(String::from(s))
+    }
+
+    fn added(s: &str) -> DiffSpan {
+        DiffSpan::Added(String::from(s))
+    }
+
+    fn removed(s: &str) -> DiffSpan {
+        DiffSpan::Removed(String::from(s))
+    }
+
+    #[test]
+    fn diff_modify() {
+        use self::DiffSpan::Newline;
+
+        let diff = Diff::from_porcelain(MODIFY_DIFF).expect("Unable to parse diff");
+
+        assert_eq!(
+            diff,
+            Diff {
+                files: vec![DiffFile {
+                    old_path: Some(String::from("page")),
+                    new_path: Some(String::from("page")),
+                    binary: false,
+                    hunks: vec![DiffHunk {
+                        old_start: 1,
+                        old_lines: 3,
+                        new_start: 1,
+                        new_lines: 4,
+                        spans: vec![
+                            context("The quick "),
+                            removed("brown"),
+                            added("red"),
+                            context(" fox"),
+                            Newline,
+                            context("jumps over"),
+                            Newline,
+                            context("the "),
+                            removed("lazy"),
+                            added("sleepy"),
+                            context(" dog."),
+                            Newline,
+                            added("New line here"),
+                            Newline,
+                        ],
+                    }],
+                }],
+            },
+        );
+    }
+
+    #[test]
+    fn diff_multiple() {
+        use self::DiffSpan::Newline;
+
+        let diff = Diff::from_porcelain(MULTIPLE_DIFF).expect("Unable to parse diff");
+        assert_eq!(diff.files.len(), 4);
+
+        // Binary file
+        let file = &diff.files[0];
+        assert_eq!(file.old_path, None);
+        assert_eq!(file.new_path.as_deref(), Some("image.bin"));
+        assert!(file.binary);
+        assert!(file.hunks.is_empty());
+
+        // Pure rename
+        let file = &diff.files[1];
+        assert_eq!(file.old_path.as_deref(), Some("old-name"));
+        assert_eq!(file.new_path.as_deref(), Some("new-name"));
+        assert!(!file.binary);
+        assert!(file.hunks.is_empty());
+
+        // Deletion
+        let file = &diff.files[2];
+        assert_eq!(file.old_path.as_deref(), Some("new-name"));
+        assert_eq!(file.new_path, None);
+        assert_eq!(file.hunks.len(), 1);
+        assert_eq!(file.hunks[0].old_start, 1);
+        assert_eq!(file.hunks[0].old_lines, 1);
+        assert_eq!(file.hunks[0].new_start, 0);
+        assert_eq!(file.hunks[0].new_lines, 0);
+        assert_eq!(file.hunks[0].spans, vec![removed("keep me"), Newline]);
+
+        // Creation
+        let file = &diff.files[3];
+        assert_eq!(file.old_path, None);
+        assert_eq!(file.new_path.as_deref(), Some("nonl"));
+        assert_eq!(
+            file.hunks[0].spans,
+            vec![
+                added("a"),
+                Newline,
+                added("b"),
+                Newline,
+                added("x"),
+                Newline
+            ],
+        );
+    }
+
+    #[test]
+    fn diff_multiple_hunks() {
+        use self::DiffSpan::Newline;
+
+        let diff = Diff::from_porcelain(MULTIPLE_HUNKS_DIFF).expect("Unable to parse diff");
+        let hunks = &diff.files[0].hunks;
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines), (2, 1));
+        assert_eq!((hunks[0].new_start, hunks[0].new_lines), (2, 1));
+        assert_eq!(
+            hunks[0].spans,
+            vec![removed("Safe"), added("Euclid"), Newline]
+        );
+        assert_eq!((hunks[1].old_start, hunks[1].old_lines), (40, 2));
+        assert_eq!(
+            hunks[1].spans,
+            vec![context("ends "), removed("here."), added("there."), Newline],
+        );
+    }
+
+    #[test]
+    fn diff_empty() {
+        let diff = Diff::from_porcelain("").expect("Unable to parse diff");
+        assert!(diff.files.is_empty());
+    }
+
+    #[test]
+    fn diff_invalid() {
+        Diff::from_porcelain("not a diff\n").expect_err("Parsed invalid diff");
+        Diff::from_porcelain("@@ -1 +1 @@\n+orphan\n").expect_err("Parsed hunk without file");
+
+        let bad_line = "diff --git a/page b/page\n@@ -1 +1 @@\n?what\n";
+        Diff::from_porcelain(bad_line).expect_err("Parsed unknown hunk line");
+    }
+}
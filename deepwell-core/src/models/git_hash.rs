@@ -24,13 +24,17 @@ use regex::Regex;
 use std::borrow::Borrow;
 use std::convert::TryFrom;
 use std::ffi::OsStr;
-use std::fmt::{self, Debug, Display};
+use std::fmt::{self, Debug, Display, LowerHex};
 use std::str;
 
 lazy_static! {
-    static ref GIT_HASH_REGEX: Regex = Regex::new(r"[a-f0-9]{40}").unwrap();
+    static ref GIT_HASH_REGEX: Regex = Regex::new(r"^[a-f0-9]{40}$").unwrap();
 }
 
+/// A full git object hash.
+///
+/// Always stored as 40 lowercase hexadecimal characters, which is
+/// what both `Display` and `LowerHex` produce.
 #[derive(Clone, PartialEq, Eq)]
 pub struct GitHash(ArrayString<[u8; 40]>);
 
@@ -91,3 +95,28 @@ impl Display for GitHash {
         write!(f, "{}", &self.0)
     }
 }
+
+impl LowerHex for GitHash {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[test]
+fn git_hash_format() {
+    let hash = GitHash::from_checked("  3b18e512dba79e4c8300dd08aeb37f8e728b8dad\n");
+    let display = format!("{}", hash);
+    let lower_hex = format!("{:x}", hash);
+
+    assert_eq!(display, lower_hex);
+    assert_eq!(display, "3b18e512dba79e4c8300dd08aeb37f8e728b8dad");
+    assert_eq!(display.len(), 40);
+    assert!(display
+        .chars()
+        .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+
+    assert!(GitHash::try_from("3B18E512DBA79E4C8300DD08AEB37F8E728B8DAD").is_err());
+    assert!(GitHash::try_from("3b18e512dba79e4c8300dd08aeb37f8e728b8dad0").is_err());
+    assert!(GitHash::try_from("3b18e512").is_err());
+}
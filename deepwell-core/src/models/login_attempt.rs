@@ -19,8 +19,10 @@
  */
 
 use super::prelude::*;
+use serde::{Serialize, Serializer};
+use std::result::Result as StdResult;
 
-#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
 pub struct LoginAttempt {
     id: LoginAttemptId,
     user_id: Option<UserId>,
@@ -60,4 +62,95 @@ impl LoginAttempt {
     pub fn attempted_at(&self) -> DateTime<Utc> {
         self.attempted_at
     }
+
+    /// Returns a copy of this login attempt with any email address masked.
+    ///
+    /// Usernames are left intact. Use this before serializing attempts for
+    /// non-administrators, admin views can serialize the attempt as-is.
+    pub fn redacted(&self) -> Self {
+        let username_or_email = self.username_or_email.ref_map(|name| {
+            if name.contains('@') {
+                redact_email(name)
+            } else {
+                name.clone()
+            }
+        });
+
+        LoginAttempt {
+            username_or_email,
+            remote_address: self.remote_address.clone(),
+            ..*self
+        }
+    }
+
+    /// Wraps this login attempt so that it serializes in redacted form.
+    #[inline]
+    pub fn serialize_redacted(&self) -> RedactedLoginAttempt<'_> {
+        RedactedLoginAttempt(self)
+    }
+}
+
+/// A login attempt which serializes with any email address masked.
+/// See [`LoginAttempt::redacted`].
+///
+/// [`LoginAttempt::redacted`]: ./struct.LoginAttempt.html#method.redacted
+#[derive(Debug, Copy, Clone)]
+pub struct RedactedLoginAttempt<'a>(&'a LoginAttempt);
+
+impl Serialize for RedactedLoginAttempt<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        self.0.redacted().serialize(serializer)
+    }
+}
+
+/// Masks an email address, such as `jsmith@example.net` to `j***@e***.net`.
+fn redact_email(email: &str) -> String {
+    fn mask(part: &str) -> String {
+        match part.chars().next() {
+            Some(first) => format!("{}***", first),
+            None => String::new(),
+        }
+    }
+
+    let (local, domain) = match email.rfind('@') {
+        Some(idx) => (&email[..idx], &email[idx + 1..]),
+        None => return mask(email),
+    };
+
+    match domain.rfind('.') {
+        Some(idx) => format!("{}@{}{}", mask(local), mask(&domain[..idx]), &domain[idx..]),
+        None => format!("{}@{}", mask(local), mask(domain)),
+    }
+}
+
+#[test]
+fn login_attempt_redacted() {
+    fn attempt(name: &str) -> LoginAttempt {
+        LoginAttempt {
+            id: LoginAttemptId::from_raw(1),
+            user_id: None,
+            username_or_email: Some(String::from(name)),
+            remote_address: Some(String::from("127.0.0.1")),
+            success: false,
+            attempted_at: Utc::now(),
+        }
+    }
+
+    macro_rules! check {
+        ($input:expr, $expected:expr) => {{
+            let original = attempt($input);
+            let redacted = original.redacted();
+
+            assert_eq!(redacted.username_or_email(), Some($expected));
+            assert_eq!(redacted.remote_address(), original.remote_address());
+            assert_eq!(redacted.attempted_at(), original.attempted_at());
+            assert_eq!(original.username_or_email(), Some($input));
+        }};
+    }
+
+    check!("jsmith@example.net", "j***@e***.net");
+    check!("admin@mail.example.com", "a***@m***.com");
+    check!("root@localhost", "r***@l***");
+    check!("aismallard", "aismallard");
+    check!("Dr Bright", "Dr Bright");
 }
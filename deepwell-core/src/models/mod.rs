@@ -19,6 +19,7 @@
  */
 
 mod blame;
+mod diff;
 mod git_hash;
 mod login_attempt;
 mod page;
@@ -34,11 +35,12 @@ mod prelude {
     pub use ref_map::*;
 }
 
-pub use self::blame::Blame;
+pub use self::blame::{Blame, BlameAuthor, BlameGroup, BlameLine, BlameParser};
+pub use self::diff::{Diff, DiffFile, DiffHunk, DiffSpan};
 pub use self::git_hash::GitHash;
-pub use self::login_attempt::LoginAttempt;
+pub use self::login_attempt::{LoginAttempt, RedactedLoginAttempt};
 pub use self::page::Page;
 pub use self::session::Session;
 pub use self::user::{User, UserMetadata, UserMetadataOwned};
 pub use self::votes::Votes;
-pub use self::wiki::{Wiki, WikiSettings};
+pub use self::wiki::{Wiki, WikiInfo, WikiSettings};
@@ -82,7 +82,11 @@ impl Page {
     }
 
     #[cfg(feature = "ftml-compat")]
-    pub fn into_pageinfo<TScoring: Scoring>(self, votes: Votes) -> ftml::PageInfoOwned {
+    pub fn into_pageinfo<TScoring: Scoring>(
+        self,
+        scoring: &TScoring,
+        votes: Votes,
+    ) -> ftml::PageInfoOwned {
         let Self {
             title,
             alt_title,
@@ -95,7 +99,7 @@ impl Page {
             tags,
             header: None,
             subheader: None,
-            rating: TScoring::score(&votes),
+            rating: scoring.score(&votes),
         }
     }
 }
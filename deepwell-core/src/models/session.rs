@@ -25,6 +25,7 @@ pub struct Session {
     id: SessionId,
     user_id: UserId,
     login_attempt_id: LoginAttemptId,
+    last_active_at: DateTime<Utc>,
 }
 
 impl Session {
@@ -42,4 +43,9 @@ impl Session {
     pub fn login_attempt_id(&self) -> LoginAttemptId {
         self.login_attempt_id
     }
+
+    #[inline]
+    pub fn last_active_at(&self) -> DateTime<Utc> {
+        self.last_active_at
+    }
 }
@@ -99,6 +99,7 @@ pub struct User {
     location: String,
     created_at: DateTime<Utc>,
     deleted_at: Option<DateTime<Utc>>,
+    email_canonical: String,
 }
 
 impl User {
@@ -117,6 +118,11 @@ impl User {
         &self.email
     }
 
+    #[inline]
+    pub fn email_canonical(&self) -> &str {
+        &self.email_canonical
+    }
+
     #[inline]
     pub fn is_verified(&self) -> bool {
         self.is_verified
@@ -19,7 +19,10 @@
  */
 
 use super::prelude::*;
+use super::Page;
+use crate::scoring::ScoringKind;
 use chrono::Duration;
+use std::convert::TryFrom;
 
 #[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
 pub struct Wiki {
@@ -28,6 +31,7 @@ pub struct Wiki {
     slug: String,
     domain: String,
     created_at: DateTime<Utc>,
+    deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Wiki {
@@ -55,12 +59,37 @@ impl Wiki {
     pub fn created_at(&self) -> DateTime<Utc> {
         self.created_at
     }
+
+    #[inline]
+    pub fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.deleted_at.is_none()
+    }
+}
+
+/// Summary information about a wiki, as shown on an "about" page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WikiInfo {
+    pub name: String,
+    pub slug: String,
+    pub domain: String,
+    pub created_at: DateTime<Utc>,
+    pub page_count: i64,
+    pub first_page: Option<Page>,
 }
 
 #[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
 pub struct WikiSettings {
     id: WikiId,
     page_lock_duration: i16,
+    lock_category_on_rename: bool,
+    scoring: String,
+    reserved_slugs: Vec<String>,
+    auto_alt_title: bool,
 }
 
 impl WikiSettings {
@@ -73,4 +102,25 @@ impl WikiSettings {
     pub fn page_lock_duration(&self) -> Duration {
         Duration::seconds(self.page_lock_duration as i64)
     }
+
+    #[inline]
+    pub fn lock_category_on_rename(&self) -> bool {
+        self.lock_category_on_rename
+    }
+
+    pub fn scoring(&self) -> ScoringKind {
+        let value = self.scoring.as_str();
+
+        ScoringKind::try_from(value).expect("scoring kind in database invalid")
+    }
+
+    #[inline]
+    pub fn reserved_slugs(&self) -> &[String] {
+        &self.reserved_slugs
+    }
+
+    #[inline]
+    pub fn auto_alt_title(&self) -> bool {
+        self.auto_alt_title
+    }
 }
@@ -26,12 +26,12 @@ use super::WikidotScoring;
 pub struct AverageScoring;
 
 impl Scoring for AverageScoring {
-    fn score(votes: &Votes) -> f32 {
+    fn score(&self, votes: &Votes) -> f32 {
         if votes.count() == 0 {
             return 0.0;
         }
 
-        let sum = WikidotScoring::score(votes);
+        let sum = WikidotScoring.score(votes);
         let total = votes.count() as f32;
 
         sum / total
@@ -74,7 +74,7 @@ fn average_voting() {
 
     macro_rules! check {
         ($votes:expr, $score:expr) => {
-            f32_eq(AverageScoring::score(&*$votes), $score, 0.1);
+            f32_eq(AverageScoring.score(&*$votes), $score, 0.1);
         };
     }
 
@@ -18,7 +18,9 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use crate::error::StdResult;
 use crate::models::Votes;
+use std::convert::TryFrom;
 
 mod average;
 mod null;
@@ -90,7 +92,72 @@ pub use self::wilson::WilsonScoring;
 /// Allows for different implementations, at the choice of wiki
 /// administrators.
 pub trait Scoring {
-    fn score(votes: &Votes) -> f32;
+    fn score(&self, votes: &Votes) -> f32;
+}
+
+/// Which scorer a wiki uses to compute ratings.
+/// Wilson scoring always uses its default confidence.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ScoringKind {
+    Wikidot,
+    Wilson,
+    Average,
+    Percent,
+    Null,
+}
+
+impl Default for ScoringKind {
+    #[inline]
+    fn default() -> Self {
+        ScoringKind::Wikidot
+    }
+}
+
+impl Scoring for ScoringKind {
+    fn score(&self, votes: &Votes) -> f32 {
+        use self::ScoringKind::*;
+
+        match self {
+            Wikidot => WikidotScoring.score(votes),
+            Wilson => WilsonScoring::default().score(votes),
+            Average => AverageScoring.score(votes),
+            Percent => PercentScoring.score(votes),
+            Null => NullScoring.score(votes),
+        }
+    }
+}
+
+impl Into<&'static str> for ScoringKind {
+    // The "enum name" used to store in the database
+    // Cannot change without a migration
+    fn into(self) -> &'static str {
+        use self::ScoringKind::*;
+
+        match self {
+            Wikidot => "wikidot",
+            Wilson => "wilson",
+            Average => "average",
+            Percent => "percent",
+            Null => "null",
+        }
+    }
+}
+
+impl TryFrom<&'_ str> for ScoringKind {
+    type Error = ();
+
+    fn try_from(value: &str) -> StdResult<Self, ()> {
+        let kind = match value {
+            "wikidot" => ScoringKind::Wikidot,
+            "wilson" => ScoringKind::Wilson,
+            "average" => ScoringKind::Average,
+            "percent" => ScoringKind::Percent,
+            "null" => ScoringKind::Null,
+            _ => return Err(()),
+        };
+
+        Ok(kind)
+    }
 }
 
 #[cfg(test)]
@@ -103,3 +170,28 @@ pub fn f32_eq(x: f32, y: f32, precision: f32) {
         );
     }
 }
+
+#[test]
+fn scoring_kind() {
+    use self::prelude::*;
+
+    let kinds = [
+        ScoringKind::Wikidot,
+        ScoringKind::Wilson,
+        ScoringKind::Average,
+        ScoringKind::Percent,
+        ScoringKind::Null,
+    ];
+
+    for &kind in &kinds {
+        let name: &str = kind.into();
+        assert_eq!(ScoringKind::try_from(name), Ok(kind));
+    }
+
+    assert_eq!(ScoringKind::try_from("invalid"), Err(()));
+    assert_eq!(ScoringKind::default(), ScoringKind::Wikidot);
+
+    f32_eq(ScoringKind::Wikidot.score(&*MIXED_VOTES_1), 26.0, 0.01);
+    f32_eq(ScoringKind::Wilson.score(&*MIXED_VOTES_1), 37.10, 0.01);
+    f32_eq(ScoringKind::Null.score(&*MIXED_VOTES_1), 0.0, 0.01);
+}
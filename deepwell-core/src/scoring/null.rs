@@ -25,7 +25,7 @@ use super::prelude::*;
 pub struct NullScoring;
 
 impl Scoring for NullScoring {
-    fn score(_votes: &Votes) -> f32 {
+    fn score(&self, _votes: &Votes) -> f32 {
         0.0
     }
 }
@@ -34,7 +34,7 @@ impl Scoring for NullScoring {
 fn null_scoring() {
     macro_rules! check {
         ($votes:expr) => {
-            f32_eq(NullScoring::score(&*$votes), 0.0, 0.0000001);
+            f32_eq(NullScoring.score(&*$votes), 0.0, 0.0000001);
         };
     }
 
@@ -26,7 +26,7 @@ use super::prelude::*;
 pub struct PercentScoring;
 
 impl Scoring for PercentScoring {
-    fn score(votes: &Votes) -> f32 {
+    fn score(&self, votes: &Votes) -> f32 {
         macro_rules! get_vote {
             ($vote:expr) => {
                 votes.count_for_vote($vote).unwrap_or(0) as f32
@@ -49,7 +49,7 @@ impl Scoring for PercentScoring {
 fn percent_voting() {
     macro_rules! check {
         ($votes:expr, $score:expr) => {
-            f32_eq(PercentScoring::score(&*$votes), $score, 0.1);
+            f32_eq(PercentScoring.score(&*$votes), $score, 0.1);
         };
     }
 
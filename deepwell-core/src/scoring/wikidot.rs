@@ -26,7 +26,7 @@ use super::prelude::*;
 pub struct WikidotScoring;
 
 impl Scoring for WikidotScoring {
-    fn score(votes: &Votes) -> f32 {
+    fn score(&self, votes: &Votes) -> f32 {
         votes.iter().fold(0.0, |score, (vote, count)| {
             let vote = f32::from(vote);
             let count = count as f32;
@@ -40,7 +40,7 @@ impl Scoring for WikidotScoring {
 fn wikidot_scoring() {
     macro_rules! check {
         ($votes:expr, $score:expr) => {
-            f32_eq(WikidotScoring::score(&*$votes), $score, 0.1);
+            f32_eq(WikidotScoring.score(&*$votes), $score, 0.1);
         };
     }
 
@@ -26,13 +26,40 @@ use crate::math::probit;
 /// https://www.itl.nist.gov/div898/handbook/prc/section2/prc241.htm
 ///
 /// Takes implementation from https://github.com/simple-statistics/simple-statistics
-#[derive(Debug, Copy, Clone, Default)]
-pub struct WilsonScoring;
+///
+/// The z-score used for the interval defaults to that of a 95% confidence level,
+/// but can be changed using [`with_confidence`].
+///
+/// [`with_confidence`]: #method.with_confidence
+#[derive(Debug, Copy, Clone)]
+pub struct WilsonScoring {
+    z: f32,
+}
 
-impl Scoring for WilsonScoring {
-    fn score(votes: &Votes) -> f32 {
-        const CONFIDENCE: f32 = 0.95;
+impl WilsonScoring {
+    const DEFAULT_CONFIDENCE: f32 = 0.95;
+
+    #[inline]
+    pub fn with_confidence(z: f32) -> Self {
+        WilsonScoring { z }
+    }
 
+    #[inline]
+    pub fn z(&self) -> f32 {
+        self.z
+    }
+}
+
+impl Default for WilsonScoring {
+    fn default() -> Self {
+        let z = probit(1.0 - (1.0 - Self::DEFAULT_CONFIDENCE) / 2.0);
+
+        WilsonScoring { z }
+    }
+}
+
+impl Scoring for WilsonScoring {
+    fn score(&self, votes: &Votes) -> f32 {
         // Note: while implementation matches
         // https://medium.com/@gattermeier/calculating-better-rating-scores-for-things-voted-on-7fa3f632c79d
         // it could definitely use some tuning, especially with regards to neutral-vote and overall
@@ -46,7 +73,7 @@ impl Scoring for WilsonScoring {
         let total = votes.count() as f32;
 
         let p_hat = 1.0 * positive / total;
-        let z = probit(1.0 - (1.0 - CONFIDENCE) / 2.0);
+        let z = self.z;
         let z_2 = z * z;
 
         let a = p_hat + z_2 / (2.0 * total);
@@ -61,7 +88,7 @@ impl Scoring for WilsonScoring {
 fn wilson_scoring() {
     macro_rules! check {
         ($votes:expr, $score:expr) => {
-            f32_eq(WilsonScoring::score(&*$votes), $score, 0.01);
+            f32_eq(WilsonScoring::default().score(&*$votes), $score, 0.01);
         };
     }
 
@@ -73,3 +100,28 @@ fn wilson_scoring() {
     check!(MIXED_VOTES_1, 37.10);
     check!(MIXED_VOTES_2, 13.52);
 }
+
+#[test]
+fn wilson_scoring_confidence() {
+    macro_rules! check {
+        ($z:expr, $votes:expr, $score:expr) => {
+            let scorer = WilsonScoring::with_confidence($z);
+            f32_eq(scorer.score(&*$votes), $score, 0.01);
+        };
+    }
+
+    check!(1.96, NO_VOTES, 0.0);
+    check!(2.58, NO_VOTES, 0.0);
+    check!(1.96, MIXED_VOTES_1, 37.08);
+    check!(2.58, MIXED_VOTES_1, 34.37);
+
+    // Wider interval means a lower bound
+    let narrow = WilsonScoring::with_confidence(1.96).score(&*MIXED_VOTES_1);
+    let wide = WilsonScoring::with_confidence(2.58).score(&*MIXED_VOTES_1);
+    assert!(wide < narrow);
+
+    // Default is close to z = 1.96, within the precision of probit()
+    let default = WilsonScoring::default();
+    f32_eq(default.z(), 1.96, 0.01);
+    f32_eq(default.score(&*MIXED_VOTES_1), narrow, 0.02);
+}
@@ -25,3 +25,218 @@ make_id_type!(revision, RevisionId);
 make_id_type!(session, SessionId);
 make_id_type!(user, UserId);
 make_id_type!(wiki, WikiId);
+
+/// Converts a string into the normal form used for page and wiki slugs.
+///
+/// The result passes [`is_valid_slug`] unless it is empty. This happens when
+/// the input has no characters usable in a slug, for instance a title written
+/// entirely in a non-Latin script, so callers must still check the result.
+///
+/// [`is_valid_slug`]: ./fn.is_valid_slug.html
+pub fn normalize_slug(input: &str) -> String {
+    let mut slug = String::from(input);
+    wikidot_normalize::normalize(&mut slug);
+
+    // Slashes and non-ASCII letters are kept by normalize(), but aren't valid in slugs
+    if !is_valid_slug(&slug) {
+        slug = slug
+            .chars()
+            .map(|ch| match ch {
+                'a'..='z' | '0'..='9' | ':' | '_' | '-' => ch,
+                _ => '-',
+            })
+            .collect();
+
+        wikidot_normalize::normalize(&mut slug);
+    }
+
+    slug
+}
+
+/// Determines if a string can be used as a page slug.
+/// This is the check the revision store applies before storing a page.
+///
+/// Slugs in normal form are accepted, as are the unnormalized slugs of
+/// system pages, which may have uppercase letters or repeated separators.
+/// Either way, a slug must be a non-empty plain filename that git will not
+/// interpret as an option or pathspec.
+pub fn is_valid_slug(input: &str) -> bool {
+    let is_valid_char =
+        |ch: char| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' || ch == ':';
+
+    !input.is_empty() && !input.starts_with('-') && input.chars().all(is_valid_char)
+}
+
+const PERMALINK_ALPHABET: &[u8; 62] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+impl RevisionId {
+    /// Encodes this ID as a short base62 string, for linking to a revision.
+    /// The result can be turned back into an ID with [`from_permalink`].
+    ///
+    /// [`from_permalink`]: #method.from_permalink
+    pub fn to_permalink(self) -> String {
+        let mut value = self.to_i64() as u64;
+        let mut digits = Vec::new();
+
+        loop {
+            digits.push(PERMALINK_ALPHABET[(value % 62) as usize]);
+            value /= 62;
+
+            if value == 0 {
+                break;
+            }
+        }
+
+        digits.reverse();
+        String::from_utf8(digits).unwrap()
+    }
+
+    /// Parses a permalink produced by [`to_permalink`].
+    /// Returns `None` if the string is not a valid permalink.
+    ///
+    /// [`to_permalink`]: #method.to_permalink
+    pub fn from_permalink(permalink: &str) -> Option<Self> {
+        if permalink.is_empty() {
+            return None;
+        }
+
+        // Each ID has exactly one permalink, so leading zeroes are rejected
+        if permalink.len() > 1 && permalink.starts_with('0') {
+            return None;
+        }
+
+        let mut value: u64 = 0;
+        for ch in permalink.bytes() {
+            let digit = PERMALINK_ALPHABET.iter().position(|&c| c == ch)? as u64;
+            value = value.checked_mul(62)?.checked_add(digit)?;
+        }
+
+        Some(RevisionId::from_raw(value as i64))
+    }
+}
+
+#[test]
+fn permalink() {
+    macro_rules! check {
+        ($id:expr, $permalink:expr) => {{
+            let id = RevisionId::from_raw($id);
+            let permalink = id.to_permalink();
+
+            assert_eq!(permalink, $permalink);
+            assert_eq!(RevisionId::from_permalink(&permalink), Some(id));
+        }};
+    }
+
+    check!(0, "0");
+    check!(1, "1");
+    check!(61, "z");
+    check!(62, "10");
+    check!(3843, "zz");
+    check!(1_000_000, "4C92");
+    check!(i64::max_value(), "AzL8n0Y58m7");
+
+    // Round trips
+    for &id in &[5, 100, 4096, 123_456_789, -1, i64::min_value()] {
+        let id = RevisionId::from_raw(id);
+        assert_eq!(RevisionId::from_permalink(&id.to_permalink()), Some(id));
+    }
+
+    // Invalid permalinks
+    assert_eq!(RevisionId::from_permalink(""), None);
+    assert_eq!(RevisionId::from_permalink("abc-def"), None);
+    assert_eq!(RevisionId::from_permalink("zzzzzzzzzzzzzzzz"), None);
+    assert_eq!(RevisionId::from_permalink("01"), None);
+    assert_eq!(RevisionId::from_permalink("00"), None);
+}
+
+#[test]
+fn slugs() {
+    macro_rules! check {
+        ($input:expr, $expected:expr) => {{
+            let slug = normalize_slug($input);
+
+            assert_eq!(slug, $expected, "Normalized slug doesn't match");
+            assert_eq!(
+                is_valid_slug(&slug),
+                !slug.is_empty(),
+                "Normalized slug '{}' validity doesn't match",
+                slug
+            );
+        }};
+    }
+
+    check!("scp-1000", "scp-1000");
+    check!("Big Cheese Horace", "big-cheese-horace");
+    check!("Tufto's Proposal", "tufto-s-proposal");
+    check!("bottom--Text", "bottom-text");
+    check!("-test-", "test");
+    check!("Component:Image Block", "component:image-block");
+    check!("fragment::part", "fragment:part");
+    check!("_default", "_default");
+    check!("a/b//c/", "a-b-c");
+    check!("/start", "start");
+    check!("Café Ünïcode", "caf-n-code");
+    check!("字", "");
+    check!("", "");
+
+    assert!(is_valid_slug("scp-1000"));
+    assert!(is_valid_slug("component:image-block"));
+    assert!(is_valid_slug("System:Template"));
+    assert!(is_valid_slug("scp--1000"));
+    assert!(!is_valid_slug(""));
+    assert!(!is_valid_slug("scp 1000"));
+    assert!(!is_valid_slug("-scp"));
+    assert!(!is_valid_slug("a/b"));
+    assert!(!is_valid_slug("../scp"));
+}
+
+#[test]
+fn slugs_round_trip() {
+    const CHARACTERS: &[char] = &[
+        'a', 'Z', '0', '9', ':', '_', '-', '/', ' ', '.', '\'', '!', '%', 'é', 'ß', '字', '\t',
+    ];
+
+    // Every string of up to four of these characters
+    let mut input = String::new();
+    let mut indices = Vec::new();
+
+    loop {
+        input.clear();
+        input.extend(indices.iter().map(|&idx| CHARACTERS[idx]));
+
+        let slug = normalize_slug(&input);
+        assert!(
+            slug.is_empty() || is_valid_slug(&slug),
+            "Normalized slug '{}' from '{}' isn't valid",
+            slug,
+            input,
+        );
+        assert_eq!(
+            normalize_slug(&slug),
+            slug,
+            "Normalization isn't idempotent"
+        );
+
+        // Advance to the next combination
+        let mut pos = 0;
+        loop {
+            if pos == indices.len() {
+                if pos == 4 {
+                    return;
+                }
+
+                indices.push(0);
+                break;
+            }
+
+            indices[pos] += 1;
+            if indices[pos] < CHARACTERS.len() {
+                break;
+            }
+
+            indices[pos] = 0;
+            pos += 1;
+        }
+    }
+}
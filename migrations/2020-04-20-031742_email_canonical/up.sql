@@ -0,0 +1,49 @@
+-- Same rules as canonicalize_email() in src/package/user/canonical.rs
+CREATE FUNCTION pg_temp.canonicalize_email(email TEXT) RETURNS TEXT AS $$
+DECLARE
+    at_idx INTEGER;
+    plus_idx INTEGER;
+    local_part TEXT;
+    domain TEXT;
+BEGIN
+    email := LOWER(TRIM(email));
+
+    -- Split on the last '@'
+    IF STRPOS(email, '@') = 0 THEN
+        RETURN email;
+    END IF;
+
+    at_idx := LENGTH(email) - STRPOS(REVERSE(email), '@') + 1;
+    local_part := SUBSTR(email, 1, at_idx - 1);
+    domain := SUBSTR(email, at_idx + 1);
+
+    -- Strip plus-addressing
+    plus_idx := STRPOS(local_part, '+');
+    IF plus_idx > 1 THEN
+        local_part := SUBSTR(local_part, 1, plus_idx - 1);
+    END IF;
+
+    IF domain IN ('gmail.com', 'googlemail.com') THEN
+        RETURN REPLACE(local_part, '.', '') || '@gmail.com';
+    END IF;
+
+    RETURN local_part || '@' || domain;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- Canonical form of the email, used for uniqueness checks
+ALTER TABLE users ADD COLUMN email_canonical TEXT;
+UPDATE users SET email_canonical = LOWER(email);
+
+-- Existing users which already share a canonical email keep their lowercased one,
+-- since only one of them can own it
+UPDATE users SET email_canonical = pg_temp.canonicalize_email(email)
+WHERE pg_temp.canonicalize_email(email) IN (
+    SELECT pg_temp.canonicalize_email(email)
+    FROM users
+    GROUP BY 1
+    HAVING COUNT(*) = 1
+);
+
+ALTER TABLE users ALTER COLUMN email_canonical SET NOT NULL;
+CREATE UNIQUE INDEX users_email_canonical_key ON users (email_canonical);
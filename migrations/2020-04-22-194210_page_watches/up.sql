@@ -0,0 +1,6 @@
+CREATE TABLE page_watches (
+    page_id BIGINT NOT NULL REFERENCES pages(page_id),
+    user_id BIGINT NOT NULL REFERENCES users(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (page_id, user_id)
+);
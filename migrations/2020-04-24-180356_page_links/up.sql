@@ -0,0 +1,7 @@
+CREATE TABLE page_links (
+    page_id BIGINT NOT NULL REFERENCES pages(page_id),
+    to_slug TEXT NOT NULL CHECK (to_slug != ''),
+    PRIMARY KEY (page_id, to_slug)
+);
+
+CREATE INDEX page_links_to_slug_idx ON page_links (to_slug);
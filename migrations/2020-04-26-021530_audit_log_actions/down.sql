@@ -0,0 +1,13 @@
+DROP INDEX audit_log_user_id_idx;
+DROP INDEX audit_log_wiki_id_idx;
+
+ALTER TABLE audit_log DROP CONSTRAINT audit_log_audit_log_entry_type_check;
+ALTER TABLE audit_log ADD CONSTRAINT audit_log_audit_log_entry_type_check CHECK (
+    audit_log_entry_type IN (
+        'view_page',
+        'add_page',
+        'edit_page_content',
+        'edit_page_tags',
+        'remove_page'
+    )
+);
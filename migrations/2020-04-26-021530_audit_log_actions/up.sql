@@ -0,0 +1,21 @@
+ALTER TABLE audit_log DROP CONSTRAINT audit_log_audit_log_entry_type_check;
+ALTER TABLE audit_log ADD CONSTRAINT audit_log_audit_log_entry_type_check CHECK (
+    audit_log_entry_type IN (
+        'view_page',
+        'add_page',
+        'edit_page_content',
+        'edit_page_tags',
+        'rename_page',
+        'remove_page',
+        'restore_page',
+        'undo_revision',
+        'revert_page',
+        'set_rating',
+        'remove_rating',
+        'add_page_lock',
+        'remove_page_lock'
+    )
+);
+
+CREATE INDEX audit_log_wiki_id_idx ON audit_log (wiki_id);
+CREATE INDEX audit_log_user_id_idx ON audit_log (user_id);
@@ -0,0 +1 @@
+ALTER TABLE revisions ADD COLUMN minor BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1 @@
+ALTER TABLE wiki_settings DROP COLUMN lock_category_on_rename;
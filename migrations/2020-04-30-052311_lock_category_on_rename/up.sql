@@ -0,0 +1 @@
+ALTER TABLE wiki_settings ADD COLUMN lock_category_on_rename BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,7 @@
+CREATE TABLE page_title_history (
+    revision_id BIGINT REFERENCES revisions(revision_id) PRIMARY KEY,
+    page_id BIGINT NOT NULL REFERENCES pages(page_id),
+    title TEXT NOT NULL,
+    alt_title TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
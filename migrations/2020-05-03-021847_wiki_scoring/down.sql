@@ -0,0 +1 @@
+ALTER TABLE wiki_settings DROP COLUMN scoring;
@@ -0,0 +1,9 @@
+ALTER TABLE wiki_settings ADD COLUMN scoring VARCHAR(8) NOT NULL DEFAULT 'wikidot' CHECK (
+    scoring IN (
+        'wikidot',
+        'wilson',
+        'average',
+        'percent',
+        'null'
+    )
+);
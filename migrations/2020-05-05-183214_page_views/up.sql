@@ -0,0 +1,4 @@
+CREATE TABLE page_views (
+    page_id BIGINT REFERENCES pages(page_id) PRIMARY KEY,
+    views BIGINT NOT NULL DEFAULT 0 CHECK (views >= 0)
+);
@@ -0,0 +1 @@
+ALTER TABLE wiki_settings ADD COLUMN reserved_slugs TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN last_active_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
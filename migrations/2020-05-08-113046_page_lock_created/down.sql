@@ -0,0 +1 @@
+ALTER TABLE page_locks DROP COLUMN created_at;
@@ -0,0 +1 @@
+ALTER TABLE page_locks ADD COLUMN created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
@@ -0,0 +1 @@
+ALTER TABLE wiki_settings DROP COLUMN auto_alt_title;
@@ -0,0 +1 @@
+ALTER TABLE wiki_settings ADD COLUMN auto_alt_title BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1 @@
+ALTER TABLE wikis DROP COLUMN deleted_at;
@@ -0,0 +1 @@
+ALTER TABLE wikis ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
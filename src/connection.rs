@@ -0,0 +1,196 @@
+/*
+ * connection.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::{Result, StdResult};
+use diesel::connection::{Connection, TransactionManager};
+use diesel::PgConnection;
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+
+/// A Postgres connection which can be shared between threads.
+///
+/// Only one thread may use the connection at a time, but that thread may
+/// lock it again while it is held. This lets a transaction keep the connection
+/// for its whole duration, while the statements inside it still lock it as usual.
+pub struct SharedConnection {
+    conn: Mutex<PgConnection>,
+    owner: Mutex<Owner>,
+    released: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Owner {
+    thread: Option<ThreadId>,
+    count: usize,
+}
+
+impl SharedConnection {
+    pub fn establish(database_url: &str) -> StdResult<Self, diesel::ConnectionError> {
+        let conn = PgConnection::establish(database_url)?;
+
+        Ok(SharedConnection {
+            conn: Mutex::new(conn),
+            owner: Mutex::new(Owner::default()),
+            released: Condvar::new(),
+        })
+    }
+
+    /// Locks the connection for a single statement.
+    ///
+    /// The guard must not be held across an `.await`,
+    /// which is why it should only ever be a temporary.
+    pub fn lock(&self) -> ConnectionGuard<'_> {
+        let ownership = self.acquire();
+        let conn = self.conn.lock().unwrap_or_else(PoisonError::into_inner);
+
+        ConnectionGuard {
+            conn,
+            _ownership: ownership,
+        }
+    }
+
+    /// Runs the closure inside a transaction, committing if it returns `Ok`.
+    ///
+    /// The current thread keeps the connection until the transaction finishes.
+    /// Transactions may be nested, in which case savepoints are used.
+    pub fn transaction<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        let _ownership = self.acquire();
+
+        {
+            let conn = self.lock();
+            conn.transaction_manager().begin_transaction(&*conn)?;
+        }
+
+        let result = f();
+
+        let conn = self.lock();
+        let manager = conn.transaction_manager();
+        match result {
+            Ok(value) => {
+                manager.commit_transaction(&*conn)?;
+                Ok(value)
+            }
+            Err(error) => {
+                manager.rollback_transaction(&*conn)?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Runs the closure inside a transaction which is always rolled back.
+    #[cfg(test)]
+    pub fn test_transaction<F>(&self, f: F)
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        let mut result = None;
+        let _ = self.transaction::<(), _>(|| {
+            result = Some(f());
+            Err(crate::Error::StaticMsg("test transaction rolled back"))
+        });
+
+        result
+            .expect("Test transaction never ran")
+            .expect("Test transaction failed");
+    }
+
+    /// Gets how many transactions are currently open on this connection.
+    pub fn transaction_depth(&self) -> u32 {
+        let conn = self.lock();
+        let manager = conn.transaction_manager();
+
+        TransactionManager::<PgConnection>::get_transaction_depth(manager)
+    }
+
+    fn acquire(&self) -> Ownership<'_> {
+        let current = thread::current().id();
+        let mut owner = self.owner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        while matches!(owner.thread, Some(thread) if thread != current) {
+            owner = self
+                .released
+                .wait(owner)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        owner.thread = Some(current);
+        owner.count += 1;
+
+        Ownership { shared: self }
+    }
+
+    fn release(&self) {
+        let mut owner = self.owner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        owner.count -= 1;
+        if owner.count == 0 {
+            owner.thread = None;
+            self.released.notify_one();
+        }
+    }
+}
+
+impl Debug for SharedConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedConnection")
+            .field("conn", &"PgConnection { .. }")
+            .field("owner", &self.owner)
+            .finish()
+    }
+}
+
+/// Marks the current thread as using the connection until dropped.
+struct Ownership<'a> {
+    shared: &'a SharedConnection,
+}
+
+impl Drop for Ownership<'_> {
+    fn drop(&mut self) {
+        self.shared.release();
+    }
+}
+
+/// A lock on a `SharedConnection`, which dereferences to the connection itself.
+pub struct ConnectionGuard<'a> {
+    conn: MutexGuard<'a, PgConnection>,
+    _ownership: Ownership<'a>,
+}
+
+impl Deref for ConnectionGuard<'_> {
+    type Target = PgConnection;
+
+    #[inline]
+    fn deref(&self) -> &PgConnection {
+        &self.conn
+    }
+}
+
+impl Debug for ConnectionGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConnectionGuard")
+            .field("conn", &"PgConnection { .. }")
+            .finish()
+    }
+}
@@ -45,6 +45,9 @@ extern crate ref_map;
 
 #[macro_use]
 extern crate serde;
+
+#[macro_use]
+extern crate serde_json;
 extern crate subprocess;
 
 #[macro_use]
@@ -54,6 +57,7 @@ extern crate wikidot_normalize;
 #[macro_use]
 mod macros;
 
+mod connection;
 mod package;
 mod schema;
 mod server;
@@ -63,13 +67,19 @@ mod utils;
 mod test;
 
 pub mod prelude {
-    pub use crate::package::page::PageCommit;
-    pub use crate::server::{Config, Server};
+    pub use crate::package::audit::{AuditAction, AuditFilter};
+    pub use crate::package::lock::PageLock;
+    pub use crate::package::page::{
+        ContentFilter, PageCommit, PageOrder, DEFAULT_MAX_CONTENT_BYTES,
+    };
+    pub use crate::package::password::PasswordPolicy;
+    pub use crate::server::{Config, Server, ServerStatus};
     pub use crate::{Error, Result, StdResult};
     pub use deepwell_core::prelude::*;
 }
 
 mod manager_prelude {
+    pub use crate::connection::SharedConnection;
     pub use crate::prelude::*;
     pub use crate::schema::*;
     pub use async_std::prelude::*;
@@ -90,5 +100,5 @@ mod manager_prelude {
 pub type StdResult<T, E> = std::result::Result<T, E>;
 pub type Result<T> = StdResult<T, Error>;
 
-pub use self::server::{Config, Server};
+pub use self::server::{Config, Server, ServerStatus};
 pub use deepwell_core::error::Error;
@@ -29,6 +29,7 @@ macro_rules! impl_async_transaction {
             {
                 use async_std::task;
 
+                // The future is run on this thread, which keeps the connection throughout
                 self.conn.transaction(|| task::block_on(f))
             }
         }
@@ -0,0 +1,155 @@
+/*
+ * audit/manager.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::{AuditAction, AuditFilter, NewAuditEntry};
+use crate::manager_prelude::*;
+use crate::schema::audit_log;
+use serde_json::Value as JsonValue;
+
+const DEFAULT_AUDIT_LOG_LIMIT: i64 = 100;
+
+#[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    audit_log_entry_id: i64,
+    audit_log_entry_type: String,
+    created_at: DateTime<Utc>,
+    wiki_id: WikiId,
+    user_id: Option<UserId>,
+    data: JsonValue,
+}
+
+impl AuditEntry {
+    #[inline]
+    pub fn id(&self) -> i64 {
+        self.audit_log_entry_id
+    }
+
+    #[inline]
+    pub fn action(&self) -> AuditAction {
+        let value = self.audit_log_entry_type.as_str();
+
+        AuditAction::try_from(value).expect("audit log entry type in database invalid")
+    }
+
+    #[inline]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    #[inline]
+    pub fn wiki_id(&self) -> WikiId {
+        self.wiki_id
+    }
+
+    #[inline]
+    pub fn user_id(&self) -> Option<UserId> {
+        self.user_id
+    }
+
+    #[inline]
+    pub fn data(&self) -> &JsonValue {
+        &self.data
+    }
+}
+
+pub struct AuditManager {
+    conn: Arc<SharedConnection>,
+}
+
+impl AuditManager {
+    #[inline]
+    pub fn new(conn: &Arc<SharedConnection>) -> Self {
+        debug!("Creating audit-manager service");
+
+        let conn = Arc::clone(conn);
+        AuditManager { conn }
+    }
+
+    pub async fn add(
+        &self,
+        wiki_id: WikiId,
+        user_id: Option<UserId>,
+        action: AuditAction,
+        data: &JsonValue,
+    ) -> Result<()> {
+        info!(
+            "Adding audit log entry for wiki ID {} / user ID {:?}: {:?}",
+            wiki_id, user_id, action,
+        );
+
+        let model = NewAuditEntry {
+            audit_log_entry_type: action.into(),
+            wiki_id: wiki_id.into(),
+            user_id: user_id.map(|id| id.into()),
+            data,
+        };
+
+        diesel::insert_into(audit_log::table)
+            .values(&model)
+            .execute(&*self.conn.lock())?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, filter: AuditFilter) -> Result<Vec<AuditEntry>> {
+        info!("Getting audit log entries for {:?}", filter);
+
+        let AuditFilter {
+            wiki_id,
+            user_id,
+            action,
+            limit,
+        } = filter;
+
+        let mut query = audit_log::table.into_boxed();
+
+        if let Some(wiki_id) = wiki_id {
+            let id: i64 = wiki_id.into();
+            query = query.filter(audit_log::wiki_id.eq(id));
+        }
+
+        if let Some(user_id) = user_id {
+            let id: i64 = user_id.into();
+            query = query.filter(audit_log::user_id.eq(id));
+        }
+
+        if let Some(action) = action {
+            let action: &str = action.into();
+            query = query.filter(audit_log::audit_log_entry_type.eq(action));
+        }
+
+        let entries = query
+            .order_by(audit_log::audit_log_entry_id.desc())
+            .limit(limit.unwrap_or(DEFAULT_AUDIT_LOG_LIMIT))
+            .load::<AuditEntry>(&*self.conn.lock())?;
+
+        Ok(entries)
+    }
+}
+
+impl_async_transaction!(AuditManager);
+
+impl Debug for AuditManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuditManager")
+            .field("conn", &"PgConnection { .. }")
+            .finish()
+    }
+}
@@ -0,0 +1,111 @@
+/*
+ * audit/models.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::schema::audit_log;
+use crate::StdResult;
+use deepwell_core::prelude::*;
+use std::convert::TryFrom;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AuditAction {
+    ViewPage,
+    AddPage,
+    EditPage,
+    EditPageTags,
+    RenamePage,
+    RemovePage,
+    RestorePage,
+    UndoRevision,
+    RevertPage,
+    SetRating,
+    RemoveRating,
+    AddPageLock,
+    RemovePageLock,
+}
+
+impl Into<&'static str> for AuditAction {
+    // The "enum name" used to store in the database
+    // Cannot change without a migration
+    fn into(self) -> &'static str {
+        use self::AuditAction::*;
+
+        match self {
+            ViewPage => "view_page",
+            AddPage => "add_page",
+            EditPage => "edit_page_content",
+            EditPageTags => "edit_page_tags",
+            RenamePage => "rename_page",
+            RemovePage => "remove_page",
+            RestorePage => "restore_page",
+            UndoRevision => "undo_revision",
+            RevertPage => "revert_page",
+            SetRating => "set_rating",
+            RemoveRating => "remove_rating",
+            AddPageLock => "add_page_lock",
+            RemovePageLock => "remove_page_lock",
+        }
+    }
+}
+
+impl TryFrom<&'_ str> for AuditAction {
+    type Error = ();
+
+    fn try_from(value: &str) -> StdResult<Self, ()> {
+        use self::AuditAction::*;
+
+        let action = match value {
+            "view_page" => ViewPage,
+            "add_page" => AddPage,
+            "edit_page_content" => EditPage,
+            "edit_page_tags" => EditPageTags,
+            "rename_page" => RenamePage,
+            "remove_page" => RemovePage,
+            "restore_page" => RestorePage,
+            "undo_revision" => UndoRevision,
+            "revert_page" => RevertPage,
+            "set_rating" => SetRating,
+            "remove_rating" => RemoveRating,
+            "add_page_lock" => AddPageLock,
+            "remove_page_lock" => RemovePageLock,
+            _ => return Err(()),
+        };
+
+        Ok(action)
+    }
+}
+
+/// Which audit log entries to retrieve.
+/// Each field which is set narrows the results.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AuditFilter {
+    pub wiki_id: Option<WikiId>,
+    pub user_id: Option<UserId>,
+    pub action: Option<AuditAction>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "audit_log"]
+pub struct NewAuditEntry<'a> {
+    pub audit_log_entry_type: &'a str,
+    pub wiki_id: i64,
+    pub user_id: Option<i64>,
+    pub data: &'a serde_json::Value,
+}
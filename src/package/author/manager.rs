@@ -20,7 +20,7 @@
 
 use super::{AuthorType, NewAuthor};
 use crate::manager_prelude::*;
-use crate::schema::authors;
+use crate::schema::{authors, pages};
 use crate::utils::rows_to_result;
 use std::convert::TryFrom;
 
@@ -57,11 +57,11 @@ impl Author {
 }
 
 pub struct AuthorManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
 }
 
 impl AuthorManager {
-    pub fn new(conn: &Arc<PgConnection>) -> Self {
+    pub fn new(conn: &Arc<SharedConnection>) -> Self {
         debug!("Creating author-manager service");
 
         let conn = Arc::clone(conn);
@@ -74,13 +74,49 @@ impl AuthorManager {
         let id: i64 = page_id.into();
         let result = authors::table
             .filter(authors::dsl::page_id.eq(id))
-            .order_by(authors::dsl::written_at.asc())
-            .order_by(authors::dsl::user_id.asc())
-            .load::<Author>(&*self.conn)?;
+            .order_by((authors::dsl::author_type.asc(), authors::dsl::user_id.asc()))
+            .load::<Author>(&*self.conn.lock())?;
 
         Ok(result)
     }
 
+    pub async fn get_pages_by_author(
+        &self,
+        user_id: UserId,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<PageId>> {
+        info!(
+            "Getting pages authored by user ID {} (from {:?} to {:?})",
+            user_id, from, to,
+        );
+
+        // The written_at column is never NULL (it defaults to the insertion date),
+        // so every authorship has a date to compare against the bounds.
+        let id: i64 = user_id.into();
+        let mut query = authors::table
+            .inner_join(pages::table)
+            .filter(authors::dsl::user_id.eq(id))
+            .filter(pages::dsl::deleted_at.is_null())
+            .select(authors::dsl::page_id)
+            .distinct()
+            .into_boxed();
+
+        if let Some(from) = from {
+            query = query.filter(authors::dsl::written_at.ge(from));
+        }
+
+        if let Some(to) = to {
+            query = query.filter(authors::dsl::written_at.le(to));
+        }
+
+        let page_ids = query
+            .order_by(authors::dsl::page_id.asc())
+            .load::<PageId>(&*self.conn.lock())?;
+
+        Ok(page_ids)
+    }
+
     pub async fn add(
         &self,
         page_id: PageId,
@@ -109,11 +145,57 @@ impl AuthorManager {
             ))
             .do_update()
             .set(&model)
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(())
     }
 
+    pub async fn change_type(
+        &self,
+        page_id: PageId,
+        user_id: UserId,
+        from: AuthorType,
+        to: AuthorType,
+    ) -> Result<bool> {
+        use self::authors::dsl;
+
+        info!(
+            "Changing author type for page ID {} / user ID {}: {:?} -> {:?}",
+            page_id, user_id, from, to,
+        );
+
+        let page_id: i64 = page_id.into();
+        let user_id: i64 = user_id.into();
+        let from: &str = from.into();
+        let to: &str = to.into();
+
+        self.transaction(async {
+            let condition = dsl::authors
+                .filter(dsl::page_id.eq(page_id))
+                .filter(dsl::user_id.eq(user_id));
+
+            // Can't change to an authorship that already exists
+            if from != to {
+                let existing = condition
+                    .filter(dsl::author_type.eq(to))
+                    .select(dsl::author_type)
+                    .first::<String>(&*self.conn.lock())
+                    .optional()?;
+
+                if existing.is_some() {
+                    return Err(Error::Conflict);
+                }
+            }
+
+            let rows = diesel::update(condition.filter(dsl::author_type.eq(from)))
+                .set(dsl::author_type.eq(to))
+                .execute(&*self.conn.lock())?;
+
+            Ok(rows_to_result(rows))
+        })
+        .await
+    }
+
     pub async fn remove(
         &self,
         page_id: PageId,
@@ -132,7 +214,7 @@ impl AuthorManager {
             .filter(authors::dsl::page_id.eq(page_id))
             .filter(authors::dsl::user_id.eq(user_id))
             .filter(authors::dsl::author_type.eq(author_type))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(rows_to_result(rows))
     }
@@ -20,15 +20,45 @@
 
 use super::models::NewPageLock;
 use crate::manager_prelude::*;
-use crate::schema::page_locks;
+use crate::schema::{page_locks, pages, wiki_settings};
+
+#[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
+pub struct PageLock {
+    page_id: PageId,
+    user_id: UserId,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl PageLock {
+    #[inline]
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    #[inline]
+    pub fn user_id(&self) -> UserId {
+        self.user_id
+    }
+
+    #[inline]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    #[inline]
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
 
 pub struct LockManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
 }
 
 impl LockManager {
     #[inline]
-    pub fn new(conn: &Arc<PgConnection>) -> Self {
+    pub fn new(conn: &Arc<SharedConnection>) -> Self {
         debug!("Creating lock-manager service");
 
         let conn = Arc::clone(conn);
@@ -42,7 +72,7 @@ impl LockManager {
 
         let rows = diesel::delete(page_locks::table)
             .filter(page_locks::dsl::locked_until.lt(now))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(rows)
     }
@@ -57,7 +87,7 @@ impl LockManager {
         let result = page_locks::table
             .filter(page_locks::dsl::page_id.eq(id))
             .select(page_locks::dsl::user_id)
-            .first::<UserId>(&*self.conn)
+            .first::<UserId>(&*self.conn.lock())
             .optional()?;
 
         match result {
@@ -67,6 +97,27 @@ impl LockManager {
         }
     }
 
+    pub async fn get_lock(&self, page_id: PageId) -> Result<Option<PageLock>> {
+        use diesel::dsl::now;
+
+        debug!("Getting page lock for page ID {}", page_id);
+
+        let id: i64 = page_id.into();
+        let lock = page_locks::table
+            .filter(page_locks::dsl::page_id.eq(id))
+            .filter(page_locks::dsl::locked_until.ge(now))
+            .select((
+                page_locks::dsl::page_id,
+                page_locks::dsl::user_id,
+                page_locks::dsl::created_at,
+                page_locks::dsl::locked_until,
+            ))
+            .first::<PageLock>(&*self.conn.lock())
+            .optional()?;
+
+        Ok(lock)
+    }
+
     pub async fn add(
         &self,
         page_id: PageId,
@@ -86,7 +137,7 @@ impl LockManager {
 
         diesel::insert_into(page_locks::table)
             .values(&model)
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(())
     }
@@ -103,20 +154,59 @@ impl LockManager {
         );
 
         let locked_until = Utc::now() + lock_duration;
+        let page_id: i64 = page_id.into();
+        let user_id: i64 = user_id.into();
         let rows = diesel::update(page_locks::table)
+            .filter(page_locks::dsl::page_id.eq(page_id))
+            .filter(page_locks::dsl::user_id.eq(user_id))
             .set(page_locks::dsl::locked_until.eq(locked_until))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         row_check(rows)
     }
 
+    /// Extends a page lock by the wiki's lock duration, starting from now.
+    /// Returns `PageLocked` if the lock is held by a different user.
+    pub async fn refresh(&self, page_id: PageId, user_id: UserId) -> Result<()> {
+        debug!(
+            "Refreshing page lock for page ID {} by user ID {}",
+            page_id, user_id,
+        );
+
+        self.transaction(async {
+            let id: i64 = page_id.into();
+            let holder = page_locks::table
+                .filter(page_locks::dsl::page_id.eq(id))
+                .select(page_locks::dsl::user_id)
+                .for_update()
+                .first::<UserId>(&*self.conn.lock())
+                .optional()?;
+
+            match holder {
+                None => return Err(Error::PageLockNotFound),
+                Some(id) if id != user_id => return Err(Error::PageLocked(id)),
+                Some(_) => (),
+            }
+
+            let seconds = pages::table
+                .inner_join(wiki_settings::table.on(wiki_settings::wiki_id.eq(pages::wiki_id)))
+                .filter(pages::page_id.eq(id))
+                .select(wiki_settings::page_lock_duration)
+                .first::<i16>(&*self.conn.lock())?;
+
+            let lock_duration = chrono::Duration::seconds(i64::from(seconds));
+            self.update(page_id, user_id, lock_duration).await
+        })
+        .await
+    }
+
     pub async fn remove(&self, page_id: PageId) -> Result<()> {
         debug!("Removing page lock for page ID {}", page_id);
 
         let id: i64 = page_id.into();
         let rows = diesel::delete(page_locks::table)
             .filter(page_locks::dsl::page_id.eq(id))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         row_check(rows)
     }
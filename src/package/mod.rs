@@ -18,6 +18,7 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+pub mod audit;
 pub mod author;
 pub mod lock;
 pub mod page;
@@ -26,4 +27,5 @@ pub mod rating;
 pub mod revision;
 pub mod session;
 pub mod user;
+pub mod watch;
 pub mod wiki;
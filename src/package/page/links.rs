@@ -0,0 +1,92 @@
+/*
+ * page/links.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeSet;
+use wikidot_normalize::normalize;
+
+/// Finds all the pages referenced by `[[[slug]]]`-style links in the given content.
+///
+/// Labels (`[[[slug|label]]]`), anchors, and the new-window marker (`*`) are
+/// stripped, and the resulting slugs are normalized. External links are ignored.
+pub fn extract_links(content: &str) -> BTreeSet<String> {
+    let mut links = BTreeSet::new();
+    let mut remaining = content;
+
+    while let Some(start) = remaining.find("[[[") {
+        remaining = &remaining[start + 3..];
+
+        let end = match remaining.find("]]]") {
+            Some(end) => end,
+            None => break,
+        };
+
+        let inner = &remaining[..end];
+        remaining = &remaining[end + 3..];
+
+        let target = inner.split('|').next().unwrap_or("");
+        let target = target.split('#').next().unwrap_or("");
+        let target = target
+            .trim()
+            .trim_start_matches('*')
+            .trim_start_matches('/');
+
+        if target.is_empty() || target.contains("://") {
+            continue;
+        }
+
+        let mut slug = String::from(target);
+        normalize(&mut slug);
+
+        if !slug.is_empty() {
+            links.insert(slug);
+        }
+    }
+
+    links
+}
+
+#[test]
+fn links() {
+    macro_rules! check {
+        ($input:expr, $expected:expr) => {{
+            let expected: &[&str] = &$expected;
+            let actual = extract_links($input);
+            let actual: Vec<&str> = actual.iter().map(|s| s.as_str()).collect();
+
+            assert_eq!(actual, expected, "Extracted links don't match");
+        }};
+    }
+
+    check!("", []);
+    check!("no links here", []);
+    check!("[[[scp-001]]]", ["scp-001"]);
+    check!(
+        "see [[[SCP-001 | the first]]] and [[[scp-002]]]",
+        ["scp-001", "scp-002"]
+    );
+    check!(
+        "[[[*component:image-block#usage|Image]]]",
+        ["component:image-block"]
+    );
+    check!("[[[scp-001]]] [[[scp-001|again]]]", ["scp-001"]);
+    check!("[[[https://example.com/|external]]]", []);
+    check!("[[[unterminated", []);
+    check!("[[[|empty]]]", []);
+}
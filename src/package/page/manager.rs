@@ -18,16 +18,27 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::{ChangeType, NewPage, NewRevision, NewTagChange, UpdatePage};
+use super::links::extract_links;
+use super::patch::apply_patch;
+use super::{
+    ChangeType, NewPage, NewPageLink, NewPageViews, NewRevision, NewTagChange, NewTitleChange,
+    PageOrder, Revision, TagChange, TagChangeEntry, TimelineEntry, UpdatePage,
+};
 use crate::manager_prelude::*;
 use crate::package::revision::{CommitInfo, RevisionStore};
-use crate::schema::{pages, revisions, tag_history};
+use crate::schema::{
+    page_links, page_title_history, page_views, pages, revisions, tag_history, users,
+    wiki_settings, wikis,
+};
 use async_std::fs;
-use async_std::sync::RwLockReadGuard;
+use diesel::pg::expression::dsl::any;
 use either::*;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::str;
+use std::sync::atomic::{AtomicU64, Ordering};
+use wikidot_normalize::is_normal;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct PageCommit<'a> {
@@ -37,33 +48,36 @@ pub struct PageCommit<'a> {
     pub user: &'a User,
 }
 
-#[derive(Debug)]
-struct ReadGuard<'a> {
-    guard: RwLockReadGuard<'a, HashMap<WikiId, RevisionStore>>,
-    wiki_id: WikiId,
-}
+/// A hook run on page contents before they are committed.
+///
+/// Returning `Err` rejects the change with the given reason,
+/// otherwise the returned contents are what is stored.
+pub type ContentFilter = Arc<dyn Fn(&[u8]) -> StdResult<Cow<[u8]>, String> + Send + Sync>;
 
-impl ReadGuard<'_> {
-    fn get(&self) -> Result<&RevisionStore> {
-        match self.guard.get(&self.wiki_id) {
-            Some(store) => Ok(store),
-            None => {
-                error!("No revision store found for wiki ID {}", self.wiki_id);
-                Err(Error::WikiNotFound)
-            }
-        }
-    }
-}
+/// A reasonable limit on the size of page contents, 2 MiB.
+pub const DEFAULT_MAX_CONTENT_BYTES: usize = 2 * 1024 * 1024;
 
 pub struct PageManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
     directory: PathBuf,
     stores: RwLock<HashMap<WikiId, RevisionStore>>,
+    allow_empty: bool,
+    commit_prefix: Option<String>,
+    content_filter: Option<ContentFilter>,
+    max_content_bytes: usize,
+    commits: AtomicU64,
 }
 
 impl PageManager {
     #[inline]
-    pub fn new(conn: &Arc<PgConnection>, directory: PathBuf) -> Self {
+    pub fn new(
+        conn: &Arc<SharedConnection>,
+        directory: PathBuf,
+        allow_empty: bool,
+        commit_prefix: Option<String>,
+        content_filter: Option<ContentFilter>,
+        max_content_bytes: usize,
+    ) -> Self {
         debug!("Creating page-manager service");
 
         let conn = Arc::clone(conn);
@@ -72,9 +86,74 @@ impl PageManager {
             conn,
             directory,
             stores: RwLock::new(HashMap::new()),
+            allow_empty,
+            commit_prefix,
+            content_filter,
+            max_content_bytes,
+            commits: AtomicU64::new(0),
+        }
+    }
+
+    /// Runs the content filter, then checks that the resulting contents are not too large.
+    fn filter_content<'a>(&self, content: &'a str) -> Result<Cow<'a, str>> {
+        let content = self.run_content_filter(content)?;
+
+        if content.len() > self.max_content_bytes {
+            warn!(
+                "Refusing page content of {} bytes (maximum {})",
+                content.len(),
+                self.max_content_bytes,
+            );
+
+            return Err(Error::ContentTooLarge(
+                content.len(),
+                self.max_content_bytes,
+            ));
+        }
+
+        Ok(content)
+    }
+
+    fn run_content_filter<'a>(&self, content: &'a str) -> Result<Cow<'a, str>> {
+        let filter = match self.content_filter {
+            Some(ref filter) => filter,
+            None => return Ok(Cow::Borrowed(content)),
+        };
+
+        let invalid_utf8 =
+            || Error::ContentRejected(String::from("filtered content is not valid UTF-8"));
+
+        match filter(content.as_bytes()) {
+            Ok(Cow::Borrowed(bytes)) => {
+                let content = str::from_utf8(bytes).map_err(|_| invalid_utf8())?;
+
+                Ok(Cow::Borrowed(content))
+            }
+            Ok(Cow::Owned(bytes)) => {
+                let content = String::from_utf8(bytes).map_err(|_| invalid_utf8())?;
+
+                Ok(Cow::Owned(content))
+            }
+            Err(reason) => {
+                warn!("Page content rejected by filter: {}", reason);
+
+                Err(Error::ContentRejected(reason))
+            }
         }
     }
 
+    /// Returns the number of commits made by this manager since it was created.
+    #[inline]
+    pub fn commit_count(&self) -> u64 {
+        self.commits.load(Ordering::Relaxed)
+    }
+
+    /// Records a commit, only called once it has been made in the repository.
+    #[inline]
+    fn count_commit(&self) {
+        self.commits.fetch_add(1, Ordering::Relaxed);
+    }
+
     fn commit_data(
         &self,
         wiki_id: WikiId,
@@ -82,13 +161,18 @@ impl PageManager {
         user_id: UserId,
         change_type: ChangeType,
     ) -> String {
-        format!(
+        let message = format!(
             "User ID {} {} page ID {} on wiki ID {}",
             user_id,
             change_type.verb(),
             page_id,
             wiki_id,
-        )
+        );
+
+        match self.commit_prefix {
+            Some(ref prefix) => format!("{} {}", prefix, message),
+            None => message,
+        }
     }
 
     pub async fn add_store(&self, wiki: &Wiki) -> Result<()> {
@@ -104,12 +188,142 @@ impl PageManager {
         Ok(())
     }
 
-    async fn store(&self, wiki_id: WikiId) -> ReadGuard<'_> {
+    /// Moves the wiki's repository to match its new slug.
+    pub async fn move_store(&self, wiki: &Wiki, old_slug: &str) -> Result<()> {
+        let old_repo = self.directory.join(old_slug);
+        let new_repo = self.directory.join(wiki.slug());
+
+        info!(
+            "Moving repository for wiki ID {} from {} to {}",
+            wiki.id(),
+            old_repo.display(),
+            new_repo.display(),
+        );
+
+        // Renaming can replace an empty directory, so check first
+        if new_repo.exists() {
+            return Err(Error::WikiExists);
+        }
+
+        // The map's lock is never held across an await,
+        // so the rename is done synchronously while it is held.
+        let store = {
+            let mut guard = self.stores.write().await;
+            std::fs::rename(&old_repo, &new_repo)?;
+
+            // Inactive wikis stay unloaded
+            if wiki.is_active() {
+                let store = RevisionStore::new(new_repo, wiki.domain());
+                guard.insert(wiki.id(), store.clone());
+                Some(store)
+            } else {
+                guard.remove(&wiki.id());
+                None
+            }
+        };
+
+        if let Some(store) = store {
+            store.check_ready().await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_repositories(&self) -> Result<Vec<PathBuf>> {
+        info!("Listing repositories in {}", self.directory.display());
+
+        let mut paths = Vec::new();
+        let mut entries = fs::read_dir(&self.directory).await?;
+
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+
+            if entry.file_type().await?.is_dir() {
+                paths.push(entry.path().into());
+            }
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Gets a handle to the wiki's revision store, loading it if needed.
+    ///
+    /// The map is only locked while the handle is cloned, so no guard is held across an await.
+    async fn store(&self, wiki_id: WikiId) -> Result<RevisionStore> {
         trace!("Getting revision store for wiki ID {}", wiki_id);
 
-        let guard = self.stores.read().await;
+        let store = self.stores.read().await.get(&wiki_id).cloned();
+        let store = match store {
+            Some(store) => Some(store),
+            None => {
+                // Not loaded yet, the wiki may still exist in the database
+                self.load_store(wiki_id).await?;
+                self.stores.read().await.get(&wiki_id).cloned()
+            }
+        };
+
+        match store {
+            Some(store) if store.is_ready() => Ok(store),
+            Some(_) => {
+                error!("Revision store for wiki ID {} is not ready", wiki_id);
+                Err(Error::WikiStoreUnavailable(wiki_id))
+            }
+            None => {
+                error!("No revision store found for wiki ID {}", wiki_id);
+                Err(Error::WikiNotFound)
+            }
+        }
+    }
+
+    async fn load_store(&self, wiki_id: WikiId) -> Result<()> {
+        debug!("Loading revision store for wiki ID {}", wiki_id);
+
+        let id: i64 = wiki_id.into();
+        let wiki = wikis::table
+            .find(id)
+            .first::<Wiki>(&*self.conn.lock())
+            .optional()?;
+
+        let wiki = match wiki {
+            Some(wiki) => wiki,
+            None => {
+                warn!("No wiki found for ID {}, cannot load store", wiki_id);
+                return Ok(());
+            }
+        };
+
+        if !wiki.is_active() {
+            warn!("Wiki ID {} is inactive, refusing to load store", wiki_id);
+            return Err(Error::WikiInactive);
+        }
+
+        // Registered even if unusable, so the problem is reported rather than obscure git errors.
+        // A missing repository is not recreated, since its history would no longer match.
+        let repo = self.directory.join(wiki.slug());
+        let store = RevisionStore::new(&repo, wiki.domain());
+
+        if repo.is_dir() {
+            store.check_ready().await;
+        } else {
+            error!(
+                "Repository for wiki ID {} missing at {}",
+                wiki_id,
+                repo.display(),
+            );
+        }
+
+        let mut guard = self.stores.write().await;
+
+        // Another task may have loaded it in the meantime
+        guard.entry(wiki_id).or_insert(store);
+
+        Ok(())
+    }
 
-        ReadGuard { guard, wiki_id }
+    pub async fn unload_store(&self, wiki_id: WikiId) {
+        let mut guard = self.stores.write().await;
+        guard.remove(&wiki_id);
     }
 
     async fn raw_commit(
@@ -121,32 +335,151 @@ impl PageManager {
     ) -> Result<GitHash> {
         trace!("Committing content to repository");
 
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
         let hash = store.commit(slug, content, info).await?;
+        self.count_commit();
+
         Ok(hash)
     }
 
+    async fn update_links(&self, page_id: PageId, content: &str) -> Result<()> {
+        debug!("Updating outgoing links for page ID {}", page_id);
+
+        self.clear_links(page_id).await?;
+
+        let id: i64 = page_id.into();
+        let links = extract_links(content);
+        let models = links
+            .iter()
+            .map(|slug| NewPageLink {
+                page_id: id,
+                to_slug: slug,
+            })
+            .collect::<Vec<_>>();
+
+        if !models.is_empty() {
+            trace!("Inserting {} links into page_links table", models.len());
+            diesel::insert_into(page_links::table)
+                .values(&models)
+                .execute(&*self.conn.lock())?;
+        }
+
+        Ok(())
+    }
+
+    async fn clear_links(&self, page_id: PageId) -> Result<()> {
+        debug!("Clearing outgoing links for page ID {}", page_id);
+
+        let id: i64 = page_id.into();
+        diesel::delete(page_links::table.filter(page_links::page_id.eq(id)))
+            .execute(&*self.conn.lock())?;
+
+        Ok(())
+    }
+
+    async fn record_title(&self, page_id: PageId, revision_id: RevisionId) -> Result<()> {
+        debug!(
+            "Recording title for page ID {} at revision ID {}",
+            page_id, revision_id,
+        );
+
+        let id: i64 = page_id.into();
+        let (title, alt_title) = pages::table
+            .find(id)
+            .select((pages::dsl::title, pages::dsl::alt_title))
+            .first::<(String, Option<String>)>(&*self.conn.lock())?;
+
+        let model = NewTitleChange {
+            revision_id: revision_id.into(),
+            page_id: id,
+            title: &title,
+            alt_title: alt_title.as_deref(),
+        };
+
+        trace!("Inserting {:?} into page_title_history table", &model);
+        diesel::insert_into(page_title_history::table)
+            .values(&model)
+            .execute(&*self.conn.lock())?;
+
+        Ok(())
+    }
+
+    async fn check_active(&self, wiki_id: WikiId) -> Result<()> {
+        trace!("Checking if wiki ID {} is active", wiki_id);
+
+        // A missing wiki is left to the query itself, which finds nothing
+        let id: i64 = wiki_id.into();
+        let deleted_at = wikis::table
+            .find(id)
+            .select(wikis::dsl::deleted_at)
+            .first::<Option<DateTime<Utc>>>(&*self.conn.lock())
+            .optional()?;
+
+        match deleted_at {
+            Some(Some(_)) => Err(Error::WikiInactive),
+            _ => Ok(()),
+        }
+    }
+
+    async fn check_page_active(&self, page_id: PageId) -> Result<()> {
+        match self.get_wiki_for_page(page_id).await? {
+            Some(wiki_id) => self.check_active(wiki_id).await,
+            None => Ok(()),
+        }
+    }
+
     pub async fn get_page_id(&self, wiki_id: WikiId, slug: &str) -> Result<Option<PageId>> {
         debug!("Getting page id in wiki ID {} for slug '{}'", wiki_id, slug);
 
+        self.check_active(wiki_id).await?;
+
         let wiki_id: i64 = wiki_id.into();
         let page_id = pages::table
             .filter(pages::dsl::wiki_id.eq(wiki_id))
             .filter(pages::dsl::slug.eq(slug))
             .select(pages::dsl::page_id)
-            .first::<PageId>(&*self.conn)
+            .first::<PageId>(&*self.conn.lock())
             .optional()?;
 
         Ok(page_id)
     }
 
+    pub async fn get_wiki_for_page(&self, page_id: PageId) -> Result<Option<WikiId>> {
+        debug!("Getting wiki ID for page ID {}", page_id);
+
+        let id: i64 = page_id.into();
+        let wiki_id = pages::table
+            .find(id)
+            .select(pages::dsl::wiki_id)
+            .first::<WikiId>(&*self.conn.lock())
+            .optional()?;
+
+        Ok(wiki_id)
+    }
+
+    async fn check_reserved(&self, wiki_id: WikiId, slug: &str) -> Result<()> {
+        trace!("Checking for reserved slug");
+
+        let id: i64 = wiki_id.into();
+        let reserved_slugs = wiki_settings::table
+            .find(id)
+            .select(wiki_settings::dsl::reserved_slugs)
+            .first::<Vec<String>>(&*self.conn.lock())?;
+
+        if reserved_slugs.iter().any(|reserved| reserved == slug) {
+            return Err(Error::SlugReserved(String::from(slug)));
+        }
+
+        Ok(())
+    }
+
     pub async fn create(
         &self,
         commit: PageCommit<'_>,
         content: &str,
         title: &str,
         alt_title: Option<&str>,
+        privileged: bool,
     ) -> Result<(PageId, RevisionId)> {
         info!("Creating page {:?} with title '{}'", commit, title);
 
@@ -157,6 +490,12 @@ impl PageManager {
             user,
         } = commit;
 
+        let content = &*self.filter_content(content)?;
+        if !self.allow_empty && content.trim().is_empty() {
+            warn!("Refusing to create page with empty content");
+            return Err(Error::EmptyPage);
+        }
+
         self.transaction(async {
             let model = NewPage {
                 wiki_id: wiki_id.into(),
@@ -165,6 +504,10 @@ impl PageManager {
                 alt_title,
             };
 
+            if !privileged {
+                self.check_reserved(wiki_id, slug).await?;
+            }
+
             trace!("Checking for existing page");
             if self.get_page_id(wiki_id, slug).await?.is_some() {
                 return Err(Error::PageExists);
@@ -174,7 +517,9 @@ impl PageManager {
             let page_id = diesel::insert_into(pages::table)
                 .values(&model)
                 .returning(pages::dsl::page_id)
-                .get_result::<PageId>(&*self.conn)?;
+                .get_result::<PageId>(&*self.conn.lock())?;
+
+            self.update_links(page_id, content).await?;
 
             let user_id = user.id();
             let change_type = ChangeType::Create;
@@ -192,13 +537,16 @@ impl PageManager {
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor: false,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
+
+            self.record_title(page_id, revision_id).await?;
 
             Ok((page_id, revision_id))
         })
@@ -212,8 +560,9 @@ impl PageManager {
         content: Option<&str>,
         title: Option<&str>,
         alt_title: Option<Nullable<&str>>,
+        minor: bool,
     ) -> Result<RevisionId> {
-        info!("Committing change to page {:?}", commit);
+        info!("Committing change to page {:?} (minor: {})", commit, minor);
 
         let PageCommit {
             wiki_id,
@@ -222,6 +571,12 @@ impl PageManager {
             user,
         } = commit;
 
+        let content = match content {
+            Some(content) => Some(self.filter_content(content)?),
+            None => None,
+        };
+        let content = content.as_deref();
+
         self.transaction(async {
             let model = UpdatePage {
                 slug: None,
@@ -237,7 +592,11 @@ impl PageManager {
                 let id: i64 = page_id.into();
                 diesel::update(dsl::pages.filter(dsl::page_id.eq(id)))
                     .set(&model)
-                    .execute(&*self.conn)?;
+                    .execute(&*self.conn.lock())?;
+            }
+
+            if let Some(content) = content {
+                self.update_links(page_id, content).await?;
             }
 
             let user_id = user.id();
@@ -256,19 +615,78 @@ impl PageManager {
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
+
+            if title.is_some() || alt_title.is_some() {
+                self.record_title(page_id, revision_id).await?;
+            }
 
             Ok(revision_id)
         })
         .await
     }
 
+    pub async fn apply_patch(
+        &self,
+        commit: PageCommit<'_>,
+        page_id: PageId,
+        patch: &[u8],
+    ) -> Result<RevisionId> {
+        info!("Applying {}-byte patch to page {:?}", patch.len(), commit,);
+
+        let patch = match str::from_utf8(patch) {
+            Ok(patch) => patch,
+            Err(_) => return Err(Error::PatchFailed(String::from("patch is not valid UTF-8"))),
+        };
+
+        let current = self
+            .get_page_contents(commit.wiki_id, commit.slug)
+            .await?
+            .ok_or(Error::PageNotFound)?;
+
+        let content = apply_patch(&current, patch)?;
+
+        self.commit(commit, page_id, Some(&content), None, None, false)
+            .await
+    }
+
+    /// Checks whether a page could be renamed to the given slug, without changing anything.
+    /// Deleted pages don't block the slug, but a live page or a file in the repository does.
+    pub async fn can_rename(&self, wiki_id: WikiId, new_slug: &str) -> Result<bool> {
+        info!(
+            "Checking if slug '{}' in wiki ID {} is free for a rename",
+            new_slug, wiki_id,
+        );
+
+        if !is_normal(new_slug, false) {
+            return Err(Error::InvalidSlug(String::from(new_slug)));
+        }
+
+        if self.check_page(wiki_id, new_slug).await? {
+            debug!("Slug is used by an existing page");
+            return Ok(false);
+        }
+
+        let store = self.store(wiki_id).await?;
+
+        if store.page_exists(new_slug).await? {
+            warn!(
+                "Slug '{}' is free, but a file exists in the repository",
+                new_slug
+            );
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
     pub async fn rename(
         &self,
         wiki_id: WikiId,
@@ -284,6 +702,8 @@ impl PageManager {
         );
 
         self.transaction(async {
+            self.check_reserved(wiki_id, new_slug).await?;
+
             let model = UpdatePage {
                 slug: Some(new_slug),
                 title: None,
@@ -297,9 +717,12 @@ impl PageManager {
                 let id: i64 = page_id.into();
                 diesel::update(dsl::pages.filter(dsl::page_id.eq(id)))
                     .set(&model)
-                    .execute(&*self.conn)?;
+                    .execute(&*self.conn.lock())?;
             }
 
+            // Outgoing links are keyed by page ID, so they follow the rename.
+            // Links to the old slug are kept, as they now point to a missing page.
+
             let user_id = user.id();
             let change_type = ChangeType::Rename;
 
@@ -310,9 +733,9 @@ impl PageManager {
             };
 
             trace!("Committing rename to repository");
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
             let hash = store.rename(old_slug, new_slug, info).await?;
+            self.count_commit();
 
             let model = NewRevision {
                 page_id: page_id.into(),
@@ -320,13 +743,14 @@ impl PageManager {
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor: false,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
 
             Ok(revision_id)
         })
@@ -353,9 +777,11 @@ impl PageManager {
                 let id: i64 = page_id.into();
                 diesel::update(dsl::pages.filter(dsl::page_id.eq(id)))
                     .set(pages::dsl::deleted_at.eq(now))
-                    .execute(&*self.conn)?;
+                    .execute(&*self.conn.lock())?;
             }
 
+            self.clear_links(page_id).await?;
+
             let user_id = user.id();
             let change_type = ChangeType::Delete;
 
@@ -366,27 +792,29 @@ impl PageManager {
             };
 
             trace!("Committing removal to repository");
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
             let result = store.remove(slug, info).await?;
             let hash = match result {
                 Some(hash) => hash,
                 None => return Err(Error::PageNotFound),
             };
 
+            self.count_commit();
+
             let model = NewRevision {
                 page_id: page_id.into(),
                 user_id: user_id.into(),
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor: false,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
 
             Ok(revision_id)
         })
@@ -408,6 +836,8 @@ impl PageManager {
         } = commit;
 
         self.transaction(async {
+            self.check_reserved(wiki_id, slug).await?;
+
             if self.check_page(wiki_id, slug).await? {
                 return Err(Error::PageExists);
             }
@@ -425,7 +855,7 @@ impl PageManager {
                         .filter(pages::deleted_at.is_not_null())
                         .order_by(pages::created_at.desc())
                         .select(pages::dsl::page_id)
-                        .first::<PageId>(&*self.conn)
+                        .first::<PageId>(&*self.conn.lock())
                         .optional()?;
 
                     // If none, found nothing to restore
@@ -444,7 +874,7 @@ impl PageManager {
                 let result = pages::table
                     .find(id)
                     .select((pages::dsl::wiki_id, pages::dsl::slug))
-                    .first::<(WikiId, String)>(&*self.conn)
+                    .first::<(WikiId, String)>(&*self.conn.lock())
                     .optional()?;
 
                 let (wiki_id, old_slug) = match result {
@@ -462,7 +892,7 @@ impl PageManager {
                     .filter(revisions::dsl::change_type.ne(change_type))
                     .order_by(revisions::dsl::revision_id.desc())
                     .select(revisions::dsl::git_commit)
-                    .first::<String>(&*self.conn)?;
+                    .first::<String>(&*self.conn.lock())?;
 
                 let hash = GitHash::from_checked(raw_hash);
 
@@ -478,9 +908,13 @@ impl PageManager {
 
             trace!("Committing page restoration to repository");
 
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
             let hash = store.restore(slug, &old_slug, &hash, info).await?;
+            self.count_commit();
+
+            if let Some(content) = store.get_page(slug).await? {
+                self.update_links(page_id, &content).await?;
+            }
 
             let model = NewRevision {
                 page_id: page_id.into(),
@@ -488,13 +922,14 @@ impl PageManager {
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor: false,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
 
             trace!("Removing deletion marker from pages table");
             {
@@ -505,7 +940,7 @@ impl PageManager {
 
                 diesel::update(dsl::pages.filter(dsl::page_id.eq(id)))
                     .set(dsl::deleted_at.eq(null))
-                    .execute(&*self.conn)?;
+                    .execute(&*self.conn.lock())?;
             }
 
             Ok(revision_id)
@@ -513,6 +948,12 @@ impl PageManager {
         .await
     }
 
+    pub async fn is_ancestor(&self, wiki_id: WikiId, hash: &GitHash) -> Result<bool> {
+        let store = self.store(wiki_id).await?;
+
+        store.is_ancestor(hash).await
+    }
+
     pub async fn undo(
         &self,
         commit: PageCommit<'_>,
@@ -538,21 +979,7 @@ impl PageManager {
             let user_id = user.id();
 
             // Verify the revision is for the specified page
-            {
-                let hash: &str = hash.as_ref().as_ref();
-                let result = revisions::table
-                    .filter(revisions::dsl::git_commit.eq(hash))
-                    .select(revisions::dsl::page_id)
-                    .first::<i64>(&*self.conn)
-                    .optional()?;
-
-                let page_id: i64 = page_id.into();
-                match result {
-                    Some(id) if id == page_id => (),
-                    Some(_) => return Err(Error::RevisionPageMismatch),
-                    None => return Err(Error::PageNotFound),
-                }
-            }
+            self.check_revision_page(page_id, &hash).await?;
 
             // Run undo method in RevisionStore
             let change_type = ChangeType::Undo;
@@ -562,9 +989,19 @@ impl PageManager {
                 message: &commit,
             };
 
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
+
+            // Commits from an abandoned history can't be cleanly reverted
+            if !store.is_ancestor(&hash).await? {
+                return Err(Error::RevisionNotInHistory);
+            }
+
             let hash = store.undo(&hash, info).await?;
+            self.count_commit();
+
+            if let Some(content) = store.get_page(slug).await? {
+                self.update_links(page_id, &content).await?;
+            }
 
             // Insert new revision into database
             let model = NewRevision {
@@ -573,13 +1010,96 @@ impl PageManager {
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor: false,
+            };
+
+            trace!("Inserting revision {:?} into revisions table", &model);
+            let revision_id = diesel::insert_into(revisions::table)
+                .values(&model)
+                .returning(revisions::dsl::revision_id)
+                .get_result::<RevisionId>(&*self.conn.lock())?;
+
+            Ok(revision_id)
+        })
+        .await
+    }
+
+    pub async fn revert(
+        &self,
+        commit: PageCommit<'_>,
+        page_id: PageId,
+        revision: Either<RevisionId, &GitHash>,
+    ) -> Result<RevisionId> {
+        info!(
+            "Reverting page ID {} to revision {:?} for {:?}",
+            page_id, revision, commit,
+        );
+
+        let PageCommit {
+            wiki_id,
+            slug,
+            message,
+            user,
+        } = commit;
+
+        self.transaction(async {
+            // Deleted pages must be restored before being reverted
+            let id: i64 = page_id.into();
+            let deleted_at = pages::table
+                .find(id)
+                .select(pages::dsl::deleted_at)
+                .first::<Option<DateTime<Utc>>>(&*self.conn.lock())
+                .optional()?;
+
+            match deleted_at {
+                Some(None) => (),
+                Some(Some(_)) | None => return Err(Error::PageNotFound),
+            }
+
+            let hash = self.commit_hash(revision).await?;
+            let user_id = user.id();
+
+            // Verify the revision is for the specified page
+            self.check_revision_page(page_id, &hash).await?;
+
+            // Get the contents at that revision.
+            // This will be missing if the page has since been renamed.
+            let content = self
+                .store(wiki_id)
+                .await?
+                .get_page_version(slug, &hash)
+                .await?
+                .ok_or(Error::PageNotFound)?;
+
+            // Old contents are checked the same as any other edit
+            let content = &*self.filter_content(&content)?;
+            self.update_links(page_id, content).await?;
+
+            // Commit contents as a new revision
+            let change_type = ChangeType::Restore;
+            let commit = self.commit_data(wiki_id, page_id, user_id, change_type);
+            let info = CommitInfo {
+                username: user.name(),
+                message: &commit,
+            };
+
+            let hash = self.raw_commit(wiki_id, slug, Some(content), info).await?;
+            let model = NewRevision {
+                page_id: page_id.into(),
+                user_id: user_id.into(),
+                message,
+                git_commit: hash.as_ref(),
+                change_type: change_type.into(),
+                minor: false,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
+
+            self.record_title(page_id, revision_id).await?;
 
             Ok(revision_id)
         })
@@ -591,6 +1111,7 @@ impl PageManager {
         commit: PageCommit<'_>,
         page_id: PageId,
         tags: &mut [&str],
+        expected_tags: Option<&[&str]>,
     ) -> Result<Option<RevisionId>> {
         info!("Modifying tags for {:?}: {:?}", commit, tags);
 
@@ -608,9 +1129,19 @@ impl PageManager {
                 pages::table
                     .find(id)
                     .select(pages::dsl::tags)
-                    .first::<Vec<String>>(&*self.conn)?
+                    .for_update()
+                    .first::<Vec<String>>(&*self.conn.lock())?
             };
 
+            // Check the tags haven't been changed from under us
+            if let Some(expected_tags) = expected_tags {
+                let (added_tags, removed_tags) = tag_diff(&current_tags, expected_tags);
+
+                if !added_tags.is_empty() || !removed_tags.is_empty() {
+                    return Err(Error::Conflict);
+                }
+            }
+
             let (added_tags, removed_tags) = tag_diff(&current_tags, tags);
 
             // Ignore if no changes have been made.
@@ -628,9 +1159,9 @@ impl PageManager {
                 message: &commit,
             };
 
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
             let hash = store.empty_commit(info).await?;
+            self.count_commit();
 
             let model = NewRevision {
                 page_id: page_id.into(),
@@ -638,13 +1169,14 @@ impl PageManager {
                 message,
                 git_commit: hash.as_ref(),
                 change_type: change_type.into(),
+                minor: false,
             };
 
             trace!("Inserting revision {:?} into revisions table", &model);
             let revision_id = diesel::insert_into(revisions::table)
                 .values(&model)
                 .returning(revisions::dsl::revision_id)
-                .get_result::<RevisionId>(&*self.conn)?;
+                .get_result::<RevisionId>(&*self.conn.lock())?;
 
             let model = NewTagChange {
                 revision_id: revision_id.into(),
@@ -655,51 +1187,408 @@ impl PageManager {
             trace!("Inserting tag change {:?} into tag history table", &model);
             diesel::insert_into(tag_history::table)
                 .values(&model)
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
 
             tags.sort();
 
             trace!("Updating tags for page");
+            let id: i64 = page_id.into();
             diesel::update(pages::table)
+                .filter(pages::dsl::page_id.eq(id))
                 .set(pages::dsl::tags.eq(&*tags))
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
 
             Ok(Some(revision_id))
         })
         .await
     }
 
-    pub async fn get_pages_with_tags(&self, wiki_id: WikiId, tags: &[&str]) -> Result<Vec<Page>> {
-        info!("Getting all pages which contain tags: {:?}", tags);
-
-        if tags.is_empty() {
+    pub async fn get_tags_batch(
+        &self,
+        wiki_id: WikiId,
+        after: Option<PageId>,
+        limit: i64,
+    ) -> Result<Vec<(PageId, String, Vec<String>)>> {
+        info!(
+            "Getting tags for up to {} pages in wiki ID {} after page ID {:?}",
+            limit, wiki_id, after,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let after: i64 = after.map(|id| id.into()).unwrap_or(0);
+        let pages = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::page_id.gt(after))
+            .filter(pages::deleted_at.is_null())
+            .select((pages::page_id, pages::slug, pages::tags))
+            .order_by(pages::page_id.asc())
+            .limit(limit)
+            .get_results::<(PageId, String, Vec<String>)>(&*self.conn.lock())?;
+
+        Ok(pages)
+    }
+
+    pub async fn get_tags_with_last_use(
+        &self,
+        wiki_id: WikiId,
+    ) -> Result<Vec<(String, DateTime<Utc>)>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::Timestamptz;
+
+        info!("Getting all tags with last use in wiki ID {}", wiki_id);
+
+        self.check_active(wiki_id).await?;
+
+        let last_edit = sql::<Timestamptz>(
+            "(SELECT MAX(revisions.created_at) \
+             FROM revisions WHERE revisions.page_id = pages.page_id)",
+        );
+
+        let id: i64 = wiki_id.into();
+        let pages = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::deleted_at.is_null())
+            .select((pages::tags, last_edit))
+            .get_results::<(Vec<String>, DateTime<Utc>)>(&*self.conn.lock())?;
+
+        // A tag was last used by the most recently edited page carrying it
+        let mut last_use = HashMap::new();
+        for (tags, edited_at) in pages {
+            for tag in tags {
+                let entry = last_use.entry(tag).or_insert(edited_at);
+                if *entry < edited_at {
+                    *entry = edited_at;
+                }
+            }
+        }
+
+        // Least recently used first
+        let mut tags: Vec<_> = last_use.into_iter().collect();
+        tags.sort_by(|(tag_a, time_a), (tag_b, time_b)| {
+            time_a.cmp(time_b).then_with(|| tag_a.cmp(tag_b))
+        });
+
+        Ok(tags)
+    }
+
+    pub async fn get_pages_with_tags(
+        &self,
+        wiki_id: WikiId,
+        tags: &[&str],
+        order: PageOrder,
+    ) -> Result<Vec<Page>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::BigInt;
+
+        info!(
+            "Getting all pages which contain tags: {:?} (order {:?})",
+            tags, order,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        if tags.is_empty() {
             warn!("Tag list was empty, returning nothing");
             return Ok(Vec::new());
         }
 
+        // Diesel cannot express a correlated aggregate subquery,
+        // so the page score is computed with a raw SQL fragment.
+        let score = sql::<BigInt>(
+            "(SELECT COALESCE(SUM(ratings.rating), 0) \
+             FROM ratings WHERE ratings.page_id = pages.page_id)",
+        );
+
         let id: i64 = wiki_id.into();
-        let pages = pages::table
+        let query = pages::table
             .filter(pages::wiki_id.eq(id))
             .filter(pages::tags.contains(tags))
             .filter(pages::deleted_at.is_null())
-            .get_results::<Page>(&*self.conn)?;
+            .into_boxed();
+
+        let query = match order {
+            PageOrder::SlugAsc => query.order_by(pages::slug.asc()),
+            PageOrder::TitleAsc => query.order_by(pages::title.asc()),
+            PageOrder::CreatedDesc => query.order_by(pages::created_at.desc()),
+            PageOrder::ScoreDesc => query.order_by(score.desc()),
+        };
+
+        // Use slug as a tiebreaker for stable results
+        let pages = query
+            .then_order_by(pages::slug.asc())
+            .get_results::<Page>(&*self.conn.lock())?;
+
+        Ok(pages)
+    }
+
+    pub async fn get_pages_by_tag_query(
+        &self,
+        wiki_id: WikiId,
+        required: &[&str],
+        excluded: &[&str],
+        any_of: &[&str],
+    ) -> Result<Vec<Page>> {
+        use diesel::dsl::not;
+
+        info!(
+            "Getting pages by tag query in wiki ID {}: required {:?}, excluded {:?}, any of {:?}",
+            wiki_id, required, excluded, any_of,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let mut query = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::deleted_at.is_null())
+            .into_boxed();
+
+        // Empty lists impose no restriction
+        if !required.is_empty() {
+            query = query.filter(pages::tags.contains(required));
+        }
+
+        if !excluded.is_empty() {
+            query = query.filter(not(pages::tags.overlaps_with(excluded)));
+        }
+
+        if !any_of.is_empty() {
+            query = query.filter(pages::tags.overlaps_with(any_of));
+        }
+
+        let pages = query
+            .order_by(pages::slug.asc())
+            .get_results::<Page>(&*self.conn.lock())?;
+
+        Ok(pages)
+    }
+
+    pub async fn count_pages_with_tags(&self, wiki_id: WikiId, tags: &[&str]) -> Result<i64> {
+        info!("Counting all pages which contain tags: {:?}", tags);
+
+        self.check_active(wiki_id).await?;
+
+        if tags.is_empty() {
+            warn!("Tag list was empty, returning zero");
+            return Ok(0);
+        }
+
+        let id: i64 = wiki_id.into();
+        let count = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::tags.contains(tags))
+            .filter(pages::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&*self.conn.lock())?;
+
+        Ok(count)
+    }
+
+    pub async fn get_backlinks(&self, wiki_id: WikiId, slug: &str) -> Result<Vec<Page>> {
+        info!(
+            "Getting pages linking to wiki ID {}, slug {}",
+            wiki_id, slug
+        );
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let pages = pages::table
+            .inner_join(page_links::table)
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::deleted_at.is_null())
+            .filter(page_links::to_slug.eq(slug))
+            .select(pages::all_columns)
+            .order_by(pages::slug.asc())
+            .get_results::<Page>(&*self.conn.lock())?;
 
         Ok(pages)
     }
 
+    pub async fn get_pages_by_recent_edit(
+        &self,
+        wiki_id: WikiId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Page>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::Timestamptz;
+
+        info!(
+            "Getting recently edited pages in wiki ID {} (limit {}, offset {})",
+            wiki_id, limit, offset,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        // Same as with scores, the latest revision time is a correlated aggregate
+        let last_edit = sql::<Timestamptz>(
+            "(SELECT MAX(revisions.created_at) \
+             FROM revisions WHERE revisions.page_id = pages.page_id)",
+        );
+
+        let id: i64 = wiki_id.into();
+        let pages = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::deleted_at.is_null())
+            .order_by(last_edit.desc())
+            .then_order_by(pages::slug.asc())
+            .limit(limit as i64)
+            .offset(offset as i64)
+            .get_results::<Page>(&*self.conn.lock())?;
+
+        Ok(pages)
+    }
+
+    pub async fn get_all_slugs(
+        &self,
+        wiki_id: WikiId,
+        include_deleted: bool,
+    ) -> Result<Vec<String>> {
+        info!(
+            "Getting all page slugs in wiki ID {} (include deleted: {})",
+            wiki_id, include_deleted,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let mut query = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .select(pages::slug)
+            .into_boxed();
+
+        if !include_deleted {
+            query = query.filter(pages::deleted_at.is_null());
+        }
+
+        let slugs = query
+            .order_by(pages::slug.asc())
+            .get_results::<String>(&*self.conn.lock())?;
+
+        Ok(slugs)
+    }
+
+    pub async fn count_pages(&self, wiki_id: WikiId) -> Result<i64> {
+        debug!("Counting pages in wiki ID {}", wiki_id);
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let count = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&*self.conn.lock())?;
+
+        Ok(count)
+    }
+
+    pub async fn get_first_page(&self, wiki_id: WikiId) -> Result<Option<Page>> {
+        debug!("Getting earliest created page in wiki ID {}", wiki_id);
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let page = pages::table
+            .filter(pages::wiki_id.eq(id))
+            .filter(pages::deleted_at.is_null())
+            .order_by(pages::created_at.asc())
+            .then_order_by(pages::page_id.asc())
+            .first::<Page>(&*self.conn.lock())
+            .optional()?;
+
+        Ok(page)
+    }
+
+    pub async fn increment_views(&self, page_id: PageId) -> Result<i64> {
+        debug!("Incrementing view count for page ID {}", page_id);
+
+        // Rows are created on first view, deleted pages are still counted
+        let model = NewPageViews {
+            page_id: page_id.into(),
+            views: 1,
+        };
+
+        let views = diesel::insert_into(page_views::table)
+            .values(&model)
+            .on_conflict(page_views::dsl::page_id)
+            .do_update()
+            .set(page_views::dsl::views.eq(page_views::dsl::views + 1))
+            .returning(page_views::dsl::views)
+            .get_result::<i64>(&*self.conn.lock())?;
+
+        Ok(views)
+    }
+
+    pub async fn increment_views_many(&self, page_ids: &[PageId]) -> Result<()> {
+        use diesel::pg::upsert::excluded;
+
+        info!("Incrementing view counts for page IDs {:?}", page_ids);
+
+        // Collapse repeated views, a row can only be upserted once per statement
+        let mut counts = HashMap::new();
+        for page_id in page_ids.iter().copied() {
+            *counts.entry(page_id).or_insert(0) += 1;
+        }
+
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        let models = counts
+            .into_iter()
+            .map(|(page_id, views)| NewPageViews {
+                page_id: page_id.into(),
+                views,
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(page_views::table)
+            .values(&models)
+            .on_conflict(page_views::dsl::page_id)
+            .do_update()
+            .set(
+                page_views::dsl::views
+                    .eq(page_views::dsl::views + excluded(page_views::dsl::views)),
+            )
+            .execute(&*self.conn.lock())?;
+
+        Ok(())
+    }
+
+    pub async fn get_views(&self, page_id: PageId) -> Result<i64> {
+        debug!("Getting view count for page ID {}", page_id);
+
+        self.check_page_active(page_id).await?;
+
+        let id: i64 = page_id.into();
+        let views = page_views::table
+            .find(id)
+            .select(page_views::dsl::views)
+            .first::<i64>(&*self.conn.lock())
+            .optional()?;
+
+        Ok(views.unwrap_or(0))
+    }
+
     pub async fn check_page(&self, wiki_id: WikiId, slug: &str) -> Result<bool> {
         info!(
             "Checking if page for exists in wiki ID {}, slug {} exists",
             wiki_id, slug,
         );
 
+        self.check_active(wiki_id).await?;
+
         let id: i64 = wiki_id.into();
         let result = pages::table
             .filter(pages::wiki_id.eq(id))
             .filter(pages::slug.eq(slug))
             .filter(pages::deleted_at.is_null())
             .select(pages::page_id)
-            .first::<PageId>(&*self.conn)
+            .first::<PageId>(&*self.conn.lock())
             .optional()?;
 
         Ok(result.is_some())
@@ -708,39 +1597,134 @@ impl PageManager {
     pub async fn get_page(&self, wiki_id: WikiId, slug: &str) -> Result<Option<Page>> {
         info!("Getting page for wiki ID {}, slug {}", wiki_id, slug);
 
+        self.check_active(wiki_id).await?;
+
         let id: i64 = wiki_id.into();
         let page = pages::table
             .filter(pages::wiki_id.eq(id))
             .filter(pages::slug.eq(slug))
             .filter(pages::deleted_at.is_null())
-            .first::<Page>(&*self.conn)
+            .first::<Page>(&*self.conn.lock())
             .optional()?;
 
         Ok(page)
     }
 
+    pub async fn get_pages(&self, wiki_id: WikiId, slugs: &[&str]) -> Result<Vec<Option<Page>>> {
+        info!("Getting pages for wiki ID {}, slugs {:?}", wiki_id, slugs);
+
+        self.check_active(wiki_id).await?;
+
+        // Load
+        let result = {
+            let id: i64 = wiki_id.into();
+            pages::table
+                .filter(pages::wiki_id.eq(id))
+                .filter(pages::slug.eq(any(slugs)))
+                .filter(pages::deleted_at.is_null())
+                .load::<Page>(&*self.conn.lock())?
+                .into_iter()
+                .map(|page| (String::from(page.slug()), page))
+                .collect::<HashMap<_, _>>()
+        };
+
+        // Put in the same order as the slugs, with nones where needed
+        let mut pages = Vec::with_capacity(slugs.len());
+        for slug in slugs {
+            pages.push(result.get(*slug).cloned());
+        }
+
+        Ok(pages)
+    }
+
     pub async fn get_page_by_id(&self, page_id: PageId) -> Result<Option<Page>> {
         info!("Getting page for page ID {}", page_id);
 
         let id: i64 = page_id.into();
         let page = pages::table
             .find(id)
-            .first::<Page>(&*self.conn)
+            .first::<Page>(&*self.conn.lock())
             .optional()?;
 
+        if let Some(ref page) = page {
+            self.check_active(page.wiki_id()).await?;
+        }
+
         Ok(page)
     }
 
     pub async fn get_page_contents(&self, wiki_id: WikiId, slug: &str) -> Result<Option<String>> {
         info!("Getting contents for wiki ID {}, slug {}", wiki_id, slug);
 
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
         let contents = store.get_page(slug).await?;
 
         Ok(contents)
     }
 
+    pub async fn get_all_contents(&self, wiki_id: WikiId) -> Result<Vec<(String, Box<[u8]>)>> {
+        info!("Getting contents for all pages in wiki ID {}", wiki_id);
+
+        let slugs = {
+            let id: i64 = wiki_id.into();
+
+            pages::table
+                .filter(pages::wiki_id.eq(id))
+                .filter(pages::deleted_at.is_null())
+                .order_by(pages::slug.asc())
+                .select(pages::slug)
+                .get_results::<String>(&*self.conn.lock())?
+        };
+
+        let store = self.store(wiki_id).await?;
+        let contents = store.get_pages(&slugs).await?;
+
+        Ok(contents)
+    }
+
+    pub async fn get_page_contents_if_changed(
+        &self,
+        wiki_id: WikiId,
+        slug: &str,
+        known_hash: &GitHash,
+    ) -> Result<Option<Option<String>>> {
+        info!(
+            "Getting contents for wiki ID {}, slug {} if changed from {}",
+            wiki_id, slug, known_hash,
+        );
+
+        let current_hash = self.get_current_hash(wiki_id, slug).await?;
+        if current_hash.as_ref() == Some(known_hash) {
+            debug!("Page has not been modified, not reading contents");
+            return Ok(None);
+        }
+
+        let contents = self.get_page_contents(wiki_id, slug).await?;
+        Ok(Some(contents))
+    }
+
+    pub async fn get_current_hash(&self, wiki_id: WikiId, slug: &str) -> Result<Option<GitHash>> {
+        debug!(
+            "Getting current commit for wiki ID {}, slug {}",
+            wiki_id, slug,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        let wiki_id: i64 = wiki_id.into();
+        let raw_hash = revisions::table
+            .inner_join(pages::table)
+            .filter(pages::dsl::wiki_id.eq(wiki_id))
+            .filter(pages::dsl::slug.eq(slug))
+            .filter(pages::dsl::deleted_at.is_null())
+            .order_by(revisions::dsl::revision_id.desc())
+            .select(revisions::dsl::git_commit)
+            .first::<String>(&*self.conn.lock())
+            .optional()?;
+
+        Ok(raw_hash.map(GitHash::from_checked))
+    }
+
     async fn get_last_hash(&self, page_id: PageId) -> Result<Option<(WikiId, String, GitHash)>> {
         debug!("Getting last commit for page ID {}", page_id);
 
@@ -748,7 +1732,7 @@ impl PageManager {
         let result = pages::table
             .find(id)
             .select((pages::dsl::wiki_id, pages::dsl::slug))
-            .first::<(WikiId, String)>(&*self.conn)
+            .first::<(WikiId, String)>(&*self.conn.lock())
             .optional()?;
 
         let (wiki_id, slug) = match result {
@@ -760,7 +1744,7 @@ impl PageManager {
             .filter(revisions::dsl::page_id.eq(id))
             .order_by(revisions::dsl::revision_id.desc())
             .select(revisions::dsl::git_commit)
-            .first::<String>(&*self.conn)?;
+            .first::<String>(&*self.conn.lock())?;
 
         let hash = GitHash::from_checked(raw_hash);
 
@@ -777,19 +1761,25 @@ impl PageManager {
                 None => return Ok(None),
             };
 
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
             let contents = store.get_page_version(&slug, &hash).await?;
             Ok(contents)
         })
         .await
     }
 
+    pub async fn repair(&self, wiki_id: WikiId, slug: &str) -> Result<bool> {
+        info!("Repairing page in wiki ID {}, slug {}", wiki_id, slug);
+
+        let store = self.store(wiki_id).await?;
+        let repaired = store.repair(slug).await?;
+        Ok(repaired)
+    }
+
     pub async fn get_blame(&self, wiki_id: WikiId, slug: &str) -> Result<Option<Blame>> {
         info!("Getting blame for wiki ID {}, slug {}", wiki_id, slug);
 
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
         let blame = store.get_blame(slug, None).await?;
         Ok(blame)
     }
@@ -804,8 +1794,7 @@ impl PageManager {
                 None => return Ok(None),
             };
 
-            let guard = self.store(wiki_id).await;
-            let store = guard.get()?;
+            let store = self.store(wiki_id).await?;
             let blame = store.get_blame(&slug, Some(&hash)).await?;
             Ok(blame)
         })
@@ -819,7 +1808,7 @@ impl PageManager {
         let result = revisions::table
             .find(id)
             .select(revisions::dsl::git_commit)
-            .first::<String>(&*self.conn)
+            .first::<String>(&*self.conn.lock())
             .optional()?;
 
         match result {
@@ -828,6 +1817,283 @@ impl PageManager {
         }
     }
 
+    pub async fn get_revision(&self, revision_id: RevisionId) -> Result<Option<Revision>> {
+        info!("Getting revision ID {}", revision_id);
+
+        let id: i64 = revision_id.into();
+        let revision = revisions::table
+            .find(id)
+            .first::<Revision>(&*self.conn.lock())
+            .optional()?;
+
+        if let Some(ref revision) = revision {
+            self.check_page_active(revision.page_id()).await?;
+        }
+
+        Ok(revision)
+    }
+
+    pub async fn get_revisions(
+        &self,
+        page_id: PageId,
+        limit: usize,
+        offset: usize,
+        include_minor: bool,
+    ) -> Result<Vec<Revision>> {
+        info!(
+            "Getting revisions for page ID {} (limit {}, offset {}, include minor: {})",
+            page_id, limit, offset, include_minor,
+        );
+
+        self.check_page_active(page_id).await?;
+
+        // Deleted pages keep their revisions, so no check on the page is done
+        let id: i64 = page_id.into();
+        let mut query = revisions::table
+            .filter(revisions::dsl::page_id.eq(id))
+            .order_by(revisions::dsl::revision_id.desc())
+            .limit(limit as i64)
+            .offset(offset as i64)
+            .into_boxed();
+
+        if !include_minor {
+            query = query.filter(revisions::dsl::minor.eq(false));
+        }
+
+        let revisions = query.load::<Revision>(&*self.conn.lock())?;
+        Ok(revisions)
+    }
+
+    pub async fn get_change_type_counts(
+        &self,
+        wiki_id: WikiId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<HashMap<ChangeType, i64>> {
+        info!(
+            "Counting change types for wiki ID {} between {} and {}",
+            wiki_id, from, to,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        // Diesel doesn't have a stable GROUP BY, so the counting is done here.
+        // Only the change type column is loaded, which keeps each row small.
+        let id: i64 = wiki_id.into();
+        let change_types = revisions::table
+            .inner_join(pages::table)
+            .filter(pages::dsl::wiki_id.eq(id))
+            .filter(revisions::dsl::created_at.ge(from))
+            .filter(revisions::dsl::created_at.lt(to))
+            .select(revisions::dsl::change_type)
+            .load::<String>(&*self.conn.lock())?;
+
+        let mut counts = HashMap::new();
+        for value in change_types {
+            let change_type =
+                ChangeType::try_from(value.as_str()).expect("change type in database invalid");
+
+            *counts.entry(change_type).or_insert(0) += 1;
+        }
+
+        Ok(counts)
+    }
+
+    pub async fn get_tag_change(&self, revision_id: RevisionId) -> Result<Option<TagChange>> {
+        info!("Getting tag change for revision ID {}", revision_id);
+
+        let id: i64 = revision_id.into();
+        let change = tag_history::table
+            .inner_join(revisions::table)
+            .filter(tag_history::dsl::revision_id.eq(id))
+            .select((tag_history::all_columns, revisions::dsl::page_id))
+            .first::<(TagChange, PageId)>(&*self.conn.lock())
+            .optional()?;
+
+        match change {
+            Some((change, page_id)) => {
+                self.check_page_active(page_id).await?;
+                Ok(Some(change))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_tag_history(&self, page_id: PageId) -> Result<Vec<TagChangeEntry>> {
+        info!("Getting tag history for page ID {}", page_id);
+
+        self.check_page_active(page_id).await?;
+
+        let id: i64 = page_id.into();
+        let history = tag_history::table
+            .inner_join(revisions::table)
+            .filter(revisions::dsl::page_id.eq(id))
+            .order_by(tag_history::dsl::revision_id.asc())
+            .select((
+                tag_history::dsl::revision_id,
+                revisions::dsl::user_id,
+                revisions::dsl::created_at,
+                tag_history::dsl::added_tags,
+                tag_history::dsl::removed_tags,
+            ))
+            .load::<TagChangeEntry>(&*self.conn.lock())?;
+
+        Ok(history)
+    }
+
+    pub async fn get_title_history(
+        &self,
+        page_id: PageId,
+    ) -> Result<Vec<(String, Option<String>, DateTime<Utc>)>> {
+        info!("Getting title history for page ID {}", page_id);
+
+        self.check_page_active(page_id).await?;
+
+        let id: i64 = page_id.into();
+        let history = page_title_history::table
+            .filter(page_title_history::dsl::page_id.eq(id))
+            .order_by(page_title_history::dsl::revision_id.asc())
+            .select((
+                page_title_history::dsl::title,
+                page_title_history::dsl::alt_title,
+                page_title_history::dsl::created_at,
+            ))
+            .load::<(String, Option<String>, DateTime<Utc>)>(&*self.conn.lock())?;
+
+        Ok(history)
+    }
+
+    pub async fn get_revisions_with_authors(
+        &self,
+        page_id: PageId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<(Revision, Option<String>)>> {
+        info!(
+            "Getting revisions with authors for page ID {} (limit {}, offset {})",
+            page_id, limit, offset,
+        );
+
+        self.check_page_active(page_id).await?;
+
+        let id: i64 = page_id.into();
+        let revisions = revisions::table
+            .left_join(users::table)
+            .filter(revisions::dsl::page_id.eq(id))
+            .order_by(revisions::dsl::revision_id.desc())
+            .limit(limit as i64)
+            .offset(offset as i64)
+            .select((revisions::all_columns, users::dsl::name.nullable()))
+            .load::<(Revision, Option<String>)>(&*self.conn.lock())?;
+
+        Ok(revisions)
+    }
+
+    pub async fn get_wiki_revisions(
+        &self,
+        wiki_id: WikiId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<(Revision, String)>> {
+        info!(
+            "Getting revisions for wiki ID {} (limit {}, offset {})",
+            wiki_id, limit, offset,
+        );
+
+        self.check_active(wiki_id).await?;
+
+        let id: i64 = wiki_id.into();
+        let revisions = revisions::table
+            .inner_join(pages::table)
+            .filter(pages::dsl::wiki_id.eq(id))
+            .order_by(revisions::dsl::revision_id.desc())
+            .limit(limit as i64)
+            .offset(offset as i64)
+            .select((revisions::all_columns, pages::dsl::slug))
+            .load::<(Revision, String)>(&*self.conn.lock())?;
+
+        Ok(revisions)
+    }
+
+    pub async fn get_timeline(&self, page_id: PageId) -> Result<Vec<TimelineEntry>> {
+        info!("Getting timeline for page ID {}", page_id);
+
+        let (wiki_id, slug, revisions) = {
+            let id: i64 = page_id.into();
+            let result = pages::table
+                .find(id)
+                .select((pages::dsl::wiki_id, pages::dsl::slug))
+                .first::<(WikiId, String)>(&*self.conn.lock())
+                .optional()?;
+
+            let (wiki_id, slug) = match result {
+                Some(data) => data,
+                None => return Err(Error::PageNotFound),
+            };
+
+            let revisions = revisions::table
+                .left_join(users::table)
+                .filter(revisions::dsl::page_id.eq(id))
+                .order_by(revisions::dsl::revision_id.asc())
+                .select((revisions::all_columns, users::dsl::name.nullable()))
+                .load::<(Revision, Option<String>)>(&*self.conn.lock())?;
+
+            (wiki_id, slug, revisions)
+        };
+
+        let store = self.store(wiki_id).await?;
+
+        // Only content changes are diffed, against the last content change.
+        // Diffs use the current slug, so revisions from before a rename
+        // will be counted as entirely new content.
+        let mut last_hash = None;
+        let mut timeline = Vec::with_capacity(revisions.len());
+
+        for (revision, author) in revisions {
+            let change_type = revision.change_type();
+            let diff_stat = if change_type.changes_content() {
+                let hash = revision.git_commit();
+                let stat = store
+                    .get_diff_stat(&slug, last_hash.as_ref(), &hash)
+                    .await?;
+
+                last_hash = Some(hash);
+                Some(stat)
+            } else {
+                None
+            };
+
+            timeline.push(TimelineEntry {
+                revision_id: revision.id(),
+                change_type,
+                author,
+                created_at: revision.created_at(),
+                message: String::from(revision.message()),
+                diff_stat,
+            });
+        }
+
+        Ok(timeline)
+    }
+
+    async fn check_revision_page(&self, page_id: PageId, hash: &GitHash) -> Result<()> {
+        debug!("Checking that commit {} is for page ID {}", hash, page_id);
+
+        let hash: &str = hash.as_ref();
+        let result = revisions::table
+            .filter(revisions::dsl::git_commit.eq(hash))
+            .select(revisions::dsl::page_id)
+            .first::<i64>(&*self.conn.lock())
+            .optional()?;
+
+        let page_id: i64 = page_id.into();
+        match result {
+            Some(id) if id == page_id => Ok(()),
+            Some(_) => Err(Error::RevisionPageMismatch),
+            None => Err(Error::PageNotFound),
+        }
+    }
+
     #[allow(clippy::needless_lifetimes)] // clippy doesn't realize explicit lifetimes are necessary here..
     async fn commit_hash<'a>(
         &self,
@@ -855,12 +2121,59 @@ impl PageManager {
 
         let hash = self.commit_hash(revision).await?;
 
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
         let contents = store.get_page_version(slug, &hash).await?;
         Ok(contents)
     }
 
+    pub async fn get_page_version_by_revision(
+        &self,
+        revision_id: RevisionId,
+    ) -> Result<Option<String>> {
+        info!("Getting page version for revision ID {}", revision_id);
+
+        self.transaction(async {
+            let id: i64 = revision_id.into();
+            let result = revisions::table
+                .inner_join(pages::table)
+                .filter(revisions::dsl::revision_id.eq(id))
+                .select((
+                    pages::dsl::wiki_id,
+                    pages::dsl::slug,
+                    revisions::dsl::git_commit,
+                ))
+                .first::<(WikiId, String, String)>(&*self.conn.lock())
+                .optional()?;
+
+            let (wiki_id, slug, raw_hash) = match result {
+                Some(result) => result,
+                None => return Ok(None),
+            };
+
+            // Slugs aren't tracked per revision, so this uses the page's current slug
+            let hash = GitHash::from_checked(raw_hash);
+            let store = self.store(wiki_id).await?;
+            let contents = store.get_page_version(&slug, &hash).await?;
+            Ok(contents)
+        })
+        .await
+    }
+
+    pub async fn get_page_blob(
+        &self,
+        wiki_id: WikiId,
+        slug: &str,
+        revision: Either<RevisionId, &GitHash>,
+    ) -> Result<Option<(GitHash, Box<[u8]>)>> {
+        info!("Getting page blob for wiki ID {}, slug {}", wiki_id, slug);
+
+        let hash = self.commit_hash(revision).await?;
+
+        let store = self.store(wiki_id).await?;
+        let blob = store.get_blob(slug, &hash).await?;
+        Ok(blob)
+    }
+
     pub async fn get_diff(
         &self,
         wiki_id: WikiId,
@@ -874,12 +2187,62 @@ impl PageManager {
         let (first, second) = try_join!(self.commit_hash(first), self.commit_hash(second))?;
 
         // Actually get the diff from the RevisionStore
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
         let diff = store.get_diff(slug, &first, &second).await?;
         Ok(diff)
     }
 
+    pub async fn get_structured_diff(
+        &self,
+        wiki_id: WikiId,
+        slug: &str,
+        first: Either<RevisionId, &GitHash>,
+        second: Either<RevisionId, &GitHash>,
+    ) -> Result<Diff> {
+        info!(
+            "Getting structured diff for wiki ID {}, slug {}",
+            wiki_id, slug
+        );
+
+        let (first, second) = try_join!(self.commit_hash(first), self.commit_hash(second))?;
+
+        let store = self.store(wiki_id).await?;
+        let diff = store.get_structured_diff(slug, &first, &second).await?;
+        Ok(diff)
+    }
+
+    pub async fn get_cross_diff(
+        &self,
+        wiki_id: WikiId,
+        first_slug: &str,
+        first: Option<Either<RevisionId, &GitHash>>,
+        second_slug: &str,
+        second: Option<Either<RevisionId, &GitHash>>,
+    ) -> Result<String> {
+        info!(
+            "Getting diff for wiki ID {} between slugs {} and {}",
+            wiki_id, first_slug, second_slug,
+        );
+
+        // Get commits, if specified
+        let first = match first {
+            Some(revision) => Some(self.commit_hash(revision).await?),
+            None => None,
+        };
+
+        let second = match second {
+            Some(revision) => Some(self.commit_hash(revision).await?),
+            None => None,
+        };
+
+        let store = self.store(wiki_id).await?;
+        let diff = store
+            .get_cross_diff(first_slug, first.as_deref(), second_slug, second.as_deref())
+            .await?;
+
+        Ok(diff)
+    }
+
     pub async fn edit_revision(&self, revision_id: RevisionId, message: &str) -> Result<()> {
         use self::revisions::dsl;
 
@@ -888,21 +2251,19 @@ impl PageManager {
         let id: i64 = revision_id.into();
         diesel::update(dsl::revisions.filter(dsl::revision_id.eq(id)))
             .set(dsl::message.eq(message))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(())
     }
 
     pub async fn set_domain(&self, wiki_id: WikiId, new_domain: &str) -> Result<()> {
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
         store.set_domain(new_domain).await;
         Ok(())
     }
 
     pub async fn git_vacuum(&self, wiki_id: WikiId, deep: bool) -> Result<usize> {
-        let guard = self.store(wiki_id).await;
-        let store = guard.get()?;
+        let store = self.store(wiki_id).await?;
 
         if deep {
             store.vacuum_deep().await
@@ -920,6 +2281,11 @@ impl Debug for PageManager {
             .field("conn", &"PgConnection { .. }")
             .field("directory", &self.directory)
             .field("stores", &self.stores)
+            .field("allow_empty", &self.allow_empty)
+            .field("commit_prefix", &self.commit_prefix)
+            .field("content_filter", &self.content_filter.is_some())
+            .field("max_content_bytes", &self.max_content_bytes)
+            .field("commits", &self.commits)
             .finish()
     }
 }
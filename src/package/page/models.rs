@@ -18,13 +18,16 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use crate::schema::{pages, revisions, tag_history};
+use crate::package::revision::DiffStat;
+use crate::schema::{page_links, page_title_history, page_views, pages, revisions, tag_history};
 use crate::StdResult;
+use chrono::prelude::*;
+use deepwell_core::prelude::*;
 use std::convert::TryFrom;
 
 type Nullable<T> = Option<T>;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum ChangeType {
     Create,
     Modify,
@@ -36,6 +39,16 @@ pub enum ChangeType {
 }
 
 impl ChangeType {
+    /// Whether this kind of change modifies the contents of the page.
+    pub fn changes_content(self) -> bool {
+        use self::ChangeType::*;
+
+        match self {
+            Create | Modify | Restore | Undo => true,
+            Delete | Rename | Tags => false,
+        }
+    }
+
     // For git commit messages
     pub fn verb(self) -> &'static str {
         use self::ChangeType::*;
@@ -80,6 +93,7 @@ impl TryFrom<&'_ str> for ChangeType {
             "delete" => ChangeType::Delete,
             "restore" => ChangeType::Restore,
             "rename" => ChangeType::Rename,
+            "undo" => ChangeType::Undo,
             "tags" => ChangeType::Tags,
             _ => return Err(()),
         };
@@ -88,6 +102,153 @@ impl TryFrom<&'_ str> for ChangeType {
     }
 }
 
+/// The order in which pages are returned when querying multiple.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PageOrder {
+    SlugAsc,
+    TitleAsc,
+    CreatedDesc,
+    ScoreDesc,
+}
+
+impl Default for PageOrder {
+    #[inline]
+    fn default() -> Self {
+        PageOrder::SlugAsc
+    }
+}
+
+#[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    revision_id: RevisionId,
+    created_at: DateTime<Utc>,
+    page_id: PageId,
+    user_id: UserId,
+    message: String,
+    git_commit: String,
+    change_type: String,
+    minor: bool,
+}
+
+impl Revision {
+    #[inline]
+    pub fn id(&self) -> RevisionId {
+        self.revision_id
+    }
+
+    #[inline]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    #[inline]
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    #[inline]
+    pub fn user_id(&self) -> UserId {
+        self.user_id
+    }
+
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    #[inline]
+    pub fn git_commit(&self) -> GitHash {
+        GitHash::from_checked(self.git_commit.as_str())
+    }
+
+    #[inline]
+    pub fn change_type(&self) -> ChangeType {
+        let value = self.change_type.as_str();
+
+        ChangeType::try_from(value).expect("change type in database invalid")
+    }
+
+    #[inline]
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+}
+
+#[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
+pub struct TagChange {
+    revision_id: RevisionId,
+    added_tags: Vec<String>,
+    removed_tags: Vec<String>,
+}
+
+impl TagChange {
+    #[inline]
+    pub fn revision_id(&self) -> RevisionId {
+        self.revision_id
+    }
+
+    #[inline]
+    pub fn added_tags(&self) -> &[String] {
+        &self.added_tags
+    }
+
+    #[inline]
+    pub fn removed_tags(&self) -> &[String] {
+        &self.removed_tags
+    }
+}
+
+/// A change to a page's tags, along with the revision which made it.
+#[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
+pub struct TagChangeEntry {
+    revision_id: RevisionId,
+    user_id: UserId,
+    created_at: DateTime<Utc>,
+    added_tags: Vec<String>,
+    removed_tags: Vec<String>,
+}
+
+impl TagChangeEntry {
+    #[inline]
+    pub fn revision_id(&self) -> RevisionId {
+        self.revision_id
+    }
+
+    #[inline]
+    pub fn user_id(&self) -> UserId {
+        self.user_id
+    }
+
+    #[inline]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    #[inline]
+    pub fn added_tags(&self) -> &[String] {
+        &self.added_tags
+    }
+
+    #[inline]
+    pub fn removed_tags(&self) -> &[String] {
+        &self.removed_tags
+    }
+}
+
+/// A single event in a page's history, as displayed in a timeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub revision_id: RevisionId,
+    pub change_type: ChangeType,
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub message: String,
+
+    /// The lines changed since the previous content revision.
+    /// Only present for revisions which modify the page's contents.
+    pub diff_stat: Option<DiffStat>,
+}
+
 #[derive(Debug, Insertable)]
 #[table_name = "pages"]
 pub struct NewPage<'a> {
@@ -111,6 +272,13 @@ impl UpdatePage<'_> {
     }
 }
 
+#[derive(Debug, Insertable)]
+#[table_name = "page_links"]
+pub struct NewPageLink<'a> {
+    pub page_id: i64,
+    pub to_slug: &'a str,
+}
+
 #[derive(Debug, Insertable)]
 #[table_name = "revisions"]
 pub struct NewRevision<'a> {
@@ -119,6 +287,7 @@ pub struct NewRevision<'a> {
     pub message: &'a str,
     pub git_commit: &'a str,
     pub change_type: &'a str,
+    pub minor: bool,
 }
 
 #[derive(Debug, Insertable)]
@@ -128,3 +297,19 @@ pub struct NewTagChange<'a> {
     pub added_tags: &'a [&'a str],
     pub removed_tags: &'a [&'a str],
 }
+
+#[derive(Debug, Insertable)]
+#[table_name = "page_title_history"]
+pub struct NewTitleChange<'a> {
+    pub revision_id: i64,
+    pub page_id: i64,
+    pub title: &'a str,
+    pub alt_title: Option<&'a str>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "page_views"]
+pub struct NewPageViews {
+    pub page_id: i64,
+    pub views: i64,
+}
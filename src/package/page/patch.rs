@@ -0,0 +1,180 @@
+/*
+ * page/patch.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::{Error, Result};
+use std::iter::Peekable;
+use std::str::Lines;
+
+macro_rules! fail {
+    ($($arg:tt)*) => {
+        return Err(Error::PatchFailed(format!($($arg)*)))
+    };
+}
+
+/// Applies a unified diff to the given content, returning the patched result.
+///
+/// File headers are ignored, so the patch is assumed to be for this content only.
+/// Every context and removed line must match exactly, otherwise `PatchFailed` is returned.
+pub fn apply_patch(original: &str, patch: &str) -> Result<String> {
+    let source = split_lines(original);
+    let mut output = String::with_capacity(original.len() + patch.len());
+    let mut position = 0;
+    let mut lines = patch.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if !line.starts_with("@@") {
+            trace!("Skipping patch header line: {}", line);
+            continue;
+        }
+
+        let (old_start, mut old_count, mut new_count) = parse_hunk_header(line)?;
+
+        // A hunk removing nothing is positioned after its start line
+        let start = match old_count {
+            0 => old_start,
+            _ => old_start.saturating_sub(1),
+        };
+
+        if start < position || start > source.len() {
+            fail!("hunk at line {} is out of range", old_start);
+        }
+
+        source[position..start]
+            .iter()
+            .for_each(|line| output.push_str(line));
+
+        position = start;
+
+        while old_count > 0 || new_count > 0 {
+            let (kind, text) = next_hunk_line(&mut lines)?;
+
+            match kind {
+                ' ' | '-' => {
+                    if old_count == 0 {
+                        fail!("hunk at line {} is longer than its header", old_start);
+                    }
+
+                    if source.get(position) != Some(&text.as_str()) {
+                        fail!("line {} does not match", position + 1);
+                    }
+
+                    if kind == ' ' {
+                        if new_count == 0 {
+                            fail!("hunk at line {} is longer than its header", old_start);
+                        }
+
+                        output.push_str(&text);
+                        new_count -= 1;
+                    }
+
+                    position += 1;
+                    old_count -= 1;
+                }
+                '+' => {
+                    if new_count == 0 {
+                        fail!("hunk at line {} is longer than its header", old_start);
+                    }
+
+                    output.push_str(&text);
+                    new_count -= 1;
+                }
+                _ => fail!("invalid line in hunk at line {}", old_start),
+            }
+        }
+    }
+
+    source[position..]
+        .iter()
+        .for_each(|line| output.push_str(line));
+
+    Ok(output)
+}
+
+/// Splits content into lines, keeping each line's terminating newline.
+fn split_lines(content: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (idx, _) in content.match_indices('\n') {
+        lines.push(&content[start..=idx]);
+        start = idx + 1;
+    }
+
+    if start < content.len() {
+        lines.push(&content[start..]);
+    }
+
+    lines
+}
+
+/// Parses a header of the form `@@ -start,count +start,count @@`.
+/// Returns the original start line and the lengths of the old and new sides.
+fn parse_hunk_header(line: &str) -> Result<(usize, usize, usize)> {
+    fn parse_range(range: Option<&str>, prefix: char) -> Option<(usize, usize)> {
+        let range = range?;
+        if !range.starts_with(prefix) {
+            return None;
+        }
+
+        let range = &range[1..];
+        let mut parts = range.splitn(2, ',');
+        let start = parts.next()?.parse().ok()?;
+        let count = match parts.next() {
+            Some(count) => count.parse().ok()?,
+            None => 1,
+        };
+
+        Some((start, count))
+    }
+
+    let mut parts = line.split_whitespace().skip(1);
+    let old = parse_range(parts.next(), '-');
+    let new = parse_range(parts.next(), '+');
+
+    match (old, new) {
+        (Some((old_start, old_count)), Some((_, new_count))) => {
+            Ok((old_start, old_count, new_count))
+        }
+        _ => fail!("invalid hunk header '{}'", line),
+    }
+}
+
+/// Gets the next line of a hunk, with its newline restored
+/// unless it is followed by a "No newline at end of file" marker.
+fn next_hunk_line(lines: &mut Peekable<Lines>) -> Result<(char, String)> {
+    let line = match lines.next() {
+        Some(line) => line,
+        None => fail!("patch ends in the middle of a hunk"),
+    };
+
+    // Some tools strip the trailing space from empty context lines
+    let mut chars = line.chars();
+    let kind = chars.next().unwrap_or(' ');
+    let mut text = String::from(chars.as_str());
+
+    match lines.peek() {
+        Some(next) if next.starts_with('\\') => {
+            lines.next();
+        }
+        _ => text.push('\n'),
+    }
+
+    Ok((kind, text))
+}
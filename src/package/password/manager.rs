@@ -18,7 +18,7 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::{build_blacklist, check_password, new_password};
+use super::{build_blacklist, check_password, new_password, PasswordPolicy};
 use crate::manager_prelude::*;
 use crate::schema::passwords;
 use std::collections::HashSet;
@@ -91,12 +91,17 @@ impl Password {
 }
 
 pub struct PasswordManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
     blacklist: HashSet<String>,
+    policy: PasswordPolicy,
 }
 
 impl PasswordManager {
-    pub fn new(conn: &Arc<PgConnection>, blacklist: Option<&Path>) -> Result<Self> {
+    pub fn new(
+        conn: &Arc<SharedConnection>,
+        blacklist: Option<&Path>,
+        policy: PasswordPolicy,
+    ) -> Result<Self> {
         debug!("Creating password-manager service");
 
         let conn = Arc::clone(conn);
@@ -110,10 +115,14 @@ impl PasswordManager {
             }
         };
 
-        Ok(PasswordManager { conn, blacklist })
+        Ok(PasswordManager {
+            conn,
+            blacklist,
+            policy,
+        })
     }
 
-    fn verify_password(&self, password: &str) -> Result<()> {
+    fn verify_password(&self, username: &str, password: &str) -> Result<()> {
         // To avoid computation-based DOS attacks
         if password.len() > MAX_PASSWORD_LEN {
             return Err(Error::NewPasswordInvalid("password too long"));
@@ -129,11 +138,11 @@ impl PasswordManager {
             return Err(Error::NewPasswordInvalid("password is too common"));
         }
 
-        Ok(())
+        self.policy.check(username, password)
     }
 
-    pub async fn set(&self, user_id: UserId, password: &str) -> Result<()> {
-        self.verify_password(password)?;
+    pub async fn set(&self, user_id: UserId, username: &str, password: &str) -> Result<()> {
+        self.verify_password(username, password)?;
 
         new_password(user_id, password.as_bytes(), |model| {
             diesel::insert_into(passwords::table)
@@ -141,7 +150,7 @@ impl PasswordManager {
                 .on_conflict(passwords::dsl::user_id)
                 .do_update()
                 .set(&model)
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
 
             Ok(())
         })
@@ -150,6 +159,15 @@ impl PasswordManager {
         Ok(())
     }
 
+    pub async fn remove(&self, user_id: UserId) -> Result<()> {
+        info!("Removing password for user ID {}", user_id);
+
+        let id: i64 = user_id.into();
+        diesel::delete(passwords::table.find(id)).execute(&*self.conn.lock())?;
+
+        Ok(())
+    }
+
     #[inline]
     pub async fn check(&self, user_id: UserId, password: &str) -> Result<()> {
         match self.check_internal(user_id, password).await {
@@ -183,7 +201,7 @@ impl PasswordManager {
         let id: i64 = user_id.into();
         let record = passwords::table
             .find(id)
-            .first::<Password>(&*self.conn)
+            .first::<Password>(&*self.conn.lock())
             .optional()?;
 
         let record = record.ok_or(Error::AuthenticationFailed)?;
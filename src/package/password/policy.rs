@@ -0,0 +1,63 @@
+/*
+ * password/policy.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::{Error, Result};
+use std::collections::HashSet;
+
+/// Requirements new passwords must meet, in addition to the basic length and blacklist checks.
+///
+/// The default policy imposes no further requirements.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    /// The minimum number of characters in the password.
+    pub min_length: usize,
+
+    /// The minimum number of different characters in the password.
+    pub min_distinct_chars: usize,
+
+    /// Whether the password must not match the username, ignoring case.
+    pub disallow_username: bool,
+}
+
+impl PasswordPolicy {
+    pub fn check(&self, username: &str, password: &str) -> Result<()> {
+        if password.chars().count() < self.min_length {
+            return Err(Error::PasswordInsecure(format!(
+                "password must be at least {} characters",
+                self.min_length,
+            )));
+        }
+
+        if password.chars().collect::<HashSet<_>>().len() < self.min_distinct_chars {
+            return Err(Error::PasswordInsecure(format!(
+                "password must have at least {} different characters",
+                self.min_distinct_chars,
+            )));
+        }
+
+        if self.disallow_username && password.to_lowercase() == username.to_lowercase() {
+            return Err(Error::PasswordInsecure(String::from(
+                "password cannot be the same as the username",
+            )));
+        }
+
+        Ok(())
+    }
+}
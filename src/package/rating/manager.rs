@@ -20,7 +20,7 @@
 
 use super::{NewRating, NewRatingHistory};
 use crate::manager_prelude::*;
-use crate::utils::rows_to_result;
+use diesel::pg::expression::dsl::any;
 use map_vec::Map;
 
 #[derive(Serialize, Deserialize, Queryable, Debug, Clone, PartialEq, Eq)]
@@ -59,13 +59,49 @@ impl RatingHistory {
     }
 }
 
+/// Describes how a user's rating on a page was changed.
+///
+/// A new vote has no `old` value, and a removed vote has no `new` value.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RatingChanged {
+    pub page_id: PageId,
+    pub user_id: UserId,
+    pub old: Option<i16>,
+    pub new: Option<i16>,
+}
+
+/// Describes a user whose current rating on a page does not match their rating history.
+///
+/// `current` is the value in the ratings table, and `history` is the value from
+/// the latest rating history entry. Either is `None` if no vote is recorded.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RatingInconsistency {
+    pub page_id: PageId,
+    pub user_id: UserId,
+    pub current: Option<i16>,
+    pub history: Option<i16>,
+}
+
+#[derive(QueryableByName, Debug)]
+struct PageScore {
+    #[sql_type = "diesel::sql_types::BigInt"]
+    page_id: i64,
+
+    #[sql_type = "diesel::sql_types::BigInt"]
+    score: i64,
+}
+
+/// How many rows to insert per statement during bulk imports,
+/// keeping well under Postgres's limit on bind parameters.
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
 pub struct RatingManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
 }
 
 impl RatingManager {
     #[inline]
-    pub fn new(conn: &Arc<PgConnection>) -> Self {
+    pub fn new(conn: &Arc<SharedConnection>) -> Self {
         debug!("Creating rating-manager service");
 
         let conn = Arc::clone(conn);
@@ -83,7 +119,7 @@ impl RatingManager {
         // let rows = ratings::table
         //     .filter(ratings::page_id.eq(id))
         //     .select((ratings::rating, count(ratings::user_id)))
-        //     .get_results::<(i16, i64)>(&*self.conn)?;
+        //     .get_results::<(i16, i64)>(&*self.conn.lock())?;
         // ```
         //
         // However diesel does not currently support queries across
@@ -104,7 +140,7 @@ impl RatingManager {
         let ratings = ratings::table
             .filter(ratings::page_id.eq(id))
             .select(ratings::rating)
-            .get_results::<i16>(&*self.conn)?;
+            .get_results::<i16>(&*self.conn.lock())?;
 
         // Increment each rating for each occurrence
         let mut votes = Map::new();
@@ -115,7 +151,61 @@ impl RatingManager {
         Ok(Votes::new(votes))
     }
 
-    pub async fn set(&self, page_id: PageId, user_id: UserId, rating: i16) -> Result<RatingId> {
+    pub async fn top_pages(&self, wiki_id: WikiId, limit: i64) -> Result<Vec<(PageId, i64)>> {
+        use diesel::sql_types::BigInt;
+
+        info!("Getting top {} rated pages in wiki ID {}", limit, wiki_id,);
+
+        // As with get_rating(), diesel can't express this mixed aggregate query
+        let id: i64 = wiki_id.into();
+        let scores = diesel::sql_query(
+            "SELECT ratings.page_id, SUM(ratings.rating) AS score \
+             FROM ratings \
+             JOIN pages ON pages.page_id = ratings.page_id \
+             WHERE pages.wiki_id = $1 AND pages.deleted_at IS NULL \
+             GROUP BY ratings.page_id \
+             ORDER BY score DESC, ratings.page_id ASC \
+             LIMIT $2",
+        )
+        .bind::<BigInt, _>(id)
+        .bind::<BigInt, _>(limit)
+        .load::<PageScore>(&*self.conn.lock())?;
+
+        let scores = scores
+            .into_iter()
+            .map(|PageScore { page_id, score }| (PageId::from_raw(page_id), score))
+            .collect();
+
+        Ok(scores)
+    }
+
+    pub async fn get_user_ratings(
+        &self,
+        user_id: UserId,
+        page_ids: &[PageId],
+    ) -> Result<HashMap<PageId, i16>> {
+        info!(
+            "Getting ratings for user ID {} on pages {:?}",
+            user_id, page_ids
+        );
+
+        let user_id: i64 = user_id.into();
+        let page_ids: Vec<_> = page_ids.iter().map(|id| id.to_i64()).collect();
+        let ratings = ratings::table
+            .filter(ratings::user_id.eq(user_id))
+            .filter(ratings::page_id.eq(any(page_ids)))
+            .select((ratings::page_id, ratings::rating))
+            .get_results::<(PageId, i16)>(&*self.conn.lock())?;
+
+        Ok(ratings.into_iter().collect())
+    }
+
+    pub async fn set(
+        &self,
+        page_id: PageId,
+        user_id: UserId,
+        rating: i16,
+    ) -> Result<(RatingId, RatingChanged)> {
         self.transaction(async {
             let model = NewRating {
                 page_id: page_id.into(),
@@ -123,40 +213,134 @@ impl RatingManager {
                 rating,
             };
 
+            trace!("Getting existing rating");
+            let old = ratings::table
+                .find((model.page_id, model.user_id))
+                .select(ratings::dsl::rating)
+                .for_update()
+                .first::<i16>(&*self.conn.lock())
+                .optional()?;
+
             trace!("Inserting rating into rating table");
             diesel::insert_into(ratings::table)
                 .values(&model)
                 .on_conflict((ratings::dsl::page_id, ratings::dsl::user_id))
                 .do_update()
                 .set(ratings::dsl::rating.eq(rating))
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
 
             trace!("Inserting rating into rating history");
             let model = NewRatingHistory::from(model);
             let rating_id = diesel::insert_into(ratings_history::table)
                 .values(&model)
                 .returning(ratings_history::dsl::rating_id)
-                .get_result::<RatingId>(&*self.conn)?;
+                .get_result::<RatingId>(&*self.conn.lock())?;
+
+            let change = RatingChanged {
+                page_id,
+                user_id,
+                old,
+                new: Some(rating),
+            };
+
+            Ok((rating_id, change))
+        })
+        .await
+    }
+
+    pub async fn set_many(&self, ratings: &[(PageId, UserId, i16)]) -> Result<Vec<RatingId>> {
+        use diesel::pg::upsert::excluded;
+
+        info!("Setting {} ratings in bulk", ratings.len());
+
+        let models: Vec<_> = ratings
+            .iter()
+            .map(|&(page_id, user_id, rating)| NewRating {
+                page_id: page_id.into(),
+                user_id: user_id.into(),
+                rating,
+            })
+            .collect();
+
+        // An upsert can't touch the same row twice, so only the last vote per user counts
+        let mut current = HashMap::new();
+        for model in &models {
+            current.insert((model.page_id, model.user_id), model.rating);
+        }
+
+        let current: Vec<_> = current
+            .into_iter()
+            .map(|((page_id, user_id), rating)| NewRating {
+                page_id,
+                user_id,
+                rating,
+            })
+            .collect();
+
+        self.transaction(async {
+            trace!("Inserting {} ratings into rating table", current.len());
+            for chunk in current.chunks(IMPORT_CHUNK_SIZE) {
+                diesel::insert_into(ratings::table)
+                    .values(chunk)
+                    .on_conflict((ratings::dsl::page_id, ratings::dsl::user_id))
+                    .do_update()
+                    .set(ratings::dsl::rating.eq(excluded(ratings::dsl::rating)))
+                    .execute(&*self.conn.lock())?;
+            }
+
+            trace!("Inserting {} ratings into rating history", models.len());
+            let mut rating_ids = Vec::with_capacity(models.len());
+            for chunk in models.chunks(IMPORT_CHUNK_SIZE) {
+                let history: Vec<_> = chunk
+                    .iter()
+                    .map(|model| NewRatingHistory {
+                        page_id: model.page_id,
+                        user_id: model.user_id,
+                        rating: Some(model.rating),
+                    })
+                    .collect();
+
+                let ids = diesel::insert_into(ratings_history::table)
+                    .values(&history)
+                    .returning(ratings_history::dsl::rating_id)
+                    .get_results::<RatingId>(&*self.conn.lock())?;
+
+                rating_ids.extend(ids);
+            }
 
-            Ok(rating_id)
+            Ok(rating_ids)
         })
         .await
     }
 
-    pub async fn remove(&self, page_id: PageId, user_id: UserId) -> Result<Option<RatingId>> {
+    pub async fn remove(
+        &self,
+        page_id: PageId,
+        user_id: UserId,
+    ) -> Result<Option<(RatingId, RatingChanged)>> {
         self.transaction(async {
+            let change = RatingChanged {
+                page_id,
+                user_id,
+                old: None,
+                new: None,
+            };
+
             let page_id: i64 = page_id.into();
             let user_id: i64 = user_id.into();
 
             trace!("Deleting rating from rating table");
-            let rows = diesel::delete(ratings::table)
+            let old = diesel::delete(ratings::table)
                 .filter(ratings::page_id.eq(page_id))
                 .filter(ratings::user_id.eq(user_id))
-                .execute(&*self.conn)?;
+                .returning(ratings::dsl::rating)
+                .get_result::<i16>(&*self.conn.lock())
+                .optional()?;
 
-            if !rows_to_result(rows) {
-                return Ok(None);
-            }
+            let old = match old {
+                Some(old) => old,
+                None => return Ok(None),
+            };
 
             let model = NewRatingHistory {
                 page_id,
@@ -167,9 +351,14 @@ impl RatingManager {
             let rating_id = diesel::insert_into(ratings_history::table)
                 .values(&model)
                 .returning(ratings_history::dsl::rating_id)
-                .get_result::<RatingId>(&*self.conn)?;
+                .get_result::<RatingId>(&*self.conn.lock())?;
+
+            let change = RatingChanged {
+                old: Some(old),
+                ..change
+            };
 
-            Ok(Some(rating_id))
+            Ok(Some((rating_id, change)))
         })
         .await
     }
@@ -191,7 +380,39 @@ impl RatingManager {
             .filter(ratings_history::page_id.eq(page_id))
             .filter(ratings_history::user_id.eq(user_id))
             .order_by(ratings_history::created_at.asc())
-            .load::<RatingHistory>(&*self.conn)?;
+            .load::<RatingHistory>(&*self.conn.lock())?;
+
+        Ok(result)
+    }
+
+    pub async fn get_user_history(
+        &self,
+        user_id: UserId,
+        since: Option<DateTime<Utc>>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<RatingHistory>> {
+        debug!(
+            "Getting rating history for user ID {} (offset {}, limit {})",
+            user_id, offset, limit,
+        );
+
+        let id: i64 = user_id.into();
+        let mut query = ratings_history::table
+            .filter(ratings_history::user_id.eq(id))
+            .into_boxed();
+
+        if let Some(since) = since {
+            query = query.filter(ratings_history::created_at.ge(since));
+        }
+
+        // Changes made in one transaction share a timestamp, so the ID breaks ties
+        let result = query
+            .order_by(ratings_history::created_at.desc())
+            .then_order_by(ratings_history::rating_id.desc())
+            .offset(offset)
+            .limit(limit)
+            .load::<RatingHistory>(&*self.conn.lock())?;
 
         Ok(result)
     }
@@ -213,19 +434,74 @@ impl RatingManager {
             .filter(ratings_history::page_id.eq(page_id))
             .filter(ratings_history::user_id.eq(user_id))
             .order_by(ratings_history::created_at.asc())
-            .first::<RatingHistory>(&*self.conn)
+            .first::<RatingHistory>(&*self.conn.lock())
             .optional()?;
 
         Ok(result)
     }
 
+    pub async fn find_inconsistencies(&self, page_id: PageId) -> Result<Vec<RatingInconsistency>> {
+        info!(
+            "Checking rating history consistency for page ID {}",
+            page_id
+        );
+
+        let id: i64 = page_id.into();
+
+        self.transaction(async {
+            let current = ratings::table
+                .filter(ratings::page_id.eq(id))
+                .select((ratings::user_id, ratings::rating))
+                .load::<(UserId, i16)>(&*self.conn.lock())?;
+
+            let entries = ratings_history::table
+                .filter(ratings_history::page_id.eq(id))
+                .select((ratings_history::user_id, ratings_history::rating))
+                .order_by(ratings_history::created_at.asc())
+                .then_order_by(ratings_history::rating_id.asc())
+                .load::<(UserId, Option<i16>)>(&*self.conn.lock())?;
+
+            // Later entries overwrite earlier ones, leaving the latest
+            let mut history = HashMap::new();
+            for (user_id, rating) in entries {
+                history.insert(user_id, rating);
+            }
+
+            let mut inconsistencies = Vec::new();
+            let mut check = |user_id, current, history| {
+                if current != history {
+                    inconsistencies.push(RatingInconsistency {
+                        page_id,
+                        user_id,
+                        current,
+                        history,
+                    });
+                }
+            };
+
+            for (user_id, rating) in current {
+                let latest = history.remove(&user_id).flatten();
+                check(user_id, Some(rating), latest);
+            }
+
+            // Remaining users have no current rating
+            for (user_id, rating) in history {
+                check(user_id, None, rating);
+            }
+
+            inconsistencies.sort_by_key(|inconsistency| inconsistency.user_id);
+            Ok(inconsistencies)
+        })
+        .await
+    }
+
     pub async fn get_history_entry(&self, rating_id: RatingId) -> Result<Option<RatingHistory>> {
         info!("Getting rating history entry for ID {}", rating_id);
 
         let id: i64 = rating_id.into();
         let result = ratings_history::table
             .find(id)
-            .first::<RatingHistory>(&*self.conn)
+            .first::<RatingHistory>(&*self.conn.lock())
             .optional()?;
 
         Ok(result)
@@ -23,3 +23,10 @@ pub struct CommitInfo<'a> {
     pub username: &'a str,
     pub message: &'a str,
 }
+
+/// The number of lines changed in a file between two commits.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DiffStat {
+    pub added: u64,
+    pub removed: u64,
+}
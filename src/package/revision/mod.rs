@@ -25,6 +25,8 @@ mod store;
 #[cfg(test)]
 mod test;
 
-pub use self::info::CommitInfo;
-pub use self::process::{spawn, spawn_output, OwnedBytes};
+pub use self::info::{CommitInfo, DiffStat};
+pub use self::process::{
+    finish_piped, run, run_output, spawn, spawn_output, spawn_piped, OwnedBytes, DEFAULT_TIMEOUT,
+};
 pub use self::store::RevisionStore;
@@ -19,13 +19,10 @@
  */
 
 use crate::{Error, Result};
-use async_std::future::timeout;
-use futures::task::{Context, Poll};
-use futures::Future;
+use async_std::task;
 use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
 use std::io::Read;
-use std::pin::Pin;
 use std::time::Duration;
 use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
 
@@ -37,35 +34,69 @@ macro_rules! mut_borrow {
 
 pub type OwnedBytes = Box<[u8]>;
 
+/// How long a process may run before it is terminated, unless otherwise configured.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1800);
+
 /// Runs a process to completion, returning `Err` if it fails.
-pub async fn spawn(repo: OsString, arguments: &[&OsStr]) -> Result<()> {
+pub async fn spawn(repo: OsString, arguments: &[&OsStr], timeout_duration: Duration) -> Result<()> {
+    let arguments = to_owned_arguments(arguments);
+
+    // Waiting on the process blocks, so it is run on a dedicated thread
+    // rather than occupying one of the executor's workers.
+    task::spawn_blocking(move || run(repo, &arguments, timeout_duration)).await
+}
+
+/// Runs a process to completion, returning its `stdout`, or `Err` if it fails.
+pub async fn spawn_output(
+    repo: OsString,
+    arguments: &[&OsStr],
+    timeout_duration: Duration,
+) -> Result<OwnedBytes> {
+    let arguments = to_owned_arguments(arguments);
+
+    task::spawn_blocking(move || run_output(repo, &arguments, timeout_duration)).await
+}
+
+/// Like [`spawn`], but blocks the current thread until the process exits.
+///
+/// [`spawn`]: ./fn.spawn.html
+pub fn run<S: AsRef<OsStr>>(
+    repo: OsString,
+    arguments: &[S],
+    timeout_duration: Duration,
+) -> Result<()> {
     debug!(
         "Running process: (in {:?}) {:?} (no capture)",
-        repo, arguments,
+        repo,
+        debug_arguments(arguments),
     );
 
-    spawn_inner(repo, arguments, false).await.map(|_| ())
+    run_process(repo, arguments, false, timeout_duration).map(|_| ())
 }
 
-/// Runs a process to completion, returning its `stdout`, or `Err` if it fails.
-pub async fn spawn_output(repo: OsString, arguments: &[&OsStr]) -> Result<OwnedBytes> {
+/// Like [`spawn_output`], but blocks the current thread until the process exits.
+///
+/// [`spawn_output`]: ./fn.spawn_output.html
+pub fn run_output<S: AsRef<OsStr>>(
+    repo: OsString,
+    arguments: &[S],
+    timeout_duration: Duration,
+) -> Result<OwnedBytes> {
     debug!(
         "Running process: (in {:?}) {:?} (capturing stdout)",
-        repo, arguments,
+        repo,
+        debug_arguments(arguments),
     );
 
-    spawn_inner(repo, arguments, true)
-        .await
-        .map(|out| out.unwrap())
+    run_process(repo, arguments, true, timeout_duration).map(|out| out.unwrap())
 }
 
-async fn spawn_inner(
+fn run_process<S: AsRef<OsStr>>(
     repo: OsString,
-    arguments: &[&OsStr],
+    arguments: &[S],
     output: bool,
+    timeout_duration: Duration,
 ) -> Result<Option<OwnedBytes>> {
-    const TIMEOUT: Duration = Duration::from_millis(1800);
-
     let config = PopenConfig {
         stdin: Redirection::Pipe,
         stdout: Redirection::Pipe,
@@ -83,20 +114,29 @@ async fn spawn_inner(
         }
     };
 
+    // Nothing is ever written, so signal end of input right away
+    popen.stdin.take();
+
     trace!(
         "Created {:?}, waiting {} ms for completion",
         popen,
-        TIMEOUT.as_millis(),
+        timeout_duration.as_millis(),
     );
 
-    macro_rules! await_exit {
-        ($popen:expr, $timeout:expr) => {
-            timeout($timeout, PopenAsync::from(&mut $popen)).await
-        };
-    }
+    // Pipes are closed on every path, so they can't build up under repeated failures
+    let result = wait_process(&mut popen, arguments, output, timeout_duration);
+    close_pipes(&mut popen);
+    result
+}
 
-    match await_exit!(popen, TIMEOUT) {
-        Ok(status) if status.success() => {
+fn wait_process<S: AsRef<OsStr>>(
+    popen: &mut Popen,
+    arguments: &[S],
+    output: bool,
+    timeout_duration: Duration,
+) -> Result<Option<OwnedBytes>> {
+    match popen.wait_timeout(timeout_duration)? {
+        Some(status) if status.success() => {
             trace!("Command succeeded, gathering stdout");
 
             if output {
@@ -111,42 +151,14 @@ async fn spawn_inner(
                 Ok(None)
             }
         }
-        Ok(status) => {
-            trace!("Command failed, status {:?}", status);
-
-            let mut buffer = String::new();
-            for argument in &arguments[..2] {
-                write!(&mut buffer, "{} ", argument.to_string_lossy()).unwrap();
-            }
-
-            buffer.push_str("command failed: ");
-
-            let stderr = mut_borrow!(popen.stderr);
-            stderr.read_to_string(&mut buffer)?;
-
-            match status {
-                ExitStatus::Exited(code) => {
-                    warn!("Process exited with non-zero status code {}", code);
-                    write!(&mut buffer, "(exit status {})", code).unwrap();
-                }
-                ExitStatus::Signaled(code) => {
-                    warn!("Process was killed by signal {}", code);
-                    write!(&mut buffer, "(killed by signal {})", code).unwrap();
-                }
-                _ => {
-                    warn!("Process was killed by unknown source ({:?})", status);
-                    write!(&mut buffer, "(unknown cause)").unwrap();
-                }
-            }
-
-            Err(Error::CommandFailed(buffer))
-        }
-        Err(_) => {
-            const KILL_TIMEOUT: Duration = Duration::from_millis(2000);
+        Some(status) => Err(command_failed(popen, arguments, status)?),
+        None => {
+            // Give the process slightly longer than it was allowed to run
+            let kill_timeout = timeout_duration * 10 / 9;
 
             warn!(
                 "Process timed out after {} ms, terminating",
-                TIMEOUT.as_millis(),
+                timeout_duration.as_millis(),
             );
 
             if let Err(error) = popen.terminate() {
@@ -154,42 +166,123 @@ async fn spawn_inner(
                 return Err(Error::Io(error));
             }
 
-            if await_exit!(popen, KILL_TIMEOUT).is_err() {
+            if popen.wait_timeout(kill_timeout)?.is_none() {
                 warn!("Process did not exit after termination, killing");
                 popen.kill()?;
             }
 
-            let message = format!("command timed out ({} ms)", TIMEOUT.as_millis());
+            let message = format!("command timed out ({} ms)", timeout_duration.as_millis(),);
             Err(Error::CommandFailed(message))
         }
     }
 }
 
-#[derive(Debug)]
-struct PopenAsync<'p> {
-    inner: &'p mut Popen,
+/// Drops all of the process's pipes, closing their file descriptors.
+fn close_pipes(popen: &mut Popen) {
+    trace!("Closing pipes for {:?}", popen);
+
+    popen.stdin.take();
+    popen.stdout.take();
+    popen.stderr.take();
 }
 
-impl<'p> From<&'p mut Popen> for PopenAsync<'p> {
-    #[inline]
-    fn from(inner: &'p mut Popen) -> Self {
-        PopenAsync { inner }
-    }
+/// Starts a process without waiting for it, so its `stdout` can be read incrementally.
+/// Once all output has been read, call [`finish_piped`] to check that it succeeded.
+///
+/// [`finish_piped`]: ./fn.finish_piped.html
+pub fn spawn_piped(repo: OsString, arguments: &[&OsStr]) -> Result<Popen> {
+    debug!(
+        "Running process: (in {:?}) {:?} (streaming stdout)",
+        repo, arguments,
+    );
+
+    let config = PopenConfig {
+        stdin: Redirection::Pipe,
+        stdout: Redirection::Pipe,
+        stderr: Redirection::Pipe,
+        cwd: Some(repo),
+        ..PopenConfig::default()
+    };
+
+    let mut popen = Popen::create(arguments, config).map_err(|error| {
+        warn!("Failed to created subprocess: {}", error);
+
+        Error::Subprocess(error)
+    })?;
+
+    popen.stdin.take();
+    Ok(popen)
 }
 
-impl<'p> Future for PopenAsync<'p> {
-    type Output = ExitStatus;
+/// Waits for a process started by [`spawn_piped`] to exit, returning `Err` if it failed.
+///
+/// [`spawn_piped`]: ./fn.spawn_piped.html
+pub async fn finish_piped(
+    mut popen: Popen,
+    arguments: &[&OsStr],
+    timeout_duration: Duration,
+) -> Result<()> {
+    trace!("Waiting for streamed process {:?} to exit", popen);
 
-    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
-        let popen = &mut self.as_mut().inner;
+    let arguments = to_owned_arguments(arguments);
 
-        match popen.poll() {
-            Some(status) => Poll::Ready(status),
-            None => {
-                ctx.waker().wake_by_ref();
+    task::spawn_blocking(move || match popen.wait_timeout(timeout_duration)? {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(command_failed(&mut popen, &arguments, status)?),
+        None => {
+            warn!("Streamed process did not exit after its output ended, killing");
+            close_pipes(&mut popen);
+            popen.kill()?;
 
-                Poll::Pending
-            }
+            let message = format!("command timed out ({} ms)", timeout_duration.as_millis());
+            Err(Error::CommandFailed(message))
         }
+    })
+    .await
+}
+
+/// Copies process arguments so they can be moved onto a blocking thread.
+fn to_owned_arguments(arguments: &[&OsStr]) -> Vec<OsString> {
+    arguments.iter().map(|arg| arg.to_os_string()).collect()
+}
+
+/// Borrows process arguments for logging.
+fn debug_arguments<S: AsRef<OsStr>>(arguments: &[S]) -> Vec<&OsStr> {
+    arguments.iter().map(|arg| arg.as_ref()).collect()
+}
+
+/// Builds the error for a process which exited unsuccessfully, including its `stderr`.
+fn command_failed<S: AsRef<OsStr>>(
+    popen: &mut Popen,
+    arguments: &[S],
+    status: ExitStatus,
+) -> Result<Error> {
+    trace!("Command failed, status {:?}", status);
+
+    let mut buffer = String::new();
+    for argument in &arguments[..2] {
+        write!(&mut buffer, "{} ", argument.as_ref().to_string_lossy()).unwrap();
     }
+
+    buffer.push_str("command failed: ");
+
+    let stderr = mut_borrow!(popen.stderr);
+    stderr.read_to_string(&mut buffer)?;
+
+    match status {
+        ExitStatus::Exited(code) => {
+            warn!("Process exited with non-zero status code {}", code);
+            write!(&mut buffer, "(exit status {})", code).unwrap();
+        }
+        ExitStatus::Signaled(code) => {
+            warn!("Process was killed by signal {}", code);
+            write!(&mut buffer, "(killed by signal {})", code).unwrap();
+        }
+        _ => {
+            warn!("Process was killed by unknown source ({:?})", status);
+            write!(&mut buffer, "(unknown cause)").unwrap();
+        }
+    }
+
+    Ok(Error::CommandFailed(buffer))
 }
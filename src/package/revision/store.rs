@@ -18,18 +18,23 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::CommitInfo;
-use super::OwnedBytes;
+use super::{CommitInfo, DiffStat};
+use super::{OwnedBytes, DEFAULT_TIMEOUT};
 use crate::{Error, Result};
-use async_std::fs::{self, File};
-use async_std::prelude::*;
-use async_std::sync::{Mutex, RwLock};
-use deepwell_core::models::{Blame, GitHash};
+use async_std::task;
+use deepwell_core::models::{Blame, BlameGroup, BlameParser, Diff, GitHash};
+use deepwell_core::types::is_valid_slug;
+use futures::stream::{self, Stream, TryStreamExt};
 use std::convert::TryFrom;
 use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::PathBuf;
 use std::str;
-use wikidot_normalize::is_normal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+use subprocess::Popen;
 
 macro_rules! arguments {
     ($($x:expr), *) => {{
@@ -63,16 +68,22 @@ macro_rules! convert_utf8 {
 // If there are multiple processes working on the same repositories,
 // an external locking mechanism (probably based on the exclusive
 // creation of a lock file) will need to be implemented.
+//
+// The lock guards no data, so it remains usable after a panic.
 macro_rules! lock {
-    ($self:expr) => {
-        &mut $self.mutex.lock().await
+    ($repo:expr) => {
+        &mut $repo.mutex.lock().unwrap_or_else(PoisonError::into_inner)
     };
 }
 
+// The hash of the empty tree, which is always present in a git repository.
+// This is used to diff against when there is no prior commit.
+const EMPTY_TREE_HASH: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
 fn check_normal(slug: &str) -> Result<()> {
     trace!("Checking slug for normal form: {}", slug);
 
-    if is_normal(slug, false) {
+    if is_valid_slug(slug) {
         Ok(())
     } else {
         Err(Error::StaticMsg("slug not in wikidot normal form"))
@@ -83,12 +94,73 @@ fn check_normal(slug: &str) -> Result<()> {
 #[derive(Debug)]
 struct RevisionBlock;
 
+/// The state held by a `blame_stream()` while it is being read.
+///
+/// If the stream is dropped early, the pipe is closed and the process killed
+/// before it is reaped. Otherwise `Popen`'s drop would wait on a git process
+/// which could be blocked writing to a full pipe.
+struct BlameStreamState {
+    reader: Option<BufReader<File>>,
+    parser: Option<BlameParser>,
+    popen: Option<Popen>,
+}
+
+impl Drop for BlameStreamState {
+    fn drop(&mut self) {
+        self.reader.take();
+
+        if let Some(mut popen) = self.popen.take() {
+            debug!("Blame stream dropped before finishing, killing process");
+
+            if let Err(error) = popen.kill() {
+                warn!("Unable to kill blame process: {}", error);
+            }
+        }
+    }
+}
+
+/// Reads porcelain output until the parser produces a group.
+/// Returns `None` once the output is exhausted.
+fn read_blame_group(
+    reader: &mut BufReader<File>,
+    parser: &mut BlameParser,
+) -> Result<Option<BlameGroup>> {
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+
+        if line.ends_with(b"\n") {
+            line.pop();
+        }
+
+        if let Some(group) = parser.push_line(&line)? {
+            return Ok(Some(group));
+        }
+    }
+}
+
 /// Represents a git repository to store page contents and their histories.
-#[derive(Debug)]
+///
+/// This is a handle, so clones refer to the same repository.
+/// Each operation runs its git processes on a blocking thread, which is the only place
+/// the repository is locked. This way the lock is never held by a suspended future.
+#[derive(Debug, Clone)]
 pub struct RevisionStore {
+    inner: Arc<Repository>,
+}
+
+/// The repository shared between clones of a `RevisionStore`.
+#[derive(Debug)]
+struct Repository {
     mutex: Mutex<RevisionBlock>,
     repo: PathBuf,
     domain: RwLock<String>,
+    timeout: Duration,
+    ready: AtomicBool,
 }
 
 impl RevisionStore {
@@ -97,6 +169,16 @@ impl RevisionStore {
     /// The domain name should not have a protocol but allows permit subdomains.
     #[inline]
     pub fn new<P, S>(repo: P, domain: S) -> Self
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        Self::with_timeout(repo, domain, DEFAULT_TIMEOUT)
+    }
+
+    /// Creates a new revision store which allows git processes to run for
+    /// the given duration before they are terminated.
+    pub fn with_timeout<P, S>(repo: P, domain: S, timeout: Duration) -> Self
     where
         P: Into<PathBuf>,
         S: Into<String>,
@@ -106,192 +188,96 @@ impl RevisionStore {
         let domain = domain.into();
 
         info!(
-            "Creating new revision store for repository {}, domain {}",
+            "Creating new revision store for repository {}, domain {}, timeout {} ms",
             repo.display(),
             domain,
+            timeout.as_millis(),
         );
 
         let domain = RwLock::new(domain);
 
-        RevisionStore {
+        let inner = Arc::new(Repository {
             mutex,
             repo,
             domain,
-        }
-    }
-
-    // Filesystem helpers
-    fn get_path(&self, slug: &str, absolute: bool) -> PathBuf {
-        trace!(
-            "Converting slug '{}' to path (absolute: {})",
-            slug,
-            absolute,
-        );
-
-        let filename = {
-            let mut filename = String::new();
-
-            for part in slug.split(':') {
-                filename.push_str(part);
-                filename.push('$');
-            }
-
-            filename.pop();
-            filename
-        };
+            timeout,
+            ready: AtomicBool::new(false),
+        });
 
-        let mut path = PathBuf::new();
-
-        if absolute {
-            path.push(&self.repo);
-        }
-
-        path.push(&filename);
-        path.set_extension("ftml");
-        path
-    }
-
-    async fn read_file(&self, _guard: &mut RevisionBlock, slug: &str) -> Result<Option<String>> {
-        let path = self.get_path(slug, true);
-
-        debug!("Reading file from {}", path.display());
-
-        let mut file = match File::open(&path).await {
-            Ok(file) => file,
-            Err(error) => {
-                use std::io::ErrorKind;
-
-                return match error.kind() {
-                    ErrorKind::NotFound => Ok(None),
-                    _ => Err(Error::from(error)),
-                };
-            }
-        };
-
-        let mut content = String::new();
-        file.read_to_string(&mut content).await?;
-        Ok(Some(content))
+        RevisionStore { inner }
     }
 
-    async fn write_file(
-        &self,
-        _guard: &mut RevisionBlock,
-        slug: &str,
-        content: &str,
-    ) -> Result<()> {
-        let path = self.get_path(slug, true);
-
-        debug!("Writing {} bytes to {}", content.len(), path.display());
-
-        let mut file = File::create(path).await?;
-        let bytes = content.as_bytes();
-        file.write_all(bytes).await?;
-        Ok(())
+    /// Returns whether the repository is known to be usable.
+    /// This is only set by `initial_commit()` or `check_ready()`.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.inner.ready.load(Ordering::Acquire)
     }
 
-    async fn remove_file(&self, _guard: &mut RevisionBlock, slug: &str) -> Result<Option<()>> {
-        let path = self.get_path(slug, true);
+    /// Checks that the repository is usable, that is, it has a `HEAD` commit.
+    /// The result is also available afterwards from `is_ready()`.
+    pub async fn check_ready(&self) -> bool {
+        let path = self.inner.repo.display();
 
-        debug!("Removing file {}", path.display());
+        info!("Checking if repository {} is ready", path);
 
-        match fs::remove_file(path).await {
-            Ok(_) => (),
+        let result = self.locked(|repo, guard| repo.get_commit(guard)).await;
+        let ready = match result {
+            Ok(_) => true,
             Err(error) => {
-                use std::io::ErrorKind;
+                warn!("Repository {} has no HEAD commit: {}", path, error);
 
-                return match error.kind() {
-                    ErrorKind::NotFound => Ok(None),
-                    _ => Err(Error::from(error)),
-                };
+                false
             }
-        }
-
-        Ok(Some(()))
-    }
-
-    // Argument helpers
-    async fn arg_author(&self, name: &str) -> String {
-        let domain = self.domain.read().await;
-
-        format!("--author={} <noreply@{}>", name, domain)
-    }
-
-    fn arg_message(&self, message: &str) -> String {
-        format!("--message={}", message)
-    }
-
-    // Process helpers
-    fn repo(&self) -> OsString {
-        self.repo.as_os_str().to_os_string()
-    }
-
-    async fn spawn(&self, _guard: &mut RevisionBlock, arguments: &[&OsStr]) -> Result<()> {
-        super::spawn(self.repo(), arguments).await
-    }
+        };
 
-    async fn spawn_output(
-        &self,
-        _guard: &mut RevisionBlock,
-        arguments: &[&OsStr],
-    ) -> Result<OwnedBytes> {
-        super::spawn_output(self.repo(), arguments).await
+        self.inner.ready.store(ready, Ordering::Release);
+        ready
     }
 
-    // Git helper
-    async fn get_commit(&self, guard: &mut RevisionBlock) -> Result<GitHash> {
-        debug!("Getting current HEAD commit");
-
-        let args = arguments!["git", "rev-parse", "--verify", "HEAD"];
-
-        let digest_bytes = self.spawn_output(guard, &args).await?;
-        let digest = str::from_utf8(&digest_bytes)
-            .map_err(|_| Error::StaticMsg("git hash wasn't valid UTF-8"))?;
-
-        let hash = GitHash::try_from(digest)
-            .map_err(|_| Error::StaticMsg("unable to parse git hash from output"))?;
-
-        Ok(hash)
+    /// Returns how long git processes may run before being terminated.
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.inner.timeout
     }
 
-    #[cfg(test)]
-    async fn check_clean(&self, guard: &mut RevisionBlock) {
-        debug!("Checking if repository is clean");
+    /// Runs the given operation on a blocking thread, while holding the repository lock.
+    async fn locked<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Repository, &mut RevisionBlock) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let repo = Arc::clone(&self.inner);
 
-        let args = arguments!["git", "status", "--porcelain"];
-        let output = self
-            .spawn_output(guard, &args)
-            .await
-            .expect("Unable to get git status");
+        task::spawn_blocking(move || {
+            let guard = lock!(repo);
 
-        if !output.is_empty() {
-            panic!(
-                "Git repository is not clean:\n{}",
-                String::from_utf8_lossy(&output),
-            );
-        }
+            f(&repo, guard)
+        })
+        .await
     }
 
-    #[cfg(not(test))]
-    async fn check_clean(&self, _guard: &mut RevisionBlock) {}
-
     /// Create the first commit of the repo.
     /// Should only be called on empty repositories.
     #[cold]
     pub async fn initial_commit(&self) -> Result<()> {
         info!("Initializing new git repository");
 
-        let guard = &mut self.mutex.lock().await;
-        let args = arguments!["git", "init"];
-        self.spawn(guard, &args).await?;
+        self.locked(|repo, guard| {
+            let args = arguments!["git", "init"];
+            repo.spawn(guard, &args)?;
 
-        let author = self.arg_author("DEEPWELL").await;
-        let message = self.arg_message("Initial commit");
-        let args = arguments!["git", "commit", "--allow-empty", &author, &message];
+            let author = repo.arg_author("DEEPWELL");
+            let message = repo.arg_message("Initial commit");
+            let args = arguments!["git", "commit", "--allow-empty", &author, &message];
 
-        self.spawn(guard, &args).await?;
-        self.check_clean(guard).await;
+            repo.spawn(guard, &args)?;
+            repo.check_clean(guard);
+            repo.ready.store(true, Ordering::Release);
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     /// For the given slug, create or edit a page to have the specified contents.
@@ -308,50 +294,70 @@ impl RevisionStore {
         );
 
         check_normal!(slug);
-        let guard = &mut self.mutex.lock().await;
-
-        if let Some(content) = content {
-            self.write_file(guard, slug, content).await?;
-        }
-
-        let path = self.get_path(slug, false);
-        let args = arguments!["git", "add", &path];
-        self.spawn(guard, &args).await?;
-
-        let author = self.arg_author(info.username).await;
-        let message = self.arg_message(info.message);
-        let args = arguments![
-            "git",
-            "commit",
-            "--allow-empty",
-            &author,
-            &message,
-            "--",
-            &path,
-        ];
-        self.spawn(guard, &args).await?;
-
-        let commit = self.get_commit(guard).await?;
-        self.check_clean(guard).await;
+        let slug = String::from(slug);
+        let content = content.map(String::from);
+        let author = self.inner.arg_author(info.username);
+        let message = self.inner.arg_message(info.message);
+
+        self.locked(move |repo, guard| {
+            if let Some(content) = content {
+                repo.write_file(guard, &slug, &content)?;
+            }
 
-        Ok(commit)
+            let path = repo.get_path(&slug, false);
+            let args = arguments!["git", "add", &path];
+            repo.spawn(guard, &args)?;
+
+            let args = arguments![
+                "git",
+                "commit",
+                "--allow-empty",
+                &author,
+                &message,
+                "--",
+                &path,
+            ];
+            repo.spawn(guard, &args)?;
+
+            let commit = repo.get_commit(guard)?;
+            repo.check_clean(guard);
+
+            Ok(commit)
+        })
+        .await
     }
 
     /// Creates an empty commit.
     pub async fn empty_commit(&self, info: CommitInfo<'_>) -> Result<GitHash> {
         info!("Creating empty commit");
 
-        let guard = &mut self.mutex.lock().await;
-        let author = self.arg_author(info.username).await;
-        let message = self.arg_message(info.message);
+        let author = self.inner.arg_author(info.username);
+        let message = self.inner.arg_message(info.message);
 
-        let args = arguments!["git", "commit", "--allow-empty", &author, &message];
-        self.spawn(guard, &args).await?;
+        self.locked(move |repo, guard| {
+            let args = arguments!["git", "commit", "--allow-empty", &author, &message];
+            repo.spawn(guard, &args)?;
 
-        let commit = self.get_commit(guard).await?;
-        self.check_clean(guard).await;
+            let commit = repo.get_commit(guard)?;
+            repo.check_clean(guard);
 
-        Ok(commit)
+            Ok(commit)
+        })
+        .await
+    }
+
+    /// Determines if a file for the given slug is present in the working tree.
+    pub async fn page_exists(&self, slug: &str) -> Result<bool> {
+        info!("Checking if file for slug '{}' exists", slug);
+
+        check_normal!(slug);
+        let slug = String::from(slug);
+
+        self.locked(move |repo, _guard| {
+            let path = repo.get_path(&slug, true);
+            Ok(path.exists())
+        })
+        .await
     }
 
     /// Renames the given page in the repository.
@@ -365,27 +371,31 @@ impl RevisionStore {
 
         check_normal!(old_slug);
         check_normal!(new_slug);
-        let guard = &mut self.mutex.lock().await;
-
-        let new_path = self.get_path(new_slug, true);
-        if new_path.exists() {
-            return Err(Error::PageExists);
-        }
+        let old_slug = String::from(old_slug);
+        let new_slug = String::from(new_slug);
+        let author = self.inner.arg_author(info.username);
+        let message = self.inner.arg_message(info.message);
+
+        self.locked(move |repo, guard| {
+            let new_path = repo.get_path(&new_slug, true);
+            if new_path.exists() {
+                return Err(Error::PageExists);
+            }
 
-        let old_path = self.get_path(old_slug, false);
-        let new_path = self.get_path(new_slug, false);
-        let args = arguments!["git", "mv", "--", &old_path, &new_path];
-        self.spawn(guard, &args).await?;
+            let old_path = repo.get_path(&old_slug, false);
+            let new_path = repo.get_path(&new_slug, false);
+            let args = arguments!["git", "mv", "--", &old_path, &new_path];
+            repo.spawn(guard, &args)?;
 
-        let author = self.arg_author(info.username).await;
-        let message = self.arg_message(info.message);
-        let args = arguments!["git", "commit", &author, &message, "--", &old_path, &new_path];
-        self.spawn(guard, &args).await?;
+            let args = arguments!["git", "commit", &author, &message, "--", &old_path, &new_path];
+            repo.spawn(guard, &args)?;
 
-        let commit = self.get_commit(guard).await?;
-        self.check_clean(guard).await;
+            let commit = repo.get_commit(guard)?;
+            repo.check_clean(guard);
 
-        Ok(commit)
+            Ok(commit)
+        })
+        .await
     }
 
     /// Remove the given page from the repository.
@@ -394,24 +404,27 @@ impl RevisionStore {
         info!("Removing file for slug '{}' (info: {:?})", slug, info);
 
         check_normal!(slug);
-        let guard = &mut self.mutex.lock().await;
-
-        let removed = self.remove_file(guard, slug).await?;
-        if removed.is_none() {
-            return Ok(None);
-        }
+        let slug = String::from(slug);
+        let author = self.inner.arg_author(info.username);
+        let message = self.inner.arg_message(info.message);
+
+        self.locked(move |repo, guard| {
+            let removed = repo.remove_file(guard, &slug)?;
+            if removed.is_none() {
+                return Ok(None);
+            }
 
-        let author = self.arg_author(info.username).await;
-        let message = self.arg_message(info.message);
-        let path = self.get_path(slug, false);
-        let args = arguments!["git", "commit", &author, &message, "--", &path];
+            let path = repo.get_path(&slug, false);
+            let args = arguments!["git", "commit", &author, &message, "--", &path];
 
-        self.spawn(guard, &args).await?;
+            repo.spawn(guard, &args)?;
 
-        let commit = self.get_commit(guard).await.map(Some)?;
-        self.check_clean(guard).await;
+            let commit = repo.get_commit(guard).map(Some)?;
+            repo.check_clean(guard);
 
-        Ok(commit)
+            Ok(commit)
+        })
+        .await
     }
 
     /// Restores the given page from the given hash.
@@ -436,45 +449,50 @@ impl RevisionStore {
         check_normal!(slug);
         check_normal!(old_slug);
 
-        let guard = &mut self.mutex.lock().await;
-
-        // Get old page content
-        let content = {
-            let path = self.get_path(old_slug, false);
-            let spec = format!("{}:{}", hash, path.display());
-            let args = arguments!["git", "show", "--format=%B", &spec];
-
-            match self.spawn_output(guard, &args).await {
-                Ok(bytes) => Ok(convert_utf8!(bytes)),
-                Err(Error::CommandFailed(_)) => Err(Error::PageNotFound),
-                Err(error) => Err(error),
-            }
-        }?;
-
-        // Write and commit contents
-        self.write_file(guard, slug, &content).await?;
-
-        let path = self.get_path(slug, false);
-        let args = arguments!["git", "add", &path];
-        self.spawn(guard, &args).await?;
-
-        let author = self.arg_author(info.username).await;
-        let message = self.arg_message(info.message);
-        let args = arguments![
-            "git",
-            "commit",
-            "--allow-empty",
-            &author,
-            &message,
-            "--",
-            &path,
-        ];
-        self.spawn(guard, &args).await?;
-
-        let commit = self.get_commit(guard).await?;
-        self.check_clean(guard).await;
-
-        Ok(commit)
+        let slug = String::from(slug);
+        let old_slug = String::from(old_slug);
+        let hash = hash.clone();
+        let author = self.inner.arg_author(info.username);
+        let message = self.inner.arg_message(info.message);
+
+        self.locked(move |repo, guard| {
+            // Get old page content
+            let content = {
+                let path = repo.get_path(&old_slug, false);
+                let spec = format!("{}:{}", hash, path.display());
+                let args = arguments!["git", "show", "--format=%B", &spec];
+
+                match repo.spawn_output(guard, &args) {
+                    Ok(bytes) => Ok(convert_utf8!(bytes)),
+                    Err(Error::CommandFailed(_)) => Err(Error::PageNotFound),
+                    Err(error) => Err(error),
+                }
+            }?;
+
+            // Write and commit contents
+            repo.write_file(guard, &slug, &content)?;
+
+            let path = repo.get_path(&slug, false);
+            let args = arguments!["git", "add", &path];
+            repo.spawn(guard, &args)?;
+
+            let args = arguments![
+                "git",
+                "commit",
+                "--allow-empty",
+                &author,
+                &message,
+                "--",
+                &path,
+            ];
+            repo.spawn(guard, &args)?;
+
+            let commit = repo.get_commit(guard)?;
+            repo.check_clean(guard);
+
+            Ok(commit)
+        })
+        .await
     }
 
     /// Reverts the given commit.
@@ -485,22 +503,96 @@ impl RevisionStore {
     pub async fn undo(&self, hash: &GitHash, info: CommitInfo<'_>) -> Result<GitHash> {
         info!("Undoing commit {} (info: {:?})", hash, info);
 
-        let guard = &mut self.mutex.lock().await;
+        let hash = hash.clone();
+        let author = self.inner.arg_author(info.username);
+        let message = self.inner.arg_message(info.message);
 
-        // Perform the revert
-        let args = arguments!["git", "revert", "--no-edit", hash];
-        self.spawn(guard, &args).await?;
+        self.locked(move |repo, guard| {
+            // Perform the revert
+            let args = arguments!["git", "revert", "--no-edit", &hash];
+            repo.spawn(guard, &args)?;
 
-        // Edit the commit message
-        let author = self.arg_author(info.username).await;
-        let message = self.arg_message(info.message);
-        let args = arguments!["git", "commit", "--amend", &author, &message,];
-        self.spawn(guard, &args).await?;
+            // Edit the commit message
+            let args = arguments!["git", "commit", "--amend", &author, &message,];
+            repo.spawn(guard, &args)?;
 
-        let commit = self.get_commit(guard).await?;
-        self.check_clean(guard).await;
+            let commit = repo.get_commit(guard)?;
+            repo.check_clean(guard);
 
-        Ok(commit)
+            Ok(commit)
+        })
+        .await
+    }
+
+    /// Determines if the given commit is part of the current history, that is, an ancestor of `HEAD`.
+    /// Returns `RevisionNotFound` if the commit does not exist in the repository.
+    pub async fn is_ancestor(&self, hash: &GitHash) -> Result<bool> {
+        info!("Checking if commit {} is an ancestor of HEAD", hash);
+
+        let hash = hash.clone();
+
+        self.locked(move |repo, guard| {
+            // Distinguish missing commits, since both cases fail merge-base
+            let spec = format!("{}^{{commit}}", hash);
+            let args = arguments!["git", "cat-file", "-e", &spec];
+
+            match repo.spawn(guard, &args) {
+                Ok(_) => (),
+                Err(Error::CommandFailed(_)) => return Err(Error::RevisionNotFound),
+                Err(error) => return Err(error),
+            }
+
+            let args = arguments!["git", "merge-base", "--is-ancestor", &hash, "HEAD"];
+
+            let result = match repo.spawn(guard, &args) {
+                Ok(_) => Ok(true),
+                Err(Error::CommandFailed(_)) => Ok(false),
+                Err(error) => Err(error),
+            };
+
+            repo.check_clean(guard);
+            result
+        })
+        .await
+    }
+
+    /// Restores a page's file from the last commit if it is missing from the working tree.
+    /// Returns `false` if the file is present, or if the page is not in the last commit.
+    pub async fn repair(&self, slug: &str) -> Result<bool> {
+        info!("Repairing page for slug '{}'", slug);
+
+        check_normal!(slug);
+        let slug = String::from(slug);
+
+        self.locked(move |repo, guard| {
+            let path = repo.get_path(&slug, true);
+            if fs::metadata(&path).is_ok() {
+                debug!("Page file is present, no repair needed");
+                return Ok(false);
+            }
+
+            let path = repo.get_path(&slug, false);
+            let spec = format!("HEAD:{}", path.display());
+            let args = arguments!["git", "cat-file", "-e", &spec];
+
+            match repo.spawn(guard, &args) {
+                Ok(()) => (),
+                Err(Error::CommandFailed(_)) => {
+                    debug!("Page file is not in the last commit, cannot repair");
+                    return Ok(false);
+                }
+                Err(error) => return Err(error),
+            }
+
+            warn!("Page file {} is missing, checking it out", path.display());
+
+            let args = arguments!["git", "checkout", "HEAD", "--", &path];
+            repo.spawn(guard, &args)?;
+            repo.check_clean(guard);
+
+            Ok(true)
+        })
+        .await
     }
 
     /// Gets the current version of a page.
@@ -509,12 +601,44 @@ impl RevisionStore {
         info!("Getting page content for slug '{}'", slug);
 
         check_normal!(slug);
-        let guard = lock!(self);
+        let slug = String::from(slug);
+
+        self.locked(move |repo, guard| {
+            let contents = repo.read_file(guard, &slug)?;
+            repo.check_clean(guard);
+
+            Ok(contents)
+        })
+        .await
+    }
+
+    /// Gets the current versions of several pages at once, holding the lock throughout.
+    /// Pages which do not exist are omitted.
+    pub async fn get_pages<S: AsRef<str>>(&self, slugs: &[S]) -> Result<Vec<(String, Box<[u8]>)>> {
+        info!("Getting page content for {} slugs", slugs.len());
+
+        let mut owned_slugs = Vec::with_capacity(slugs.len());
+        for slug in slugs {
+            let slug = slug.as_ref();
+            check_normal!(slug);
+            owned_slugs.push(String::from(slug));
+        }
+
+        self.locked(move |repo, guard| {
+            let mut pages = Vec::with_capacity(owned_slugs.len());
+
+            for slug in owned_slugs {
+                if let Some(contents) = repo.read_file(guard, &slug)? {
+                    let bytes = contents.into_bytes().into_boxed_slice();
+                    pages.push((slug, bytes));
+                }
+            }
 
-        let contents = self.read_file(guard, slug).await?;
-        self.check_clean(guard).await;
+            repo.check_clean(guard);
 
-        Ok(contents)
+            Ok(pages)
+        })
+        .await
     }
 
     /// Gets the version of a page at the specified commit.
@@ -526,20 +650,63 @@ impl RevisionStore {
         );
 
         check_normal!(slug);
-        let guard = lock!(self);
+        let slug = String::from(slug);
+        let hash = hash.clone();
 
-        let path = self.get_path(slug, false);
-        let spec = format!("{}:{}", hash, path.display());
-        let args = arguments!["git", "show", "--format=%B", &spec];
+        self.locked(move |repo, guard| {
+            let path = repo.get_path(&slug, false);
+            let spec = format!("{}:{}", hash, path.display());
+            let args = arguments!["git", "show", "--format=%B", &spec];
 
-        let result = match self.spawn_output(guard, &args).await {
-            Ok(bytes) => Ok(Some(convert_utf8!(bytes))),
-            Err(Error::CommandFailed(_)) => Ok(None),
-            Err(error) => Err(error),
-        };
+            let result = match repo.spawn_output(guard, &args) {
+                Ok(bytes) => Ok(Some(convert_utf8!(bytes))),
+                Err(Error::CommandFailed(_)) => Ok(None),
+                Err(error) => Err(error),
+            };
 
-        self.check_clean(guard).await;
-        result
+            repo.check_clean(guard);
+            result
+        })
+        .await
+    }
+
+    /// Gets the raw blob for a page at the given commit, along with its object ID.
+    /// Returns `None` if the page does not exist at that commit.
+    pub async fn get_blob(
+        &self,
+        slug: &str,
+        hash: &GitHash,
+    ) -> Result<Option<(GitHash, OwnedBytes)>> {
+        info!("Getting blob for slug '{}' at commit {}", slug, hash);
+
+        check_normal!(slug);
+        let slug = String::from(slug);
+        let hash = hash.clone();
+
+        self.locked(move |repo, guard| {
+            let path = repo.get_path(&slug, false);
+            let spec = format!("{}:{}", hash, path.display());
+            let args = arguments!["git", "rev-parse", "--verify", "--quiet", &spec];
+
+            let object_bytes = match repo.spawn_output(guard, &args) {
+                Ok(bytes) => bytes,
+                Err(Error::CommandFailed(_)) => return Ok(None),
+                Err(error) => return Err(error),
+            };
+
+            let object = str::from_utf8(&object_bytes)
+                .map_err(|_| Error::StaticMsg("git object ID wasn't valid UTF-8"))?;
+
+            let object = GitHash::try_from(object)
+                .map_err(|_| Error::StaticMsg("unable to parse git object ID from output"))?;
+
+            let args = arguments!["git", "cat-file", "blob", &object];
+            let contents = repo.spawn_output(guard, &args)?;
+            repo.check_clean(guard);
+
+            Ok(Some((object, contents)))
+        })
+        .await
     }
 
     /// Gets the diff between commits of a particular page.
@@ -551,23 +718,137 @@ impl RevisionStore {
         );
 
         check_normal!(slug);
-        let guard = lock!(self);
-        let path = self.get_path(slug, false);
+        let path = self.inner.get_path(slug, false);
+        let first = first.clone();
+        let second = second.clone();
+
+        self.locked(move |repo, guard| {
+            let args = arguments![
+                "git",
+                "diff",
+                "--word-diff=porcelain",
+                &first,
+                &second,
+                "--",
+                &path,
+            ];
+
+            let diff = repo.spawn_output(guard, &args)?;
+            repo.check_clean(guard);
+
+            Ok(convert_utf8!(diff))
+        })
+        .await
+    }
 
-        let args = arguments![
-            "git",
-            "diff",
-            "--word-diff=porcelain",
-            &first,
-            &second,
-            "--",
-            &path,
-        ];
+    /// Gets the diff between commits of a particular page, parsed into hunks.
+    pub async fn get_structured_diff(
+        &self,
+        slug: &str,
+        first: &GitHash,
+        second: &GitHash,
+    ) -> Result<Diff> {
+        let raw_diff = self.get_diff(slug, first, second).await?;
+        let diff = Diff::from_porcelain(&raw_diff)?;
 
-        let diff = self.spawn_output(guard, &args).await?;
-        self.check_clean(guard).await;
+        Ok(diff)
+    }
+
+    /// Gets the number of lines added and removed between commits of a particular page.
+    /// If the first commit is not specified, then all lines are counted as added.
+    pub async fn get_diff_stat(
+        &self,
+        slug: &str,
+        first: Option<&GitHash>,
+        second: &GitHash,
+    ) -> Result<DiffStat> {
+        info!(
+            "Getting diff stat for slug '{}' between {:?}..{}",
+            slug, first, second,
+        );
 
-        Ok(convert_utf8!(diff))
+        check_normal!(slug);
+        let path = self.inner.get_path(slug, false);
+        let first = first
+            .map(|hash| hash.as_str())
+            .unwrap_or(EMPTY_TREE_HASH)
+            .to_string();
+        let second = second.clone();
+
+        let output = self
+            .locked(move |repo, guard| {
+                let args = arguments!["git", "diff", "--numstat", &first, &second, "--", &path];
+                let output = convert_utf8!(repo.spawn_output(guard, &args)?);
+                repo.check_clean(guard);
+
+                Ok(output)
+            })
+            .await?;
+
+        // Each line is "<added>\t<removed>\t<path>"
+        // Binary files use "-" instead of line counts, and are skipped.
+        let mut stat = DiffStat::default();
+        for line in output.lines() {
+            let mut parts = line.split('\t');
+            let added = parts.next().and_then(|part| part.parse::<u64>().ok());
+            let removed = parts.next().and_then(|part| part.parse::<u64>().ok());
+
+            if let (Some(added), Some(removed)) = (added, removed) {
+                stat.added += added;
+                stat.removed += removed;
+            }
+        }
+
+        Ok(stat)
+    }
+
+    /// Gets the diff between two different pages.
+    /// If a commit is not specified, then the current version of that page is used.
+    pub async fn get_cross_diff(
+        &self,
+        first_slug: &str,
+        first_hash: Option<&GitHash>,
+        second_slug: &str,
+        second_hash: Option<&GitHash>,
+    ) -> Result<String> {
+        info!(
+            "Getting diff between slug '{}' ({:?}) and slug '{}' ({:?})",
+            first_slug, first_hash, second_slug, second_hash,
+        );
+
+        check_normal!(first_slug);
+        check_normal!(second_slug);
+
+        // The working tree is always clean, so HEAD is the current version
+        let spec = |slug: &str, hash: Option<&GitHash>| {
+            let path = self.inner.get_path(slug, false);
+            let hash = hash.map(|hash| hash.as_str()).unwrap_or("HEAD");
+
+            format!("{}:{}", hash, path.display())
+        };
+
+        let first_spec = spec(first_slug, first_hash);
+        let second_spec = spec(second_slug, second_hash);
+
+        self.locked(move |repo, guard| {
+            let args = arguments![
+                "git",
+                "diff",
+                "--word-diff=porcelain",
+                &first_spec,
+                &second_spec,
+            ];
+
+            let diff = match repo.spawn_output(guard, &args) {
+                Ok(bytes) => convert_utf8!(bytes),
+                Err(Error::CommandFailed(_)) => return Err(Error::PageNotFound),
+                Err(error) => return Err(error),
+            };
+
+            repo.check_clean(guard);
+            Ok(diff)
+        })
+        .await
     }
 
     /// Gets the blame for a particular page.
@@ -575,32 +856,93 @@ impl RevisionStore {
     pub async fn get_blame(&self, slug: &str, hash: Option<&GitHash>) -> Result<Option<Blame>> {
         info!("Getting blame for slug '{}'", slug);
 
-        check_normal!(slug);
-        let guard = lock!(self);
-        let path = self.get_path(slug, false);
+        let stream = self.blame_stream(slug, hash).await?;
 
-        let args = match hash {
-            Some(ref hash) => arguments!["git", "blame", "--porcelain", hash, "--", &path],
-            None => arguments!["git", "blame", "--porcelain", "--", &path],
+        match stream.try_collect().await {
+            Ok(groups) => Ok(Some(Blame { groups })),
+            Err(Error::CommandFailed(_)) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Gets the blame for a page as a stream of groups, parsing the output as it is read.
+    /// This avoids holding the entire blame in memory for very large pages.
+    ///
+    /// If no commit is given, `HEAD` is resolved when the stream is created.
+    /// Since the blamed commit can't change, the store isn't locked while the stream is read.
+    /// If the page does not exist, the stream yields `Error::CommandFailed`.
+    pub async fn blame_stream<'a>(
+        &'a self,
+        slug: &str,
+        hash: Option<&GitHash>,
+    ) -> Result<impl Stream<Item = Result<BlameGroup>> + 'a> {
+        info!("Streaming blame for slug '{}'", slug);
+
+        check_normal!(slug);
+        let hash = match hash {
+            Some(hash) => hash.clone(),
+            None => self.locked(|repo, guard| repo.get_commit(guard)).await?,
         };
 
-        let raw_blame = match self.spawn_output(guard, &args).await {
-            Ok(bytes) => bytes,
-            Err(Error::CommandFailed(_)) => return Ok(None),
-            Err(error) => return Err(error),
+        let path = self.inner.get_path(slug, false);
+        let args = arguments!["git", "blame", "--porcelain", &hash, "--", &path];
+
+        let mut popen = super::spawn_piped(self.inner.repo(), &args)?;
+        let stdout = popen.stdout.take().expect("No stdout for piped process");
+
+        let state = BlameStreamState {
+            reader: Some(BufReader::new(stdout)),
+            parser: Some(BlameParser::new()),
+            popen: Some(popen),
         };
 
-        let blame = Blame::from_porcelain(&raw_blame)?;
-        self.check_clean(guard).await;
+        let stream = stream::unfold(state, move |mut state| async move {
+            // Parser is removed once the stream is finished
+            let mut parser = state.parser.take()?;
+            let mut reader = state.reader.take()?;
+
+            // Reading from the pipe blocks, so it is done on a dedicated thread
+            let (reader, parser, result) = task::spawn_blocking(move || {
+                let result = read_blame_group(&mut reader, &mut parser);
+                (reader, parser, result)
+            })
+            .await;
+
+            let item = match result {
+                Ok(Some(group)) => {
+                    state.reader = Some(reader);
+                    state.parser = Some(parser);
+                    Ok(group)
+                }
+                // End of output, finish process and get last group
+                Ok(None) => {
+                    let popen = state.popen.take().unwrap();
+                    let args = arguments!["git", "blame"];
+
+                    match super::finish_piped(popen, &args, self.timeout()).await {
+                        Ok(()) => Ok(parser.finish()?),
+                        Err(error) => Err(error),
+                    }
+                }
+                Err(error) => Err(error),
+            };
+
+            Some((item, state))
+        });
 
-        Ok(Some(blame))
+        Ok(stream)
     }
 
     /// Sets the domain to a different value.
     pub async fn set_domain(&self, new_domain: &str) {
         trace!("Acquiring domain write lock to change: {}", new_domain);
 
-        let mut guard = self.domain.write().await;
+        let mut guard = self
+            .inner
+            .domain
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+
         guard.clear();
         guard.push_str(new_domain);
     }
@@ -626,7 +968,7 @@ impl RevisionStore {
         // Doesn't obtain the lock since this is intended to run in the background
         macro_rules! run {
             ($call:ident, $arguments:expr) => {
-                super::$call(self.repo(), &$arguments).await?
+                super::$call(self.inner.repo(), &$arguments, self.timeout()).await?
             };
         }
 
@@ -644,3 +986,149 @@ impl RevisionStore {
         Ok(pruned)
     }
 }
+
+impl Repository {
+    // Filesystem helpers
+    fn get_path(&self, slug: &str, absolute: bool) -> PathBuf {
+        trace!(
+            "Converting slug '{}' to path (absolute: {})",
+            slug,
+            absolute,
+        );
+
+        let filename = {
+            let mut filename = String::new();
+
+            for part in slug.split(':') {
+                filename.push_str(part);
+                filename.push('$');
+            }
+
+            filename.pop();
+            filename
+        };
+
+        let mut path = PathBuf::new();
+
+        if absolute {
+            path.push(&self.repo);
+        }
+
+        path.push(&filename);
+        path.set_extension("ftml");
+        path
+    }
+
+    fn read_file(&self, _guard: &mut RevisionBlock, slug: &str) -> Result<Option<String>> {
+        let path = self.get_path(slug, true);
+
+        debug!("Reading file from {}", path.display());
+
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(error) => {
+                use std::io::ErrorKind;
+
+                return match error.kind() {
+                    ErrorKind::NotFound => Ok(None),
+                    _ => Err(Error::from(error)),
+                };
+            }
+        };
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        Ok(Some(content))
+    }
+
+    fn write_file(&self, _guard: &mut RevisionBlock, slug: &str, content: &str) -> Result<()> {
+        let path = self.get_path(slug, true);
+
+        debug!("Writing {} bytes to {}", content.len(), path.display());
+
+        let mut file = File::create(path)?;
+        let bytes = content.as_bytes();
+        file.write_all(bytes)?;
+        Ok(())
+    }
+
+    fn remove_file(&self, _guard: &mut RevisionBlock, slug: &str) -> Result<Option<()>> {
+        let path = self.get_path(slug, true);
+
+        debug!("Removing file {}", path.display());
+
+        match fs::remove_file(path) {
+            Ok(_) => (),
+            Err(error) => {
+                use std::io::ErrorKind;
+
+                return match error.kind() {
+                    ErrorKind::NotFound => Ok(None),
+                    _ => Err(Error::from(error)),
+                };
+            }
+        }
+
+        Ok(Some(()))
+    }
+
+    // Argument helpers
+    fn arg_author(&self, name: &str) -> String {
+        let domain = self.domain.read().unwrap_or_else(PoisonError::into_inner);
+
+        format!("--author={} <noreply@{}>", name, domain)
+    }
+
+    fn arg_message(&self, message: &str) -> String {
+        format!("--message={}", message)
+    }
+
+    // Process helpers
+    fn repo(&self) -> OsString {
+        self.repo.as_os_str().to_os_string()
+    }
+
+    fn spawn(&self, _guard: &mut RevisionBlock, arguments: &[&OsStr]) -> Result<()> {
+        super::run(self.repo(), arguments, self.timeout)
+    }
+
+    fn spawn_output(&self, _guard: &mut RevisionBlock, arguments: &[&OsStr]) -> Result<OwnedBytes> {
+        super::run_output(self.repo(), arguments, self.timeout)
+    }
+
+    // Git helper
+    fn get_commit(&self, guard: &mut RevisionBlock) -> Result<GitHash> {
+        debug!("Getting current HEAD commit");
+
+        let args = arguments!["git", "rev-parse", "--verify", "HEAD"];
+
+        let digest_bytes = self.spawn_output(guard, &args)?;
+        let digest = str::from_utf8(&digest_bytes)
+            .map_err(|_| Error::StaticMsg("git hash wasn't valid UTF-8"))?;
+
+        let hash = GitHash::try_from(digest)
+            .map_err(|_| Error::StaticMsg("unable to parse git hash from output"))?;
+
+        Ok(hash)
+    }
+
+    #[cfg(test)]
+    fn check_clean(&self, guard: &mut RevisionBlock) {
+        debug!("Checking if repository is clean");
+
+        let args = arguments!["git", "status", "--porcelain"];
+        let output = self
+            .spawn_output(guard, &args)
+            .expect("Unable to get git status");
+
+        if !output.is_empty() {
+            panic!(
+                "Git repository is not clean:\n{}",
+                String::from_utf8_lossy(&output),
+            );
+        }
+    }
+
+    #[cfg(not(test))]
+    fn check_clean(&self, _guard: &mut RevisionBlock) {}
+}
@@ -367,6 +367,229 @@ async fn git_internal() {
     }
 }
 
+#[test]
+fn blame_stream() {
+    color_backtrace::install();
+
+    task::block_on(blame_stream_internal());
+}
+
+async fn blame_stream_internal() {
+    use futures::stream::TryStreamExt;
+    use std::time::Duration;
+
+    // Create revision store
+    let directory = tempdir().expect("Unable to create temporary directory");
+    let repo = directory.path();
+    let store = RevisionStore::new(repo, "example.org");
+    store
+        .initial_commit()
+        .await
+        .expect("Unable to create initial commit");
+
+    // Build up a file with lines from several commits
+    let contents = [
+        ("alpha", "line one\nline two\nline three\n"),
+        ("beta", "line one\nline 2\nline three\nline four\n"),
+        (
+            "gamma",
+            "line zero\nline one\nline 2\nline three\nline four\n",
+        ),
+        (
+            "alpha",
+            "line zero\nline one\nline 2\nline 3\nline four\nline five\n",
+        ),
+    ];
+
+    for (username, content) in contents.iter() {
+        let info = CommitInfo {
+            username,
+            message: "blame stream test",
+        };
+
+        store
+            .commit("blame-test", Some(*content), info)
+            .await
+            .expect("Unable to commit");
+    }
+
+    let blame = store
+        .get_blame("blame-test", None)
+        .await
+        .expect("Unable to get blame")
+        .expect("No blame for page");
+
+    let groups = store
+        .blame_stream("blame-test", None)
+        .await
+        .expect("Unable to start blame stream")
+        .try_collect::<Vec<_>>()
+        .await
+        .expect("Unable to stream blame");
+
+    assert!(!groups.is_empty(), "No blame groups streamed");
+    assert_eq!(groups, blame.groups, "Streamed blame doesn't match");
+
+    let line_count: usize = groups.iter().map(|group| group.lines.len()).sum();
+    assert_eq!(line_count, 6, "Streamed blame has wrong number of lines");
+
+    // Missing page
+    let result = store
+        .blame_stream("nonexistent", None)
+        .await
+        .expect("Unable to start blame stream")
+        .try_collect::<Vec<_>>()
+        .await;
+
+    assert!(result.is_err(), "Blame stream for missing page succeeded");
+
+    // Alternate lines between two commits, so the output is far larger than a pipe buffer
+    let mut first = String::new();
+    let mut second = String::new();
+
+    for i in 0..4000 {
+        writeln!(&mut first, "line {}", i).unwrap();
+
+        if i % 2 == 0 {
+            writeln!(&mut second, "line {}", i).unwrap();
+        } else {
+            writeln!(&mut second, "changed line {}", i).unwrap();
+        }
+    }
+
+    for content in &[first, second] {
+        let info = CommitInfo {
+            username: "blame",
+            message: "large blame test",
+        };
+
+        store
+            .commit("large-blame", Some(content), info)
+            .await
+            .expect("Unable to commit");
+    }
+
+    // Dropping a stream before it is read must not hang, and releases the store
+    let stream = store
+        .blame_stream("large-blame", None)
+        .await
+        .expect("Unable to start blame stream");
+
+    task::sleep(Duration::from_millis(100)).await;
+    drop(stream);
+
+    let blame = store
+        .get_blame("large-blame", None)
+        .await
+        .expect("Unable to get blame")
+        .expect("No blame for page");
+
+    let line_count: usize = blame.groups.iter().map(|group| group.lines.len()).sum();
+    assert_eq!(line_count, 4000, "Blame has wrong number of lines");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn timeout_fds() {
+    color_backtrace::install();
+
+    task::block_on(timeout_fds_internal());
+}
+
+#[cfg(target_os = "linux")]
+async fn timeout_fds_internal() {
+    use super::{spawn, DEFAULT_TIMEOUT};
+    use crate::Error;
+    use futures::future::join_all;
+    use std::ffi::OsStr;
+    use std::fs;
+
+    const PROCESSES: usize = 16;
+
+    fn open_fds() -> usize {
+        fs::read_dir("/proc/self/fd")
+            .expect("Unable to read open file descriptors")
+            .count()
+    }
+
+    let directory = tempdir().expect("Unable to create temporary directory");
+    let arguments = [OsStr::new("sleep"), OsStr::new("10")];
+
+    // Warm up, so lazily-opened descriptors aren't counted
+    let _ = spawn(directory.path().into(), &arguments, DEFAULT_TIMEOUT).await;
+    let before = open_fds();
+
+    for _ in 0..2 {
+        let results = join_all(
+            (0..PROCESSES).map(|_| spawn(directory.path().into(), &arguments, DEFAULT_TIMEOUT)),
+        )
+        .await;
+
+        for result in results {
+            match result {
+                Err(Error::CommandFailed(_)) => (),
+                _ => panic!("Command didn't time out"),
+            }
+        }
+    }
+
+    // Other tests run concurrently in this process, so allow some slack.
+    // A leak would leave at least one pipe per timed out process.
+    let after = open_fds();
+    assert!(
+        after < before + PROCESSES,
+        "File descriptors leaked by timed out processes ({} -> {})",
+        before,
+        after,
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn custom_timeout() {
+    color_backtrace::install();
+
+    task::block_on(custom_timeout_internal());
+}
+
+#[cfg(unix)]
+async fn custom_timeout_internal() {
+    use super::{spawn, DEFAULT_TIMEOUT};
+    use crate::Error;
+    use std::ffi::OsStr;
+    use std::time::Duration;
+
+    let directory = tempdir().expect("Unable to create temporary directory");
+    let repo = directory.path();
+
+    // Store uses the configured timeout
+    let store = RevisionStore::new(repo, "example.org");
+    assert_eq!(store.timeout(), DEFAULT_TIMEOUT);
+
+    let timeout = Duration::from_secs(10);
+    let store = RevisionStore::with_timeout(repo, "example.org", timeout);
+    assert_eq!(store.timeout(), timeout);
+
+    store
+        .initial_commit()
+        .await
+        .expect("Unable to create initial commit");
+
+    // Process which outlasts a short timeout
+    let arguments = [OsStr::new("sleep"), OsStr::new("1")];
+    let result = spawn(repo.into(), &arguments, Duration::from_millis(100)).await;
+
+    match result {
+        Err(Error::CommandFailed(_)) => (),
+        _ => panic!("Command didn't time out"),
+    }
+
+    // Same process with a longer timeout
+    spawn(repo.into(), &arguments, Duration::from_secs(5))
+        .await
+        .expect("Command timed out despite longer timeout");
+}
+
 #[test]
 fn thread() {
     color_backtrace::install();
@@ -441,3 +664,135 @@ async fn thread_internal() {
 
     assert_eq!(pruned, 0, "Pruned objects were found");
 }
+
+#[test]
+fn concurrent_reads() {
+    color_backtrace::install();
+
+    task::block_on(concurrent_reads_internal());
+}
+
+async fn concurrent_reads_internal() {
+    use async_std::future::timeout;
+    use futures::future::join_all;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const TASKS: usize = 50;
+    const PAGES: usize = 5;
+
+    // Create revision store
+    let directory = tempdir().expect("Unable to create temporary directory");
+    let repo = directory.path();
+    let store = RevisionStore::new(repo, "example.org");
+    store
+        .initial_commit()
+        .await
+        .expect("Unable to create initial commit");
+
+    let info = CommitInfo {
+        username: "username",
+        message: "message",
+    };
+
+    for i in 0..PAGES {
+        let slug = format!("page-{}", i);
+        let content = format!("Contents of page {}", i);
+
+        store
+            .commit(&slug, Some(content.as_str()), info)
+            .await
+            .expect("Unable to commit page");
+    }
+
+    // Read pages from many tasks at once
+    let store = Arc::new(store);
+    let handles = (0..TASKS).map(|i| {
+        let store = Arc::clone(&store);
+
+        task::spawn(async move {
+            let slug = format!("page-{}", i % PAGES);
+            let content = store
+                .get_page(&slug)
+                .await
+                .expect("Unable to get page")
+                .expect("Page not found");
+
+            assert_eq!(content, format!("Contents of page {}", i % PAGES));
+        })
+    });
+
+    timeout(Duration::from_secs(60), join_all(handles))
+        .await
+        .expect("Concurrent reads did not complete in time");
+}
+
+#[test]
+fn ancestor() {
+    color_backtrace::install();
+
+    task::block_on(ancestor_internal());
+}
+
+async fn ancestor_internal() {
+    use super::spawn_output;
+    use crate::Error;
+    use deepwell_core::models::GitHash;
+    use std::convert::TryFrom;
+    use std::ffi::OsStr;
+
+    let directory = tempdir().expect("Unable to create temporary directory");
+    let repo = directory.path();
+    let store = RevisionStore::new(repo, "example.org");
+    store
+        .initial_commit()
+        .await
+        .expect("Unable to create initial commit");
+
+    macro_rules! commit {
+        ($content:expr) => {{
+            let info = CommitInfo {
+                username: "test",
+                message: "ancestor test",
+            };
+
+            store
+                .commit("page", Some($content), info)
+                .await
+                .expect("Unable to commit")
+        }};
+    }
+
+    let first = commit!("first");
+    let second = commit!("second");
+
+    assert!(store.is_ancestor(&first).await.expect("Unable to check"));
+    assert!(store.is_ancestor(&second).await.expect("Unable to check"));
+
+    // Commit which exists, but not in the current history
+    let arguments = [
+        OsStr::new("git"),
+        OsStr::new("commit-tree"),
+        OsStr::new("HEAD^{tree}"),
+        OsStr::new("-m"),
+        OsStr::new("dangling"),
+    ];
+
+    let output = spawn_output(repo.into(), &arguments, store.timeout())
+        .await
+        .expect("Unable to create dangling commit");
+
+    let output = str::from_utf8(&output).expect("Output wasn't UTF-8");
+    let dangling = GitHash::try_from(output.trim()).expect("Invalid git hash");
+
+    assert!(!store.is_ancestor(&dangling).await.expect("Unable to check"));
+
+    // Commit which doesn't exist at all
+    let fabricated =
+        GitHash::try_from("0123456789abcdef0123456789abcdef01234567").expect("Invalid git hash");
+
+    match store.is_ancestor(&fabricated).await {
+        Err(Error::RevisionNotFound) => (),
+        _ => panic!("Fabricated commit didn't error"),
+    }
+}
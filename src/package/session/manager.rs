@@ -23,18 +23,78 @@ use crate::manager_prelude::*;
 use crate::schema::{login_attempts, sessions};
 use crate::utils::rows_to_result;
 use chrono::prelude::*;
+use chrono::Duration;
+use diesel::pg::expression::dsl::any;
 
 pub struct SessionManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
+    max_login_failures: i64,
+    login_failure_window: Duration,
+    expires_after: Duration,
 }
 
 impl SessionManager {
     #[inline]
-    pub fn new(conn: &Arc<PgConnection>) -> Self {
+    pub fn new(
+        conn: &Arc<SharedConnection>,
+        max_login_failures: u32,
+        login_failure_window: Duration,
+        expires_after: Duration,
+    ) -> Self {
         debug!("Creating session-manager service");
 
         let conn = Arc::clone(conn);
-        SessionManager { conn }
+        SessionManager {
+            conn,
+            max_login_failures: i64::from(max_login_failures),
+            login_failure_window,
+            expires_after,
+        }
+    }
+
+    /// Counts failed login attempts for this user within the given window.
+    /// Only attempts made after their most recent successful login are counted.
+    pub async fn count_recent_failures(&self, user_id: UserId, within: Duration) -> Result<i64> {
+        debug!(
+            "Counting failed login attempts for user ID {} in the last {}",
+            user_id, within,
+        );
+
+        let mut since = Utc::now() - within;
+        if let Some(attempt) = self.get_last_successful_login(user_id).await? {
+            if attempt.attempted_at() > since {
+                since = attempt.attempted_at();
+            }
+        }
+
+        let id: i64 = user_id.into();
+        let count = login_attempts::table
+            .filter(login_attempts::user_id.eq(id))
+            .filter(login_attempts::success.eq(false))
+            .filter(login_attempts::attempted_at.gt(since))
+            .count()
+            .get_result::<i64>(&*self.conn.lock())?;
+
+        Ok(count)
+    }
+
+    /// Returns `TooManyLoginAttempts` if the user has reached
+    /// the configured number of recent failed logins.
+    pub async fn check_login_throttle(&self, user_id: UserId) -> Result<()> {
+        let failures = self
+            .count_recent_failures(user_id, self.login_failure_window)
+            .await?;
+
+        if failures >= self.max_login_failures {
+            warn!(
+                "User ID {} has {} recent failed login attempts, rejecting",
+                user_id, failures,
+            );
+
+            return Err(Error::TooManyLoginAttempts);
+        }
+
+        Ok(())
     }
 
     pub async fn add_login_attempt(
@@ -77,7 +137,7 @@ impl SessionManager {
         let id = diesel::insert_into(login_attempts::table)
             .values(&model)
             .returning(login_attempts::dsl::login_attempt_id)
-            .get_result::<LoginAttemptId>(&*self.conn)?;
+            .get_result::<LoginAttemptId>(&*self.conn.lock())?;
 
         Ok(id)
     }
@@ -100,7 +160,7 @@ impl SessionManager {
         // Mark login attempt as successful
         diesel::update(dsl::login_attempts.filter(dsl::login_attempt_id.eq(login_attempt_id)))
             .set(dsl::success.eq(true))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         // Add session
         let model = NewSession {
@@ -114,8 +174,9 @@ impl SessionManager {
                 sessions::dsl::session_id,
                 sessions::dsl::user_id,
                 sessions::dsl::login_attempt_id,
+                sessions::dsl::last_active_at,
             ))
-            .get_result::<Session>(&*self.conn)?;
+            .get_result::<Session>(&*self.conn.lock())?;
 
         Ok(session)
     }
@@ -128,13 +189,82 @@ impl SessionManager {
         let result = sessions::table
             .filter(sessions::session_id.eq(session))
             .filter(sessions::user_id.eq(user))
-            .first::<Session>(&*self.conn)
+            .first::<Session>(&*self.conn.lock())
             .optional()?;
 
+        let now = Utc::now();
         match result {
-            Some(_) => Ok(()),
             None => Err(Error::InvalidSession),
+            Some(session) if session.last_active_at() + self.expires_after < now => {
+                debug!("Session ID {} has expired", session_id);
+
+                Err(Error::SessionExpired)
+            }
+            Some(_) => {
+                diesel::update(sessions::table.find(session))
+                    .set(sessions::last_active_at.eq(now))
+                    .execute(&*self.conn.lock())?;
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks several sessions at once, returning whether each is valid.
+    /// Results are aligned with the input, and valid sessions have their activity refreshed.
+    pub async fn check_sessions(&self, pairs: &[(SessionId, UserId)]) -> Result<Vec<bool>> {
+        debug!("Checking {} sessions", pairs.len());
+
+        let sessions = {
+            let ids: Vec<_> = pairs.iter().map(|(id, _)| id.to_i64()).collect();
+            sessions::table
+                .filter(sessions::session_id.eq(any(ids)))
+                .load::<Session>(&*self.conn.lock())?
+        };
+
+        let now = Utc::now();
+        let valid = pairs
+            .iter()
+            .map(|&(session_id, user_id)| {
+                sessions.iter().any(|session| {
+                    session.session_id() == session_id
+                        && session.user_id() == user_id
+                        && session.last_active_at() + self.expires_after >= now
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let refresh: Vec<_> = pairs
+            .iter()
+            .zip(&valid)
+            .filter(|(_, &valid)| valid)
+            .map(|((id, _), _)| id.to_i64())
+            .collect();
+
+        if !refresh.is_empty() {
+            diesel::update(sessions::table)
+                .filter(sessions::session_id.eq(any(refresh)))
+                .set(sessions::last_active_at.eq(now))
+                .execute(&*self.conn.lock())?;
         }
+
+        Ok(valid)
+    }
+
+    /// Deletes all sessions which have been inactive longer than the expiry period.
+    /// Returns the number of sessions removed.
+    pub async fn purge_expired(&self) -> Result<usize> {
+        info!(
+            "Purging sessions inactive for longer than {}",
+            self.expires_after
+        );
+
+        let cutoff = Utc::now() - self.expires_after;
+        let rows = diesel::delete(sessions::table)
+            .filter(sessions::last_active_at.lt(cutoff))
+            .execute(&*self.conn.lock())?;
+
+        Ok(rows)
     }
 
     pub async fn end_session(&self, session_id: SessionId, user_id: UserId) -> Result<()> {
@@ -145,7 +275,7 @@ impl SessionManager {
         let rows = diesel::delete(sessions::table)
             .filter(sessions::session_id.eq(session))
             .filter(sessions::user_id.eq(user))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         if rows_to_result(rows) {
             Ok(())
@@ -177,13 +307,24 @@ impl SessionManager {
             diesel::delete(sessions::table)
                 .filter(sessions::session_id.eq_any(other_ids))
                 .filter(sessions::user_id.eq(user))
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
 
             Ok(others)
         })
         .await
     }
 
+    pub async fn end_all_sessions(&self, user_id: UserId) -> Result<usize> {
+        debug!("Ending all sessions for user ID {}", user_id);
+
+        let id: i64 = user_id.into();
+        let rows = diesel::delete(sessions::table)
+            .filter(sessions::user_id.eq(id))
+            .execute(&*self.conn.lock())?;
+
+        Ok(rows)
+    }
+
     pub async fn get_sessions(
         &self,
         session_id: SessionId,
@@ -198,7 +339,7 @@ impl SessionManager {
         let id: i64 = user_id.into();
         let mut sessions = sessions::table
             .filter(sessions::user_id.eq(id))
-            .get_results::<Session>(&*self.conn)?;
+            .get_results::<Session>(&*self.conn.lock())?;
 
         // Pick out the current session
         let mut current = None;
@@ -210,14 +351,21 @@ impl SessionManager {
         }
 
         // Return an error if there is no current session
-        match current {
-            None => Err(Error::InvalidSession),
-            Some(idx) => {
-                let current = sessions.remove(idx);
+        let current = match current {
+            None => return Err(Error::InvalidSession),
+            Some(idx) => sessions.remove(idx),
+        };
 
-                Ok((current, sessions))
-            }
+        // Expired sessions are no longer active, even if not yet purged
+        let cutoff = Utc::now() - self.expires_after;
+        if current.last_active_at() < cutoff {
+            debug!("Session ID {} has expired", session_id);
+
+            return Err(Error::SessionExpired);
         }
+
+        sessions.retain(|session| session.last_active_at() >= cutoff);
+        Ok((current, sessions))
     }
 
     pub async fn get_login_attempt(
@@ -229,7 +377,7 @@ impl SessionManager {
         let id: i64 = login_attempt_id.into();
         let attempt = login_attempts::table
             .find(id)
-            .first::<LoginAttempt>(&*self.conn)?;
+            .first::<LoginAttempt>(&*self.conn.lock())?;
 
         Ok(attempt)
     }
@@ -251,11 +399,60 @@ impl SessionManager {
             .filter(login_attempts::user_id.eq(id))
             .order_by(login_attempts::attempted_at.desc())
             .limit(100)
-            .get_results::<LoginAttempt>(&*self.conn)?;
+            .get_results::<LoginAttempt>(&*self.conn.lock())?;
 
         Ok(attempts)
     }
 
+    pub async fn get_last_successful_login(&self, user_id: UserId) -> Result<Option<LoginAttempt>> {
+        debug!("Getting last successful login for user ID {}", user_id);
+
+        let id: i64 = user_id.into();
+        let attempt = login_attempts::table
+            .filter(login_attempts::user_id.eq(id))
+            .filter(login_attempts::success.eq(true))
+            .order_by(login_attempts::attempted_at.desc())
+            .first::<LoginAttempt>(&*self.conn.lock())
+            .optional()?;
+
+        Ok(attempt)
+    }
+
+    pub async fn get_last_logins(
+        &self,
+        user_ids: &[UserId],
+    ) -> Result<HashMap<UserId, DateTime<Utc>>> {
+        info!(
+            "Getting last successful logins for user IDs: {:?}",
+            user_ids
+        );
+
+        let result = {
+            let ids: Vec<_> = user_ids.iter().map(|id| id.to_i64()).collect();
+            login_attempts::table
+                .filter(login_attempts::user_id.eq(any(ids)))
+                .filter(login_attempts::success.eq(true))
+                .select((login_attempts::user_id, login_attempts::attempted_at))
+                .load::<(Option<UserId>, DateTime<Utc>)>(&*self.conn.lock())?
+        };
+
+        // Keep only the most recent attempt for each user
+        let mut logins = HashMap::with_capacity(user_ids.len());
+        for (user_id, attempted_at) in result {
+            let user_id = match user_id {
+                Some(user_id) => user_id,
+                None => continue,
+            };
+
+            let last = logins.entry(user_id).or_insert(attempted_at);
+            if attempted_at > *last {
+                *last = attempted_at;
+            }
+        }
+
+        Ok(logins)
+    }
+
     pub async fn get_all_login_attempts<Tz: TimeZone>(
         &self,
         since: DateTime<Tz>,
@@ -266,10 +463,49 @@ impl SessionManager {
             .filter(login_attempts::attempted_at.gt(since))
             .order_by(login_attempts::attempted_at.desc())
             .limit(100)
-            .get_results::<LoginAttempt>(&*self.conn)?;
+            .get_results::<LoginAttempt>(&*self.conn.lock())?;
 
         Ok(attempts)
     }
+
+    /// Removes identifying information from a user's login attempts.
+    /// Failed attempts by their name or email are relabeled with `placeholder`.
+    ///
+    /// The attempts themselves are kept, since they may not be deleted.
+    pub async fn scrub_login_attempts(
+        &self,
+        user_id: UserId,
+        names: &[&str],
+        placeholder: &str,
+    ) -> Result<usize> {
+        use self::login_attempts::dsl;
+
+        info!("Scrubbing login attempts for user ID {}", user_id);
+
+        self.transaction(async {
+            let id: i64 = user_id.into();
+            let by_id = diesel::update(dsl::login_attempts.filter(dsl::user_id.eq(id)))
+                .set((
+                    dsl::username_or_email.eq(None::<&str>),
+                    dsl::remote_address.eq(None::<&str>),
+                ))
+                .execute(&*self.conn.lock())?;
+
+            let by_name = diesel::update(
+                dsl::login_attempts
+                    .filter(dsl::user_id.is_null())
+                    .filter(dsl::username_or_email.eq_any(names)),
+            )
+            .set((
+                dsl::username_or_email.eq(placeholder),
+                dsl::remote_address.eq(None::<&str>),
+            ))
+            .execute(&*self.conn.lock())?;
+
+            Ok(by_id + by_name)
+        })
+        .await
+    }
 }
 
 impl_async_transaction!(SessionManager);
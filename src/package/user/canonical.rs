@@ -0,0 +1,67 @@
+/*
+ * package/user/canonical.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/// Converts an email address into its canonical form.
+///
+/// Two addresses with the same canonical form deliver to the same inbox,
+/// so this is used to prevent registering multiple accounts with one email.
+///
+/// This lowercases the address, strips plus-addressing (`user+tag@`),
+/// and removes dots from Gmail addresses.
+pub fn canonicalize_email(email: &str) -> String {
+    let email = email.trim().to_ascii_lowercase();
+    let (local, domain) = match email.rfind('@') {
+        Some(idx) => (&email[..idx], &email[idx + 1..]),
+        None => return email,
+    };
+
+    // Strip plus-addressing
+    let local = match local.find('+') {
+        Some(0) | None => local,
+        Some(idx) => &local[..idx],
+    };
+
+    match domain {
+        "gmail.com" | "googlemail.com" => format!("{}@gmail.com", local.replace('.', "")),
+        _ => format!("{}@{}", local, domain),
+    }
+}
+
+#[test]
+fn canonical() {
+    macro_rules! check {
+        ($input:expr, $expected:expr) => {
+            assert_eq!(
+                canonicalize_email($input),
+                $expected,
+                "Canonical email doesn't match",
+            );
+        };
+    }
+
+    check!("user@example.com", "user@example.com");
+    check!("User@Example.com", "user@example.com");
+    check!("user+tag@example.com", "user@example.com");
+    check!("first.last@example.com", "first.last@example.com");
+    check!("first.last@gmail.com", "firstlast@gmail.com");
+    check!("First.Last+deepwell@GoogleMail.com", "firstlast@gmail.com");
+    check!("+user@example.com", "+user@example.com");
+    check!("not-an-email", "not-an-email");
+}
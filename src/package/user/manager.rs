@@ -18,25 +18,59 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::models::{NewUser, NewUserVerification, UpdateUser};
+use super::canonical::canonicalize_email;
+use super::models::{NewUser, NewUserVerification, UpdateUser, UserStats};
+use super::validate::*;
 use crate::manager_prelude::*;
-use crate::schema::{user_verification, users};
+use crate::schema::{authors, pages, ratings, revisions, user_verification, users};
 use crate::utils::{lower, rand_alphanum, rows_to_result};
 use cow_utils::CowUtils;
 use diesel::pg::expression::dsl::any;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use ref_map::*;
 
+/// The unique index on `users.email_canonical`.
+const EMAIL_CANONICAL_INDEX: &str = "users_email_canonical_key";
+
+/// Reports a violation of the canonical email index as an email conflict.
+///
+/// `check_conflicts()` cannot see users inserted concurrently,
+/// so the index is what ultimately guarantees uniqueness.
+fn email_conflict(error: DieselError) -> Error {
+    match error {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, ref info)
+            if info.constraint_name() == Some(EMAIL_CANONICAL_INDEX) =>
+        {
+            warn!("Email conflicted with a concurrently written user");
+            Error::UserEmailExists
+        }
+        _ => Error::from(error),
+    }
+}
+
 pub struct UserManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
+    canonicalize_emails: bool,
 }
 
 impl UserManager {
     #[inline]
-    pub fn new(conn: &Arc<PgConnection>) -> Self {
+    pub fn new(conn: &Arc<SharedConnection>, canonicalize_emails: bool) -> Self {
         debug!("Creating user-manager service");
 
         let conn = Arc::clone(conn);
-        UserManager { conn }
+        UserManager {
+            conn,
+            canonicalize_emails,
+        }
+    }
+
+    fn email_canonical(&self, email: &str) -> String {
+        if self.canonicalize_emails {
+            canonicalize_email(email)
+        } else {
+            email.to_ascii_lowercase()
+        }
     }
 
     async fn check_conflicts(&self, name: Option<&str>, email: Option<&str>) -> Result<()> {
@@ -56,22 +90,27 @@ impl UserManager {
         // Compare against empty string to avoid conflicts with yourself
         let name = name.unwrap_or("");
         let email = email.unwrap_or("");
+        let email_canonical = match email {
+            "" => String::new(),
+            _ => self.email_canonical(email),
+        };
 
         // Query table for conflicts
         let result = users::table
             .filter(lower(users::name).eq(lower(name)))
             .or_filter(users::email.eq(lower(email)))
-            .select((dsl::user_id, dsl::name, dsl::email))
-            .get_result::<(UserId, String, String)>(&*self.conn)
+            .or_filter(users::email_canonical.eq(&email_canonical))
+            .select((dsl::user_id, dsl::name, dsl::email, dsl::email_canonical))
+            .get_result::<(UserId, String, String, String)>(&*self.conn.lock())
             .optional()?;
 
-        if let Some((user_id, conflict_name, conflict_email)) = result {
+        if let Some((user_id, conflict_name, conflict_email, conflict_canonical)) = result {
             if name == conflict_name {
                 warn!("Cannot create user, name conflicts with ID {}", user_id);
                 return Err(Error::UserNameExists);
             }
 
-            if email == conflict_email {
+            if email == conflict_email || email_canonical == conflict_canonical {
                 warn!("Cannot create user, email conflicts with ID {}", user_id);
                 return Err(Error::UserEmailExists);
             }
@@ -94,17 +133,20 @@ impl UserManager {
 
         // Lowercase fields
         let email = email.cow_to_ascii_lowercase();
+        let email_canonical = self.email_canonical(&email);
 
         // If not, insert into database
         let model = NewUser {
             name,
             email: &email,
+            email_canonical: &email_canonical,
         };
 
         let id = diesel::insert_into(users::table)
             .values(&model)
             .returning(users::dsl::user_id)
-            .get_result::<UserId>(&*self.conn)?;
+            .get_result::<UserId>(&*self.conn.lock())
+            .map_err(email_conflict)?;
 
         Ok(id)
     }
@@ -115,7 +157,7 @@ impl UserManager {
         let id: i64 = id.into();
         let result = users::table
             .filter(users::user_id.eq(id))
-            .first::<User>(&*self.conn)
+            .first::<User>(&*self.conn.lock())
             .optional()?;
 
         Ok(result)
@@ -130,7 +172,7 @@ impl UserManager {
             users::table
                 .filter(users::user_id.eq(any(ids)))
                 .order_by(users::user_id.asc())
-                .load::<User>(&*self.conn)?
+                .load::<User>(&*self.conn.lock())?
         };
 
         // Add in nones where needed
@@ -155,6 +197,33 @@ impl UserManager {
         Ok(users)
     }
 
+    pub async fn get_ids_by_names(&self, names: &[&str]) -> Result<HashMap<String, UserId>> {
+        info!("Getting user IDs for names: {:?}", names);
+
+        // Map lowercase names back to how they were passed in
+        let mut lookup = HashMap::with_capacity(names.len());
+        for name in names.iter().copied() {
+            lookup.insert(name.to_lowercase(), name);
+        }
+
+        let result = {
+            let names: Vec<_> = lookup.keys().cloned().collect();
+            users::table
+                .filter(lower(users::name).eq(any(names)))
+                .select((users::user_id, users::name))
+                .load::<(UserId, String)>(&*self.conn.lock())?
+        };
+
+        let mut ids = HashMap::with_capacity(result.len());
+        for (id, name) in result {
+            if let Some(name) = lookup.get(&name.to_lowercase()) {
+                ids.insert(String::from(*name), id);
+            }
+        }
+
+        Ok(ids)
+    }
+
     pub async fn get_id_from_email_or_name(&self, name_or_email: &str) -> Result<Option<UserId>> {
         info!("Getting user ID for username or email '{}'", name_or_email);
 
@@ -162,7 +231,7 @@ impl UserManager {
             .filter(lower(users::name).eq(lower(name_or_email)))
             .or_filter(users::email.eq(lower(name_or_email)))
             .select(users::dsl::user_id)
-            .first::<UserId>(&*self.conn)
+            .first::<UserId>(&*self.conn.lock())
             .optional()?;
 
         Ok(result)
@@ -173,7 +242,7 @@ impl UserManager {
 
         let result = users::table
             .filter(users::email.eq(lower(email)))
-            .first::<User>(&*self.conn)
+            .first::<User>(&*self.conn.lock())
             .optional()?;
 
         Ok(result)
@@ -184,12 +253,85 @@ impl UserManager {
 
         let result = users::table
             .filter(lower(users::name).eq(lower(name)))
-            .first::<User>(&*self.conn)
+            .first::<User>(&*self.conn.lock())
             .optional()?;
 
         Ok(result)
     }
 
+    pub async fn get_users_created_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_inactive: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<User>> {
+        info!(
+            "Getting users created between {} and {} (include inactive: {}, limit {}, offset {})",
+            from, to, include_inactive, limit, offset,
+        );
+
+        let mut query = users::table
+            .filter(users::created_at.ge(from))
+            .filter(users::created_at.lt(to))
+            .into_boxed();
+
+        if !include_inactive {
+            query = query.filter(users::deleted_at.is_null());
+        }
+
+        let users = query
+            .order_by(users::created_at.asc())
+            .then_order_by(users::user_id.asc())
+            .limit(limit as i64)
+            .offset(offset as i64)
+            .load::<User>(&*self.conn.lock())?;
+
+        Ok(users)
+    }
+
+    pub async fn get_stats(&self, id: UserId) -> Result<UserStats> {
+        use diesel::dsl::{sql, sum};
+        use diesel::sql_types::BigInt;
+
+        info!("Getting statistics for user ID {}", id);
+
+        let id: i64 = id.into();
+
+        self.transaction(async {
+            let authored = authors::table
+                .inner_join(pages::table)
+                .filter(authors::user_id.eq(id))
+                .filter(pages::deleted_at.is_null());
+
+            // Diesel does not support COUNT(DISTINCT ...), and a user
+            // can be multiple kinds of author for the same page.
+            let authored_pages = authored
+                .clone()
+                .select(sql::<BigInt>("COUNT(DISTINCT authors.page_id)"))
+                .get_result::<i64>(&*self.conn.lock())?;
+
+            let total_edits = revisions::table
+                .filter(revisions::user_id.eq(id))
+                .count()
+                .get_result::<i64>(&*self.conn.lock())?;
+
+            let karma = ratings::table
+                .filter(ratings::page_id.eq_any(authored.select(authors::page_id)))
+                .select(sum(ratings::rating))
+                .get_result::<Option<i64>>(&*self.conn.lock())?
+                .unwrap_or(0);
+
+            Ok(UserStats {
+                authored_pages,
+                total_edits,
+                karma,
+            })
+        })
+        .await
+    }
+
     pub async fn edit(&self, id: UserId, changes: UserMetadata<'_>) -> Result<()> {
         use self::users::dsl;
 
@@ -204,6 +346,13 @@ impl UserManager {
             location,
         } = changes;
 
+        // Trim and validate profile fields
+        let user_page = check_url_field("user_page", user_page)?;
+        let website = check_url_field("website", website)?;
+        let about = check_field("about", about, MAX_ABOUT_LENGTH)?;
+        let gender = check_field("gender", gender, MAX_GENDER_LENGTH)?;
+        let location = check_field("location", location, MAX_LOCATION_LENGTH)?;
+
         // Always run this to ensure the user exists
         let user = self //
             .get_from_id(id)
@@ -229,6 +378,8 @@ impl UserManager {
         // Lowercase fields
         let email = email.map(|s| s.cow_to_ascii_lowercase());
         let email = email.ref_map(|s| s.as_ref());
+        let email_canonical = email.map(|s| self.email_canonical(s));
+        let email_canonical = email_canonical.ref_map(|s| s.as_str());
 
         let gender = gender.map(|s| s.cow_to_ascii_lowercase());
         let gender = gender.ref_map(|s| s.as_ref());
@@ -238,6 +389,7 @@ impl UserManager {
         let model = UpdateUser {
             name,
             email,
+            email_canonical,
             is_verified,
             user_page,
             website,
@@ -253,12 +405,40 @@ impl UserManager {
             let id: i64 = id.into();
             diesel::update(dsl::users.filter(dsl::user_id.eq(id)))
                 .set(&model)
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())
+                .map_err(email_conflict)?;
         }
 
         Ok(())
     }
 
+    pub async fn change_email(&self, id: UserId, new_email: &str) -> Result<String> {
+        use self::users::dsl;
+
+        info!("Changing email for user ID {} to '{}'", id, new_email);
+
+        self.transaction(async {
+            // Checks for conflicts with other users
+            let changes = UserMetadata {
+                email: Some(new_email),
+                ..UserMetadata::default()
+            };
+
+            self.edit(id, changes).await?;
+
+            // Ensure the user is unverified even if the address was unchanged
+            let id_raw: i64 = id.into();
+            diesel::update(dsl::users.filter(dsl::user_id.eq(id_raw)))
+                .set(dsl::is_verified.eq(false))
+                .execute(&*self.conn.lock())?;
+
+            // Tokens sent to the previous address must not verify the new one
+            self.clear_tokens(id).await?;
+            self.create_token(id).await
+        })
+        .await
+    }
+
     pub async fn verify(&self, id: UserId) -> Result<()> {
         use self::users::dsl;
 
@@ -267,7 +447,7 @@ impl UserManager {
         let id: i64 = id.into();
         diesel::update(dsl::users.filter(dsl::user_id.eq(id)))
             .set(dsl::is_verified.eq(true))
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(())
     }
@@ -279,7 +459,7 @@ impl UserManager {
             let user_id = user_verification::table
                 .filter(user_verification::token.eq(token))
                 .select(user_verification::dsl::user_id)
-                .first::<UserId>(&*self.conn)
+                .first::<UserId>(&*self.conn.lock())
                 .optional()?;
 
             match user_id {
@@ -289,7 +469,7 @@ impl UserManager {
 
                     let rows = diesel::delete(user_verification::table)
                         .filter(user_verification::token.eq(token))
-                        .execute(&*self.conn)?;
+                        .execute(&*self.conn.lock())?;
 
                     if rows_to_result(rows) {
                         Ok(())
@@ -313,11 +493,61 @@ impl UserManager {
 
         diesel::insert_into(user_verification::table)
             .values(&model)
-            .execute(&*self.conn)?;
+            .execute(&*self.conn.lock())?;
 
         Ok(token)
     }
 
+    pub async fn anonymize(&self, id: UserId) -> Result<()> {
+        use self::users::dsl;
+        use diesel::dsl::now;
+
+        info!("Anonymizing user ID {}", id);
+
+        let name = format!("deleted-user-{}", id);
+        let email = format!("{}@deleted.invalid", name);
+
+        self.transaction(async {
+            let id: i64 = id.into();
+            let rows = diesel::update(dsl::users.filter(dsl::user_id.eq(id)))
+                .set((
+                    dsl::name.eq(&name),
+                    dsl::email.eq(&email),
+                    dsl::email_canonical.eq(&email),
+                    dsl::is_verified.eq(false),
+                    dsl::user_page.eq(""),
+                    dsl::website.eq(""),
+                    dsl::about.eq(""),
+                    dsl::gender.eq(""),
+                    dsl::location.eq(""),
+                    dsl::deleted_at.eq(now),
+                ))
+                .execute(&*self.conn.lock())?;
+
+            if !rows_to_result(rows) {
+                return Err(Error::UserNotFound);
+            }
+
+            diesel::delete(user_verification::table)
+                .filter(user_verification::user_id.eq(id))
+                .execute(&*self.conn.lock())?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn clear_tokens(&self, id: UserId) -> Result<usize> {
+        info!("Removing all verification tokens for user ID {}", id);
+
+        let id: i64 = id.into();
+        let rows = diesel::delete(user_verification::table)
+            .filter(user_verification::user_id.eq(id))
+            .execute(&*self.conn.lock())?;
+
+        Ok(rows)
+    }
+
     pub async fn mark_inactive(&self, id: UserId, value: bool) -> Result<()> {
         use self::users::dsl;
         use diesel::dsl::now;
@@ -335,11 +565,12 @@ impl UserManager {
         if value {
             diesel::update(condition)
                 .set(dsl::deleted_at.eq(now))
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
         } else {
             let model = UpdateUser {
                 name: None,
                 email: None,
+                email_canonical: None,
                 is_verified: None,
                 user_page: None,
                 website: None,
@@ -349,7 +580,9 @@ impl UserManager {
                 deleted_at: Some(None),
             };
 
-            diesel::update(condition).set(&model).execute(&*self.conn)?;
+            diesel::update(condition)
+                .set(&model)
+                .execute(&*self.conn.lock())?;
         }
 
         Ok(())
@@ -362,6 +595,7 @@ impl Debug for UserManager {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("UserManager")
             .field("conn", &"PgConnection { .. }")
+            .field("canonicalize_emails", &self.canonicalize_emails)
             .finish()
     }
 }
@@ -18,8 +18,10 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+mod canonical;
 mod manager;
 mod models;
+mod validate;
 
 pub use self::manager::*;
 pub use self::models::*;
@@ -26,6 +26,7 @@ use chrono::prelude::*;
 pub struct NewUser<'a> {
     pub name: &'a str,
     pub email: &'a str,
+    pub email_canonical: &'a str,
 }
 
 type Nullable<T> = Option<T>;
@@ -35,6 +36,7 @@ type Nullable<T> = Option<T>;
 pub struct UpdateUser<'a> {
     pub name: Option<&'a str>,
     pub email: Option<&'a str>,
+    pub email_canonical: Option<&'a str>,
     pub is_verified: Option<bool>,
     pub user_page: Option<&'a str>,
     pub website: Option<&'a str>,
@@ -48,6 +50,7 @@ impl UpdateUser<'_> {
     pub fn has_changes(&self) -> bool {
         self.name.is_some()
             || self.email.is_some()
+            || self.email_canonical.is_some()
             || self.is_verified.is_some()
             || self.user_page.is_some()
             || self.website.is_some()
@@ -64,3 +67,15 @@ pub struct NewUserVerification<'a> {
     pub user_id: i64,
     pub token: &'a str,
 }
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct UserStats {
+    /// The number of extant pages the user is listed as an author of.
+    pub authored_pages: i64,
+
+    /// The number of revisions the user has made, across all pages.
+    pub total_edits: i64,
+
+    /// The sum of all ratings on extant pages the user is an author of.
+    pub karma: i64,
+}
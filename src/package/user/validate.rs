@@ -0,0 +1,83 @@
+/*
+ * package/user/validate.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::{Error, Result};
+
+pub const MAX_URL_LENGTH: usize = 256;
+pub const MAX_ABOUT_LENGTH: usize = 4096;
+pub const MAX_GENDER_LENGTH: usize = 64;
+pub const MAX_LOCATION_LENGTH: usize = 128;
+
+/// Trims the given profile field, ensuring it is not too long.
+pub fn check_field<'a>(
+    field: &'static str,
+    value: Option<&'a str>,
+    max_length: usize,
+) -> Result<Option<&'a str>> {
+    let value = match value {
+        Some(value) => value.trim(),
+        None => return Ok(None),
+    };
+
+    if value.len() > max_length {
+        warn!(
+            "Profile field '{}' too long ({} > {})",
+            field,
+            value.len(),
+            max_length,
+        );
+
+        return Err(Error::InvalidProfileField(field));
+    }
+
+    Ok(Some(value))
+}
+
+/// Like `check_field()`, but also ensures that a non-empty value looks like a URL.
+pub fn check_url_field<'a>(field: &'static str, value: Option<&'a str>) -> Result<Option<&'a str>> {
+    let value = check_field(field, value, MAX_URL_LENGTH)?;
+
+    if let Some(url) = value {
+        if !url.is_empty() && !is_url(url) {
+            warn!("Profile field '{}' is not a valid URL: {}", field, url);
+
+            return Err(Error::InvalidProfileField(field));
+        }
+    }
+
+    Ok(value)
+}
+
+fn is_url(url: &str) -> bool {
+    let (scheme, rest) = match url.find("://") {
+        Some(idx) => (&url[..idx], &url[idx + 3..]),
+        None => return false,
+    };
+
+    if scheme != "http" && scheme != "https" {
+        return false;
+    }
+
+    let host = rest.split('/').next().unwrap_or("");
+
+    !host.is_empty()
+        && !host.starts_with('.')
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
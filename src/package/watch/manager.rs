@@ -0,0 +1,96 @@
+/*
+ * package/watch/manager.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::NewPageWatch;
+use crate::manager_prelude::*;
+use crate::schema::page_watches;
+use crate::utils::rows_to_result;
+
+pub struct WatchManager {
+    conn: Arc<SharedConnection>,
+}
+
+impl WatchManager {
+    #[inline]
+    pub fn new(conn: &Arc<SharedConnection>) -> Self {
+        debug!("Creating watch-manager service");
+
+        let conn = Arc::clone(conn);
+        WatchManager { conn }
+    }
+
+    pub async fn add(&self, page_id: PageId, user_id: UserId) -> Result<bool> {
+        info!(
+            "Adding watch for page ID {} by user ID {}",
+            page_id, user_id
+        );
+
+        let model = NewPageWatch {
+            page_id: page_id.into(),
+            user_id: user_id.into(),
+        };
+
+        let rows = diesel::insert_into(page_watches::table)
+            .values(&model)
+            .on_conflict_do_nothing()
+            .execute(&*self.conn.lock())?;
+
+        Ok(rows_to_result(rows))
+    }
+
+    pub async fn remove(&self, page_id: PageId, user_id: UserId) -> Result<bool> {
+        info!(
+            "Removing watch for page ID {} by user ID {}",
+            page_id, user_id,
+        );
+
+        let page_id: i64 = page_id.into();
+        let user_id: i64 = user_id.into();
+        let rows = diesel::delete(page_watches::table)
+            .filter(page_watches::dsl::page_id.eq(page_id))
+            .filter(page_watches::dsl::user_id.eq(user_id))
+            .execute(&*self.conn.lock())?;
+
+        Ok(rows_to_result(rows))
+    }
+
+    pub async fn get_watchers(&self, page_id: PageId) -> Result<Vec<UserId>> {
+        debug!("Getting watchers for page ID {}", page_id);
+
+        let id: i64 = page_id.into();
+        let watchers = page_watches::table
+            .filter(page_watches::dsl::page_id.eq(id))
+            .order_by(page_watches::dsl::user_id.asc())
+            .select(page_watches::dsl::user_id)
+            .load::<UserId>(&*self.conn.lock())?;
+
+        Ok(watchers)
+    }
+}
+
+impl_async_transaction!(WatchManager);
+
+impl Debug for WatchManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WatchManager")
+            .field("conn", &"PgConnection { .. }")
+            .finish()
+    }
+}
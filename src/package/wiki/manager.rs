@@ -21,19 +21,19 @@
 use super::models::*;
 use crate::manager_prelude::*;
 use crate::schema::{wiki_settings, wikis};
-use async_std::sync::RwLockWriteGuard;
+use diesel::pg::expression::dsl::any;
 
 pub struct WikiManager {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
     wikis: RwLock<HashMap<WikiId, Wiki>>,
 }
 
 impl WikiManager {
-    pub fn new(conn: &Arc<PgConnection>) -> Result<Self> {
+    pub fn new(conn: &Arc<SharedConnection>) -> Result<Self> {
         debug!("Creating wiki-manager service");
 
         let conn = Arc::clone(conn);
-        let values = wikis::table.load::<Wiki>(&*conn)?;
+        let values = wikis::table.load::<Wiki>(&*conn.lock())?;
 
         let wikis = {
             let mut map = HashMap::with_capacity(values.len());
@@ -47,51 +47,87 @@ impl WikiManager {
         Ok(WikiManager { conn, wikis })
     }
 
-    pub async fn create(
-        &self,
-        name: &str,
-        slug: &str,
-        domain: &str,
-    ) -> Result<(WikiId, RwLockWriteGuard<'_, HashMap<WikiId, Wiki>>)> {
+    /// Creates a new wiki, caching it once the transaction has committed.
+    pub async fn create(&self, name: &str, slug: &str, domain: &str) -> Result<Wiki> {
         const DEFAULT_PAGE_LOCK_DURATION: i16 = 900;
 
         info!("Creating new wiki with name '{}' ('{}')", name, slug);
 
-        self.transaction(async {
-            // Insert wiki
-            let model = NewWiki { name, slug, domain };
-            let wiki = diesel::insert_into(wikis::table)
-                .values(&model)
-                .get_result::<Wiki>(&*self.conn)?;
-
-            let wiki_id = wiki.id();
-            let mut guard = self.wikis.write().await;
-            guard.insert(wiki_id, wiki);
-
-            // Insert default wiki settings
-            let model = NewWikiSettings {
-                wiki_id: wiki_id.into(),
-                page_lock_duration: DEFAULT_PAGE_LOCK_DURATION,
-            };
-
-            diesel::insert_into(wiki_settings::table)
-                .values(&model)
-                .execute(&*self.conn)?;
-
-            Ok((wiki_id, guard))
-        })
-        .await
+        let wiki = self
+            .transaction(async {
+                // Insert wiki
+                let model = NewWiki { name, slug, domain };
+                let wiki = diesel::insert_into(wikis::table)
+                    .values(&model)
+                    .get_result::<Wiki>(&*self.conn.lock())?;
+
+                // Insert default wiki settings
+                let model = NewWikiSettings {
+                    wiki_id: wiki.id().into(),
+                    page_lock_duration: DEFAULT_PAGE_LOCK_DURATION,
+                };
+
+                diesel::insert_into(wiki_settings::table)
+                    .values(&model)
+                    .execute(&*self.conn.lock())?;
+
+                Ok(wiki)
+            })
+            .await?;
+
+        self.cache(wiki.clone()).await;
+        Ok(wiki)
     }
 
-    pub async fn get_by_id(&self, id: WikiId) -> Result<Wiki> {
+    pub async fn get_all(&self, include_inactive: bool) -> Result<Vec<Wiki>> {
+        info!("Getting all wikis (include inactive: {})", include_inactive);
+
+        let mut query = wikis::table.into_boxed();
+
+        if !include_inactive {
+            query = query.filter(wikis::deleted_at.is_null());
+        }
+
+        let wikis = query
+            .order_by(wikis::slug.asc())
+            .load::<Wiki>(&*self.conn.lock())?;
+
+        Ok(wikis)
+    }
+
+    pub async fn get_by_id(&self, id: WikiId, include_inactive: bool) -> Result<Wiki> {
         let guard = self.wikis.read().await;
         match guard.get(&id) {
-            Some(wiki) => Ok(wiki.clone()),
-            None => Err(Error::WikiNotFound),
+            Some(wiki) if include_inactive || wiki.is_active() => Ok(wiki.clone()),
+            _ => Err(Error::WikiNotFound),
         }
     }
 
-    pub async fn get_by_slug(&self, slug: &str) -> Result<Wiki> {
+    pub async fn get_by_ids(&self, ids: &[WikiId]) -> Result<Vec<Option<Wiki>>> {
+        info!("Getting wikis for ids: {:?}", ids);
+
+        let mut result = {
+            let ids: Vec<_> = ids.iter().map(|id| id.to_i64()).collect();
+            wikis::table
+                .filter(wikis::wiki_id.eq(any(ids)))
+                .load::<Wiki>(&*self.conn.lock())?
+        };
+
+        // Align with input, using None for missing wikis
+        let wikis = ids
+            .iter()
+            .map(|id| {
+                result
+                    .iter()
+                    .position(|wiki| wiki.id() == *id)
+                    .map(|idx| result.swap_remove(idx))
+            })
+            .collect();
+
+        Ok(wikis)
+    }
+
+    pub async fn get_by_slug(&self, slug: &str, include_inactive: bool) -> Result<Wiki> {
         fn get<'a>(wikis: &'a HashMap<WikiId, Wiki>, slug: &'_ str) -> Option<&'a Wiki> {
             for wiki in wikis.values() {
                 if wiki.slug().eq_ignore_ascii_case(slug) {
@@ -104,8 +140,8 @@ impl WikiManager {
 
         let guard = self.wikis.read().await;
         match get(&*guard, slug) {
-            Some(wiki) => Ok(wiki.clone()),
-            None => Err(Error::WikiNotFound),
+            Some(wiki) if include_inactive || wiki.is_active() => Ok(wiki.clone()),
+            _ => Err(Error::WikiNotFound),
         }
     }
 
@@ -120,19 +156,77 @@ impl WikiManager {
             let id: i64 = id.into();
             diesel::update(dsl::wikis.filter(dsl::wiki_id.eq(id)))
                 .set(&model)
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
         }
 
         Ok(())
     }
 
+    /// Changes the wiki's slug in the database, returning the updated wiki.
+    /// The cached wiki is left as-is until `cache()` is called.
+    pub async fn set_slug(&self, id: WikiId, slug: &str) -> Result<Wiki> {
+        use self::wikis::dsl;
+
+        info!("Changing slug for wiki ID {} to '{}'", id, slug);
+
+        let id: i64 = id.into();
+        let wiki = diesel::update(dsl::wikis.filter(dsl::wiki_id.eq(id)))
+            .set(dsl::slug.eq(slug))
+            .get_result::<Wiki>(&*self.conn.lock())
+            .optional()?;
+
+        wiki.ok_or(Error::WikiNotFound)
+    }
+
+    pub async fn cache(&self, wiki: Wiki) {
+        let mut guard = self.wikis.write().await;
+        guard.insert(wiki.id(), wiki);
+    }
+
+    pub async fn mark_inactive(&self, id: WikiId, value: bool) -> Result<()> {
+        use self::wikis::dsl;
+        use diesel::dsl::now;
+
+        info!(
+            "Marking wiki ID {} as {}",
+            id,
+            if value { "inactive" } else { "active" }
+        );
+
+        let wiki_id = id;
+        let id: i64 = id.into();
+        let condition = dsl::wikis.filter(dsl::wiki_id.eq(id));
+
+        // Set to NOW() or NULL
+        let wiki = if value {
+            diesel::update(condition)
+                .set(dsl::deleted_at.eq(now))
+                .get_result::<Wiki>(&*self.conn.lock())
+        } else {
+            diesel::update(condition)
+                .set(dsl::deleted_at.eq(None::<DateTime<Utc>>))
+                .get_result::<Wiki>(&*self.conn.lock())
+        }
+        .optional()?;
+
+        match wiki {
+            Some(wiki) => {
+                let mut guard = self.wikis.write().await;
+                guard.insert(wiki_id, wiki);
+
+                Ok(())
+            }
+            None => Err(Error::WikiNotFound),
+        }
+    }
+
     pub async fn get_settings(&self, wiki_id: WikiId) -> Result<WikiSettings> {
         info!("Getting settings for wiki ID {}", wiki_id);
 
         let id: i64 = wiki_id.into();
         let result = wiki_settings::table
             .find(id)
-            .first::<WikiSettings>(&*self.conn)
+            .first::<WikiSettings>(&*self.conn.lock())
             .optional()?;
 
         match result {
@@ -145,10 +239,20 @@ impl WikiManager {
         &self,
         wiki_id: WikiId,
         page_lock_duration: Option<i16>,
+        lock_category_on_rename: Option<bool>,
+        scoring: Option<ScoringKind>,
+        reserved_slugs: Option<Vec<String>>,
+        auto_alt_title: Option<bool>,
     ) -> Result<()> {
         use self::wiki_settings::dsl;
 
-        let model = UpdateWikiSettings { page_lock_duration };
+        let model = UpdateWikiSettings {
+            page_lock_duration,
+            lock_category_on_rename,
+            scoring: scoring.map(|kind| kind.into()),
+            reserved_slugs,
+            auto_alt_title,
+        };
 
         info!("Editing settings for wiki ID {}: {:?}", wiki_id, model);
 
@@ -156,7 +260,7 @@ impl WikiManager {
             let id: i64 = wiki_id.into();
             diesel::update(dsl::wiki_settings.filter(dsl::wiki_id.eq(id)))
                 .set(&model)
-                .execute(&*self.conn)?;
+                .execute(&*self.conn.lock())?;
         }
 
         Ok(())
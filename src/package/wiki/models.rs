@@ -52,10 +52,18 @@ pub struct NewWikiSettings {
 #[table_name = "wiki_settings"]
 pub struct UpdateWikiSettings {
     pub page_lock_duration: Option<i16>,
+    pub lock_category_on_rename: Option<bool>,
+    pub scoring: Option<&'static str>,
+    pub reserved_slugs: Option<Vec<String>>,
+    pub auto_alt_title: Option<bool>,
 }
 
 impl UpdateWikiSettings {
     pub fn has_changes(&self) -> bool {
         self.page_lock_duration.is_some()
+            || self.lock_category_on_rename.is_some()
+            || self.scoring.is_some()
+            || self.reserved_slugs.is_some()
+            || self.auto_alt_title.is_some()
     }
 }
@@ -39,11 +39,44 @@ table! {
     }
 }
 
+table! {
+    page_links (page_id, to_slug) {
+        page_id -> Int8,
+        to_slug -> Text,
+    }
+}
+
 table! {
     page_locks (page_id) {
         page_id -> Int8,
         user_id -> Int8,
         locked_until -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    page_title_history (revision_id) {
+        revision_id -> Int8,
+        page_id -> Int8,
+        title -> Text,
+        alt_title -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    page_views (page_id) {
+        page_id -> Int8,
+        views -> Int8,
+    }
+}
+
+table! {
+    page_watches (page_id, user_id) {
+        page_id -> Int8,
+        user_id -> Int8,
+        created_at -> Timestamptz,
     }
 }
 
@@ -107,6 +140,7 @@ table! {
         message -> Text,
         git_commit -> Bpchar,
         change_type -> Varchar,
+        minor -> Bool,
     }
 }
 
@@ -133,6 +167,7 @@ table! {
         session_id -> Int8,
         user_id -> Int8,
         login_attempt_id -> Int8,
+        last_active_at -> Timestamptz,
     }
 }
 
@@ -167,6 +202,7 @@ table! {
         location -> Text,
         created_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        email_canonical -> Text,
     }
 }
 
@@ -185,6 +221,10 @@ table! {
     wiki_settings (wiki_id) {
         wiki_id -> Int8,
         page_lock_duration -> Int2,
+        lock_category_on_rename -> Bool,
+        scoring -> Varchar,
+        reserved_slugs -> Array<Text>,
+        auto_alt_title -> Bool,
     }
 }
 
@@ -195,6 +235,7 @@ table! {
         slug -> Text,
         domain -> Text,
         created_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -204,8 +245,14 @@ joinable!(authors -> pages (page_id));
 joinable!(authors -> users (user_id));
 joinable!(files -> pages (page_id));
 joinable!(login_attempts -> users (user_id));
+joinable!(page_links -> pages (page_id));
 joinable!(page_locks -> pages (page_id));
 joinable!(page_locks -> users (user_id));
+joinable!(page_title_history -> pages (page_id));
+joinable!(page_title_history -> revisions (revision_id));
+joinable!(page_views -> pages (page_id));
+joinable!(page_watches -> pages (page_id));
+joinable!(page_watches -> users (user_id));
 joinable!(pages -> wikis (wiki_id));
 joinable!(parents -> users (parented_by));
 joinable!(passwords -> users (user_id));
@@ -232,7 +279,11 @@ allow_tables_to_appear_in_same_query!(
     authors,
     files,
     login_attempts,
+    page_links,
     page_locks,
+    page_title_history,
+    page_views,
+    page_watches,
     pages,
     parents,
     passwords,
@@ -0,0 +1,69 @@
+/*
+ * server/audit.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::manager_prelude::*;
+use crate::package::audit::{AuditAction, AuditEntry, AuditFilter};
+use serde_json::Value as JsonValue;
+
+impl Server {
+    /// Records an entry in the audit log, if enabled.
+    /// This should be called inside the transaction for the operation being audited.
+    pub(super) async fn audit(
+        &self,
+        wiki_id: WikiId,
+        user_id: Option<UserId>,
+        action: AuditAction,
+        data: JsonValue,
+    ) -> Result<()> {
+        if !self.audit_enabled {
+            return Ok(());
+        }
+
+        self.audit.add(wiki_id, user_id, action, &data).await
+    }
+
+    /// Records an entry in the audit log for an operation on a page, if enabled.
+    pub(super) async fn audit_page(
+        &self,
+        page_id: PageId,
+        user_id: Option<UserId>,
+        action: AuditAction,
+        data: JsonValue,
+    ) -> Result<()> {
+        if !self.audit_enabled {
+            return Ok(());
+        }
+
+        let page = self
+            .page
+            .get_page_by_id(page_id)
+            .await?
+            .ok_or(Error::PageNotFound)?;
+
+        self.audit.add(page.wiki_id(), user_id, action, &data).await
+    }
+
+    /// Gets entries from the audit log matching the given filter, newest first.
+    /// Nothing is recorded unless `enable_audit_log` is set in the configuration.
+    #[inline]
+    pub async fn get_audit_log(&self, filter: AuditFilter) -> Result<Vec<AuditEntry>> {
+        self.audit.get(filter).await
+    }
+}
@@ -23,7 +23,7 @@ use crate::manager_prelude::*;
 use crate::package::author::{Author, AuthorType};
 
 impl Server {
-    async fn get_page_id<S: Into<String>>(
+    pub(super) async fn get_page_id<S: Into<String>>(
         &self,
         page: Either<PageId, (WikiId, S)>,
     ) -> Result<PageId> {
@@ -55,6 +55,18 @@ impl Server {
         .await
     }
 
+    /// Gets the IDs of all pages the user is an author of, excluding deleted pages.
+    /// Optionally restricts results to authorships written within the given dates (inclusive).
+    #[inline]
+    pub async fn get_user_authored_pages(
+        &self,
+        user_id: UserId,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<PageId>> {
+        self.author.get_pages_by_author(user_id, from, to).await
+    }
+
     /// Adds or sets a group of authors.
     pub async fn add_page_authors(
         &self,
@@ -77,6 +89,24 @@ impl Server {
         .await
     }
 
+    /// Changes the type of an existing authorship, preserving its date.
+    /// Returns `false` if the user is not an author of that type for the page,
+    /// or `Conflict` if they already have an authorship of the new type.
+    pub async fn change_page_author_type(
+        &self,
+        page: Either<PageId, (WikiId, &str)>,
+        user_id: UserId,
+        from: AuthorType,
+        to: AuthorType,
+    ) -> Result<bool> {
+        self.transaction(async {
+            let page_id = self.get_page_id(page).await?;
+
+            self.author.change_type(page_id, user_id, from, to).await
+        })
+        .await
+    }
+
     /// Removes a group of authors.
     pub async fn remove_page_authors(
         &self,
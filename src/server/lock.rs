@@ -20,6 +20,8 @@
 
 use super::utils::normalize_slug;
 use crate::manager_prelude::*;
+use crate::package::audit::AuditAction;
+use crate::package::lock::PageLock;
 
 impl Server {
     /// Removes any page locks which are no longer active.
@@ -41,6 +43,24 @@ impl Server {
         Ok(duration)
     }
 
+    /// Gets the current lock on a page, including who holds it and when it expires.
+    /// Returns `None` if the page is not locked.
+    pub async fn get_page_lock(
+        &self,
+        page: Either<PageId, (WikiId, &str)>,
+    ) -> Result<Option<PageLock>> {
+        info!("Getting page lock for page {:?}", page);
+
+        self.lock.invalidate_expired().await?;
+
+        self.transaction(async {
+            let page_id = self.get_page_id(page).await?;
+
+            self.lock.get_lock(page_id).await
+        })
+        .await
+    }
+
     /// Creates a page lock for the given user.
     ///
     /// The amount of time to acquire the lock for is dependent on the wiki's settings.
@@ -68,6 +88,10 @@ impl Server {
             let lock_duration = self.lock_duration(wiki_id).await?;
             self.lock.add(page_id, user_id, lock_duration).await?;
 
+            let data = json!({ "page_id": page_id });
+            self.audit(wiki_id, Some(user_id), AuditAction::AddPageLock, data)
+                .await?;
+
             Ok(())
         })
         .await
@@ -104,6 +128,28 @@ impl Server {
         .await
     }
 
+    /// Keeps the given user's page lock alive, extending it by the wiki's lock duration.
+    ///
+    /// This will fail if the lock is held by another user, or if there is no lock.
+    pub async fn refresh_page_lock(
+        &self,
+        wiki_id: WikiId,
+        slug: &str,
+        user_id: UserId,
+    ) -> Result<()> {
+        let slug = normalize_slug(slug);
+
+        info!(
+            "Refreshing page lock for wiki ID {} / slug '{}' for user ID {}",
+            wiki_id, slug, user_id,
+        );
+
+        self.lock.invalidate_expired().await?;
+
+        let page_id = self.lock_page_id(wiki_id, &slug).await?;
+        self.lock.refresh(page_id, user_id).await
+    }
+
     /// Lifts the page lock for a particular page.
     ///
     /// This will fail if there is no page lock present.
@@ -117,8 +163,16 @@ impl Server {
 
         // Don't prune old locks to avoid a race condition with this lock
 
-        let page_id = self.lock_page_id(wiki_id, &slug).await?;
+        self.transaction(async {
+            let page_id = self.lock_page_id(wiki_id, &slug).await?;
+            self.lock.remove(page_id).await?;
 
-        self.lock.remove(page_id).await
+            let data = json!({ "page_id": page_id });
+            self.audit(wiki_id, None, AuditAction::RemovePageLock, data)
+                .await?;
+
+            Ok(())
+        })
+        .await
     }
 }
@@ -18,6 +18,7 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+mod audit;
 mod author;
 mod lock;
 mod page;
@@ -25,32 +26,59 @@ mod password;
 mod rating;
 mod revision;
 mod session;
+mod status;
 mod user;
 mod utils;
+mod watch;
 mod wiki;
 
 use crate::manager_prelude::*;
+use crate::package::audit::AuditManager;
 use crate::package::author::AuthorManager;
 use crate::package::lock::LockManager;
-use crate::package::page::PageManager;
-use crate::package::password::PasswordManager;
+use crate::package::page::{ContentFilter, PageManager};
+use crate::package::password::{PasswordManager, PasswordPolicy};
 use crate::package::rating::RatingManager;
 use crate::package::session::SessionManager;
 use crate::package::user::UserManager;
+use crate::package::watch::WatchManager;
 use crate::package::wiki::WikiManager;
+use async_std::task;
+use chrono::Duration;
 use std::fmt::{self, Debug};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
-#[derive(Debug, Clone)]
+use self::status::Counters;
+pub use self::status::ServerStatus;
+
+#[derive(Clone)]
 pub struct Config<'a> {
     pub database_url: &'a str,
     pub revisions_dir: PathBuf,
     pub password_blacklist: Option<&'a Path>,
+    pub password_policy: Option<PasswordPolicy>,
+    pub canonicalize_emails: bool,
+    pub allow_empty_pages: bool,
+    pub commit_message_prefix: Option<String>,
+    pub content_filter: Option<ContentFilter>,
+    pub max_content_bytes: usize,
+    pub enable_audit_log: bool,
+    pub max_batch_size: usize,
+    pub max_login_failures: u32,
+    pub login_failure_window: Duration,
+    pub session_expiry: Duration,
+    pub default_user_name: Option<&'a str>,
 }
 
+/// The main interface into deepwell's functionality.
+///
+/// A server is `Send` and `Sync`, so it may be shared between tasks on any executor.
+/// It holds a single database connection, which is used by one thread at a time.
 pub struct Server {
-    conn: Arc<PgConnection>,
+    conn: Arc<SharedConnection>,
+    audit: AuditManager,
     author: AuthorManager,
     lock: LockManager,
     page: PageManager,
@@ -58,20 +86,42 @@ pub struct Server {
     rating: RatingManager,
     session: SessionManager,
     user: UserManager,
+    watch: WatchManager,
     wiki: WikiManager,
+    default_user_name: String,
+    audit_enabled: bool,
+    max_batch_size: usize,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    counters: Counters,
 }
 
 impl Server {
     pub fn new(config: Config) -> Result<Self> {
         info!("Creating deepwell::Server, establishing connection to Postgres");
 
+        let started_at = Utc::now();
+        let started = Instant::now();
+
         let Config {
             database_url,
             revisions_dir,
             password_blacklist,
+            password_policy,
+            canonicalize_emails,
+            allow_empty_pages,
+            commit_message_prefix,
+            content_filter,
+            max_content_bytes,
+            enable_audit_log,
+            max_batch_size,
+            max_login_failures,
+            login_failure_window,
+            session_expiry,
+            default_user_name,
         } = config;
 
-        let conn = match PgConnection::establish(database_url) {
+        let conn = match SharedConnection::establish(database_url) {
             Ok(conn) => Arc::new(conn),
             Err(error) => {
                 error!("Error establishing Postgres connection: {}", error);
@@ -80,17 +130,45 @@ impl Server {
             }
         };
 
+        let audit = AuditManager::new(&conn);
         let author = AuthorManager::new(&conn);
         let lock = LockManager::new(&conn);
-        let page = PageManager::new(&conn, revisions_dir);
-        let password = PasswordManager::new(&conn, password_blacklist)?;
+        let page = PageManager::new(
+            &conn,
+            revisions_dir,
+            allow_empty_pages,
+            commit_message_prefix,
+            content_filter,
+            max_content_bytes,
+        );
+        let password = PasswordManager::new(
+            &conn,
+            password_blacklist,
+            password_policy.unwrap_or_default(),
+        )?;
         let rating = RatingManager::new(&conn);
-        let session = SessionManager::new(&conn);
-        let user = UserManager::new(&conn);
+        let session = SessionManager::new(
+            &conn,
+            max_login_failures,
+            login_failure_window,
+            session_expiry,
+        );
+        let user = UserManager::new(&conn, canonicalize_emails);
+        let watch = WatchManager::new(&conn);
         let wiki = WikiManager::new(&conn)?;
 
+        // Ensure the default user exists
+        let default_user_name = String::from(default_user_name.unwrap_or("unknown"));
+        if task::block_on(user.get_from_name(&default_user_name))?.is_none() {
+            info!("Default user '{}' not found, creating", default_user_name);
+
+            let email = format!("{}@example.com", default_user_name);
+            task::block_on(user.create(&default_user_name, &email))?;
+        }
+
         Ok(Server {
             conn,
+            audit,
             author,
             lock,
             page,
@@ -98,26 +176,99 @@ impl Server {
             rating,
             session,
             user,
+            watch,
             wiki,
+            default_user_name,
+            audit_enabled: enable_audit_log,
+            max_batch_size,
+            started_at,
+            started,
+            counters: Counters::default(),
         })
     }
 
+    /// Gets the user used for anonymous or system operations.
+    pub async fn default_user(&self) -> Result<User> {
+        self.user
+            .get_from_name(&self.default_user_name)
+            .await?
+            .ok_or(Error::UserNotFound)
+    }
+
     /* Helper methods */
     pub async fn ping(&self) -> Result<()> {
         debug!("Pinging database");
 
-        self.conn.execute("SELECT 1")?;
+        self.conn.lock().execute("SELECT 1")?;
         Ok(())
     }
 
+    /// Runs several operations inside a single database transaction.
+    ///
+    /// The closure is given this server, and any methods called on it
+    /// are committed together once it returns `Ok`, or all rolled back if it
+    /// returns an error. Changes to the wiki repositories cannot be rolled back,
+    /// so pages created in a failed batch leave commits behind in git.
+    pub async fn batch<'a, F, Fut, R>(&'a self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a Server) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        debug!("Running batch of operations in one transaction");
+
+        self.transaction(f(self)).await
+    }
+
+    #[cfg(test)]
+    #[inline]
+    pub async fn unload_wiki_store(&self, wiki_id: WikiId) {
+        self.page.unload_store(wiki_id).await;
+    }
+
     #[cfg(test)]
     #[inline]
     pub fn test_transaction<F: FnOnce() -> Result<()>>(&self, f: F) {
-        self.conn.test_transaction::<_, Error, _>(f);
+        self.conn.test_transaction(f);
+    }
+}
+
+impl Server {
+    /// Like `impl_async_transaction!`, but counts failures of the outermost transaction.
+    async fn transaction<F, T>(&self, f: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let result = self.conn.transaction(|| task::block_on(f));
+
+        if result.is_err() && self.conn.transaction_depth() == 0 {
+            self.counters.add_error();
+        }
+
+        result
     }
 }
 
-impl_async_transaction!(Server);
+impl Debug for Config<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("deepwell::Config")
+            .field("database_url", &self.database_url)
+            .field("revisions_dir", &self.revisions_dir)
+            .field("password_blacklist", &self.password_blacklist)
+            .field("password_policy", &self.password_policy)
+            .field("canonicalize_emails", &self.canonicalize_emails)
+            .field("allow_empty_pages", &self.allow_empty_pages)
+            .field("commit_message_prefix", &self.commit_message_prefix)
+            .field("content_filter", &self.content_filter.is_some())
+            .field("max_content_bytes", &self.max_content_bytes)
+            .field("enable_audit_log", &self.enable_audit_log)
+            .field("max_batch_size", &self.max_batch_size)
+            .field("max_login_failures", &self.max_login_failures)
+            .field("login_failure_window", &self.login_failure_window)
+            .field("session_expiry", &self.session_expiry)
+            .field("default_user_name", &self.default_user_name)
+            .finish()
+    }
+}
 
 impl Debug for Server {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
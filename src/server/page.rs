@@ -18,12 +18,33 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::utils::normalize_slug;
+use super::utils::{
+    check_batch_size, check_normal_slug, derive_alt_title, normalize_slug, normalize_tags,
+    page_category,
+};
 use crate::manager_prelude::*;
+use crate::package::audit::AuditAction;
 use crate::package::author::AuthorType;
 
+const TAG_NORMALIZATION_BATCH: i64 = 100;
+
+/// The committing user followed by any other users, all as regular authors.
+fn default_authors(
+    user: &User,
+    other_authors: &[UserId],
+) -> Vec<(UserId, AuthorType, Option<NaiveDate>)> {
+    let mut authors = Vec::with_capacity(other_authors.len() + 1);
+    authors.push((user.id(), AuthorType::Author, None));
+
+    for user_id in other_authors.iter().copied() {
+        authors.push((user_id, AuthorType::Author, None));
+    }
+
+    authors
+}
+
 impl Server {
-    async fn check_page_lock(
+    pub(super) async fn check_page_lock(
         &self,
         wiki_id: WikiId,
         slug: &str,
@@ -43,7 +64,19 @@ impl Server {
         Ok(page_id)
     }
 
+    async fn get_score(&self, wiki_id: WikiId, votes: &Votes) -> Result<f32> {
+        let settings = self.wiki.get_settings(wiki_id).await?;
+        let score = settings.scoring().score(votes);
+
+        Ok(score)
+    }
+
     /// Creates a new page with the given contents and metadata.
+    /// An empty alternate title is derived from the title if the wiki has `auto_alt_title` enabled.
+    ///
+    /// Returns `InvalidSlug` if the slug is not normalized,
+    /// or `SlugReserved` if it is reserved in this wiki.
+    #[inline]
     pub async fn create_page(
         &self,
         commit: PageCommit<'_>,
@@ -52,31 +85,122 @@ impl Server {
         title: &str,
         alt_title: &str,
     ) -> Result<(PageId, RevisionId)> {
-        let PageCommit { user, .. } = commit;
+        check_normal_slug(commit.slug)?;
+
+        let authors = default_authors(commit.user, other_authors);
+        self.create_page_internal(commit, content, &authors, title, alt_title, false)
+            .await
+    }
+
+    /// Creates a new page with the given contents and metadata.
+    ///
+    /// Unlike `create_page()`, this is permitted to use reserved slugs.
+    #[inline]
+    pub async fn create_privileged_page(
+        &self,
+        commit: PageCommit<'_>,
+        content: &str,
+        other_authors: &[UserId],
+        title: &str,
+        alt_title: &str,
+    ) -> Result<(PageId, RevisionId)> {
+        check_normal_slug(commit.slug)?;
+
+        let authors = default_authors(commit.user, other_authors);
+        self.create_page_internal(commit, content, &authors, title, alt_title, true)
+            .await
+    }
+
+    /// Creates a new internal page, such as a template or dashboard.
+    ///
+    /// The slug is used as-is rather than being normalized, but must still be safe
+    /// to store, otherwise `InvalidSlug` is returned. Reserved slugs are permitted.
+    pub async fn create_system_page(
+        &self,
+        commit: PageCommit<'_>,
+        content: &str,
+        title: &str,
+        alt_title: &str,
+    ) -> Result<(PageId, RevisionId)> {
+        if !is_valid_slug(commit.slug) {
+            return Err(Error::InvalidSlug(String::from(commit.slug)));
+        }
 
-        // Empty string means use default
-        let alt_title: Option<&str> = match alt_title {
-            "" => None,
-            _ => Some(alt_title),
-        };
+        let authors = default_authors(commit.user, &[]);
+        self.create_page_internal(commit, content, &authors, title, alt_title, true)
+            .await
+    }
+
+    /// Creates a new page with the given contents and metadata, along with its full list of authors.
+    /// This is intended for importing pages, so the committing user is not added as an author
+    /// unless they are listed.
+    ///
+    /// Returns `InvalidSlug` if the slug is not normalized,
+    /// or `SlugReserved` if it is reserved in this wiki.
+    pub async fn create_page_with_authors(
+        &self,
+        commit: PageCommit<'_>,
+        content: &str,
+        authors: &[(UserId, AuthorType, Option<NaiveDate>)],
+        title: &str,
+        alt_title: &str,
+    ) -> Result<(PageId, RevisionId)> {
+        check_normal_slug(commit.slug)?;
+
+        self.create_page_internal(commit, content, authors, title, alt_title, false)
+            .await
+    }
+
+    /// Gets the alternate title to use when none is given.
+    /// This is derived from the title if the wiki has `auto_alt_title` enabled.
+    async fn default_alt_title<'a>(
+        &self,
+        wiki_id: WikiId,
+        title: &'a str,
+    ) -> Result<Option<&'a str>> {
+        let settings = self.wiki.get_settings(wiki_id).await?;
+
+        if settings.auto_alt_title() {
+            Ok(derive_alt_title(title))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn create_page_internal(
+        &self,
+        commit: PageCommit<'_>,
+        content: &str,
+        authors: &[(UserId, AuthorType, Option<NaiveDate>)],
+        title: &str,
+        alt_title: &str,
+        privileged: bool,
+    ) -> Result<(PageId, RevisionId)> {
+        let PageCommit { wiki_id, user, .. } = commit;
 
         self.transaction(async {
-            // Create page
-            let (page_id, revision_id) =
-                self.page.create(commit, content, title, alt_title).await?;
+            // Empty string means use default
+            let alt_title: Option<&str> = match alt_title {
+                "" => self.default_alt_title(wiki_id, title).await?,
+                _ => Some(alt_title),
+            };
 
-            // Add committing user as author
-            self.author
-                .add(page_id, user.id(), AuthorType::Author, None)
+            // Create page
+            let (page_id, revision_id) = self
+                .page
+                .create(commit, content, title, alt_title, privileged)
                 .await?;
 
-            // Add other users
-            for user_id in other_authors.iter().copied() {
+            for &(user_id, author_type, written_at) in authors {
                 self.author
-                    .add(page_id, user_id, AuthorType::Author, None)
+                    .add(page_id, user_id, author_type, written_at)
                     .await?;
             }
 
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::AddPage, data)
+                .await?;
+
             Ok((page_id, revision_id))
         })
         .await
@@ -84,13 +208,17 @@ impl Server {
 
     /// Edits an existing page to have the given content.
     /// Optionally permits modifying the title or alternate title.
-    /// (An empty alternate title signifies that none is used)
+    /// (An empty alternate title signifies that none is used,
+    /// or one derived from the title if the wiki has `auto_alt_title` enabled)
+    ///
+    /// Minor edits are recorded as such, and can be excluded from revision listings.
     pub async fn edit_page(
         &self,
         commit: PageCommit<'_>,
         content: Option<&str>,
         title: Option<&str>,
         alt_title: Option<&str>,
+        minor: bool,
     ) -> Result<RevisionId> {
         let PageCommit {
             wiki_id,
@@ -99,24 +227,112 @@ impl Server {
             ..
         } = commit;
 
-        // Empty string means use default
-        let alt_title: Option<Option<&str>> = match alt_title {
-            Some("") => Some(None),
-            Some(_) => Some(alt_title),
-            None => None,
-        };
+        self.transaction(async {
+            let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
+
+            // Empty string means use default
+            let current_title;
+            let alt_title: Option<Option<&str>> = match alt_title {
+                Some("") => {
+                    let title = match title {
+                        Some(title) => title,
+                        None => {
+                            current_title = self
+                                .page
+                                .get_page_by_id(page_id)
+                                .await?
+                                .map(|page| String::from(page.title()))
+                                .ok_or(Error::PageNotFound)?;
+
+                            &current_title
+                        }
+                    };
+
+                    Some(self.default_alt_title(wiki_id, title).await?)
+                }
+                Some(_) => Some(alt_title),
+                None => None,
+            };
+
+            let revision_id = self
+                .page
+                .commit(commit, page_id, content, title, alt_title, minor)
+                .await?;
+
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::EditPage, data)
+                .await?;
+
+            Ok(revision_id)
+        })
+        .await
+    }
+
+    /// Edits a page by applying a unified diff to its current contents.
+    /// Returns `PatchFailed` if the patch does not apply cleanly.
+    pub async fn patch_page(&self, commit: PageCommit<'_>, patch: &[u8]) -> Result<RevisionId> {
+        let PageCommit {
+            wiki_id,
+            slug,
+            user,
+            ..
+        } = commit;
 
         self.transaction(async {
             let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
+            let revision_id = self.page.apply_patch(commit, page_id, patch).await?;
+
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::EditPage, data)
+                .await?;
 
-            self.page
-                .commit(commit, page_id, content, title, alt_title)
-                .await
+            Ok(revision_id)
         })
         .await
     }
 
+    /// Changes a page's contents back to what they were at the given revision.
+    /// Unlike `undo_revision`, this does not modify any intermediate changes,
+    /// and is recorded as a new revision on top of the page's history.
+    pub async fn revert_page(
+        &self,
+        commit: PageCommit<'_>,
+        revision: Either<RevisionId, &GitHash>,
+    ) -> Result<RevisionId> {
+        let PageCommit {
+            wiki_id,
+            slug,
+            user,
+            ..
+        } = commit;
+
+        self.transaction(async {
+            let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
+            let revision_id = self.page.revert(commit, page_id, revision).await?;
+
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::RevertPage, data)
+                .await?;
+
+            Ok(revision_id)
+        })
+        .await
+    }
+
+    /// Same as `revert_page`, but takes ownership of the target revision's hash.
+    #[inline]
+    pub async fn revert_page_to(
+        &self,
+        commit: PageCommit<'_>,
+        target: Either<RevisionId, GitHash>,
+    ) -> Result<RevisionId> {
+        let target = target.as_ref().map_left(|&id| id);
+
+        self.revert_page(commit, target).await
+    }
+
     /// Renames a page to use a different slug.
+    /// Returns `SlugReserved` if the new slug is reserved in this wiki.
     #[inline]
     pub async fn rename_page<S1, S2>(
         &self,
@@ -136,13 +352,42 @@ impl Server {
         self.transaction(async {
             let page_id = self.check_page_lock(wiki_id, &old_slug, user.id()).await?;
 
-            self.page
+            let settings = self.wiki.get_settings(wiki_id).await?;
+            if settings.lock_category_on_rename()
+                && page_category(&old_slug) != page_category(&new_slug)
+            {
+                return Err(Error::CategoryChangeForbidden);
+            }
+
+            let revision_id = self
+                .page
                 .rename(wiki_id, &old_slug, &new_slug, page_id, message, user)
-                .await
+                .await?;
+
+            let data = json!({
+                "page_id": page_id,
+                "revision_id": revision_id,
+                "old_slug": old_slug,
+                "new_slug": new_slug,
+            });
+            self.audit(wiki_id, Some(user.id()), AuditAction::RenamePage, data)
+                .await?;
+
+            Ok(revision_id)
         })
         .await
     }
 
+    /// Checks whether a page could be renamed to the given slug.
+    /// Returns `false` if a page already exists there, deleted pages don't count.
+    ///
+    /// Unlike `rename_page()`, the slug is not normalized, and `InvalidSlug` is returned
+    /// if it is not already in normal form.
+    #[inline]
+    pub async fn check_rename(&self, wiki_id: WikiId, new_slug: &str) -> Result<bool> {
+        self.page.can_rename(wiki_id, new_slug).await
+    }
+
     /// Removes the given page.
     pub async fn remove_page(&self, commit: PageCommit<'_>) -> Result<RevisionId> {
         let PageCommit {
@@ -155,7 +400,65 @@ impl Server {
         self.transaction(async {
             let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
 
-            self.page.remove(commit, page_id).await
+            let revision_id = self.page.remove(commit, page_id).await?;
+
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::RemovePage, data)
+                .await?;
+
+            Ok(revision_id)
+        })
+        .await
+    }
+
+    /// Removes every page in the wiki which has the given tag.
+    /// Returns the IDs of the removed pages, in slug order.
+    ///
+    /// Unless `force` is set, all the pages' locks are checked before any are removed,
+    /// and `PageLocked` is returned if another user holds one of them.
+    pub async fn remove_pages_with_tag(
+        &self,
+        wiki_id: WikiId,
+        tag: &str,
+        user: &User,
+        message: &str,
+        force: bool,
+    ) -> Result<Vec<PageId>> {
+        self.transaction(async {
+            let pages = self
+                .page
+                .get_pages_with_tags(wiki_id, &[tag], PageOrder::SlugAsc)
+                .await?;
+
+            if !force {
+                self.lock.invalidate_expired().await?;
+
+                for page in &pages {
+                    self.lock.check(page.id(), user.id()).await?;
+                }
+            }
+
+            let mut page_ids = Vec::with_capacity(pages.len());
+
+            for page in &pages {
+                let page_id = page.id();
+                let commit = PageCommit {
+                    wiki_id,
+                    slug: page.slug(),
+                    message,
+                    user,
+                };
+
+                let revision_id = self.page.remove(commit, page_id).await?;
+
+                let data = json!({ "page_id": page_id, "revision_id": revision_id });
+                self.audit(wiki_id, Some(user.id()), AuditAction::RemovePage, data)
+                    .await?;
+
+                page_ids.push(page_id);
+            }
+
+            Ok(page_ids)
         })
         .await
     }
@@ -168,12 +471,13 @@ impl Server {
         self.page.check_page(wiki_id, &slug).await
     }
 
-    /// Gets the metadata for a given page, as well as its vote information.
+    /// Gets the metadata for a given page, as well as its vote information
+    /// and the score from the wiki's configured scorer.
     pub async fn get_page<S: Into<String>>(
         &self,
         wiki_id: WikiId,
         slug: S,
-    ) -> Result<Option<(Page, Votes)>> {
+    ) -> Result<Option<(Page, Votes, f32)>> {
         debug!("Creating transaction for page and rating");
 
         let slug = normalize_slug(slug);
@@ -187,14 +491,34 @@ impl Server {
 
             let page_id = page.id();
             let rating = self.rating.get_rating(page_id).await?;
+            let score = self.get_score(wiki_id, &rating).await?;
 
-            Ok(Some((page, rating)))
+            Ok(Some((page, rating, score)))
         })
         .await
     }
 
-    /// Gets the metadata for a given page ID, as well as its vote information.
-    pub async fn get_page_by_id(&self, page_id: PageId) -> Result<Option<(Page, Votes)>> {
+    /// Gets the metadata for several pages at once.
+    /// Results are returned in the same order as the slugs, and any missing
+    /// or deleted pages give `None` instead.
+    ///
+    /// Rejects any requests with more slugs than the configured maximum batch size.
+    pub async fn get_pages(&self, wiki_id: WikiId, slugs: &[&str]) -> Result<Vec<Option<Page>>> {
+        check_batch_size(slugs.len(), self.max_batch_size)?;
+
+        let slugs = slugs
+            .iter()
+            .map(|slug| normalize_slug(*slug))
+            .collect::<Vec<_>>();
+
+        let slugs = slugs.iter().map(|slug| slug.as_str()).collect::<Vec<_>>();
+
+        self.page.get_pages(wiki_id, &slugs).await
+    }
+
+    /// Gets the metadata for a given page ID, as well as its vote information
+    /// and the score from the wiki's configured scorer.
+    pub async fn get_page_by_id(&self, page_id: PageId) -> Result<Option<(Page, Votes, f32)>> {
         debug!("Creating transaction for page ID and rating");
 
         self.transaction(async {
@@ -205,12 +529,19 @@ impl Server {
             };
 
             let rating = self.rating.get_rating(page_id).await?;
+            let score = self.get_score(page.wiki_id(), &rating).await?;
 
-            Ok(Some((page, rating)))
+            Ok(Some((page, rating, score)))
         })
         .await
     }
 
+    /// Gets the ID of the wiki the given page belongs to, if it exists.
+    #[inline]
+    pub async fn get_page_wiki(&self, page_id: PageId) -> Result<Option<WikiId>> {
+        self.page.get_wiki_for_page(page_id).await
+    }
+
     /// Gets the contents for a given page.
     #[inline]
     pub async fn get_page_contents<S: Into<String>>(
@@ -223,6 +554,44 @@ impl Server {
         self.page.get_page_contents(wiki_id, &slug).await
     }
 
+    /// Restores a page's file in the wiki repository if it was lost from the working tree.
+    /// Returns `true` if the file was missing and has been checked out again.
+    #[inline]
+    pub async fn repair_page<S: Into<String>>(&self, wiki_id: WikiId, slug: S) -> Result<bool> {
+        let slug = normalize_slug(slug);
+
+        self.page.repair(wiki_id, &slug).await
+    }
+
+    /// Gets the contents for a given page, unless the page is still at the given commit.
+    /// Returns `None` if the page has not been modified, otherwise contents as in `get_page_contents()`.
+    #[inline]
+    pub async fn get_page_contents_if_changed<S: Into<String>>(
+        &self,
+        wiki_id: WikiId,
+        slug: S,
+        known_hash: &GitHash,
+    ) -> Result<Option<Option<String>>> {
+        let slug = normalize_slug(slug);
+
+        self.page
+            .get_page_contents_if_changed(wiki_id, &slug, known_hash)
+            .await
+    }
+
+    /// Gets the hash of the latest commit for a given page.
+    /// This changes whenever a new revision is made to the page.
+    #[inline]
+    pub async fn get_page_current_hash<S: Into<String>>(
+        &self,
+        wiki_id: WikiId,
+        slug: S,
+    ) -> Result<Option<GitHash>> {
+        let slug = normalize_slug(slug);
+
+        self.page.get_current_hash(wiki_id, &slug).await
+    }
+
     /// Gets the contents for a given page ID.
     #[inline]
     pub async fn get_page_contents_by_id(&self, page_id: PageId) -> Result<Option<String>> {
@@ -249,16 +618,240 @@ impl Server {
         self.transaction(async {
             let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
 
-            self.page.tags(commit, page_id, &mut tags).await
+            let revision_id = self.page.tags(commit, page_id, &mut tags, None).await?;
+
+            if let Some(revision_id) = revision_id {
+                let data = json!({ "page_id": page_id, "revision_id": revision_id });
+                self.audit(wiki_id, Some(user.id()), AuditAction::EditPageTags, data)
+                    .await?;
+            }
+
+            Ok(revision_id)
         })
         .await
     }
 
-    /// Gets all pages which have at least the given tags.
+    /// Sets all the tags for a given page, but only if its current tags match the expected ones.
+    /// Returns `Error::Conflict` if the tags were modified in the meantime,
+    /// or `None` if no changes were made.
+    pub async fn swap_page_tags<S: AsRef<str>>(
+        &self,
+        commit: PageCommit<'_>,
+        expected_tags: &[S],
+        tags: &[S],
+    ) -> Result<Option<RevisionId>> {
+        let PageCommit {
+            wiki_id,
+            slug,
+            user,
+            ..
+        } = commit;
+
+        let expected_tags = expected_tags
+            .iter()
+            .map(|tag| tag.as_ref())
+            .collect::<Vec<&str>>();
+
+        let mut tags = tags.iter().map(|tag| tag.as_ref()).collect::<Vec<&str>>();
+
+        self.transaction(async {
+            let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
+
+            let revision_id = self
+                .page
+                .tags(commit, page_id, &mut tags, Some(&expected_tags))
+                .await?;
+
+            if let Some(revision_id) = revision_id {
+                let data = json!({ "page_id": page_id, "revision_id": revision_id });
+                self.audit(wiki_id, Some(user.id()), AuditAction::EditPageTags, data)
+                    .await?;
+            }
+
+            Ok(revision_id)
+        })
+        .await
+    }
+
+    /// Re-normalizes the tags on every page in the wiki.
+    ///
+    /// Tags are trimmed, lowercased, and deduplicated. A tags revision is only
+    /// made for pages whose tag set changes, and the number of such pages is returned.
+    /// Pages are processed in batches, each in its own transaction.
+    pub async fn normalize_all_tags(&self, wiki_id: WikiId, user: &User) -> Result<usize> {
+        let mut last_page_id = None;
+        let mut updated = 0;
+
+        loop {
+            let pages = self
+                .page
+                .get_tags_batch(wiki_id, last_page_id, TAG_NORMALIZATION_BATCH)
+                .await?;
+
+            last_page_id = match pages.last() {
+                Some((page_id, _, _)) => Some(*page_id),
+                None => break,
+            };
+
+            updated += self
+                .transaction(async {
+                    let mut count: usize = 0;
+
+                    for (page_id, slug, tags) in &pages {
+                        let normalized = normalize_tags(tags);
+                        if normalized == *tags {
+                            continue;
+                        }
+
+                        let mut tags = normalized
+                            .iter()
+                            .map(|tag| tag.as_str())
+                            .collect::<Vec<&str>>();
+
+                        let commit = PageCommit {
+                            wiki_id,
+                            slug,
+                            message: "Normalize tags",
+                            user,
+                        };
+
+                        let revision_id = self.page.tags(commit, *page_id, &mut tags, None).await?;
+
+                        if let Some(revision_id) = revision_id {
+                            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+                            self.audit(wiki_id, Some(user.id()), AuditAction::EditPageTags, data)
+                                .await?;
+
+                            count += 1;
+                        }
+                    }
+
+                    Ok(count)
+                })
+                .await?;
+        }
+
+        Ok(updated)
+    }
+
+    /// Gets all pages which have at least the given tags, in the given order.
     ///
     /// Returns an empty set if no tags are passed in.
     #[inline]
-    pub async fn get_pages_with_tags(&self, wiki_id: WikiId, tags: &[&str]) -> Result<Vec<Page>> {
-        self.page.get_pages_with_tags(wiki_id, tags).await
+    pub async fn get_pages_with_tags(
+        &self,
+        wiki_id: WikiId,
+        tags: &[&str],
+        order: PageOrder,
+    ) -> Result<Vec<Page>> {
+        self.page.get_pages_with_tags(wiki_id, tags, order).await
+    }
+
+    /// Gets all pages matching a tag query, ordered by slug.
+    ///
+    /// Pages must have every tag in `required`, none of the tags in `excluded`,
+    /// and at least one of the tags in `any_of`. Empty lists are ignored,
+    /// so passing only exclusions returns every page without those tags.
+    #[inline]
+    pub async fn get_pages_by_tag_query(
+        &self,
+        wiki_id: WikiId,
+        required: &[&str],
+        excluded: &[&str],
+        any_of: &[&str],
+    ) -> Result<Vec<Page>> {
+        self.page
+            .get_pages_by_tag_query(wiki_id, required, excluded, any_of)
+            .await
+    }
+
+    /// Counts all pages which have at least the given tags.
+    ///
+    /// Returns zero if no tags are passed in.
+    #[inline]
+    pub async fn count_pages_with_tags(&self, wiki_id: WikiId, tags: &[&str]) -> Result<i64> {
+        self.page.count_pages_with_tags(wiki_id, tags).await
+    }
+
+    /// Gets every tag in use on the wiki, with the time of the most recent
+    /// revision to any page carrying it. Least recently used tags come first.
+    #[inline]
+    pub async fn get_tags_last_used(
+        &self,
+        wiki_id: WikiId,
+    ) -> Result<Vec<(String, DateTime<Utc>)>> {
+        self.page.get_tags_with_last_use(wiki_id).await
+    }
+
+    /// Gets all pages which link to the given page.
+    ///
+    /// The target page does not need to exist.
+    pub async fn get_backlinks<S: Into<String>>(
+        &self,
+        wiki_id: WikiId,
+        slug: S,
+    ) -> Result<Vec<Page>> {
+        let slug = normalize_slug(slug);
+
+        self.page.get_backlinks(wiki_id, &slug).await
+    }
+
+    /// Lists the slugs of all pages in the wiki, in alphabetical order.
+    ///
+    /// If `include_deleted` is set, slugs of deleted pages are included as well,
+    /// so a slug may appear more than once.
+    #[inline]
+    pub async fn list_page_slugs(
+        &self,
+        wiki_id: WikiId,
+        include_deleted: bool,
+    ) -> Result<Vec<String>> {
+        self.page.get_all_slugs(wiki_id, include_deleted).await
+    }
+
+    /// Records a view of the given page, returning the new view count.
+    /// Views of deleted pages are still counted.
+    #[inline]
+    pub async fn increment_page_views(&self, page_id: PageId) -> Result<i64> {
+        self.page.increment_views(page_id).await
+    }
+
+    /// Records views for several pages at once.
+    /// Page IDs may be repeated to count multiple views of the same page.
+    pub async fn increment_page_views_many(&self, page_ids: &[PageId]) -> Result<()> {
+        check_batch_size(page_ids.len(), self.max_batch_size)?;
+
+        self.page.increment_views_many(page_ids).await
+    }
+
+    /// Gets the number of times the given page has been viewed.
+    #[inline]
+    pub async fn get_page_views(&self, page_id: PageId) -> Result<i64> {
+        self.page.get_views(page_id).await
+    }
+
+    /// Gets pages in the wiki, ordered by when they were last edited, most recent first.
+    ///
+    /// Rejects any requests with more pages than the configured maximum batch size.
+    pub async fn get_recently_edited_pages(
+        &self,
+        wiki_id: WikiId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Page>> {
+        check_batch_size(limit, self.max_batch_size)?;
+
+        self.page
+            .get_pages_by_recent_edit(wiki_id, limit, offset)
+            .await
+    }
+
+    /// Gets the current contents of every page in the wiki, as slug and content pairs.
+    ///
+    /// This loads the entire wiki into memory at once, so it should only be used
+    /// for exports and other bulk operations.
+    #[inline]
+    pub async fn export_wiki_contents(&self, wiki_id: WikiId) -> Result<Vec<(String, Box<[u8]>)>> {
+        self.page.get_all_contents(wiki_id).await
     }
 }
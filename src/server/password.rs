@@ -29,8 +29,15 @@ impl Server {
             return Err(Error::NewPasswordInvalid("passwords may not be empty"));
         }
 
-        task::block_on(self.password.set(user_id, password))?;
-        Ok(())
+        task::block_on(async {
+            let user = self
+                .user
+                .get_from_id(user_id)
+                .await?
+                .ok_or(Error::UserNotFound)?;
+
+            self.password.set(user_id, user.name(), password).await
+        })
     }
 
     /// Validates the password for the given user.
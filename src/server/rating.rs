@@ -18,24 +18,88 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use super::utils::check_batch_size;
 use crate::manager_prelude::*;
-use crate::package::rating::RatingHistory;
+use crate::package::audit::AuditAction;
+use crate::package::rating::{RatingChanged, RatingHistory, RatingInconsistency};
+
+const TOP_PAGES_LIMIT: i64 = 100;
+const USER_HISTORY_LIMIT: i64 = 100;
 
 impl Server {
     /// Sets the rating for a given page and user.
+    /// Also returns the previous rating, if any.
     #[inline]
     pub async fn set_rating(
         &self,
         page_id: PageId,
         user_id: UserId,
         rating: i16,
-    ) -> Result<RatingId> {
+    ) -> Result<(RatingId, RatingChanged)> {
         info!(
             "Setting rating for page ID {} / user ID {}: {}",
             page_id, user_id, rating,
         );
 
-        self.rating.set(page_id, user_id, rating).await
+        self.transaction(async {
+            let result = self.rating.set(page_id, user_id, rating).await?;
+
+            let data = json!({ "page_id": page_id, "rating": rating });
+            self.audit_page(page_id, Some(user_id), AuditAction::SetRating, data)
+                .await?;
+
+            Ok(result)
+        })
+        .await
+    }
+
+    /// Sets many ratings at once, such as when importing a wiki from elsewhere.
+    /// Returns the rating history IDs, in the same order as the input.
+    ///
+    /// If a page and user appear more than once, the last rating is the one kept.
+    /// This is not limited by the maximum batch size, and individual votes are not audited.
+    pub async fn import_ratings(&self, ratings: &[(PageId, UserId, i16)]) -> Result<Vec<RatingId>> {
+        info!("Importing {} ratings", ratings.len());
+
+        self.rating.set_many(ratings).await
+    }
+
+    /// Gets the highest rated pages in the wiki, by the sum of their votes.
+    /// Deleted pages are excluded, and at most 100 pages are returned.
+    /// A negative limit returns nothing.
+    pub async fn get_top_pages(&self, wiki_id: WikiId, limit: i64) -> Result<Vec<(PageId, i64)>> {
+        let limit = limit.clamp(0, TOP_PAGES_LIMIT);
+
+        self.rating.top_pages(wiki_id, limit).await
+    }
+
+    /// Gets the vote totals for a page, along with the given user's own vote on it, if any.
+    pub async fn get_page_votes_for_user(
+        &self,
+        page_id: PageId,
+        user_id: UserId,
+    ) -> Result<(Votes, Option<i16>)> {
+        self.transaction(async {
+            let votes = self.rating.get_rating(page_id).await?;
+            let mut own = self.rating.get_user_ratings(user_id, &[page_id]).await?;
+
+            Ok((votes, own.remove(&page_id)))
+        })
+        .await
+    }
+
+    /// Gets the given user's ratings on each of the pages.
+    /// Pages the user has not voted on are omitted from the result.
+    ///
+    /// Rejects any requests with more IDs than the configured maximum batch size.
+    pub async fn get_user_ratings(
+        &self,
+        user_id: UserId,
+        page_ids: &[PageId],
+    ) -> Result<HashMap<PageId, i16>> {
+        check_batch_size(page_ids.len(), self.max_batch_size)?;
+
+        self.rating.get_user_ratings(user_id, page_ids).await
     }
 
     /// Removes the rating for a given page and user.
@@ -45,13 +109,24 @@ impl Server {
         &self,
         page_id: PageId,
         user_id: UserId,
-    ) -> Result<Option<RatingId>> {
+    ) -> Result<Option<(RatingId, RatingChanged)>> {
         info!(
             "Removing rating for page ID {} / user ID {}",
             page_id, user_id,
         );
 
-        self.rating.remove(page_id, user_id).await
+        self.transaction(async {
+            let result = self.rating.remove(page_id, user_id).await?;
+
+            if result.is_some() {
+                let data = json!({ "page_id": page_id });
+                self.audit_page(page_id, Some(user_id), AuditAction::RemoveRating, data)
+                    .await?;
+            }
+
+            Ok(result)
+        })
+        .await
     }
 
     /// Gets all changes in the rating for a given page and user.
@@ -65,6 +140,24 @@ impl Server {
         self.rating.get_history(page_id, user_id).await
     }
 
+    /// Gets all of a user's rating changes across every page, most recent first.
+    /// If `since` is given, only changes made at or after that time are included.
+    /// At most 100 entries are returned, and negative offsets or limits are treated as zero.
+    pub async fn get_user_rating_history(
+        &self,
+        user_id: UserId,
+        since: Option<DateTime<Utc>>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<RatingHistory>> {
+        let offset = offset.max(0);
+        let limit = limit.clamp(0, USER_HISTORY_LIMIT);
+
+        self.rating
+            .get_user_history(user_id, since, offset, limit)
+            .await
+    }
+
     /// Gets the latest rating history entry for the given page and user.
     #[inline]
     pub async fn get_rating_history_entry_last(
@@ -83,4 +176,11 @@ impl Server {
     ) -> Result<Option<RatingHistory>> {
         self.rating.get_history_entry(rating_id).await
     }
+
+    /// Checks that each user's current rating on the page matches their latest rating history entry.
+    /// Any mismatches are returned, ordered by user ID. Nothing is modified.
+    #[inline]
+    pub async fn audit_ratings(&self, page_id: PageId) -> Result<Vec<RatingInconsistency>> {
+        self.rating.find_inconsistencies(page_id).await
+    }
 }
@@ -18,10 +18,113 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::utils::normalize_slug;
+use super::utils::{check_batch_size, normalize_slug};
 use crate::manager_prelude::*;
+use crate::package::audit::AuditAction;
+use crate::package::page::{ChangeType, Revision, TagChange, TagChangeEntry, TimelineEntry};
 
 impl Server {
+    /// Gets the full metadata for the given revision, if it exists.
+    #[inline]
+    pub async fn get_revision(&self, revision_id: RevisionId) -> Result<Option<Revision>> {
+        self.page.get_revision(revision_id).await
+    }
+
+    /// Gets the revision referred to by a permalink from `RevisionId::to_permalink`.
+    /// Returns `None` if the permalink is invalid or the revision does not exist.
+    pub async fn resolve_permalink(&self, permalink: &str) -> Result<Option<Revision>> {
+        match RevisionId::from_permalink(permalink) {
+            Some(revision_id) => self.page.get_revision(revision_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Gets the revisions for a page, newest first.
+    /// Minor edits are only included if requested.
+    ///
+    /// Deleted pages still have their history, and unknown pages have none.
+    /// Rejects any requests with more revisions than the configured maximum batch size.
+    pub async fn get_page_history(
+        &self,
+        page_id: PageId,
+        limit: usize,
+        offset: usize,
+        include_minor: bool,
+    ) -> Result<Vec<Revision>> {
+        check_batch_size(limit, self.max_batch_size)?;
+
+        self.page
+            .get_revisions(page_id, limit, offset, include_minor)
+            .await
+    }
+
+    /// Counts the revisions of each kind made in the wiki within the given time range.
+    /// The start of the range is inclusive and the end is exclusive.
+    /// Kinds of change which did not occur are omitted.
+    #[inline]
+    pub async fn get_change_type_counts(
+        &self,
+        wiki_id: WikiId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<HashMap<ChangeType, i64>> {
+        self.page.get_change_type_counts(wiki_id, from, to).await
+    }
+
+    /// Gets the tags added and removed by the given revision.
+    /// Returns `None` if the revision does not exist or did not change tags.
+    #[inline]
+    pub async fn get_tag_change(&self, revision_id: RevisionId) -> Result<Option<TagChange>> {
+        self.page.get_tag_change(revision_id).await
+    }
+
+    /// Gets every change made to the page's tags, oldest first.
+    #[inline]
+    pub async fn get_page_tag_history(&self, page_id: PageId) -> Result<Vec<TagChangeEntry>> {
+        self.page.get_tag_history(page_id).await
+    }
+
+    /// Gets every title and alt-title the page has had, oldest first,
+    /// along with when each was set.
+    #[inline]
+    pub async fn get_page_title_history(
+        &self,
+        page_id: PageId,
+    ) -> Result<Vec<(String, Option<String>, DateTime<Utc>)>> {
+        self.page.get_title_history(page_id).await
+    }
+
+    /// Gets the revisions for a page, newest first, along with the name of each author.
+    ///
+    /// Rejects any requests with more revisions than the configured maximum batch size.
+    pub async fn get_page_history_with_authors(
+        &self,
+        page_id: PageId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<(Revision, Option<String>)>> {
+        check_batch_size(limit, self.max_batch_size)?;
+
+        self.page
+            .get_revisions_with_authors(page_id, limit, offset)
+            .await
+    }
+
+    /// Gets the revisions made anywhere in the wiki, newest first, along with each page's slug.
+    /// The slug is the page's current one, which may differ from when the revision was made.
+    ///
+    /// Rejects any requests with more revisions than the configured maximum batch size.
+    pub async fn get_wiki_activity(
+        &self,
+        wiki_id: WikiId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<(Revision, String)>> {
+        check_batch_size(limit, self.max_batch_size)?;
+
+        self.page.get_wiki_revisions(wiki_id, limit, offset).await
+    }
+
     /// Get the version of a page at the specified revision.
     #[inline]
     pub async fn get_page_version(
@@ -33,15 +136,57 @@ impl Server {
         self.page.get_page_version(wiki_id, slug, revision).await
     }
 
+    /// Gets the exact stored bytes of a page at the specified revision, along with
+    /// the git object ID of that content, which is suitable as a cache key.
+    #[inline]
+    pub async fn get_page_blob(
+        &self,
+        wiki_id: WikiId,
+        slug: &str,
+        revision: Either<RevisionId, &GitHash>,
+    ) -> Result<Option<(GitHash, Box<[u8]>)>> {
+        self.page.get_page_blob(wiki_id, slug, revision).await
+    }
+
+    /// Get the version of a page at the specified revision, without needing its slug.
+    ///
+    /// Returns `None` if the revision does not exist.
+    /// If the page was renamed after this revision, its contents cannot be found.
+    #[inline]
+    pub async fn get_page_version_by_revision(
+        &self,
+        revision_id: RevisionId,
+    ) -> Result<Option<String>> {
+        self.page.get_page_version_by_revision(revision_id).await
+    }
+
     /// Restores the given deleted page.
     /// If an ID is not specified, then the last page occupying the given slug is used.
-    #[inline]
+    ///
+    /// Returns `SlugReserved` if the slug is reserved in this wiki.
     pub async fn restore_page(
         &self,
         commit: PageCommit<'_>,
         page_id: Option<PageId>,
     ) -> Result<RevisionId> {
-        self.page.restore(commit, page_id).await
+        let PageCommit { wiki_id, user, .. } = commit;
+
+        self.transaction(async {
+            let revision_id = self.page.restore(commit, page_id).await?;
+            let page_id = self
+                .page
+                .get_revision(revision_id)
+                .await?
+                .ok_or(Error::RevisionNotFound)?
+                .page_id();
+
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::RestorePage, data)
+                .await?;
+
+            Ok(revision_id)
+        })
+        .await
     }
 
     /// Get the blame for a given page, if it exists.
@@ -70,20 +215,91 @@ impl Server {
         self.page.get_diff(wiki_id, &slug, first, second).await
     }
 
+    /// Get a diff for a given page between the two specified revisions,
+    /// parsed into hunks of added, removed, and unchanged text.
+    #[inline]
+    pub async fn get_page_diff_structured<S: Into<String>>(
+        &self,
+        wiki_id: WikiId,
+        slug: S,
+        first: Either<RevisionId, &GitHash>,
+        second: Either<RevisionId, &GitHash>,
+    ) -> Result<Diff> {
+        let slug = normalize_slug(slug);
+
+        self.page
+            .get_structured_diff(wiki_id, &slug, first, second)
+            .await
+    }
+
+    /// Get a diff between two different pages, at the specified revisions.
+    /// If a revision is not given, then the current version of that page is used.
+    #[inline]
+    pub async fn get_cross_page_diff<S1, S2>(
+        &self,
+        wiki_id: WikiId,
+        first_slug: S1,
+        first: Option<Either<RevisionId, &GitHash>>,
+        second_slug: S2,
+        second: Option<Either<RevisionId, &GitHash>>,
+    ) -> Result<String>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        let first_slug = normalize_slug(first_slug);
+        let second_slug = normalize_slug(second_slug);
+
+        self.page
+            .get_cross_diff(wiki_id, &first_slug, first, &second_slug, second)
+            .await
+    }
+
     /// Overwrite the revision message for a given change.
     #[inline]
     pub async fn edit_revision(&self, revision_id: RevisionId, message: &str) -> Result<()> {
         self.page.edit_revision(revision_id, message).await
     }
 
-    /// Undoes the given revision for a page.
+    /// Checks whether the given commit is part of the wiki's current history.
+    /// Returns `RevisionNotFound` if the commit does not exist.
     #[inline]
+    pub async fn is_revision_in_history(&self, wiki_id: WikiId, hash: &GitHash) -> Result<bool> {
+        self.page.is_ancestor(wiki_id, hash).await
+    }
+
+    /// Undoes the given revision for a page.
+    /// Returns `RevisionNotInHistory` if the revision is not part of the current history.
     pub async fn undo_revision(
         &self,
         commit: PageCommit<'_>,
         revision: Either<RevisionId, &GitHash>,
     ) -> Result<RevisionId> {
-        self.page.undo(commit, revision).await
+        let PageCommit {
+            wiki_id,
+            slug,
+            user,
+            ..
+        } = commit;
+
+        self.transaction(async {
+            let page_id = self.check_page_lock(wiki_id, slug, user.id()).await?;
+            let revision_id = self.page.undo(commit, revision).await?;
+
+            let data = json!({ "page_id": page_id, "revision_id": revision_id });
+            self.audit(wiki_id, Some(user.id()), AuditAction::UndoRevision, data)
+                .await?;
+
+            Ok(revision_id)
+        })
+        .await
+    }
+
+    /// Gets the full history of a page, oldest first, in a form suitable for timelines.
+    /// Revisions which change the page's contents include the number of lines changed.
+    #[inline]
+    pub async fn get_page_timeline(&self, page_id: PageId) -> Result<Vec<TimelineEntry>> {
+        self.page.get_timeline(page_id).await
     }
 
     /// Performs git vacuum in the page repository.
@@ -18,7 +18,12 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use super::utils::check_batch_size;
 use crate::manager_prelude::*;
+use chrono::Duration;
+
+const RECENT_ACTIVITY_DAYS: i64 = 30;
+const RECENT_ACTIVITY_LIMIT: usize = 10;
 
 macro_rules! wrap_login {
     ($future:expr) => {
@@ -37,6 +42,7 @@ macro_rules! wrap_login {
 impl Server {
     /// Attempts to login a user via user ID.
     /// Returns the new session if successful, `AuthenticationFailed` otherwise.
+    /// Returns `TooManyLoginAttempts` if the user has failed to login too often recently.
     pub async fn try_login_id(
         &self,
         user_id: UserId,
@@ -71,6 +77,9 @@ impl Server {
             return Err(Error::AuthenticationFailed);
         }
 
+        // Refuse before checking the password if there have been too many failures
+        self.session.check_login_throttle(user_id).await?;
+
         // Outside of a transaction so it doesn't get rolled back
         let login_attempt_id = self
             .session
@@ -85,6 +94,7 @@ impl Server {
                 .create_session(user_id, login_attempt_id)
                 .await?;
 
+            self.counters.add_login();
             Ok(session)
         })
         .await
@@ -132,13 +142,74 @@ impl Server {
         }
     }
 
+    /// Attempts to login a user via username or email.
+    /// Returns the new session and the user's most recent login attempts,
+    /// including this one, if successful, `AuthenticationFailed` otherwise.
+    ///
+    /// Email addresses in the attempts are redacted unless `admin` is set.
+    pub async fn login_with_recent_activity(
+        &self,
+        name_or_email: &str,
+        password: &str,
+        remote_address: Option<&str>,
+        admin: bool,
+    ) -> Result<(Session, Vec<LoginAttempt>)> {
+        let session = self
+            .try_login(name_or_email, password, remote_address)
+            .await?;
+
+        let since = Utc::now() - Duration::days(RECENT_ACTIVITY_DAYS);
+        let mut attempts = self
+            .session
+            .get_login_attempts(session.user_id(), since)
+            .await?;
+
+        attempts.truncate(RECENT_ACTIVITY_LIMIT);
+
+        Ok((session, redact_attempts(attempts, admin)))
+    }
+
     /// Validate a user's session to ensure they are logged in.
-    /// Returns `()` if successful, `InvalidSession` otherwise.
+    /// Returns `()` if successful, `SessionExpired` if it has been inactive too long,
+    /// or `InvalidSession` otherwise. Checking a session refreshes its activity time.
     #[inline]
     pub async fn check_session(&self, session_id: SessionId, user_id: UserId) -> Result<()> {
         self.session.check_session(session_id, user_id).await
     }
 
+    /// Validate several sessions at once.
+    /// Returns whether each `(session_id, user_id)` pair is a valid, unexpired session,
+    /// in the same order as the input.
+    ///
+    /// Rejects any requests with more pairs than the configured maximum batch size.
+    pub async fn check_sessions(&self, pairs: &[(SessionId, UserId)]) -> Result<Vec<bool>> {
+        check_batch_size(pairs.len(), self.max_batch_size)?;
+
+        self.session.check_sessions(pairs).await
+    }
+
+    /// Validate a user's session, and return the user it belongs to.
+    /// Returns `InvalidSession` if the session is not valid,
+    /// or `UserNotFound` if the user no longer exists or is inactive.
+    pub async fn authenticate(&self, session_id: SessionId, user_id: UserId) -> Result<User> {
+        self.transaction(async {
+            self.session.check_session(session_id, user_id).await?;
+
+            match self.user.get_from_id(user_id).await? {
+                Some(user) if user.is_active() => Ok(user),
+                _ => Err(Error::UserNotFound),
+            }
+        })
+        .await
+    }
+
+    /// Removes all sessions which have been inactive past the expiry period.
+    /// Returns the number of sessions removed.
+    #[inline]
+    pub async fn purge_expired_sessions(&self) -> Result<usize> {
+        self.session.purge_expired().await
+    }
+
     /// Deactivate a session currently logged in.
     /// Returns `()` if successful, `InvalidSession` if no such session was found.
     #[inline]
@@ -148,6 +219,8 @@ impl Server {
 
     /// Deactivates all sessions except the one currently logged in.
     /// Returns a list of the sessions which were deactivated.
+    ///
+    /// Returns `SessionExpired` if the current session has expired.
     pub async fn end_other_sessions(
         &self,
         session_id: SessionId,
@@ -156,9 +229,11 @@ impl Server {
         self.session.end_other_sessions(session_id, user_id).await
     }
 
-    /// Get all sessions for the given user.
+    /// Get all active sessions for the given user.
     /// Returns the current session (the one passed in the argument) first,
     /// and the other sessions in the list.
+    ///
+    /// Returns `SessionExpired` if the current session has expired.
     pub async fn get_sessions(
         &self,
         session_id: SessionId,
@@ -178,23 +253,67 @@ impl Server {
 
     /// Returns all login attempts for a user since the given date.
     /// Limited to 100 entries.
-    #[inline]
+    ///
+    /// Email addresses are redacted unless `admin` is set.
     pub async fn get_login_attempts<Tz: TimeZone>(
         &self,
         user_id: UserId,
         since: DateTime<Tz>,
+        admin: bool,
     ) -> Result<Vec<LoginAttempt>> {
-        self.session.get_login_attempts(user_id, since).await
+        let attempts = self.session.get_login_attempts(user_id, since).await?;
+
+        Ok(redact_attempts(attempts, admin))
+    }
+
+    /// Returns the most recent successful login attempt for a user, if any.
+    #[inline]
+    pub async fn get_last_successful_login(&self, user_id: UserId) -> Result<Option<LoginAttempt>> {
+        self.session.get_last_successful_login(user_id).await
+    }
+
+    /// Counts a user's failed login attempts within the given window,
+    /// ignoring any made before their most recent successful login.
+    #[inline]
+    pub async fn count_recent_login_failures(
+        &self,
+        user_id: UserId,
+        within: Duration,
+    ) -> Result<i64> {
+        self.session.count_recent_failures(user_id, within).await
+    }
+
+    /// Returns the time of the last successful login for each of the given users.
+    /// Users who have never logged in are omitted from the map.
+    pub async fn get_last_logins(
+        &self,
+        user_ids: &[UserId],
+    ) -> Result<HashMap<UserId, DateTime<Utc>>> {
+        check_batch_size(user_ids.len(), self.max_batch_size)?;
+
+        self.session.get_last_logins(user_ids).await
     }
 
     /// Returns all login attempts for all users since the given date.
     /// Limited to 100 entries.
-    #[inline]
+    ///
+    /// Email addresses are redacted unless `admin` is set.
     pub async fn get_all_login_attempts<Tz: TimeZone>(
         &self,
         since: DateTime<Tz>,
+        admin: bool,
     ) -> Result<Vec<LoginAttempt>> {
-        self.session.get_all_login_attempts(since).await
+        let attempts = self.session.get_all_login_attempts(since).await?;
+
+        Ok(redact_attempts(attempts, admin))
+    }
+}
+
+fn redact_attempts(attempts: Vec<LoginAttempt>, admin: bool) -> Vec<LoginAttempt> {
+    if admin {
+        attempts
+    } else {
+        attempts.iter().map(LoginAttempt::redacted).collect()
     }
 }
 
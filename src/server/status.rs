@@ -0,0 +1,66 @@
+/*
+ * server/status.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::manager_prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Running totals of operations performed by a `Server`.
+#[derive(Debug, Default)]
+pub struct Counters {
+    logins: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    #[inline]
+    pub fn add_login(&self) {
+        self.logins.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A snapshot of the server's uptime and operation counts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ServerStatus {
+    pub started_at: DateTime<Utc>,
+    pub uptime: Duration,
+    pub commits: u64,
+    pub logins: u64,
+    pub errors: u64,
+}
+
+impl Server {
+    /// Returns when the server started, how long it has been running,
+    /// and how many commits, logins, and failed transactions it has seen.
+    pub fn status(&self) -> ServerStatus {
+        ServerStatus {
+            started_at: self.started_at,
+            uptime: self.started.elapsed(),
+            commits: self.page.commit_count(),
+            logins: self.counters.logins.load(Ordering::Relaxed),
+            errors: self.counters.errors.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -18,7 +18,9 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use super::utils::check_batch_size;
 use crate::manager_prelude::*;
+use crate::package::user::UserStats;
 
 impl Server {
     /// Creates a new user with the given name and email. Returns its ID.
@@ -26,7 +28,7 @@ impl Server {
     pub async fn create_user(&self, name: &str, email: &str, password: &str) -> Result<UserId> {
         self.transaction(async {
             let user_id = self.user.create(name, email).await?;
-            self.password.set(user_id, password).await?;
+            self.password.set(user_id, name, password).await?;
 
             Ok(user_id)
         })
@@ -45,19 +47,56 @@ impl Server {
         self.user.get_from_id(id).await
     }
 
+    /// Gets the model for a user from its ID, along with statistics about their activity.
+    pub async fn get_user_with_stats(&self, id: UserId) -> Result<Option<(User, UserStats)>> {
+        self.transaction(async {
+            let user = match self.user.get_from_id(id).await? {
+                Some(user) => user,
+                None => return Ok(None),
+            };
+
+            let stats = self.user.get_stats(id).await?;
+
+            Ok(Some((user, stats)))
+        })
+        .await
+    }
+
     /// Gets the models for users from their IDs.
     /// Results are returned in the same order as the IDs, and any missing
     /// users give `None` instead.
     ///
-    /// Rejects any requests with more than 100 IDs.
+    /// Rejects any requests with more IDs than the configured maximum batch size.
     pub async fn get_users_from_ids(&self, ids: &[UserId]) -> Result<Vec<Option<User>>> {
-        if ids.len() > 100 {
-            return Err(Error::RequestTooLarge(ids.len(), 100));
-        }
+        check_batch_size(ids.len(), self.max_batch_size)?;
 
         self.user.get_from_ids(ids).await
     }
 
+    /// Gets users who signed up within the given time range, oldest first.
+    /// The start of the range is inclusive and the end is exclusive.
+    pub async fn get_users_created_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        include_inactive: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<User>> {
+        check_batch_size(limit, self.max_batch_size)?;
+
+        self.user
+            .get_users_created_between(from, to, include_inactive, limit, offset)
+            .await
+    }
+
+    /// Gets the IDs for users from their names, matching case-insensitively.
+    /// Names which do not correspond to any user are omitted from the result.
+    #[inline]
+    pub async fn resolve_usernames(&self, names: &[&str]) -> Result<HashMap<String, UserId>> {
+        self.user.get_ids_by_names(names).await
+    }
+
     /// Gets the model for a user from its name.
     #[inline]
     pub async fn get_user_from_name(&self, name: &str) -> Result<Option<User>> {
@@ -89,6 +128,79 @@ impl Server {
         self.user.create_token(id).await
     }
 
+    /// Changes a user's email, marking them as unverified until the new address is confirmed.
+    /// Returns a verification token for the new address, any previous tokens are invalidated.
+    #[inline]
+    pub async fn change_user_email(&self, id: UserId, new_email: &str) -> Result<String> {
+        self.user.change_email(id, new_email).await
+    }
+
+    /// Permanently removes all personal information from a user, and marks them inactive.
+    /// Their name is replaced with a placeholder, all sessions and tokens are invalidated,
+    /// and their password is removed. Their login attempts are kept,
+    /// but without the names, emails or remote addresses used.
+    ///
+    /// Content they have created, such as revisions and authorship, is preserved.
+    pub async fn anonymize_user(&self, id: UserId) -> Result<()> {
+        info!("Anonymizing user ID {}", id);
+
+        self.transaction(async {
+            let user = self
+                .user
+                .get_from_id(id)
+                .await?
+                .ok_or(Error::UserNotFound)?;
+
+            self.user.anonymize(id).await?;
+            self.session.end_all_sessions(id).await?;
+            self.password.remove(id).await?;
+
+            let anonymized = self
+                .user
+                .get_from_id(id)
+                .await?
+                .ok_or(Error::UserNotFound)?;
+
+            self.session
+                .scrub_login_attempts(id, &[user.name(), user.email()], anonymized.name())
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Secures a potentially compromised account.
+    /// All sessions are ended, verification tokens are invalidated,
+    /// and if provided, the password is replaced.
+    ///
+    /// Returns the number of sessions which were ended.
+    pub async fn lock_down_user(&self, id: UserId, new_password: Option<&str>) -> Result<usize> {
+        info!("Locking down user ID {}", id);
+
+        if new_password == Some("") {
+            return Err(Error::NewPasswordInvalid("passwords may not be empty"));
+        }
+
+        self.transaction(async {
+            let user = self
+                .user
+                .get_from_id(id)
+                .await?
+                .ok_or(Error::UserNotFound)?;
+
+            let sessions = self.session.end_all_sessions(id).await?;
+            self.user.clear_tokens(id).await?;
+
+            if let Some(password) = new_password {
+                self.password.set(id, user.name(), password).await?;
+            }
+
+            Ok(sessions)
+        })
+        .await
+    }
+
     /// Marks the user as "inactive", effectively deleting them.
     #[inline]
     pub async fn mark_user_inactive(&self, id: UserId) -> Result<()> {
@@ -18,7 +18,26 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use wikidot_normalize::normalize;
+use crate::{Error, Result};
+use wikidot_normalize::{is_normal, normalize};
+
+pub fn check_batch_size(len: usize, max: usize) -> Result<()> {
+    if len > max {
+        warn!("Rejecting batch request of {} items (maximum {})", len, max);
+
+        return Err(Error::RequestTooLarge(len, max));
+    }
+
+    Ok(())
+}
+
+pub fn check_normal_slug(slug: &str) -> Result<()> {
+    if is_normal(slug, false) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSlug(String::from(slug)))
+    }
+}
 
 pub fn normalize_slug<S: Into<String>>(slug: S) -> String {
     let mut slug = slug.into();
@@ -26,6 +45,38 @@ pub fn normalize_slug<S: Into<String>>(slug: S) -> String {
     slug
 }
 
+pub fn page_category(slug: &str) -> &str {
+    match slug.find(':') {
+        Some(idx) => &slug[..idx],
+        None => "_default",
+    }
+}
+
+/// Derives an alternate title from the part of the title after the first colon,
+/// for instance "SCP-173: The Sculpture" gives "The Sculpture".
+pub fn derive_alt_title(title: &str) -> Option<&str> {
+    let idx = title.find(':')?;
+    let alt_title = title[idx + 1..].trim();
+
+    if alt_title.is_empty() {
+        None
+    } else {
+        Some(alt_title)
+    }
+}
+
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut tags = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
 pub fn to_lowercase<S: Into<String>>(value: S) -> String {
     let mut value = value.into();
     value.make_ascii_lowercase();
@@ -0,0 +1,43 @@
+/*
+ * server/watch.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::manager_prelude::*;
+
+impl Server {
+    /// Adds the page to the user's watch list.
+    /// Returns `false` if they were already watching it.
+    #[inline]
+    pub async fn watch_page(&self, user_id: UserId, page_id: PageId) -> Result<bool> {
+        self.watch.add(page_id, user_id).await
+    }
+
+    /// Removes the page from the user's watch list.
+    /// Returns `false` if they were not watching it.
+    #[inline]
+    pub async fn unwatch_page(&self, user_id: UserId, page_id: PageId) -> Result<bool> {
+        self.watch.remove(page_id, user_id).await
+    }
+
+    /// Gets all users who are watching the given page.
+    #[inline]
+    pub async fn get_watchers(&self, page_id: PageId) -> Result<Vec<UserId>> {
+        self.watch.get_watchers(page_id).await
+    }
+}
@@ -18,8 +18,10 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::utils::{normalize_slug, to_lowercase};
+use super::utils::{check_batch_size, normalize_slug, to_lowercase};
 use crate::manager_prelude::*;
+use std::collections::HashSet;
+use std::path::PathBuf;
 
 impl Server {
     /// Creates a new wiki with the given parameters. Returns its ID.
@@ -31,18 +33,14 @@ impl Server {
         let slug = normalize_slug(slug);
         let domain = to_lowercase(domain);
 
-        let (id, guard) = self.wiki.create(name, &slug, &domain).await?;
-        let wiki = guard
-            .get(&id)
-            .expect("Can't find wiki object after inserting");
-
+        let wiki = self.wiki.create(name, &slug, &domain).await?;
         self.page.add_store(&wiki).await?;
 
-        Ok(id)
+        Ok(wiki.id())
     }
 
     /// Renames the given wiki.
-    /// Changing a wiki's slug is not supported.
+    /// To change the wiki's slug, use `change_wiki_slug()`.
     pub async fn rename_wiki(&self, id: WikiId, new_name: &str) -> Result<()> {
         info!("Renaming wiki ID {} to '{}'", id, new_name);
 
@@ -50,6 +48,60 @@ impl Server {
         Ok(())
     }
 
+    /// Changes the slug for the given wiki, moving its repository to match.
+    /// If the repository cannot be moved, the wiki is left unchanged.
+    pub async fn change_wiki_slug<S: Into<String>>(&self, id: WikiId, new_slug: S) -> Result<()> {
+        let new_slug = normalize_slug(new_slug);
+
+        info!("Changing slug for wiki ID {} to '{}'", id, new_slug);
+
+        let mut moved = None;
+        let result = self
+            .transaction(async {
+                let old_wiki = self.wiki.get_by_id(id, true).await?;
+                if old_wiki.slug() == new_slug {
+                    return Ok(None);
+                }
+
+                match self.wiki.get_by_slug(&new_slug, true).await {
+                    Ok(_) => return Err(Error::WikiExists),
+                    Err(Error::WikiNotFound) => (),
+                    Err(error) => return Err(error),
+                }
+
+                let wiki = self.wiki.set_slug(id, &new_slug).await?;
+
+                // The filesystem can't be rolled back, so the repository is moved last.
+                // If this fails, the slug change is rolled back with the transaction.
+                self.page.move_store(&wiki, old_wiki.slug()).await?;
+                moved = Some(old_wiki);
+
+                Ok(Some(wiki))
+            })
+            .await;
+
+        match result {
+            Ok(Some(wiki)) => {
+                self.wiki.cache(wiki).await;
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(error) => {
+                // Only reachable if the transaction failed to commit after the move
+                if let Some(old_wiki) = moved {
+                    warn!(
+                        "Slug change for wiki ID {} failed to commit, moving repository back",
+                        id,
+                    );
+
+                    self.page.move_store(&old_wiki, &new_slug).await?;
+                }
+
+                Err(error)
+            }
+        }
+    }
+
     /// Changes the associated domain for the given wiki.
     pub async fn set_wiki_domain(&self, id: WikiId, new_domain: &str) -> Result<()> {
         info!("Changing domain for wiki ID {} to '{}'", id, new_domain);
@@ -63,18 +115,78 @@ impl Server {
         .await
     }
 
-    /// Gets information about the wiki with the given ID
+    /// Marks the wiki as "inactive", effectively deleting it.
+    /// Its pages and history are kept, but can no longer be read or modified.
+    pub async fn deactivate_wiki(&self, id: WikiId) -> Result<()> {
+        self.wiki.mark_inactive(id, true).await?;
+        self.page.unload_store(id).await;
+
+        Ok(())
+    }
+
+    /// Marks the wiki as "active" again, effectively un-deleting it.
+    #[inline]
+    pub async fn reactivate_wiki(&self, id: WikiId) -> Result<()> {
+        self.wiki.mark_inactive(id, false).await
+    }
+
+    /// Gets information about the wiki with the given ID, even if it is inactive.
     #[inline]
     pub async fn get_wiki_by_id(&self, id: WikiId) -> Result<(Wiki, WikiSettings)> {
-        try_join!(self.wiki.get_by_id(id), self.wiki.get_settings(id))
+        try_join!(self.wiki.get_by_id(id, true), self.wiki.get_settings(id))
+    }
+
+    /// Gets summary information about a wiki, including its earliest page.
+    /// Returns `None` if the wiki doesn't exist or is inactive.
+    pub async fn get_wiki_info(&self, id: WikiId) -> Result<Option<WikiInfo>> {
+        info!("Getting wiki info for wiki ID {}", id);
+
+        self.transaction(async {
+            let wiki = match self.wiki.get_by_id(id, false).await {
+                Ok(wiki) => wiki,
+                Err(Error::WikiNotFound) => return Ok(None),
+                Err(error) => return Err(error),
+            };
+
+            let page_count = self.page.count_pages(id).await?;
+            let first_page = self.page.get_first_page(id).await?;
+
+            Ok(Some(WikiInfo {
+                name: String::from(wiki.name()),
+                slug: String::from(wiki.slug()),
+                domain: String::from(wiki.domain()),
+                created_at: wiki.created_at(),
+                page_count,
+                first_page,
+            }))
+        })
+        .await
+    }
+
+    /// Gets every wiki, ordered by slug.
+    /// Inactive wikis are only included if requested.
+    #[inline]
+    pub async fn list_wikis(&self, include_inactive: bool) -> Result<Vec<Wiki>> {
+        self.wiki.get_all(include_inactive).await
+    }
+
+    /// Gets the models for wikis from their IDs.
+    /// Results are returned in the same order as the IDs, and any missing
+    /// wikis give `None` instead.
+    ///
+    /// Rejects any requests with more IDs than the configured maximum batch size.
+    pub async fn get_wikis_by_ids(&self, ids: &[WikiId]) -> Result<Vec<Option<Wiki>>> {
+        check_batch_size(ids.len(), self.max_batch_size)?;
+
+        self.wiki.get_by_ids(ids).await
     }
 
     /// Gets the wiki ID with the given slug.
-    /// Returns an error if the wiki doesn't exist.
+    /// Returns an error if the wiki doesn't exist or is inactive.
     pub async fn get_wiki_by_slug<S: Into<String>>(&self, slug: S) -> Result<(Wiki, WikiSettings)> {
         let slug = normalize_slug(slug);
 
-        let wiki = self.wiki.get_by_slug(&slug).await?;
+        let wiki = self.wiki.get_by_slug(&slug, false).await?;
         let settings = self.wiki.get_settings(wiki.id()).await?;
 
         Ok((wiki, settings))
@@ -85,12 +197,80 @@ impl Server {
         &self,
         id: WikiId,
         page_lock_duration: Option<i16>,
+        lock_category_on_rename: Option<bool>,
     ) -> Result<()> {
         info!(
-            "Changing settings for wiki ID {}: page_lock_duration {:?}",
-            id, page_lock_duration,
+            "Changing settings for wiki ID {}: page_lock_duration {:?}, lock_category_on_rename {:?}",
+            id, page_lock_duration, lock_category_on_rename,
+        );
+
+        self.wiki
+            .edit_settings(
+                id,
+                page_lock_duration,
+                lock_category_on_rename,
+                None,
+                None,
+                None,
+            )
+            .await
+    }
+
+    /// Changes which scorer the given wiki uses to compute page ratings.
+    pub async fn set_wiki_scoring(&self, id: WikiId, kind: ScoringKind) -> Result<()> {
+        info!("Changing scoring for wiki ID {} to {:?}", id, kind);
+
+        self.wiki
+            .edit_settings(id, None, None, Some(kind), None, None)
+            .await
+    }
+
+    /// Sets which slugs in the given wiki may only be created with privilege.
+    /// This replaces the existing list, slugs are normalized before being stored.
+    pub async fn set_wiki_reserved_slugs(&self, id: WikiId, slugs: &[&str]) -> Result<()> {
+        let mut slugs = slugs
+            .iter()
+            .copied()
+            .map(normalize_slug)
+            .collect::<Vec<_>>();
+        slugs.sort();
+        slugs.dedup();
+
+        info!("Changing reserved slugs for wiki ID {} to {:?}", id, slugs);
+
+        self.wiki
+            .edit_settings(id, None, None, None, Some(slugs), None)
+            .await
+    }
+
+    /// Sets whether pages created or edited with an empty alternate title
+    /// have one derived from their title instead.
+    pub async fn set_wiki_auto_alt_title(&self, id: WikiId, enabled: bool) -> Result<()> {
+        info!("Changing auto alt title for wiki ID {} to {}", id, enabled);
+
+        self.wiki
+            .edit_settings(id, None, None, None, None, Some(enabled))
+            .await
+    }
+
+    /// Finds directories in the revisions directory which do not belong to any wiki.
+    ///
+    /// These are typically left over from failed operations. They are only
+    /// reported, it is up to the caller to inspect and remove them.
+    pub async fn find_orphaned_repositories(&self) -> Result<Vec<PathBuf>> {
+        info!("Finding orphaned wiki repositories");
+
+        let wikis = self.wiki.get_all(true).await?;
+        let slugs = wikis.iter().map(|wiki| wiki.slug()).collect::<HashSet<_>>();
+
+        let mut paths = self.page.list_repositories().await?;
+        paths.retain(
+            |path| match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => !slugs.contains(name),
+                None => true,
+            },
         );
 
-        self.wiki.edit_settings(id, page_lock_duration).await
+        Ok(paths)
     }
 }
@@ -0,0 +1,199 @@
+/*
+ * test/audit.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+
+#[tokio::test]
+async fn audit_log() {
+    let server = &create_server_with(|config| config.enable_audit_log = true).await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "audited-page",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _revision_id) = server
+        .create_page(commit, "contents", &[], "Audited Page", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .set_rating(page_id, user.id(), 1)
+        .await
+        .expect("Unable to set rating");
+
+    let filter = AuditFilter {
+        wiki_id: Some(wiki_id),
+        ..AuditFilter::default()
+    };
+
+    let entries = server
+        .get_audit_log(filter)
+        .await
+        .expect("Unable to get audit log");
+
+    let actions = entries
+        .iter()
+        .map(|entry| entry.action())
+        .collect::<Vec<_>>();
+
+    assert_eq!(actions, vec![AuditAction::SetRating, AuditAction::AddPage]);
+
+    for entry in &entries {
+        assert_eq!(entry.wiki_id(), wiki_id);
+        assert_eq!(entry.user_id(), Some(user.id()));
+        assert_eq!(entry.data()["page_id"], json!(page_id));
+    }
+
+    let filter = AuditFilter {
+        wiki_id: Some(wiki_id),
+        action: Some(AuditAction::AddPage),
+        ..AuditFilter::default()
+    };
+
+    let entries = server
+        .get_audit_log(filter)
+        .await
+        .expect("Unable to get audit log");
+
+    assert_eq!(entries.len(), 1);
+}
+
+#[tokio::test]
+async fn audit_log_disabled() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "unaudited-page",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "contents", &[], "Unaudited Page", "")
+        .await
+        .expect("Unable to create page");
+
+    let filter = AuditFilter {
+        wiki_id: Some(wiki_id),
+        ..AuditFilter::default()
+    };
+
+    let entries = server
+        .get_audit_log(filter)
+        .await
+        .expect("Unable to get audit log");
+
+    assert!(entries.is_empty());
+}
+
+#[tokio::test]
+async fn audit_log_history() {
+    let server = &create_server_with(|config| config.enable_audit_log = true).await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        () => {
+            PageCommit {
+                wiki_id,
+                slug: "audited-history",
+                message: "audit test",
+                user: &user,
+            }
+        };
+    }
+
+    let (page_id, _) = server
+        .create_page(commit!(), "first", &[], "Audited History", "")
+        .await
+        .expect("Unable to create page");
+
+    let edit_revision_id = server
+        .edit_page(commit!(), Some("second"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let undo_revision_id = server
+        .undo_revision(commit!(), Left(edit_revision_id))
+        .await
+        .expect("Unable to undo revision");
+
+    let revert_revision_id = server
+        .revert_page(commit!(), Left(edit_revision_id))
+        .await
+        .expect("Unable to revert page");
+
+    server
+        .remove_page(commit!())
+        .await
+        .expect("Unable to remove page");
+
+    let restore_revision_id = server
+        .restore_page(commit!(), Some(page_id))
+        .await
+        .expect("Unable to restore page");
+
+    let filter = AuditFilter {
+        wiki_id: Some(wiki_id),
+        ..AuditFilter::default()
+    };
+
+    let entries = server
+        .get_audit_log(filter)
+        .await
+        .expect("Unable to get audit log");
+
+    let actions = entries
+        .iter()
+        .map(|entry| entry.action())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actions,
+        vec![
+            AuditAction::RestorePage,
+            AuditAction::RemovePage,
+            AuditAction::RevertPage,
+            AuditAction::UndoRevision,
+            AuditAction::EditPage,
+            AuditAction::AddPage,
+        ],
+    );
+
+    for entry in &entries {
+        assert_eq!(entry.data()["page_id"], json!(page_id));
+    }
+
+    assert_eq!(entries[0].data()["revision_id"], json!(restore_revision_id));
+    assert_eq!(entries[2].data()["revision_id"], json!(revert_revision_id));
+    assert_eq!(entries[3].data()["revision_id"], json!(undo_revision_id));
+}
@@ -20,6 +20,7 @@
 
 use super::prelude::*;
 use crate::package::author::AuthorType;
+use chrono::prelude::*;
 
 #[tokio::test]
 async fn author() {
@@ -40,11 +41,7 @@ async fn author() {
         .expect("Unable to get user")
         .expect("Created user not found");
 
-    let user_3 = server
-        .get_user_from_name("unknown")
-        .await
-        .expect("Unable to get user")
-        .expect("Default user not found");
+    let user_3 = server.default_user().await.expect("Unable to get user");
 
     let commit = PageCommit {
         wiki_id,
@@ -111,3 +108,280 @@ async fn author() {
     assert_eq!(authors[3].page_id(), page_id);
     assert_eq!(authors[3].author_type(), AuthorType::Translator);
 }
+
+#[tokio::test]
+async fn author_order() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+
+    let user_id_1 = create_user(server).await;
+    let user_id_2 = create_user(server).await;
+    let user_id_3 = create_user(server).await;
+    let user = server
+        .get_user_from_id(user_id_1)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "author-order",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _revision_id) = server
+        .create_page(commit, "contents", &[], "Author Order", "")
+        .await
+        .expect("Unable to create page");
+
+    let page = Left(page_id);
+    server
+        .remove_page_authors(page, &[(user_id_1, AuthorType::Author)])
+        .await
+        .expect("Unable to remove authors");
+
+    // Insert in an arbitrary order
+    server
+        .add_page_authors(
+            page,
+            &[
+                (user_id_3, AuthorType::Translator, None),
+                (user_id_2, AuthorType::Author, None),
+                (user_id_3, AuthorType::Author, None),
+                (user_id_1, AuthorType::Rewrite, None),
+                (user_id_1, AuthorType::Author, None),
+            ],
+        )
+        .await
+        .expect("Unable to add authors");
+
+    let authors = server
+        .get_page_authors(page)
+        .await
+        .expect("Unable to get page authors");
+
+    let authors: Vec<_> = authors
+        .iter()
+        .map(|author| (author.author_type(), author.user_id()))
+        .collect();
+
+    assert_eq!(
+        authors,
+        vec![
+            (AuthorType::Author, user_id_1),
+            (AuthorType::Author, user_id_2),
+            (AuthorType::Author, user_id_3),
+            (AuthorType::Rewrite, user_id_1),
+            (AuthorType::Translator, user_id_3),
+        ],
+    );
+}
+
+#[tokio::test]
+async fn author_change_type() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+
+    let user_id = create_user(server).await;
+    let user = server
+        .get_user_from_id(user_id)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "author-type",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _revision_id) = server
+        .create_page(commit, "contents", &[], "Author Type", "")
+        .await
+        .expect("Unable to create page");
+
+    let page = Left(page_id);
+    let written_at = NaiveDate::from_ymd(2012, 7, 14);
+
+    server
+        .add_page_authors(page, &[(user_id, AuthorType::Author, Some(written_at))])
+        .await
+        .expect("Unable to add authors");
+
+    // Change type
+    let changed = server
+        .change_page_author_type(page, user_id, AuthorType::Author, AuthorType::Rewrite)
+        .await
+        .expect("Unable to change author type");
+
+    assert!(changed, "Author type not changed");
+
+    let authors = server
+        .get_page_authors(page)
+        .await
+        .expect("Unable to get page authors");
+
+    assert_eq!(authors.len(), 1);
+    assert_eq!(authors[0].user_id(), user_id);
+    assert_eq!(authors[0].author_type(), AuthorType::Rewrite);
+    assert_eq!(
+        authors[0].written_at(),
+        written_at,
+        "Written date not preserved"
+    );
+
+    // Nonexistent source authorship
+    let changed = server
+        .change_page_author_type(page, user_id, AuthorType::Author, AuthorType::Translator)
+        .await
+        .expect("Unable to change author type");
+
+    assert!(!changed, "Nonexistent author type changed");
+
+    // Target authorship already exists
+    server
+        .add_page_authors(page, &[(user_id, AuthorType::Translator, None)])
+        .await
+        .expect("Unable to add authors");
+
+    let error = server
+        .change_page_author_type(page, user_id, AuthorType::Rewrite, AuthorType::Translator)
+        .await
+        .expect_err("Changed author type to existing authorship");
+
+    match error {
+        Error::Conflict => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn authored_pages() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+    let user_id = create_user(server).await;
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let mut page_ids = Vec::new();
+    for (slug, year) in &[("scp-1000", 2018), ("scp-2000", 2019), ("scp-3000", 2020)] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        let (page_id, _) = server
+            .create_page(commit, "contents", &[], "", "")
+            .await
+            .expect("Unable to create page");
+
+        let date = NaiveDate::from_ymd(*year, 6, 1);
+        server
+            .add_page_authors(
+                Left(page_id),
+                &[
+                    (user_id, AuthorType::Author, Some(date)),
+                    (user_id, AuthorType::Maintainer, Some(date)),
+                ],
+            )
+            .await
+            .expect("Unable to add authors");
+
+        page_ids.push(page_id);
+    }
+
+    macro_rules! check {
+        ($from:expr, $to:expr, $expected:expr) => {{
+            let pages = server
+                .get_user_authored_pages(user_id, $from, $to)
+                .await
+                .expect("Unable to get authored pages");
+
+            let expected: Vec<PageId> = $expected.iter().map(|&idx| page_ids[idx]).collect();
+            assert_eq!(pages, expected);
+        }};
+    }
+
+    check!(None, None, [0, 1, 2]);
+    check!(
+        Some(NaiveDate::from_ymd(2019, 1, 1)),
+        Some(NaiveDate::from_ymd(2019, 12, 31)),
+        [1]
+    );
+    check!(Some(NaiveDate::from_ymd(2019, 6, 1)), None, [1, 2]);
+    check!(None, Some(NaiveDate::from_ymd(2019, 6, 1)), [0, 1]);
+
+    // Deleted pages are excluded
+    let commit = PageCommit {
+        wiki_id,
+        slug: "scp-3000",
+        message: "delete page",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    check!(None, None, [0, 1]);
+}
+
+#[tokio::test]
+async fn create_with_authors() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let author_id = create_user(server).await;
+    let translator_id = create_user(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "imported",
+        message: "import page",
+        user: &user,
+    };
+
+    let written = NaiveDate::from_ymd(2012, 5, 20);
+    let translated = NaiveDate::from_ymd(2015, 11, 3);
+
+    let (page_id, _) = server
+        .create_page_with_authors(
+            commit,
+            "Imported contents",
+            &[
+                (author_id, AuthorType::Author, Some(written)),
+                (translator_id, AuthorType::Translator, Some(translated)),
+            ],
+            "Imported",
+            "",
+        )
+        .await
+        .expect("Unable to create page");
+
+    let mut authors = server
+        .get_page_authors(Left(page_id))
+        .await
+        .expect("Unable to get page authors")
+        .into_iter()
+        .map(|author| {
+            assert_eq!(author.page_id(), page_id);
+
+            (author.user_id(), author.author_type(), author.written_at())
+        })
+        .collect::<Vec<_>>();
+
+    authors.sort_by_key(|&(_, _, written_at)| written_at);
+
+    // The committing user is not added
+    assert_eq!(
+        authors,
+        vec![
+            (author_id, AuthorType::Author, written),
+            (translator_id, AuthorType::Translator, translated),
+        ],
+    );
+}
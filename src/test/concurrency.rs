@@ -0,0 +1,163 @@
+/*
+ * test/concurrency.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use futures::future::join_all;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WIKIS: usize = 4;
+const PAGES: usize = 8;
+
+#[test]
+fn server_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Server>();
+}
+
+#[tokio::test]
+async fn concurrent_wikis() {
+    let server = &create_server().await;
+    let user = &server.default_user().await.expect("Unable to get user");
+    let voter_id = create_user(server).await;
+    let wiki_ids = create_wikis(server).await;
+
+    // Interleave operations across every wiki at once
+    let tasks = wiki_ids.iter().flat_map(|&wiki_id| {
+        (0..PAGES).map(move |i| page_operations(server, user, voter_id, wiki_id, i))
+    });
+
+    let results = join_all(tasks).await;
+    let page_ids = results
+        .into_iter()
+        .collect::<Result<Vec<_>>>()
+        .expect("Concurrent operation failed");
+
+    check_wikis(server, voter_id, &wiki_ids, &page_ids).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn spawned_tasks() {
+    let server = Arc::new(create_server().await);
+    let user = server.default_user().await.expect("Unable to get user");
+    let voter_id = create_user(&server).await;
+    let wiki_ids = create_wikis(&server).await;
+
+    // Each operation runs in its own task, possibly on another thread
+    let mut handles = Vec::with_capacity(WIKIS * PAGES);
+    for &wiki_id in &wiki_ids {
+        for i in 0..PAGES {
+            let server = Arc::clone(&server);
+            let user = user.clone();
+
+            handles.push(tokio::spawn(async move {
+                page_operations(&server, &user, voter_id, wiki_id, i).await
+            }));
+        }
+    }
+
+    let results = tokio::time::timeout(Duration::from_secs(120), join_all(handles))
+        .await
+        .expect("Spawned tasks did not complete in time");
+
+    let page_ids = results
+        .into_iter()
+        .map(|result| result.expect("Spawned task panicked"))
+        .collect::<Result<Vec<_>>>()
+        .expect("Concurrent operation failed");
+
+    check_wikis(&server, voter_id, &wiki_ids, &page_ids).await;
+}
+
+async fn create_wikis(server: &Server) -> Vec<WikiId> {
+    let mut wiki_ids = Vec::with_capacity(WIKIS);
+    for _ in 0..WIKIS {
+        wiki_ids.push(create_wiki(server).await);
+    }
+
+    wiki_ids
+}
+
+async fn page_operations(
+    server: &Server,
+    user: &User,
+    voter_id: UserId,
+    wiki_id: WikiId,
+    i: usize,
+) -> Result<PageId> {
+    let slug = format!("page-{}", i);
+    let content = format!("Page {} on wiki {}", i, wiki_id);
+    let commit = PageCommit {
+        wiki_id,
+        slug: &slug,
+        message: "concurrent page",
+        user,
+    };
+
+    let (page_id, _) = server.create_page(commit, &content, &[], &slug, "").await?;
+
+    let contents = server.get_page_contents(wiki_id, &slug).await?;
+    assert_eq!(contents.as_ref(), Some(&content));
+
+    server.set_rating(page_id, voter_id, 1).await?;
+
+    let (page, votes, _) = server
+        .get_page(wiki_id, &slug)
+        .await?
+        .expect("Page not found");
+
+    assert_eq!(page.id(), page_id);
+    assert_eq!(votes.count(), 1);
+
+    Ok(page_id)
+}
+
+async fn check_wikis(server: &Server, voter_id: UserId, wiki_ids: &[WikiId], page_ids: &[PageId]) {
+    assert_eq!(page_ids.len(), WIKIS * PAGES);
+
+    // Each wiki only sees its own pages
+    for &wiki_id in wiki_ids {
+        let slugs = server
+            .list_page_slugs(wiki_id, false)
+            .await
+            .expect("Unable to list page slugs");
+
+        assert_eq!(slugs.len(), PAGES);
+
+        for slug in &slugs {
+            let contents = server
+                .get_page_contents(wiki_id, slug)
+                .await
+                .expect("Unable to get page contents")
+                .expect("Page contents not found");
+
+            assert!(contents.ends_with(&format!("on wiki {}", wiki_id)));
+        }
+    }
+
+    let ratings = server
+        .get_user_ratings(voter_id, page_ids)
+        .await
+        .expect("Unable to get user ratings");
+
+    assert_eq!(ratings.len(), WIKIS * PAGES);
+    assert!(ratings.values().all(|&rating| rating == 1));
+}
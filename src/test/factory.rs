@@ -20,16 +20,31 @@
 
 use crate::prelude::*;
 use crate::utils::rand_alphanum;
+use chrono::Duration;
+use diesel::{Connection, PgConnection};
 use std::env;
 use std::ops::Deref;
+use std::path::Path;
 use tempfile::TempDir;
 
+lazy_static! {
+    static ref DATABASE_URL: String =
+        env::var("DATABASE_TEST_URL").expect("No DATABASE_TEST_URL specified!");
+}
+
 #[derive(Debug)]
 pub struct ServerWrap {
     server: Server,
     temp_dir: TempDir,
 }
 
+impl ServerWrap {
+    #[inline]
+    pub fn revisions_dir(&self) -> &Path {
+        self.temp_dir.path()
+    }
+}
+
 impl Deref for ServerWrap {
     type Target = Server;
 
@@ -39,24 +54,51 @@ impl Deref for ServerWrap {
     }
 }
 
+#[inline]
 pub async fn create_server() -> ServerWrap {
+    create_server_with(|_| ()).await
+}
+
+pub async fn create_server_with<'a, F>(f: F) -> ServerWrap
+where
+    F: FnOnce(&mut Config<'a>),
+{
     color_backtrace::install();
 
-    let database_url = &env::var("DATABASE_TEST_URL").expect("No DATABASE_TEST_URL specified!");
+    let database_url: &str = &DATABASE_URL;
     let temp_dir = TempDir::new().expect("Unable to create temp dir");
     let revisions_dir = temp_dir.path().into();
 
-    let config = Config {
+    let mut config = Config {
         database_url,
         revisions_dir,
         password_blacklist: None,
+        password_policy: None,
+        canonicalize_emails: false,
+        allow_empty_pages: true,
+        commit_message_prefix: None,
+        content_filter: None,
+        max_content_bytes: DEFAULT_MAX_CONTENT_BYTES,
+        enable_audit_log: false,
+        max_batch_size: 100,
+        max_login_failures: 10,
+        login_failure_window: Duration::minutes(15),
+        session_expiry: Duration::days(30),
+        default_user_name: None,
     };
 
+    f(&mut config);
+
     let server = Server::new(config).expect("Unable to create deepwell server");
 
     ServerWrap { server, temp_dir }
 }
 
+/// Opens a separate connection to the test database, for modifying data directly.
+pub fn create_connection() -> PgConnection {
+    PgConnection::establish(&DATABASE_URL).expect("Unable to connect to test database")
+}
+
 // User
 pub async fn create_user_full(server: &Server, password: &str) -> (UserId, String, String) {
     let username = {
@@ -105,3 +147,65 @@ pub async fn create_wiki_full(server: &Server) -> (WikiId, String) {
 pub async fn create_wiki(server: &Server) -> WikiId {
     create_wiki_full(server).await.0
 }
+
+/// Creates a wiki with a new user and the given number of pages.
+/// Each page gets a random subset of tags and a random rating from the user.
+pub async fn create_populated_wiki(server: &Server, pages: usize) -> (WikiId, Vec<PageId>) {
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    const TAGS: [&str; 6] = ["scp", "tale", "keter", "euclid", "safe", "humor"];
+
+    let mut rng = rand::thread_rng();
+    let wiki_id = create_wiki(server).await;
+    let user_id = create_user(server).await;
+    let user = server
+        .get_user_from_id(user_id)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let mut page_ids = Vec::with_capacity(pages);
+
+    for i in 0..pages {
+        let slug = format!("page-{}", i);
+        let title = format!("Page {}", i);
+        let content = format!("Test page number {}", i);
+
+        let count = rng.gen_range(0, TAGS.len() + 1);
+        let tags = TAGS
+            .choose_multiple(&mut rng, count)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        macro_rules! commit {
+            () => {
+                PageCommit {
+                    wiki_id,
+                    slug: &slug,
+                    message: "populated wiki page",
+                    user: &user,
+                }
+            };
+        }
+
+        let (page_id, _revision_id) = server
+            .create_page(commit!(), &content, &[], &title, "")
+            .await
+            .expect("Unable to create page");
+
+        server
+            .set_page_tags(commit!(), &tags)
+            .await
+            .expect("Unable to set page tags");
+
+        server
+            .set_rating(page_id, user_id, rng.gen_range(-1, 2))
+            .await
+            .expect("Unable to set rating");
+
+        page_ids.push(page_id);
+    }
+
+    (wiki_id, page_ids)
+}
@@ -0,0 +1,124 @@
+/*
+ * test/links.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+
+#[tokio::test]
+async fn backlinks() {
+    let server = &create_server().await;
+
+    // Setup
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! check_backlinks {
+        ($slug:expr, $expected:expr) => {{
+            let expected: &[&str] = &$expected;
+            let pages = server
+                .get_backlinks(wiki_id, $slug)
+                .await
+                .expect("Unable to get backlinks");
+
+            let slugs = pages.iter().map(|page| page.slug()).collect::<Vec<_>>();
+            assert_eq!(slugs, expected, "Backlinks don't match");
+        }};
+    }
+
+    // Create pages
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-a",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "See [[[page-b|the other page]]]!", &[], "A", "")
+        .await
+        .expect("Unable to create page");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-b",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "Nothing to see here.", &[], "B", "")
+        .await
+        .expect("Unable to create page");
+
+    check_backlinks!("page-b", ["page-a"]);
+    check_backlinks!("Page B", ["page-a"]);
+    check_backlinks!("page-a", []);
+
+    // Edit links
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-b",
+        message: "link back",
+        user: &user,
+    };
+
+    server
+        .edit_page(commit, Some("Back to [[[page-a]]]."), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    check_backlinks!("page-a", ["page-b"]);
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-a",
+        message: "unlink",
+        user: &user,
+    };
+
+    server
+        .edit_page(
+            commit,
+            Some("No more links, see [[[page-c]]]."),
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("Unable to edit page");
+
+    check_backlinks!("page-b", []);
+    check_backlinks!("page-c", ["page-a"]);
+
+    // Remove page
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-a",
+        message: "delete",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    check_backlinks!("page-c", []);
+}
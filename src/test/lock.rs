@@ -19,6 +19,8 @@
  */
 
 use super::prelude::*;
+use async_std::task;
+use std::time::Duration;
 
 #[tokio::test]
 async fn locks() {
@@ -62,13 +64,13 @@ async fn locks() {
     macro_rules! edit {
         ($content:expr) => {
             server
-                .edit_page(commit, Some($content), None, None)
+                .edit_page(commit, Some($content), None, None, false)
                 .await
                 .expect("Unable to edit page")
         };
         ($content:expr, $user_id:expr) => {{
             let error = server
-                .edit_page(commit, Some($content), None, None)
+                .edit_page(commit, Some($content), None, None, false)
                 .await
                 .expect_err("Able to edit page despite lock");
 
@@ -204,3 +206,142 @@ async fn locks() {
         .await
         .expect("Unable to remove page lock");
 }
+
+#[tokio::test]
+async fn refresh_lock() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+    let user_id = create_user(server).await;
+    let other_user_id = create_user(server).await;
+    let user = server.default_user().await.expect("Unable to get user");
+
+    server
+        .edit_wiki_settings(wiki_id, Some(2), None)
+        .await
+        .expect("Unable to edit wiki settings");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "long-draft",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "Draft contents", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .create_page_lock(wiki_id, "long-draft", user_id)
+        .await
+        .expect("Unable to create page lock");
+
+    // Only the holder can refresh the lock
+    let error = server
+        .refresh_page_lock(wiki_id, "long-draft", other_user_id)
+        .await
+        .expect_err("Refreshed another user's lock");
+
+    match error {
+        Error::PageLocked(id) if id == user_id => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    // Refresh shortly before expiry, then wait past the original expiry
+    task::sleep(Duration::from_millis(1500)).await;
+
+    server
+        .refresh_page_lock(wiki_id, "long-draft", user_id)
+        .await
+        .expect("Unable to refresh page lock");
+
+    task::sleep(Duration::from_millis(1000)).await;
+
+    server
+        .invalidate_expired_locks()
+        .await
+        .expect("Unable to invalidate expired locks");
+
+    server
+        .refresh_page_lock(wiki_id, "long-draft", user_id)
+        .await
+        .expect("Lock expired despite refresh");
+
+    // Without refreshing, the lock does expire
+    task::sleep(Duration::from_millis(2500)).await;
+
+    let error = server
+        .refresh_page_lock(wiki_id, "long-draft", user_id)
+        .await
+        .expect_err("Refreshed an expired lock");
+
+    match error {
+        Error::PageLockNotFound => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn get_lock() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+    let user_id = create_user(server).await;
+    let user = server.default_user().await.expect("Unable to get user");
+
+    server
+        .edit_wiki_settings(wiki_id, Some(1), None)
+        .await
+        .expect("Unable to edit wiki settings");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "locked-page",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _) = server
+        .create_page(commit, "contents", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    let lock = server
+        .get_page_lock(Left(page_id))
+        .await
+        .expect("Unable to get page lock");
+
+    assert_eq!(lock, None);
+
+    server
+        .create_page_lock(wiki_id, "locked-page", user_id)
+        .await
+        .expect("Unable to create page lock");
+
+    let lock = server
+        .get_page_lock(Right((wiki_id, "locked-page")))
+        .await
+        .expect("Unable to get page lock")
+        .expect("No page lock found");
+
+    assert_eq!(lock.page_id(), page_id);
+    assert_eq!(lock.user_id(), user_id);
+    assert!(lock.created_at() < lock.expires_at());
+
+    let same_lock = server
+        .get_page_lock(Left(page_id))
+        .await
+        .expect("Unable to get page lock");
+
+    assert_eq!(same_lock, Some(lock));
+
+    // Expired locks aren't returned
+    task::sleep(Duration::from_millis(1500)).await;
+
+    let lock = server
+        .get_page_lock(Left(page_id))
+        .await
+        .expect("Unable to get page lock");
+
+    assert_eq!(lock, None);
+}
@@ -19,7 +19,9 @@
  */
 
 use super::prelude::*;
+use crate::utils::rand_alphanum;
 use chrono::prelude::*;
+use chrono::Duration;
 
 const IP_ADDRESS_1: Option<&str> = Some("alpha-beta.local");
 const IP_ADDRESS_2: Option<&str> = Some("1.1.1.1");
@@ -69,7 +71,7 @@ async fn logins() {
 
     // Check all login attempts
     let mut attempts = server
-        .get_login_attempts(user_id, start_time())
+        .get_login_attempts(user_id, start_time(), false)
         .await
         .expect("Unable to get login attempts");
 
@@ -98,3 +100,155 @@ async fn logins() {
     assert_eq!(third.remote_address(), IP_ADDRESS_3);
     assert_eq!(third.success(), true);
 }
+
+#[tokio::test]
+async fn login_activity() {
+    let server = &create_server().await;
+    let (user_id, name, _) = create_user_full(server, "blackmoonhowls").await;
+
+    let error = server
+        .try_login_id(user_id, "letmein", IP_ADDRESS_2)
+        .await
+        .expect_err("Allowed invalid login");
+
+    check_err!(error);
+
+    let (session, attempts) = server
+        .login_with_recent_activity(&name, "blackmoonhowls", IP_ADDRESS_1, false)
+        .await
+        .expect("Unable to login");
+
+    assert_eq!(session.user_id(), user_id);
+    assert_eq!(attempts.len(), 2);
+
+    // Most recent first
+    let latest = &attempts[0];
+    assert_eq!(latest.login_attempt_id(), session.login_attempt_id());
+    assert_eq!(latest.user_id(), Some(user_id));
+    assert_eq!(latest.remote_address(), IP_ADDRESS_1);
+    assert_eq!(latest.success(), true);
+
+    let previous = &attempts[1];
+    assert_eq!(previous.user_id(), Some(user_id));
+    assert_eq!(previous.remote_address(), IP_ADDRESS_2);
+    assert_eq!(previous.success(), false);
+}
+
+#[tokio::test]
+async fn login_redaction() {
+    let server = &create_server().await;
+    let since = Utc::now();
+    let email = format!("{}@example.net", rand_alphanum(12));
+
+    let error = server
+        .try_login(&email, "letmein", IP_ADDRESS_1)
+        .await
+        .expect_err("Allowed login for nonexistent user");
+
+    check_err!(error);
+
+    let find_attempt = |attempts: Vec<LoginAttempt>| {
+        attempts
+            .into_iter()
+            .find(|attempt| attempt.remote_address() == IP_ADDRESS_1 && attempt.user_id().is_none())
+            .expect("Login attempt not found")
+    };
+
+    // Redacted by default
+    let attempts = server
+        .get_all_login_attempts(since, false)
+        .await
+        .expect("Unable to get login attempts");
+
+    let attempt = find_attempt(attempts);
+    let name = attempt
+        .username_or_email()
+        .expect("No name in login attempt");
+    assert!(name.ends_with("@e***.net"), "Email not redacted: {}", name);
+    assert_ne!(name, email);
+
+    // Administrators see the full email
+    let attempts = server
+        .get_all_login_attempts(since, true)
+        .await
+        .expect("Unable to get login attempts");
+
+    let attempt = find_attempt(attempts);
+    assert_eq!(attempt.username_or_email(), Some(email.as_str()));
+
+    // Serialization mode
+    let value = serde_json::to_value(attempt.serialize_redacted())
+        .expect("Unable to serialize login attempt");
+
+    assert_eq!(value["username_or_email"], json!(name));
+    assert_eq!(value["remote_address"], json!(IP_ADDRESS_1));
+}
+
+#[tokio::test]
+async fn login_throttle() {
+    let server = &create_server_with(|config| {
+        config.max_login_failures = 3;
+        config.login_failure_window = Duration::seconds(2);
+    })
+    .await;
+
+    let password = "correct-horse";
+    let (user_id, _, _) = create_user_full(server, password).await;
+
+    macro_rules! check_throttled {
+        ($error:expr) => {
+            match $error {
+                Error::TooManyLoginAttempts => (),
+                _ => panic!("Error wasn't too many login attempts"),
+            }
+        };
+    }
+
+    // Failures below the threshold don't block logins,
+    // and a successful login resets the count.
+    for _ in 0..2 {
+        let error = server
+            .try_login_id(user_id, "wrong", IP_ADDRESS_1)
+            .await
+            .expect_err("Logged in with wrong password");
+
+        check_err!(error);
+    }
+
+    server
+        .try_login_id(user_id, password, IP_ADDRESS_1)
+        .await
+        .expect("Unable to login");
+
+    // Hammer the wrong password until locked out
+    for _ in 0..3 {
+        let error = server
+            .try_login_id(user_id, "wrong", IP_ADDRESS_2)
+            .await
+            .expect_err("Logged in with wrong password");
+
+        check_err!(error);
+    }
+
+    let failures = server
+        .count_recent_login_failures(user_id, Duration::minutes(1))
+        .await
+        .expect("Unable to count login failures");
+
+    assert_eq!(failures, 3);
+
+    let error = server
+        .try_login_id(user_id, password, IP_ADDRESS_2)
+        .await
+        .expect_err("Logged in while throttled");
+
+    check_throttled!(error);
+
+    // Once the window passes, logins are allowed again
+    async_std::task::sleep(std::time::Duration::from_millis(2500)).await;
+
+    server
+        .try_login_id(user_id, password, IP_ADDRESS_2)
+        .await
+        .expect("Unable to login after throttle expired");
+}
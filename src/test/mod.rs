@@ -22,16 +22,23 @@ extern crate color_backtrace;
 extern crate tempfile;
 extern crate tokio;
 
+mod audit;
 mod authors;
+mod concurrency;
 mod factory;
+mod links;
 mod lock;
 mod login;
 mod page;
 mod password;
+mod rating;
+mod revision;
 mod session;
+mod status;
 mod tags;
 mod user;
 mod verify;
+mod watch;
 mod wiki;
 
 mod prelude {
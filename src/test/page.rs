@@ -19,17 +19,17 @@
  */
 
 use super::prelude::*;
+use std::borrow::Cow;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[tokio::test]
 async fn pages() {
     let server = &create_server().await;
 
     // Setup
-    let user = server
-        .get_user_from_name("unknown")
-        .await
-        .expect("Unable to get user")
-        .expect("Default user not found");
+    let user = server.default_user().await.expect("Unable to get user");
 
     let wiki_id = create_wiki(server).await;
 
@@ -77,6 +77,7 @@ async fn pages() {
             None,
             Some("Amazing Take-down of 682!"),
             Some("049 appears too"),
+            false,
         )
         .await
         .expect("Unable to edit page");
@@ -100,6 +101,7 @@ async fn pages() {
             Some("and then 049 cured him!! it was epic"),
             None,
             None,
+            false,
         )
         .await
         .expect("Unable to edit page");
@@ -161,3 +163,1358 @@ async fn pages() {
     let objects = server.revision_vacuum(wiki_id).await.unwrap();
     assert_eq!(objects, 0, "Pruned objects found");
 }
+
+#[tokio::test]
+async fn export_contents() {
+    let server = &create_server().await;
+
+    // Setup
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let contents = server
+        .export_wiki_contents(wiki_id)
+        .await
+        .expect("Unable to export wiki contents");
+
+    assert!(contents.is_empty(), "Export of empty wiki not empty");
+
+    // Create pages
+    let pages = [
+        ("scp-002", "The living room"),
+        ("scp-001", "Classified"),
+        ("component:theme", "@import url('...');"),
+        ("sandbox", "test page, please ignore"),
+    ];
+
+    for (slug, content) in &pages {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        server
+            .create_page(commit, content, &[], "", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "sandbox",
+        message: "cleanup",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    // Export
+    let contents = server
+        .export_wiki_contents(wiki_id)
+        .await
+        .expect("Unable to export wiki contents");
+
+    let contents = contents
+        .iter()
+        .map(|(slug, content)| (slug.as_str(), &**content))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        contents,
+        vec![
+            ("component:theme", &b"@import url('...');"[..]),
+            ("scp-001", &b"Classified"[..]),
+            ("scp-002", &b"The living room"[..]),
+        ],
+        "Exported wiki contents don't match",
+    );
+}
+
+#[tokio::test]
+async fn empty_page() {
+    // Disallow empty pages
+    let server = &create_server_with(|config| config.allow_empty_pages = false).await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    for content in &["", "  \n\t "] {
+        let commit = PageCommit {
+            wiki_id,
+            slug: "empty",
+            message: "new page",
+            user: &user,
+        };
+
+        let error = server
+            .create_page(commit, content, &[], "Empty", "")
+            .await
+            .expect_err("Empty page was created");
+
+        match error {
+            Error::EmptyPage => (),
+            _ => panic!("Error doesn't match"),
+        }
+    }
+
+    let has_page = server.check_page(wiki_id, "empty").await.unwrap();
+    assert_eq!(has_page, false);
+
+    // Allow empty pages
+    let server = &create_server_with(|config| config.allow_empty_pages = true).await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "empty",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "", &[], "Empty", "")
+        .await
+        .expect("Unable to create empty page");
+
+    let has_page = server.check_page(wiki_id, "empty").await.unwrap();
+    assert_eq!(has_page, true);
+}
+
+#[tokio::test]
+async fn page_wiki_scope() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id_1 = create_wiki(server).await;
+    let wiki_id_2 = create_wiki(server).await;
+
+    // Create page in first wiki
+    let commit = PageCommit {
+        wiki_id: wiki_id_1,
+        slug: "scp-173",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "The sculpture", &[], "SCP-173", "")
+        .await
+        .expect("Unable to create page");
+
+    let has_page = server.check_page(wiki_id_1, "scp-173").await.unwrap();
+    assert_eq!(has_page, true);
+
+    let has_page = server.check_page(wiki_id_2, "scp-173").await.unwrap();
+    assert_eq!(has_page, false, "Page found in other wiki");
+
+    let page = server.get_page(wiki_id_2, "scp-173").await.unwrap();
+    assert!(page.is_none(), "Page found in other wiki");
+
+    // Create page with the same slug in second wiki
+    let commit = PageCommit {
+        wiki_id: wiki_id_2,
+        slug: "scp-173",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "The statue", &[], "SCP-173", "")
+        .await
+        .expect("Unable to create page with same slug in other wiki");
+
+    let has_page = server.check_page(wiki_id_2, "scp-173").await.unwrap();
+    assert_eq!(has_page, true);
+
+    for &(wiki_id, expected) in &[(wiki_id_1, "The sculpture"), (wiki_id_2, "The statue")] {
+        let contents = server
+            .get_page_contents(wiki_id, "scp-173")
+            .await
+            .expect("Unable to get page contents")
+            .expect("Page not found");
+
+        assert_eq!(contents, expected, "Page contents from wrong wiki");
+    }
+}
+
+#[tokio::test]
+async fn recently_edited() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "recency test",
+                user: &user,
+            }
+        };
+    }
+
+    macro_rules! check_order {
+        ($limit:expr, $offset:expr, $expected:expr) => {{
+            let pages = server
+                .get_recently_edited_pages(wiki_id, $limit, $offset)
+                .await
+                .expect("Unable to get recently edited pages");
+
+            let slugs = pages.iter().map(|page| page.slug()).collect::<Vec<_>>();
+            let expected: &[&str] = &$expected;
+            assert_eq!(&slugs, expected, "Recently edited order doesn't match");
+        }};
+    }
+
+    for slug in &["page-a", "page-b", "page-c"] {
+        server
+            .create_page(commit!(slug), "contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    check_order!(10, 0, ["page-c", "page-b", "page-a"]);
+
+    server
+        .edit_page(commit!("page-a"), Some("new contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    check_order!(10, 0, ["page-a", "page-c", "page-b"]);
+
+    server
+        .set_page_tags(commit!("page-b"), &["tale"])
+        .await
+        .expect("Unable to set page tags");
+
+    check_order!(10, 0, ["page-b", "page-a", "page-c"]);
+    check_order!(2, 0, ["page-b", "page-a"]);
+    check_order!(2, 1, ["page-a", "page-c"]);
+
+    server
+        .remove_page(commit!("page-c"))
+        .await
+        .expect("Unable to remove page");
+
+    check_order!(10, 0, ["page-b", "page-a"]);
+
+    let error = server
+        .get_recently_edited_pages(wiki_id, 101, 0)
+        .await
+        .expect_err("Able to fetch over 100 pages");
+
+    match error {
+        Error::RequestTooLarge(101, 100) => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn repair_page() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let (wiki_id, wiki_slug) = create_wiki_full(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "repair-test",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "Page contents", &[], "Repair Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let repaired = server
+        .repair_page(wiki_id, "repair-test")
+        .await
+        .expect("Unable to repair page");
+
+    assert_eq!(repaired, false, "Intact page was repaired");
+
+    // Remove file out-of-band
+    let path = server
+        .revisions_dir()
+        .join(&wiki_slug)
+        .join("repair-test.ftml");
+
+    fs::remove_file(&path).expect("Unable to remove page file");
+
+    let repaired = server
+        .repair_page(wiki_id, "repair-test")
+        .await
+        .expect("Unable to repair page");
+
+    assert_eq!(repaired, true, "Missing page was not repaired");
+
+    let contents = server
+        .get_page_contents(wiki_id, "repair-test")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page contents not found");
+
+    assert_eq!(contents, "Page contents");
+
+    let repaired = server
+        .repair_page(wiki_id, "nonexistent")
+        .await
+        .expect("Unable to repair page");
+
+    assert_eq!(repaired, false, "Nonexistent page was repaired");
+}
+
+#[tokio::test]
+async fn batch() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let user = &user;
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! create_pages {
+        ($server:expr, $prefix:expr) => {
+            for i in 0..3 {
+                let slug = format!("{}-{}", $prefix, i);
+                let commit = PageCommit {
+                    wiki_id,
+                    slug: &slug,
+                    message: "batch page",
+                    user,
+                };
+
+                $server
+                    .create_page(commit, "contents", &[], "Batch Page", "")
+                    .await?;
+            }
+        };
+    }
+
+    macro_rules! check_pages {
+        ($prefix:expr, $expected:expr) => {
+            for i in 0..3 {
+                let slug = format!("{}-{}", $prefix, i);
+                let exists = server
+                    .check_page(wiki_id, &slug)
+                    .await
+                    .expect("Unable to check page");
+
+                assert_eq!(exists, $expected, "Page existence doesn't match");
+            }
+        };
+    }
+
+    // Successful batch
+    let count = server
+        .batch(|server| async move {
+            create_pages!(server, "committed");
+            Ok::<_, Error>(3)
+        })
+        .await
+        .expect("Batch failed");
+
+    assert_eq!(count, 3);
+    check_pages!("committed", true);
+
+    // Failing batch
+    let result = server
+        .batch(|server| async move {
+            create_pages!(server, "rolled-back");
+            Err::<(), _>(Error::StaticMsg("forced rollback"))
+        })
+        .await;
+
+    match result.expect_err("Batch succeeded") {
+        Error::StaticMsg(_) => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    check_pages!("rolled-back", false);
+}
+
+#[tokio::test]
+async fn pages_batch() {
+    let server = &create_server().await;
+    let (wiki_id, page_ids) = create_populated_wiki(server, 3).await;
+
+    let pages = server
+        .get_pages(wiki_id, &["page-2", "nonexistent", "Page 0", "page-2"])
+        .await
+        .expect("Unable to get multiple pages");
+
+    let ids = pages
+        .iter()
+        .map(|page| page.as_ref().map(|page| page.id()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        ids,
+        vec![
+            Some(page_ids[2]),
+            None,
+            Some(page_ids[0]),
+            Some(page_ids[2])
+        ],
+    );
+
+    // Deleted pages aren't returned
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-1",
+        message: "delete page",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    let pages = server
+        .get_pages(wiki_id, &["page-0", "page-1"])
+        .await
+        .expect("Unable to get multiple pages");
+
+    assert!(pages[0].is_some());
+    assert!(pages[1].is_none());
+
+    let error = server
+        .get_pages(wiki_id, &["page-0"; 101])
+        .await
+        .expect_err("Able to fetch over 100 pages");
+
+    match error {
+        Error::RequestTooLarge(101, 100) => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn page_wiki() {
+    let server = &create_server().await;
+    let (first_wiki_id, first_page_ids) = create_populated_wiki(server, 2).await;
+    let (second_wiki_id, second_page_ids) = create_populated_wiki(server, 1).await;
+
+    macro_rules! check {
+        ($page_id:expr, $wiki_id:expr) => {{
+            let wiki_id = server
+                .get_page_wiki($page_id)
+                .await
+                .expect("Unable to get wiki for page");
+
+            assert_eq!(wiki_id, $wiki_id);
+        }};
+    }
+
+    check!(first_page_ids[0], Some(first_wiki_id));
+    check!(first_page_ids[1], Some(first_wiki_id));
+    check!(second_page_ids[0], Some(second_wiki_id));
+    check!(PageId::from_raw(-1), None);
+}
+
+#[tokio::test]
+async fn rename_category_lock() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "tale:foo",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "Story", &[], "Foo", "")
+        .await
+        .expect("Unable to create page");
+
+    // Setting is off by default
+    let (_, settings) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    assert_eq!(settings.lock_category_on_rename(), false);
+
+    server
+        .edit_wiki_settings(wiki_id, None, Some(true))
+        .await
+        .expect("Unable to edit wiki settings");
+
+    // Same category
+    server
+        .rename_page(wiki_id, "tale:foo", "tale:bar", "rename", &user)
+        .await
+        .expect("Unable to rename page within category");
+
+    // Different category
+    let error = server
+        .rename_page(wiki_id, "tale:bar", "scp:bar", "rename", &user)
+        .await
+        .expect_err("Renamed page into a different category");
+
+    match error {
+        Error::CategoryChangeForbidden => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    let has_page = server.check_page(wiki_id, "tale:bar").await.unwrap();
+    assert_eq!(has_page, true);
+
+    // Allowed again once the setting is off
+    server
+        .edit_wiki_settings(wiki_id, None, Some(false))
+        .await
+        .expect("Unable to edit wiki settings");
+
+    server
+        .rename_page(wiki_id, "tale:bar", "scp:bar", "rename", &user)
+        .await
+        .expect("Unable to rename page into a different category");
+}
+
+#[tokio::test]
+async fn title_history() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($message:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: "titles",
+                message: $message,
+                user: &user,
+            }
+        };
+    }
+
+    let (page_id, _) = server
+        .create_page(commit!("create"), "Contents", &[], "First Title", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .edit_page(commit!("retitle"), None, Some("Second Title"), None, false)
+        .await
+        .expect("Unable to edit page");
+
+    // Content-only changes don't affect the title history
+    server
+        .edit_page(commit!("edit"), Some("New contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    server
+        .edit_page(
+            commit!("retitle again"),
+            None,
+            Some("Third Title"),
+            Some("Alternate"),
+            false,
+        )
+        .await
+        .expect("Unable to edit page");
+
+    let history = server
+        .get_page_title_history(page_id)
+        .await
+        .expect("Unable to get title history");
+
+    let titles = history
+        .iter()
+        .map(|(title, alt_title, _)| (title.as_str(), alt_title.as_deref()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        titles,
+        vec![
+            ("First Title", None),
+            ("Second Title", None),
+            ("Third Title", Some("Alternate")),
+        ],
+    );
+
+    for window in history.windows(2) {
+        assert!(window[0].2 <= window[1].2);
+    }
+}
+
+#[tokio::test]
+async fn list_slugs() {
+    let server = &create_server().await;
+
+    // Setup
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let slugs = server
+        .list_page_slugs(wiki_id, true)
+        .await
+        .expect("Unable to list page slugs");
+
+    assert!(slugs.is_empty(), "Slugs of empty wiki not empty");
+
+    // Create pages
+    for slug in &["scp-002", "scp-001", "component:theme", "sandbox"] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        server
+            .create_page(commit, "", &[], "", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "sandbox",
+        message: "cleanup",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    // List slugs
+    let slugs = server
+        .list_page_slugs(wiki_id, false)
+        .await
+        .expect("Unable to list page slugs");
+
+    assert_eq!(slugs, vec!["component:theme", "scp-001", "scp-002"]);
+
+    let slugs = server
+        .list_page_slugs(wiki_id, true)
+        .await
+        .expect("Unable to list page slugs");
+
+    assert_eq!(
+        slugs,
+        vec!["component:theme", "sandbox", "scp-001", "scp-002"],
+    );
+}
+
+#[tokio::test]
+async fn page_views() {
+    let server = &create_server().await;
+    let (wiki_id, page_ids) = create_populated_wiki(server, 3).await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    macro_rules! check {
+        ($page_id:expr, $views:expr) => {{
+            let views = server
+                .get_page_views($page_id)
+                .await
+                .expect("Unable to get page views");
+
+            assert_eq!(views, $views, "View count doesn't match");
+        }};
+    }
+
+    // No views yet
+    check!(page_ids[0], 0);
+
+    // Single increments
+    for expected in 1..=2 {
+        let views = server
+            .increment_page_views(page_ids[0])
+            .await
+            .expect("Unable to increment page views");
+
+        assert_eq!(views, expected, "Returned view count doesn't match");
+    }
+
+    check!(page_ids[0], 2);
+
+    // Batched increments
+    server
+        .increment_page_views_many(&[page_ids[0], page_ids[1], page_ids[1], page_ids[0]])
+        .await
+        .expect("Unable to increment page views");
+
+    server
+        .increment_page_views_many(&[])
+        .await
+        .expect("Unable to increment page views");
+
+    check!(page_ids[0], 4);
+    check!(page_ids[1], 2);
+    check!(page_ids[2], 0);
+
+    // Deleted pages still count views
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-2",
+        message: "delete page",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    let views = server
+        .increment_page_views(page_ids[2])
+        .await
+        .expect("Unable to increment deleted page views");
+
+    assert_eq!(views, 1, "Returned view count doesn't match");
+    check!(page_ids[2], 1);
+}
+
+#[tokio::test]
+async fn reserved_slugs() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    server
+        .set_wiki_reserved_slugs(wiki_id, &["admin", "System"])
+        .await
+        .expect("Unable to set reserved slugs");
+
+    let (_, settings) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    assert_eq!(settings.reserved_slugs(), &["admin", "system"]);
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "reserved slug",
+                user: &user,
+            }
+        };
+    }
+
+    // Reserved slugs are rejected
+    for slug in &["admin", "system"] {
+        let error = server
+            .create_page(commit!(slug), "Contents", &[], "Page", "")
+            .await
+            .expect_err("Created page with reserved slug");
+
+        match error {
+            Error::SlugReserved(ref reserved) if reserved == slug => (),
+            _ => panic!("Error doesn't match"),
+        }
+    }
+
+    // Other slugs are unaffected
+    server
+        .create_page(commit!("admin-notes"), "Contents", &[], "Page", "")
+        .await
+        .expect("Unable to create page");
+
+    // Privileged creation is allowed
+    server
+        .create_privileged_page(commit!("admin"), "Contents", &[], "Admin", "")
+        .await
+        .expect("Unable to create privileged page");
+
+    let (page, _, _) = server
+        .get_page(wiki_id, "admin")
+        .await
+        .expect("Unable to get page")
+        .expect("No page found");
+
+    assert_eq!(page.title(), "Admin");
+
+    // Pages can't be moved onto reserved slugs either
+    let error = server
+        .rename_page(wiki_id, "admin-notes", "system", "Move page", &user)
+        .await
+        .expect_err("Renamed page to reserved slug");
+
+    match error {
+        Error::SlugReserved(ref reserved) if reserved == "system" => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    server
+        .remove_page(commit!("admin"))
+        .await
+        .expect("Unable to remove page");
+
+    let error = server
+        .restore_page(commit!("admin"), None)
+        .await
+        .expect_err("Restored page to reserved slug");
+
+    match error {
+        Error::SlugReserved(ref reserved) if reserved == "admin" => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn patch_page() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "patch-test",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "alpha\nbeta\ngamma\ndelta\n", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    // Valid patch
+    let patch = "\
+--- a/patch-test.ftml
++++ b/patch-test.ftml
+@@ -1,2 +1,2 @@
+-alpha
++ALPHA
+ beta
+@@ -4 +4,2 @@
+ delta
++epsilon
+";
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "patch-test",
+        message: "apply patch",
+        user: &user,
+    };
+
+    server
+        .patch_page(commit, patch.as_bytes())
+        .await
+        .expect("Unable to patch page");
+
+    let contents = server
+        .get_page_contents(wiki_id, "patch-test")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page contents not found");
+
+    assert_eq!(contents, "ALPHA\nbeta\ngamma\ndelta\nepsilon\n");
+
+    // Conflicting patch, the removed line no longer exists
+    let patch = "\
+@@ -1,2 +1,2 @@
+-alpha
++omega
+ beta
+";
+
+    let error = server
+        .patch_page(commit, patch.as_bytes())
+        .await
+        .expect_err("Conflicting patch was applied");
+
+    match error {
+        Error::PatchFailed(_) => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    let contents = server
+        .get_page_contents(wiki_id, "patch-test")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page contents not found");
+
+    assert_eq!(contents, "ALPHA\nbeta\ngamma\ndelta\nepsilon\n");
+}
+
+#[tokio::test]
+async fn content_filter() {
+    fn reject_includes(content: &[u8]) -> StdResult<Cow<'_, [u8]>, String> {
+        let content = String::from_utf8_lossy(content);
+
+        if content.contains("[[include") {
+            Err(String::from("includes are not permitted"))
+        } else {
+            Ok(Cow::Owned(content.replace("\r\n", "\n").into_bytes()))
+        }
+    }
+
+    let server = &create_server_with(|config| {
+        config.content_filter = Some(Arc::new(reject_includes));
+    })
+    .await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "filtered",
+        message: "new page",
+        user: &user,
+    };
+
+    macro_rules! check_contents {
+        ($expected:expr) => {{
+            let contents = server
+                .get_page_contents(wiki_id, "filtered")
+                .await
+                .expect("Unable to get page contents")
+                .expect("Page contents not found");
+
+            assert_eq!(contents, $expected);
+        }};
+    }
+
+    // Rejected content
+    let error = server
+        .create_page(commit, "[[include component:thing]]", &[], "", "")
+        .await
+        .expect_err("Filtered content was accepted");
+
+    match error {
+        Error::ContentRejected(_) => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    let has_page = server.check_page(wiki_id, "filtered").await.unwrap();
+    assert_eq!(has_page, false);
+
+    // Rewritten content
+    server
+        .create_page(commit, "line one\r\nline two\r\n", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    check_contents!("line one\nline two\n");
+
+    server
+        .edit_page(commit, Some("edited\r\n"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    check_contents!("edited\n");
+
+    let error = server
+        .edit_page(commit, Some("[[include other]]"), None, None, false)
+        .await
+        .expect_err("Filtered content was accepted");
+
+    match error {
+        Error::ContentRejected(_) => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    check_contents!("edited\n");
+}
+
+#[tokio::test]
+async fn content_filter_revert() {
+    static REJECT: AtomicBool = AtomicBool::new(false);
+
+    fn reject_toggle(content: &[u8]) -> StdResult<Cow<'_, [u8]>, String> {
+        if REJECT.load(Ordering::SeqCst) {
+            Err(String::from("all content is rejected"))
+        } else {
+            Ok(Cow::Borrowed(content))
+        }
+    }
+
+    let server = &create_server_with(|config| {
+        config.content_filter = Some(Arc::new(reject_toggle));
+    })
+    .await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "filtered-revert",
+        message: "revert filter test",
+        user: &user,
+    };
+
+    let (_, revision_id) = server
+        .create_page(commit, "first", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .edit_page(commit, Some("second"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    // Old contents go through the filter again
+    REJECT.store(true, Ordering::SeqCst);
+
+    let error = server
+        .revert_page(commit, Left(revision_id))
+        .await
+        .expect_err("Filtered content was accepted");
+
+    match error {
+        Error::ContentRejected(_) => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    REJECT.store(false, Ordering::SeqCst);
+
+    let contents = server
+        .get_page_contents(wiki_id, "filtered-revert")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page contents not found");
+
+    assert_eq!(contents, "second");
+}
+
+#[tokio::test]
+async fn auto_alt_title() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let plain_wiki_id = create_wiki(server).await;
+    let auto_wiki_id = create_wiki(server).await;
+
+    server
+        .set_wiki_auto_alt_title(auto_wiki_id, true)
+        .await
+        .expect("Unable to edit wiki settings");
+
+    macro_rules! check_alt_title {
+        ($wiki_id:expr, $expected:expr) => {{
+            let (page, _, _) = server
+                .get_page($wiki_id, "scp-173")
+                .await
+                .expect("Unable to get page")
+                .expect("Page not found");
+
+            assert_eq!(page.alt_title(), $expected);
+        }};
+    }
+
+    for &wiki_id in &[plain_wiki_id, auto_wiki_id] {
+        let commit = PageCommit {
+            wiki_id,
+            slug: "scp-173",
+            message: "new page",
+            user: &user,
+        };
+
+        server
+            .create_page(commit, "contents", &[], "SCP-173: The Sculpture", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    check_alt_title!(plain_wiki_id, None);
+    check_alt_title!(auto_wiki_id, Some("The Sculpture"));
+
+    // Explicit alternate titles are kept
+    let commit = PageCommit {
+        wiki_id: auto_wiki_id,
+        slug: "scp-173",
+        message: "edit alt title",
+        user: &user,
+    };
+
+    server
+        .edit_page(commit, None, None, Some("Peanut"), false)
+        .await
+        .expect("Unable to edit page");
+
+    check_alt_title!(auto_wiki_id, Some("Peanut"));
+
+    // Clearing derives from the current title
+    server
+        .edit_page(commit, None, None, Some(""), false)
+        .await
+        .expect("Unable to edit page");
+
+    check_alt_title!(auto_wiki_id, Some("The Sculpture"));
+
+    // Or from the new one, if there is no colon nothing is derived
+    server
+        .edit_page(commit, None, Some("SCP-173"), Some(""), false)
+        .await
+        .expect("Unable to edit page");
+
+    check_alt_title!(auto_wiki_id, None);
+
+    let commit = PageCommit {
+        wiki_id: plain_wiki_id,
+        ..commit
+    };
+
+    server
+        .edit_page(commit, None, Some("SCP-173: Statue"), Some(""), false)
+        .await
+        .expect("Unable to edit page");
+
+    check_alt_title!(plain_wiki_id, None);
+}
+
+#[tokio::test]
+async fn system_page() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "system page",
+                user: &user,
+            }
+        };
+    }
+
+    // Ordinary pages must already be normalized
+    let error = server
+        .create_page(commit!("_Default"), "Contents", &[], "Default", "")
+        .await
+        .expect_err("Created page with non-normal slug");
+
+    match error {
+        Error::InvalidSlug(ref slug) if slug == "_Default" => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    // System pages keep their slug as given
+    let (page_id, _) = server
+        .create_system_page(commit!("_Default"), "Template", "Default", "")
+        .await
+        .expect("Unable to create system page");
+
+    let (page, _, _) = server
+        .get_page_by_id(page_id)
+        .await
+        .expect("Unable to get page")
+        .expect("No page found");
+
+    assert_eq!(page.slug(), "_Default");
+
+    let contents = server
+        .get_page_contents_by_id(page_id)
+        .await
+        .expect("Unable to get page contents")
+        .expect("No page contents found");
+
+    assert_eq!(contents, "Template");
+
+    // Unsafe slugs are still rejected
+    for slug in &["", "-option", "../escape", "admin/dashboard", "page.ftml"] {
+        let error = server
+            .create_system_page(commit!(slug), "Contents", "Unsafe", "")
+            .await
+            .expect_err("Created system page with unsafe slug");
+
+        match error {
+            Error::InvalidSlug(_) => (),
+            _ => panic!("Error doesn't match"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn content_size_limit() {
+    const MAX: usize = 64;
+
+    let server = &create_server_with(|config| {
+        config.max_content_bytes = MAX;
+    })
+    .await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "size limit test",
+                user: &user,
+            }
+        };
+    }
+
+    macro_rules! check_too_large {
+        ($result:expr) => {
+            match $result.expect_err("Accepted content over the limit") {
+                Error::ContentTooLarge(len, max) => {
+                    assert_eq!(len, MAX + 1);
+                    assert_eq!(max, MAX);
+                }
+                _ => panic!("Error doesn't match"),
+            }
+        };
+    }
+
+    let exact = "x".repeat(MAX);
+    let over = "x".repeat(MAX + 1);
+
+    // Creation
+    server
+        .create_page(commit!("exact"), &exact, &[], "Exact", "")
+        .await
+        .expect("Unable to create page at the limit");
+
+    check_too_large!(
+        server
+            .create_page(commit!("over"), &over, &[], "Over", "")
+            .await
+    );
+
+    let exists = server
+        .check_page(wiki_id, "over")
+        .await
+        .expect("Unable to check page");
+
+    assert!(!exists, "Oversized page was created");
+
+    // Editing
+    check_too_large!(
+        server
+            .edit_page(commit!("exact"), Some(&over), None, None, false)
+            .await
+    );
+
+    let contents = server
+        .get_page_contents(wiki_id, "exact")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page not found");
+
+    assert_eq!(contents, exact);
+
+    server
+        .edit_page(commit!("exact"), Some("small"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    server
+        .edit_page(commit!("exact"), Some(&exact), None, None, false)
+        .await
+        .expect("Unable to edit page to the limit");
+}
+
+#[tokio::test]
+async fn check_rename() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "rename check",
+                user: &user,
+            }
+        };
+    }
+
+    macro_rules! check {
+        ($slug:expr, $expected:expr) => {{
+            let free = server
+                .check_rename(wiki_id, $slug)
+                .await
+                .expect("Unable to check rename");
+
+            assert_eq!(
+                free, $expected,
+                "Rename check for '{}' doesn't match",
+                $slug
+            );
+        }};
+    }
+
+    for slug in &["live", "tombstone"] {
+        server
+            .create_page(commit!(slug), "Contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    server
+        .remove_page(commit!("tombstone"))
+        .await
+        .expect("Unable to remove page");
+
+    check!("live", false);
+    check!("tombstone", true);
+    check!("unused", true);
+
+    // Nothing was changed by checking
+    let slugs = server
+        .list_page_slugs(wiki_id, true)
+        .await
+        .expect("Unable to list slugs");
+
+    assert_eq!(slugs.len(), 2);
+
+    // Renaming onto the deleted page's slug works
+    server
+        .rename_page(wiki_id, "live", "tombstone", "rename", &user)
+        .await
+        .expect("Unable to rename page");
+
+    check!("live", true);
+    check!("tombstone", false);
+
+    // Slugs must already be normalized
+    let error = server
+        .check_rename(wiki_id, "Not Normal")
+        .await
+        .expect_err("Accepted non-normal slug");
+
+    match error {
+        Error::InvalidSlug(ref slug) if slug == "Not Normal" => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
@@ -85,3 +85,58 @@ async fn password_default() {
     bad_password!(4, "blackmoon");
     bad_password!(5, "blackmoon");
 }
+
+#[tokio::test]
+async fn password_policy() {
+    let policy = PasswordPolicy {
+        min_length: 12,
+        min_distinct_chars: 5,
+        disallow_username: true,
+    };
+
+    let server = &create_server_with(|config| config.password_policy = Some(policy)).await;
+    let (user_id, username, _) = create_user_full(server, "correct horse battery").await;
+
+    macro_rules! insecure_password {
+        ($password:expr, $reason:expr) => {
+            match server.set_user_password(user_id, $password) {
+                Err(Error::PasswordInsecure(reason)) => assert_eq!(reason, $reason),
+                Err(error) => panic!("Unexpected error: {}", error),
+                Ok(_) => panic!("Insecure password was accepted"),
+            }
+        };
+    }
+
+    insecure_password!("blackmoon1", "password must be at least 12 characters");
+    insecure_password!(
+        "abababababababab",
+        "password must have at least 5 different characters"
+    );
+    insecure_password!(
+        &username.to_uppercase(),
+        "password cannot be the same as the username"
+    );
+
+    server
+        .set_user_password(user_id, "blackmoonhowls")
+        .expect("Unable to set new password");
+
+    // Policy is also applied to new users
+    let error = server
+        .create_user("policyuser", "policyuser@example.com", "aaaaaaaaaaaaaaaa")
+        .await
+        .expect_err("Created user with insecure password");
+
+    match error {
+        Error::PasswordInsecure(_) => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    // Without a policy, only the basic checks apply
+    let server = &create_server().await;
+    let (user_id, _, _) = create_user_full(server, "correct horse battery").await;
+
+    server
+        .set_user_password(user_id, "aaaaaaaa")
+        .expect("Unable to set new password");
+}
@@ -0,0 +1,685 @@
+/*
+ * test/rating.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use chrono::prelude::*;
+
+#[tokio::test]
+async fn rating_changes() {
+    let server = &create_server().await;
+
+    // Setup
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let voter_id = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "scp-1000",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _) = server
+        .create_page(commit, "Bigfoot", &[], "SCP-1000", "Bigfoot")
+        .await
+        .expect("Unable to create page");
+
+    macro_rules! check_change {
+        ($change:expr, $old:expr, $new:expr) => {{
+            let change = $change;
+
+            assert_eq!(change.page_id, page_id, "Page ID doesn't match");
+            assert_eq!(change.user_id, voter_id, "User ID doesn't match");
+            assert_eq!(change.old, $old, "Old rating doesn't match");
+            assert_eq!(change.new, $new, "New rating doesn't match");
+        }};
+    }
+
+    // New vote
+    let (_, change) = server
+        .set_rating(page_id, voter_id, 1)
+        .await
+        .expect("Unable to set rating");
+
+    check_change!(change, None, Some(1));
+
+    // Changed vote
+    let (_, change) = server
+        .set_rating(page_id, voter_id, -1)
+        .await
+        .expect("Unable to set rating");
+
+    check_change!(change, Some(1), Some(-1));
+
+    // Same vote again
+    let (_, change) = server
+        .set_rating(page_id, voter_id, -1)
+        .await
+        .expect("Unable to set rating");
+
+    check_change!(change, Some(-1), Some(-1));
+
+    // Removed vote
+    let (_, change) = server
+        .remove_rating(page_id, voter_id)
+        .await
+        .expect("Unable to remove rating")
+        .expect("No rating was removed");
+
+    check_change!(change, Some(-1), None);
+
+    let result = server
+        .remove_rating(page_id, voter_id)
+        .await
+        .expect("Unable to remove rating");
+
+    assert!(result.is_none(), "Rating was removed twice");
+}
+
+#[tokio::test]
+async fn rating_user_bulk() {
+    let server = &create_server().await;
+
+    // Setup
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let voter_id = create_user(server).await;
+    let other_voter_id = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    let mut page_ids = Vec::new();
+    for slug in &["page-a", "page-b", "page-c"] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        let (page_id, _) = server
+            .create_page(commit, "contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+
+        page_ids.push(page_id);
+    }
+
+    // Vote on pages
+    let votes = [
+        (page_ids[0], voter_id, 1),
+        (page_ids[2], voter_id, -1),
+        (page_ids[1], other_voter_id, 1),
+    ];
+
+    for &(page_id, voter_id, rating) in &votes {
+        server
+            .set_rating(page_id, voter_id, rating)
+            .await
+            .expect("Unable to set rating");
+    }
+
+    // Get ratings
+    let ratings = server
+        .get_user_ratings(voter_id, &page_ids)
+        .await
+        .expect("Unable to get user ratings");
+
+    assert_eq!(ratings.len(), 2, "Incorrect number of ratings");
+    assert_eq!(ratings.get(&page_ids[0]), Some(&1));
+    assert_eq!(ratings.get(&page_ids[1]), None);
+    assert_eq!(ratings.get(&page_ids[2]), Some(&-1));
+
+    let ratings = server
+        .get_user_ratings(voter_id, &[])
+        .await
+        .expect("Unable to get user ratings");
+
+    assert!(ratings.is_empty(), "Ratings returned for no pages");
+}
+
+#[tokio::test]
+async fn wiki_scoring() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "scp-1000",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _) = server
+        .create_page(commit, "Bigfoot", &[], "SCP-1000", "Bigfoot")
+        .await
+        .expect("Unable to create page");
+
+    for &vote in &[1, 1, -1] {
+        let voter_id = create_user(server).await;
+
+        server
+            .set_rating(page_id, voter_id, vote)
+            .await
+            .expect("Unable to set rating");
+    }
+
+    macro_rules! check_score {
+        ($expected:expr) => {{
+            let (_, votes, score) = server
+                .get_page(wiki_id, "scp-1000")
+                .await
+                .expect("Unable to get page")
+                .expect("Page not found");
+
+            assert_eq!(votes.count(), 3);
+            assert!(
+                (score - $expected).abs() < 0.01,
+                "Score doesn't match: {} != {}",
+                score,
+                $expected,
+            );
+
+            let (_, _, score_by_id) = server
+                .get_page_by_id(page_id)
+                .await
+                .expect("Unable to get page")
+                .expect("Page not found");
+
+            assert_eq!(score, score_by_id);
+        }};
+    }
+
+    // Default is wikidot-style
+    let (_, settings) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    assert_eq!(settings.scoring(), ScoringKind::Wikidot);
+    check_score!(1.0);
+
+    server
+        .set_wiki_scoring(wiki_id, ScoringKind::Percent)
+        .await
+        .expect("Unable to set wiki scoring");
+
+    let (_, settings) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    assert_eq!(settings.scoring(), ScoringKind::Percent);
+    check_score!(66.67);
+
+    server
+        .set_wiki_scoring(wiki_id, ScoringKind::Average)
+        .await
+        .expect("Unable to set wiki scoring");
+
+    check_score!(0.33);
+
+    server
+        .set_wiki_scoring(wiki_id, ScoringKind::Null)
+        .await
+        .expect("Unable to set wiki scoring");
+
+    check_score!(0.0);
+}
+
+#[tokio::test]
+async fn rating_audit() {
+    use crate::package::rating::RatingInconsistency;
+    use crate::schema::ratings;
+    use diesel::prelude::*;
+
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "scp-1000",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _) = server
+        .create_page(commit, "Bigfoot", &[], "SCP-1000", "Bigfoot")
+        .await
+        .expect("Unable to create page");
+
+    let mut voter_ids = Vec::new();
+    for &vote in &[1, 1, -1] {
+        let voter_id = create_user(server).await;
+
+        server
+            .set_rating(page_id, voter_id, vote)
+            .await
+            .expect("Unable to set rating");
+
+        voter_ids.push(voter_id);
+    }
+
+    server
+        .set_rating(page_id, voter_ids[0], -1)
+        .await
+        .expect("Unable to set rating");
+
+    server
+        .remove_rating(page_id, voter_ids[2])
+        .await
+        .expect("Unable to remove rating");
+
+    // Consistent after normal operation
+    let inconsistencies = server
+        .audit_ratings(page_id)
+        .await
+        .expect("Unable to audit ratings");
+
+    assert!(inconsistencies.is_empty(), "Inconsistencies found");
+
+    // Corrupt one user's rating directly
+    {
+        let conn = create_connection();
+        let page_id: i64 = page_id.into();
+        let user_id: i64 = voter_ids[1].into();
+
+        diesel::update(ratings::table)
+            .filter(ratings::page_id.eq(page_id))
+            .filter(ratings::user_id.eq(user_id))
+            .set(ratings::rating.eq(-1))
+            .execute(&conn)
+            .expect("Unable to corrupt rating");
+    }
+
+    let inconsistencies = server
+        .audit_ratings(page_id)
+        .await
+        .expect("Unable to audit ratings");
+
+    assert_eq!(
+        inconsistencies,
+        vec![RatingInconsistency {
+            page_id,
+            user_id: voter_ids[1],
+            current: Some(-1),
+            history: Some(1),
+        }],
+    );
+}
+
+#[tokio::test]
+async fn import_ratings() {
+    const PAGES: usize = 10;
+    const VOTERS: usize = 50;
+
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    let mut page_ids = Vec::with_capacity(PAGES);
+    for i in 0..PAGES {
+        let slug = format!("imported-{}", i);
+        let commit = PageCommit {
+            wiki_id,
+            slug: &slug,
+            message: "imported page",
+            user: &user,
+        };
+
+        let (page_id, _) = server
+            .create_page(commit, "contents", &[], "", "")
+            .await
+            .expect("Unable to create page");
+
+        page_ids.push(page_id);
+    }
+
+    let mut voter_ids = Vec::with_capacity(VOTERS);
+    for _ in 0..VOTERS {
+        voter_ids.push(create_user(server).await);
+    }
+
+    // Every fifth voter downvotes each page
+    let mut ratings = Vec::with_capacity(PAGES * VOTERS);
+    for (i, &page_id) in page_ids.iter().enumerate() {
+        for (j, &voter_id) in voter_ids.iter().enumerate() {
+            let vote = if (i + j) % 5 == 0 { -1 } else { 1 };
+            ratings.push((page_id, voter_id, vote));
+        }
+    }
+
+    let rating_ids = server
+        .import_ratings(&ratings)
+        .await
+        .expect("Unable to import ratings");
+
+    assert_eq!(rating_ids.len(), 500);
+
+    for &page_id in &page_ids {
+        let (_, votes, _) = server
+            .get_page_by_id(page_id)
+            .await
+            .expect("Unable to get page")
+            .expect("Page not found");
+
+        assert_eq!(votes.count(), 50);
+        assert_eq!(votes.count_for_vote(1), Some(40));
+        assert_eq!(votes.count_for_vote(-1), Some(10));
+    }
+
+    // History entries line up with the input
+    let entry = server
+        .get_rating_history_entry_from_id(rating_ids[7])
+        .await
+        .expect("Unable to get rating history")
+        .expect("Rating history entry not found");
+
+    assert_eq!(entry.page_id(), ratings[7].0);
+    assert_eq!(entry.user_id(), ratings[7].1);
+    assert_eq!(entry.rating(), Some(ratings[7].2));
+
+    // Importing again replaces existing votes, keeping the last duplicate
+    let (page_id, voter_id, _) = ratings[0];
+    server
+        .import_ratings(&[(page_id, voter_id, 1), (page_id, voter_id, 0)])
+        .await
+        .expect("Unable to import ratings");
+
+    let user_ratings = server
+        .get_user_ratings(voter_id, &[page_id])
+        .await
+        .expect("Unable to get user ratings");
+
+    assert_eq!(user_ratings.get(&page_id), Some(&0));
+}
+
+#[tokio::test]
+async fn top_pages() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+    let other_wiki_id = create_wiki(server).await;
+
+    let mut voter_ids = Vec::new();
+    for _ in 0..3 {
+        voter_ids.push(create_user(server).await);
+    }
+
+    macro_rules! create_page {
+        ($wiki_id:expr, $slug:expr, $votes:expr) => {{
+            let commit = PageCommit {
+                wiki_id: $wiki_id,
+                slug: $slug,
+                message: "new page",
+                user: &user,
+            };
+
+            let (page_id, _) = server
+                .create_page(commit, "contents", &[], "", "")
+                .await
+                .expect("Unable to create page");
+
+            let ratings: Vec<_> = voter_ids
+                .iter()
+                .zip($votes.iter())
+                .map(|(&voter_id, &vote)| (page_id, voter_id, vote))
+                .collect();
+
+            server
+                .import_ratings(&ratings)
+                .await
+                .expect("Unable to import ratings");
+
+            page_id
+        }};
+    }
+
+    let page_1 = create_page!(wiki_id, "okay", [1, 1, -1]);
+    let page_2 = create_page!(wiki_id, "great", [1, 1, 1]);
+    let page_3 = create_page!(wiki_id, "bad", [-1, -1, 0]);
+    let deleted = create_page!(wiki_id, "deleted", [1, 1, 1]);
+    create_page!(other_wiki_id, "elsewhere", [1, 1, 1]);
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "deleted",
+        message: "delete page",
+        user: &user,
+    };
+
+    server
+        .remove_page(commit)
+        .await
+        .expect("Unable to remove page");
+
+    let top = server
+        .get_top_pages(wiki_id, 10)
+        .await
+        .expect("Unable to get top pages");
+
+    assert_eq!(top, vec![(page_2, 3), (page_1, 1), (page_3, -2)]);
+    assert!(top.iter().all(|&(page_id, _)| page_id != deleted));
+
+    let top = server
+        .get_top_pages(wiki_id, 1)
+        .await
+        .expect("Unable to get top pages");
+
+    assert_eq!(top, vec![(page_2, 3)]);
+
+    let top = server
+        .get_top_pages(wiki_id, -1)
+        .await
+        .expect("Unable to get top pages");
+
+    assert!(top.is_empty());
+}
+
+#[tokio::test]
+async fn user_rating_history() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let voter_id = create_user(server).await;
+    let other_voter_id = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    let mut page_ids = Vec::new();
+    for slug in &["first", "second", "third"] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        let (page_id, _) = server
+            .create_page(commit, "contents", &[], "", "")
+            .await
+            .expect("Unable to create page");
+
+        page_ids.push(page_id);
+    }
+
+    server
+        .set_rating(page_ids[0], voter_id, 1)
+        .await
+        .expect("Unable to set rating");
+
+    server
+        .set_rating(page_ids[0], other_voter_id, -1)
+        .await
+        .expect("Unable to set rating");
+
+    let since = Utc::now();
+
+    server
+        .set_rating(page_ids[1], voter_id, -1)
+        .await
+        .expect("Unable to set rating");
+
+    server
+        .remove_rating(page_ids[0], voter_id)
+        .await
+        .expect("Unable to remove rating")
+        .expect("No rating was removed");
+
+    server
+        .set_rating(page_ids[2], voter_id, 1)
+        .await
+        .expect("Unable to set rating");
+
+    // Full history, most recent first
+    let history = server
+        .get_user_rating_history(voter_id, None, 0, 10)
+        .await
+        .expect("Unable to get rating history");
+
+    let entries: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.page_id(), entry.rating()))
+        .collect();
+
+    assert_eq!(
+        entries,
+        vec![
+            (page_ids[2], Some(1)),
+            (page_ids[0], None),
+            (page_ids[1], Some(-1)),
+            (page_ids[0], Some(1)),
+        ],
+    );
+    assert!(history.iter().all(|entry| entry.user_id() == voter_id));
+
+    // Since filter
+    let history = server
+        .get_user_rating_history(voter_id, Some(since), 0, 10)
+        .await
+        .expect("Unable to get rating history");
+
+    assert_eq!(history.len(), 3);
+    assert!(history.iter().all(|entry| entry.created_at() >= since));
+
+    // Pagination
+    let history = server
+        .get_user_rating_history(voter_id, None, 1, 2)
+        .await
+        .expect("Unable to get rating history");
+
+    let entries: Vec<_> = history.iter().map(|entry| entry.page_id()).collect();
+    assert_eq!(entries, vec![page_ids[0], page_ids[1]]);
+
+    // Negative values are clamped
+    let history = server
+        .get_user_rating_history(voter_id, None, -5, 2)
+        .await
+        .expect("Unable to get rating history");
+
+    let entries: Vec<_> = history.iter().map(|entry| entry.page_id()).collect();
+    assert_eq!(entries, vec![page_ids[2], page_ids[0]]);
+
+    let history = server
+        .get_user_rating_history(voter_id, None, 0, -1)
+        .await
+        .expect("Unable to get rating history");
+
+    assert!(history.is_empty());
+
+    // Changes in one transaction share a timestamp, but keep their order
+    let (first_id, second_id) = (page_ids[1], page_ids[0]);
+    server
+        .batch(|server| async move {
+            server.set_rating(first_id, voter_id, 1).await?;
+            server.set_rating(second_id, voter_id, -1).await?;
+            Ok(())
+        })
+        .await
+        .expect("Unable to set ratings");
+
+    let history = server
+        .get_user_rating_history(voter_id, None, 0, 2)
+        .await
+        .expect("Unable to get rating history");
+
+    let entries: Vec<_> = history.iter().map(|entry| entry.page_id()).collect();
+    assert_eq!(entries, vec![second_id, first_id]);
+    assert_eq!(history[0].created_at(), history[1].created_at());
+}
+
+#[tokio::test]
+async fn page_votes_for_user() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "scp-1000",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _) = server
+        .create_page(commit, "Bigfoot", &[], "SCP-1000", "Bigfoot")
+        .await
+        .expect("Unable to create page");
+
+    let voter_id = create_user(server).await;
+    let non_voter_id = create_user(server).await;
+
+    for &vote in &[1, 1, -1] {
+        let other_id = create_user(server).await;
+
+        server
+            .set_rating(page_id, other_id, vote)
+            .await
+            .expect("Unable to set rating");
+    }
+
+    server
+        .set_rating(page_id, voter_id, -1)
+        .await
+        .expect("Unable to set rating");
+
+    let (votes, own) = server
+        .get_page_votes_for_user(page_id, voter_id)
+        .await
+        .expect("Unable to get votes");
+
+    assert_eq!(votes.count(), 4);
+    assert_eq!(votes.count_for_vote(1), Some(2));
+    assert_eq!(votes.count_for_vote(-1), Some(2));
+    assert_eq!(own, Some(-1));
+
+    let (votes, own) = server
+        .get_page_votes_for_user(page_id, non_voter_id)
+        .await
+        .expect("Unable to get votes");
+
+    assert_eq!(votes.count(), 4);
+    assert_eq!(own, None);
+}
@@ -0,0 +1,1127 @@
+/*
+ * test/revision.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::package::page::{ChangeType, Revision};
+use crate::package::revision::DiffStat;
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
+use std::process::Command;
+
+#[tokio::test]
+async fn revision() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "revision-test",
+        message: "first revision",
+        user: &user,
+    };
+
+    let (page_id, revision_id) = server
+        .create_page(commit, "page contents", &[], "Revision Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let revision = server
+        .get_revision(revision_id)
+        .await
+        .expect("Unable to get revision")
+        .expect("Created revision not found");
+
+    assert_eq!(revision.id(), revision_id);
+    assert_eq!(revision.page_id(), page_id);
+    assert_eq!(revision.user_id(), user.id());
+    assert_eq!(revision.message(), "first revision");
+    assert_eq!(revision.change_type(), ChangeType::Create);
+
+    let contents = server
+        .get_page_version(wiki_id, "revision-test", Right(&revision.git_commit()))
+        .await
+        .expect("Unable to get page version")
+        .expect("Page version not found");
+
+    assert_eq!(contents, "page contents");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "revision-test",
+        message: "second revision",
+        user: &user,
+    };
+
+    let revision_id = server
+        .edit_page(commit, Some("new contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let revision = server
+        .get_revision(revision_id)
+        .await
+        .expect("Unable to get revision")
+        .expect("Edited revision not found");
+
+    assert_eq!(revision.message(), "second revision");
+    assert_eq!(revision.change_type(), ChangeType::Modify);
+
+    let revision = server
+        .get_revision(RevisionId::from_raw(i64::max_value()))
+        .await
+        .expect("Unable to get revision");
+
+    assert!(revision.is_none());
+}
+
+#[tokio::test]
+async fn current_hash() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let hash = server
+        .get_page_current_hash(wiki_id, "hash-test")
+        .await
+        .expect("Unable to get current hash");
+
+    assert!(hash.is_none());
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "hash-test",
+        message: "first revision",
+        user: &user,
+    };
+
+    let (_page_id, revision_id_1) = server
+        .create_page(commit, "first contents", &[], "Hash Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let revision_id_2 = server
+        .edit_page(commit, Some("second contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let revision_1 = server
+        .get_revision(revision_id_1)
+        .await
+        .expect("Unable to get revision")
+        .expect("Revision not found");
+
+    let revision_2 = server
+        .get_revision(revision_id_2)
+        .await
+        .expect("Unable to get revision")
+        .expect("Revision not found");
+
+    let hash = server
+        .get_page_current_hash(wiki_id, "hash-test")
+        .await
+        .expect("Unable to get current hash")
+        .expect("No hash for page");
+
+    assert_ne!(hash, revision_1.git_commit());
+    assert_eq!(hash, revision_2.git_commit());
+}
+
+#[tokio::test]
+async fn contents_if_changed() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "cached-page",
+        message: "first revision",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "first contents", &[], "Cached Page", "")
+        .await
+        .expect("Unable to create page");
+
+    let hash = server
+        .get_page_current_hash(wiki_id, "cached-page")
+        .await
+        .expect("Unable to get current hash")
+        .expect("No hash for page");
+
+    let result = server
+        .get_page_contents_if_changed(wiki_id, "cached-page", &hash)
+        .await
+        .expect("Unable to get page contents");
+
+    assert_eq!(result, None);
+
+    server
+        .edit_page(commit, Some("second contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let result = server
+        .get_page_contents_if_changed(wiki_id, "cached-page", &hash)
+        .await
+        .expect("Unable to get page contents");
+
+    assert_eq!(result, Some(Some(String::from("second contents"))));
+}
+
+#[tokio::test]
+async fn history_authors() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+
+    let (user_id_1, name_1, _) = create_user_full(server, "defaultpasswordhere2").await;
+    let (user_id_2, name_2, _) = create_user_full(server, "defaultpasswordhere2").await;
+
+    let user_1 = server
+        .get_user_from_id(user_id_1)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let user_2 = server
+        .get_user_from_id(user_id_2)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "history-test",
+        message: "first revision",
+        user: &user_1,
+    };
+
+    let (page_id, _revision_id) = server
+        .create_page(commit, "first contents", &[], "History Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "history-test",
+        message: "second revision",
+        user: &user_2,
+    };
+
+    server
+        .edit_page(commit, Some("second contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let history = server
+        .get_page_history_with_authors(page_id, 10, 0)
+        .await
+        .expect("Unable to get page history");
+
+    assert_eq!(history.len(), 2);
+
+    let (revision, name) = &history[0];
+    assert_eq!(revision.message(), "second revision");
+    assert_eq!(revision.user_id(), user_id_2);
+    assert_eq!(name.as_ref(), Some(&name_2));
+
+    let (revision, name) = &history[1];
+    assert_eq!(revision.message(), "first revision");
+    assert_eq!(revision.user_id(), user_id_1);
+    assert_eq!(name.as_ref(), Some(&name_1));
+
+    let history = server
+        .get_page_history_with_authors(page_id, 10, 1)
+        .await
+        .expect("Unable to get page history");
+
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].0.message(), "first revision");
+
+    let error = server
+        .get_page_history_with_authors(page_id, 101, 0)
+        .await
+        .expect_err("Able to fetch over 100 revisions");
+
+    match error {
+        Error::RequestTooLarge(101, 100) => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn structured_diff() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "diff-test",
+        message: "diff page",
+        user: &user,
+    };
+
+    let (_page_id, first_revision_id) = server
+        .create_page(commit, "apple banana cherry\n", &[], "Diff", "")
+        .await
+        .expect("Unable to create page");
+
+    let second_revision_id = server
+        .edit_page(commit, Some("apple durian cherry\n"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let diff = server
+        .get_page_diff_structured(
+            wiki_id,
+            "diff-test",
+            Left(first_revision_id),
+            Left(second_revision_id),
+        )
+        .await
+        .expect("Unable to get structured diff");
+
+    assert_eq!(diff.files.len(), 1);
+
+    let file = &diff.files[0];
+    assert!(!file.binary);
+    assert_eq!(file.hunks.len(), 1);
+    assert_eq!(
+        file.hunks[0].spans,
+        vec![
+            DiffSpan::Context(String::from("apple ")),
+            DiffSpan::Removed(String::from("banana")),
+            DiffSpan::Added(String::from("durian")),
+            DiffSpan::Context(String::from(" cherry")),
+            DiffSpan::Newline,
+        ],
+    );
+}
+
+#[tokio::test]
+async fn cross_diff() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "original",
+        message: "original page",
+        user: &user,
+    };
+
+    let (_page_id, revision_id) = server
+        .create_page(commit, "apple banana cherry", &[], "Original", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .edit_page(commit, Some("apple banana durian"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "translation",
+        message: "translated page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "pomme banane cerise", &[], "Translation", "")
+        .await
+        .expect("Unable to create page");
+
+    let diff = server
+        .get_cross_page_diff(wiki_id, "original", None, "translation", None)
+        .await
+        .expect("Unable to get diff");
+
+    assert!(diff.contains("apple banana durian"));
+    assert!(diff.contains("pomme banane cerise"));
+
+    let diff = server
+        .get_cross_page_diff(
+            wiki_id,
+            "original",
+            Some(Left(revision_id)),
+            "translation",
+            None,
+        )
+        .await
+        .expect("Unable to get diff");
+
+    assert!(diff.contains("apple banana cherry"));
+    assert!(diff.contains("pomme banane cerise"));
+
+    let error = server
+        .get_cross_page_diff(wiki_id, "original", None, "nonexistent", None)
+        .await
+        .expect_err("Diffed against missing page");
+
+    match error {
+        Error::PageNotFound => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn commit_prefix() {
+    let server = &create_server_with(|config| {
+        config.commit_message_prefix = Some(String::from("[staging]"));
+    })
+    .await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let (wiki_id, slug) = create_wiki_full(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "prefix-test",
+        message: "new page",
+        user: &user,
+    };
+
+    let (_, revision_id) = server
+        .create_page(commit, "page contents", &[], "Prefix Test", "")
+        .await
+        .expect("Unable to create page");
+
+    // Check git commit subject
+    let output = Command::new("git")
+        .arg("log")
+        .arg("-1")
+        .arg("--format=%s")
+        .current_dir(server.revisions_dir().join(&slug))
+        .output()
+        .expect("Unable to run git log");
+
+    let subject = String::from_utf8(output.stdout).expect("Commit subject not UTF-8");
+    assert!(
+        subject.starts_with("[staging] "),
+        "Commit subject doesn't have prefix: {:?}",
+        subject,
+    );
+
+    // Check revision message is unchanged
+    let revision = server
+        .get_revision(revision_id)
+        .await
+        .expect("Unable to get revision")
+        .expect("Created revision not found");
+
+    assert_eq!(revision.message(), "new page");
+}
+
+#[tokio::test]
+async fn revert() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr, $message:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: $message,
+                user: &user,
+            }
+        };
+    }
+
+    // Create and edit pages
+    let (_, first_revision_id) = server
+        .create_page(commit!("revert-test", "first"), "version one", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .edit_page(
+            commit!("revert-test", "second"),
+            Some("version two"),
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("Unable to edit page");
+
+    server
+        .edit_page(
+            commit!("revert-test", "third"),
+            Some("version three"),
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("Unable to edit page");
+
+    let (_, other_revision_id) = server
+        .create_page(commit!("other-page", "other"), "other page", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    // Revert to first version
+    let revision_id = server
+        .revert_page_to(commit!("revert-test", "revert"), Left(first_revision_id))
+        .await
+        .expect("Unable to revert page");
+
+    let contents = server
+        .get_page_contents(wiki_id, "revert-test")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page not found");
+
+    assert_eq!(contents, "version one");
+
+    let revision = server
+        .get_revision(revision_id)
+        .await
+        .expect("Unable to get revision")
+        .expect("Created revision not found");
+
+    assert_eq!(revision.message(), "revert");
+    assert_eq!(revision.change_type(), ChangeType::Restore);
+
+    // Title is recorded for the new revision
+    let history = server
+        .get_page_title_history(revision.page_id())
+        .await
+        .expect("Unable to get title history");
+
+    assert_eq!(history.len(), 2);
+
+    // Revert by commit hash
+    let second_hash = server
+        .get_page_history(revision.page_id(), 10, 0, true)
+        .await
+        .expect("Unable to get page history")
+        .iter()
+        .find(|revision| revision.message() == "second")
+        .expect("Second revision not found")
+        .git_commit();
+
+    server
+        .revert_page_to(commit!("revert-test", "revert"), Right(second_hash))
+        .await
+        .expect("Unable to revert page");
+
+    let contents = server
+        .get_page_contents(wiki_id, "revert-test")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page not found");
+
+    assert_eq!(contents, "version two");
+
+    // Revision from another page
+    let error = server
+        .revert_page_to(commit!("revert-test", "revert"), Left(other_revision_id))
+        .await
+        .expect_err("Reverted to another page's revision");
+
+    match error {
+        Error::RevisionPageMismatch => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    // Deleted page
+    server
+        .remove_page(commit!("revert-test", "delete"))
+        .await
+        .expect("Unable to remove page");
+
+    let error = server
+        .revert_page_to(commit!("revert-test", "revert"), Left(first_revision_id))
+        .await
+        .expect_err("Reverted a deleted page");
+
+    match error {
+        Error::PageNotFound => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn timeline() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($message:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: "timeline-test",
+                message: $message,
+                user: &user,
+            }
+        };
+    }
+
+    // Build history
+    let (page_id, first_revision_id) = server
+        .create_page(commit!("create"), "one\ntwo\n", &[], "Timeline", "")
+        .await
+        .expect("Unable to create page");
+
+    server
+        .edit_page(
+            commit!("rewrite"),
+            Some("one\nthree\nfour\n"),
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("Unable to edit page");
+
+    server
+        .set_page_tags(commit!("tagging"), &["tale"])
+        .await
+        .expect("Unable to set page tags");
+
+    server
+        .edit_page(commit!("trim"), Some("one\n"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    server
+        .revert_page(commit!("revert"), Left(first_revision_id))
+        .await
+        .expect("Unable to revert page");
+
+    // Check timeline
+    let timeline = server
+        .get_page_timeline(page_id)
+        .await
+        .expect("Unable to get page timeline");
+
+    let stat = |added, removed| Some(DiffStat { added, removed });
+    let expected = [
+        ("create", ChangeType::Create, stat(2, 0)),
+        ("rewrite", ChangeType::Modify, stat(2, 1)),
+        ("tagging", ChangeType::Tags, None),
+        ("trim", ChangeType::Modify, stat(0, 2)),
+        ("revert", ChangeType::Restore, stat(1, 0)),
+    ];
+
+    assert_eq!(
+        timeline.len(),
+        expected.len(),
+        "Timeline length doesn't match"
+    );
+    assert_eq!(timeline[0].revision_id, first_revision_id);
+
+    for (entry, (message, change_type, diff_stat)) in timeline.iter().zip(expected.iter()) {
+        assert_eq!(&entry.message, message, "Message doesn't match");
+        assert_eq!(&entry.change_type, change_type, "Change type doesn't match");
+        assert_eq!(&entry.diff_stat, diff_stat, "Diff stat doesn't match");
+        assert_eq!(entry.author.as_deref(), Some("unknown"));
+    }
+
+    // Missing page
+    let error = server
+        .get_page_timeline(PageId::from_raw(-1))
+        .await
+        .expect_err("Got timeline for missing page");
+
+    match error {
+        Error::PageNotFound => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn version_by_revision() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "version-test",
+        message: "first revision",
+        user: &user,
+    };
+
+    let (_page_id, first_revision_id) = server
+        .create_page(commit, "first contents", &[], "Version Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "version-test",
+        message: "second revision",
+        user: &user,
+    };
+
+    let second_revision_id = server
+        .edit_page(commit, Some("second contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let contents = server
+        .get_page_version_by_revision(first_revision_id)
+        .await
+        .expect("Unable to get page version")
+        .expect("Page version not found");
+
+    assert_eq!(contents, "first contents");
+
+    let contents = server
+        .get_page_version_by_revision(second_revision_id)
+        .await
+        .expect("Unable to get page version")
+        .expect("Page version not found");
+
+    assert_eq!(contents, "second contents");
+
+    let contents = server
+        .get_page_version_by_revision(RevisionId::from_raw(i64::max_value()))
+        .await
+        .expect("Unable to get page version");
+
+    assert!(contents.is_none());
+}
+
+#[tokio::test]
+async fn minor_edit() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($message:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: "minor-test",
+                message: $message,
+                user: &user,
+            }
+        };
+    }
+
+    let (page_id, first_revision_id) = server
+        .create_page(commit!("create"), "contents", &[], "Minor Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let minor_revision_id = server
+        .edit_page(commit!("typo"), Some("content"), None, None, true)
+        .await
+        .expect("Unable to make minor edit");
+
+    let major_revision_id = server
+        .edit_page(commit!("rewrite"), Some("new content"), None, None, false)
+        .await
+        .expect("Unable to make edit");
+
+    let revision = server
+        .get_revision(minor_revision_id)
+        .await
+        .expect("Unable to get revision")
+        .expect("Minor revision not found");
+
+    assert!(revision.is_minor());
+
+    let revision = server
+        .get_revision(major_revision_id)
+        .await
+        .expect("Unable to get revision")
+        .expect("Major revision not found");
+
+    assert!(!revision.is_minor());
+
+    let revision_ids = |revisions: Vec<Revision>| {
+        revisions
+            .iter()
+            .map(|revision| revision.id())
+            .collect::<Vec<_>>()
+    };
+
+    let revisions = server
+        .get_page_history(page_id, 100, 0, true)
+        .await
+        .expect("Unable to get revisions");
+
+    assert_eq!(
+        revision_ids(revisions),
+        vec![major_revision_id, minor_revision_id, first_revision_id],
+    );
+
+    let revisions = server
+        .get_page_history(page_id, 100, 0, false)
+        .await
+        .expect("Unable to get revisions");
+
+    assert_eq!(
+        revision_ids(revisions),
+        vec![major_revision_id, first_revision_id],
+    );
+}
+
+#[tokio::test]
+async fn page_blob() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "blob-test",
+        message: "new page",
+        user: &user,
+    };
+
+    let (_page_id, revision_id) = server
+        .create_page(commit, "Blob contents\n", &[], "Blob Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let (object, bytes) = server
+        .get_page_blob(wiki_id, "blob-test", Left(revision_id))
+        .await
+        .expect("Unable to get page blob")
+        .expect("Page blob not found");
+
+    assert_eq!(&*bytes, b"Blob contents\n");
+    assert_eq!(object.as_str().len(), 40);
+
+    // Same as `git hash-object` for this content
+    assert_eq!(object.as_str(), "2c2ba8dc130d8c1bb714db07d43f5edfdd5b3d3d");
+
+    let blob = server
+        .get_page_blob(wiki_id, "nonexistent", Left(revision_id))
+        .await
+        .expect("Unable to get page blob");
+
+    assert!(blob.is_none(), "Blob found for nonexistent page");
+}
+
+#[tokio::test]
+async fn change_type_counts() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "change type test",
+                user: &user,
+            }
+        };
+    }
+
+    for slug in &["page-a", "page-b"] {
+        server
+            .create_page(commit!(slug), "contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    for content in &["first edit", "second edit"] {
+        server
+            .edit_page(commit!("page-a"), Some(*content), None, None, false)
+            .await
+            .expect("Unable to edit page");
+    }
+
+    server
+        .set_page_tags(commit!("page-a"), &["tale"])
+        .await
+        .expect("Unable to set page tags");
+
+    server
+        .rename_page(wiki_id, "page-a", "page-c", "rename", &user)
+        .await
+        .expect("Unable to rename page");
+
+    server
+        .remove_page(commit!("page-b"))
+        .await
+        .expect("Unable to remove page");
+
+    let now = Utc::now();
+    let counts = server
+        .get_change_type_counts(wiki_id, now - Duration::days(1), now + Duration::days(1))
+        .await
+        .expect("Unable to get change type counts");
+
+    let mut expected = HashMap::new();
+    expected.insert(ChangeType::Create, 2);
+    expected.insert(ChangeType::Modify, 2);
+    expected.insert(ChangeType::Tags, 1);
+    expected.insert(ChangeType::Rename, 1);
+    expected.insert(ChangeType::Delete, 1);
+
+    assert_eq!(counts, expected, "Change type counts don't match");
+
+    let counts = server
+        .get_change_type_counts(wiki_id, now + Duration::days(1), now + Duration::days(2))
+        .await
+        .expect("Unable to get change type counts");
+
+    assert!(counts.is_empty(), "Changes found outside time range");
+}
+
+#[tokio::test]
+async fn page_history() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($message:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: "history-test",
+                message: $message,
+                user: &user,
+            }
+        };
+    }
+
+    let (page_id, create_revision_id) = server
+        .create_page(commit!("create"), "contents", &[], "History Test", "")
+        .await
+        .expect("Unable to create page");
+
+    let edit_revision_id = server
+        .edit_page(commit!("edit"), Some("new contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let delete_revision_id = server
+        .remove_page(commit!("delete"))
+        .await
+        .expect("Unable to remove page");
+
+    macro_rules! check_history {
+        ($limit:expr, $offset:expr, $expected:expr) => {{
+            let revisions = server
+                .get_page_history(page_id, $limit, $offset, true)
+                .await
+                .expect("Unable to get page history");
+
+            let ids = revisions
+                .iter()
+                .map(|revision| revision.id())
+                .collect::<Vec<_>>();
+
+            assert_eq!(ids, $expected, "Page history doesn't match");
+            revisions
+        }};
+    }
+
+    // Deleted pages still have history
+    let revisions = check_history!(
+        10,
+        0,
+        vec![delete_revision_id, edit_revision_id, create_revision_id]
+    );
+
+    let messages = revisions
+        .iter()
+        .map(|revision| revision.message())
+        .collect::<Vec<_>>();
+
+    assert_eq!(messages, vec!["delete", "edit", "create"]);
+    assert_eq!(revisions[0].change_type(), ChangeType::Delete);
+    assert_eq!(revisions[0].user_id(), user.id());
+
+    // Paging
+    check_history!(1, 0, vec![delete_revision_id]);
+    check_history!(2, 1, vec![edit_revision_id, create_revision_id]);
+    check_history!(10, 3, Vec::<RevisionId>::new());
+
+    // Unknown page
+    let revisions = server
+        .get_page_history(PageId::from_raw(-1), 10, 0, true)
+        .await
+        .expect("Unable to get page history");
+
+    assert!(revisions.is_empty());
+
+    let error = server
+        .get_page_history(page_id, 101, 0, true)
+        .await
+        .expect_err("Able to fetch over 100 revisions");
+
+    match error {
+        Error::RequestTooLarge(101, 100) => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn permalink() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "permalink-test",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, revision_id) = server
+        .create_page(commit, "Contents", &[], "Permalink", "")
+        .await
+        .expect("Unable to create page");
+
+    let permalink = revision_id.to_permalink();
+    let revision = server
+        .resolve_permalink(&permalink)
+        .await
+        .expect("Unable to resolve permalink")
+        .expect("Permalink revision not found");
+
+    assert_eq!(revision.id(), revision_id);
+    assert_eq!(revision.page_id(), page_id);
+
+    // Invalid permalinks
+    let revision = server
+        .resolve_permalink("not-a-permalink")
+        .await
+        .expect("Unable to resolve permalink");
+
+    assert!(revision.is_none());
+
+    let permalink = RevisionId::from_raw(i64::max_value()).to_permalink();
+    let revision = server
+        .resolve_permalink(&permalink)
+        .await
+        .expect("Unable to resolve permalink");
+
+    assert!(revision.is_none());
+}
+
+#[tokio::test]
+async fn wiki_activity() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+    let other_wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($wiki_id:expr, $slug:expr) => {
+            PageCommit {
+                wiki_id: $wiki_id,
+                slug: $slug,
+                message: "activity test",
+                user: &user,
+            }
+        };
+    }
+
+    let mut expected = Vec::new();
+
+    for slug in &["first", "second", "third"] {
+        let (_, revision_id) = server
+            .create_page(commit!(wiki_id, slug), "Contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+
+        expected.push((revision_id, *slug));
+    }
+
+    for slug in &["second", "first", "third", "second"] {
+        let revision_id = server
+            .edit_page(commit!(wiki_id, slug), Some(slug), None, None, false)
+            .await
+            .expect("Unable to edit page");
+
+        expected.push((revision_id, *slug));
+    }
+
+    // Edits elsewhere don't appear
+    server
+        .create_page(commit!(other_wiki_id, "first"), "Contents", &[], "Page", "")
+        .await
+        .expect("Unable to create page");
+
+    expected.reverse();
+
+    let activity = server
+        .get_wiki_activity(wiki_id, 20, 0)
+        .await
+        .expect("Unable to get wiki activity");
+
+    let actual: Vec<_> = activity
+        .iter()
+        .map(|(revision, slug)| (revision.id(), slug.as_str()))
+        .collect();
+
+    assert_eq!(actual, expected);
+
+    // Pagination
+    let activity = server
+        .get_wiki_activity(wiki_id, 2, 1)
+        .await
+        .expect("Unable to get wiki activity");
+
+    let actual: Vec<_> = activity
+        .iter()
+        .map(|(revision, slug)| (revision.id(), slug.as_str()))
+        .collect();
+
+    assert_eq!(actual, &expected[1..3]);
+}
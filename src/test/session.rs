@@ -19,6 +19,8 @@
  */
 
 use super::prelude::*;
+use async_std::task;
+use std::time::Duration;
 
 macro_rules! check_err {
     ($error:expr) => {
@@ -184,3 +186,305 @@ async fn session_end_other() {
 
     check_err!(error);
 }
+
+#[tokio::test]
+async fn session_lock_down() {
+    let server = &create_server().await;
+    let (user_id, _, _) = create_user_full(server, "blackmoonhowls").await;
+
+    // Create sessions and token
+    let mut sessions = Vec::new();
+    for _ in 0..3 {
+        let session = server
+            .try_login_id(user_id, "blackmoonhowls", None)
+            .await
+            .expect("Unable to login");
+
+        sessions.push(session);
+    }
+
+    let token = server
+        .new_verification_token(user_id)
+        .await
+        .expect("Unable to create verification token");
+
+    // Lock down account
+    let ended = server
+        .lock_down_user(user_id, Some("oldfaithful"))
+        .await
+        .expect("Unable to lock down user");
+
+    assert_eq!(ended, 3, "Incorrect number of sessions ended");
+
+    for session in &sessions {
+        let error = server
+            .check_session(session.session_id(), session.user_id())
+            .await
+            .expect_err("Session still valid");
+
+        check_err!(error);
+    }
+
+    match server.verify_token(&token).await {
+        Err(Error::InvalidVerificationToken) => (),
+        _ => panic!("Verification token still valid"),
+    }
+
+    server
+        .validate_user_password(user_id, "blackmoonhowls")
+        .expect_err("Old password still valid");
+
+    server
+        .validate_user_password(user_id, "oldfaithful")
+        .expect("New password not valid");
+
+    // Lock down again, without changing password
+    let ended = server
+        .lock_down_user(user_id, None)
+        .await
+        .expect("Unable to lock down user");
+
+    assert_eq!(ended, 0, "Sessions ended when none exist");
+
+    server
+        .validate_user_password(user_id, "oldfaithful")
+        .expect("Password changed");
+}
+
+#[tokio::test]
+async fn session_authenticate() {
+    let server = &create_server().await;
+    let (user_id, username, _) = create_user_full(server, "blackmoonhowls").await;
+
+    let session = server
+        .try_login_id(user_id, "blackmoonhowls", None)
+        .await
+        .expect("Unable to login");
+
+    // Valid session
+    let user = server
+        .authenticate(session.session_id(), session.user_id())
+        .await
+        .expect("Unable to authenticate");
+
+    assert_eq!(user.id(), user_id);
+    assert_eq!(user.name(), username);
+
+    // Bogus session
+    let error = server
+        .authenticate(SessionId::from_raw(-1), user_id)
+        .await
+        .expect_err("Authenticated with bogus session");
+
+    check_err!(error);
+
+    // Mismatched user
+    let other_user_id = create_user(server).await;
+    let error = server
+        .authenticate(session.session_id(), other_user_id)
+        .await
+        .expect_err("Authenticated with another user's session");
+
+    check_err!(error);
+
+    // Inactive user
+    server
+        .mark_user_inactive(user_id)
+        .await
+        .expect("Unable to mark user inactive");
+
+    let error = server
+        .authenticate(session.session_id(), session.user_id())
+        .await
+        .expect_err("Authenticated as inactive user");
+
+    match error {
+        Error::UserNotFound => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn last_logins() {
+    let server = &create_server().await;
+    let (user_id_1, _, _) = create_user_full(server, "blackmoonhowls").await;
+    let (user_id_2, _, _) = create_user_full(server, "bluemoonsings").await;
+    let user_id_3 = create_user(server).await;
+
+    // No logins yet
+    let attempt = server
+        .get_last_successful_login(user_id_1)
+        .await
+        .expect("Unable to get last login");
+
+    assert!(attempt.is_none());
+
+    // Log in both users, with a failed attempt at the end
+    for _ in 0..2 {
+        server
+            .try_login_id(user_id_1, "blackmoonhowls", None)
+            .await
+            .expect("Unable to login");
+    }
+
+    let session_2 = server
+        .try_login_id(user_id_2, "bluemoonsings", None)
+        .await
+        .expect("Unable to login");
+
+    server
+        .try_login_id(user_id_2, "wrongpassword", None)
+        .await
+        .expect_err("Logged in with invalid password");
+
+    // Check individual last logins
+    let attempt_1 = server
+        .get_last_successful_login(user_id_1)
+        .await
+        .expect("Unable to get last login")
+        .expect("No successful login found");
+
+    assert_eq!(attempt_1.user_id(), Some(user_id_1));
+    assert!(attempt_1.success());
+
+    let attempt_2 = server
+        .get_last_successful_login(user_id_2)
+        .await
+        .expect("Unable to get last login")
+        .expect("No successful login found");
+
+    assert_eq!(attempt_2.login_attempt_id(), session_2.login_attempt_id());
+
+    // Check batched last logins
+    let logins = server
+        .get_last_logins(&[user_id_1, user_id_2, user_id_3])
+        .await
+        .expect("Unable to get last logins");
+
+    assert_eq!(logins.len(), 2);
+    assert_eq!(logins.get(&user_id_1), Some(&attempt_1.attempted_at()));
+    assert_eq!(logins.get(&user_id_2), Some(&attempt_2.attempted_at()));
+    assert_eq!(logins.get(&user_id_3), None);
+}
+
+#[tokio::test]
+async fn session_expiry() {
+    let server = &create_server_with(|config| {
+        config.session_expiry = chrono::Duration::seconds(2);
+    })
+    .await;
+
+    let password = "blackmoonhowls";
+    let (user_id, _, _) = create_user_full(server, password).await;
+
+    let session = server
+        .try_login_id(user_id, password, None)
+        .await
+        .expect("Unable to login");
+
+    let other_session = server
+        .try_login_id(user_id, password, None)
+        .await
+        .expect("Unable to login");
+
+    // Checking the session keeps it alive
+    for _ in 0..2 {
+        task::sleep(Duration::from_millis(1200)).await;
+
+        server
+            .check_session(session.session_id(), user_id)
+            .await
+            .expect("Session was invalid");
+    }
+
+    // The unused session has expired, and is no longer listed
+    let (current, others) = server
+        .get_sessions(session.session_id(), user_id)
+        .await
+        .expect("Unable to get sessions");
+
+    assert_eq!(current.session_id(), session.session_id());
+    assert!(others.is_empty(), "Expired session was listed");
+
+    let error = server
+        .get_sessions(other_session.session_id(), user_id)
+        .await
+        .expect_err("Expired session was valid");
+
+    match error {
+        Error::SessionExpired => (),
+        _ => panic!("Error wasn't session expired"),
+    }
+
+    let ended = server
+        .end_other_sessions(session.session_id(), user_id)
+        .await
+        .expect("Unable to end other sessions");
+
+    assert!(ended.is_empty(), "Expired session was ended");
+
+    // Inactive past the window
+    task::sleep(Duration::from_millis(2500)).await;
+
+    let error = server
+        .end_other_sessions(session.session_id(), user_id)
+        .await
+        .expect_err("Expired session was valid");
+
+    match error {
+        Error::SessionExpired => (),
+        _ => panic!("Error wasn't session expired"),
+    }
+
+    let error = server
+        .check_session(session.session_id(), user_id)
+        .await
+        .expect_err("Expired session was valid");
+
+    match error {
+        Error::SessionExpired => (),
+        _ => panic!("Error wasn't session expired"),
+    }
+}
+
+#[tokio::test]
+async fn check_sessions_batch() {
+    let server = &create_server().await;
+    let password = "blackmoonhowls";
+    let (user_id, _, _) = create_user_full(server, password).await;
+    let other_user_id = create_user(server).await;
+
+    let session_1 = server
+        .try_login_id(user_id, password, None)
+        .await
+        .expect("Unable to login");
+
+    let session_2 = server
+        .try_login_id(user_id, password, None)
+        .await
+        .expect("Unable to login");
+
+    server
+        .end_session(session_2.session_id(), user_id)
+        .await
+        .expect("Unable to end session");
+
+    let valid = server
+        .check_sessions(&[
+            (session_1.session_id(), user_id),
+            (session_1.session_id(), other_user_id),
+            (session_2.session_id(), user_id),
+            (session_1.session_id(), user_id),
+        ])
+        .await
+        .expect("Unable to check sessions");
+
+    assert_eq!(valid, vec![true, false, false, true]);
+
+    let valid = server
+        .check_sessions(&[])
+        .await
+        .expect("Unable to check sessions");
+
+    assert!(valid.is_empty());
+}
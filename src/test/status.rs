@@ -0,0 +1,93 @@
+/*
+ * test/status.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use chrono::prelude::*;
+use std::fs;
+use std::time::Duration;
+
+#[tokio::test]
+async fn status() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+    let password = "blackmoonhowls";
+    let (user_id, _, _) = create_user_full(server, password).await;
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let before = server.status();
+    assert!(before.started_at <= Utc::now());
+
+    // Commits
+    let commit = PageCommit {
+        wiki_id,
+        slug: "status-check",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "content", &[], "", "")
+        .await
+        .expect("Unable to create page");
+
+    // Failed commits aren't counted
+    let (other_wiki_id, other_slug) = create_wiki_full(server).await;
+    let commits = server.status().commits;
+
+    server.unload_wiki_store(other_wiki_id).await;
+
+    let repo = server.revisions_dir().join(&other_slug);
+    fs::remove_dir_all(&repo).expect("Unable to remove repository");
+
+    let commit = PageCommit {
+        wiki_id: other_wiki_id,
+        slug: "status-check",
+        message: "new page",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "content", &[], "", "")
+        .await
+        .expect_err("Created page in missing repository");
+
+    assert_eq!(server.status().commits, commits);
+
+    // Logins
+    server
+        .try_login_id(user_id, password, None)
+        .await
+        .expect("Unable to login");
+
+    // Errors
+    server
+        .try_login_id(user_id, "wrong password", None)
+        .await
+        .expect_err("Logged in with wrong password");
+
+    async_std::task::sleep(Duration::from_millis(5)).await;
+
+    let after = server.status();
+    assert_eq!(before.started_at, after.started_at);
+    assert!(after.uptime > before.uptime);
+    assert!(after.commits > before.commits);
+    assert!(after.logins > before.logins);
+    assert!(after.errors > before.errors);
+}
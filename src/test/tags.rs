@@ -19,16 +19,13 @@
  */
 
 use super::prelude::*;
+use chrono::prelude::*;
 
 #[tokio::test]
 async fn tags() {
     let server = &create_server().await;
 
-    let user_1 = server
-        .get_user_from_name("unknown")
-        .await
-        .expect("Unable to get user")
-        .expect("Default user not found");
+    let user_1 = server.default_user().await.expect("Unable to get user");
 
     let user_2_id = create_user(server).await;
     let user_2 = server
@@ -119,7 +116,7 @@ async fn tags() {
         .await
         .expect("Unable to set page tags");
 
-    let (page, _) = server
+    let (page, _, _) = server
         .get_page(wiki_id, "scp-xxxx")
         .await
         .expect("Unable to get page")
@@ -146,22 +143,36 @@ async fn tags() {
     // Query by page tags
 
     let pages = server
-        .get_pages_with_tags(wiki_id, &[])
+        .get_pages_with_tags(wiki_id, &[], PageOrder::default())
         .await
         .expect("Unable to get pages from tags");
 
     assert!(pages.is_empty());
 
+    let count = server
+        .count_pages_with_tags(wiki_id, &[])
+        .await
+        .expect("Unable to count pages from tags");
+
+    assert_eq!(count, 0);
+
     let pages = server
-        .get_pages_with_tags(wiki_id, &["keter"])
+        .get_pages_with_tags(wiki_id, &["keter"], PageOrder::default())
         .await
         .expect("Unable to get pages from tags");
 
     assert_eq!(pages.len(), 1);
     assert_eq!(pages[0].id(), page.id());
 
+    let count = server
+        .count_pages_with_tags(wiki_id, &["keter"])
+        .await
+        .expect("Unable to count pages from tags");
+
+    assert_eq!(count as usize, pages.len());
+
     let pages = server
-        .get_pages_with_tags(wiki_id, &["keter", "ontokinetic"])
+        .get_pages_with_tags(wiki_id, &["keter", "ontokinetic"], PageOrder::default())
         .await
         .expect("Unable to get pages from tags");
 
@@ -169,7 +180,7 @@ async fn tags() {
     assert_eq!(pages[0].id(), page.id());
 
     let pages = server
-        .get_pages_with_tags(wiki_id, &["ontokinetic", "keter"])
+        .get_pages_with_tags(wiki_id, &["ontokinetic", "keter"], PageOrder::default())
         .await
         .expect("Unable to get pages from tags");
 
@@ -177,9 +188,660 @@ async fn tags() {
     assert_eq!(pages[0].id(), page.id());
 
     let pages = server
-        .get_pages_with_tags(wiki_id, &["esoteric-class", "ontokinetic"])
+        .get_pages_with_tags(
+            wiki_id,
+            &["esoteric-class", "ontokinetic"],
+            PageOrder::default(),
+        )
         .await
         .expect("Unable to get pages from tags");
 
     assert!(pages.is_empty());
+
+    let count = server
+        .count_pages_with_tags(wiki_id, &["esoteric-class", "ontokinetic"])
+        .await
+        .expect("Unable to count pages from tags");
+
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn tags_conflict() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "contested-page",
+        message: "New article!",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "contents", &[], "Contested Page", "")
+        .await
+        .expect("Unable to create page");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "contested-page",
+        message: "tagging",
+        user: &user,
+    };
+
+    // Both editors see the page with no tags
+    let stale_tags: &[&str] = &[];
+
+    server
+        .swap_page_tags(commit, stale_tags, &["tale", "horror"])
+        .await
+        .expect("Unable to set page tags")
+        .expect("No revision created");
+
+    // Second editor's view is now out of date
+    let error = server
+        .swap_page_tags(commit, stale_tags, &["tale", "comedy"])
+        .await
+        .expect_err("Stale tag set was accepted");
+
+    match error {
+        Error::Conflict => (),
+        _ => panic!("Error wasn't a conflict"),
+    }
+
+    // Retry with the fresh tag set
+    server
+        .swap_page_tags(commit, &["horror", "tale"], &["tale", "comedy"])
+        .await
+        .expect("Unable to set page tags")
+        .expect("No revision created");
+
+    let (page, _, _) = server
+        .get_page(wiki_id, "contested-page")
+        .await
+        .expect("Unable to get page")
+        .expect("No page found");
+
+    assert_eq!(page.tags(), &["comedy", "tale"]);
+}
+
+#[tokio::test]
+async fn tags_order() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let voter_1 = create_user(server).await;
+    let voter_2 = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    // Create pages
+    let pages = [
+        ("scp-001", "Sigma", -1, 0),
+        ("scp-002", "Alpha", 1, 1),
+        ("scp-003", "Omega", 0, 0),
+    ];
+
+    for &(slug, title, vote_1, vote_2) in &pages {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        let (page_id, _) = server
+            .create_page(commit, "content", &[], title, "")
+            .await
+            .expect("Unable to create page");
+
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "tagging",
+            user: &user,
+        };
+
+        server
+            .set_page_tags(commit, &["scp"])
+            .await
+            .expect("Unable to set page tags");
+
+        for &(voter, vote) in &[(voter_1, vote_1), (voter_2, vote_2)] {
+            if vote != 0 {
+                server
+                    .set_rating(page_id, voter, vote)
+                    .await
+                    .expect("Unable to set rating");
+            }
+        }
+    }
+
+    // Check orderings
+    macro_rules! check_order {
+        ($order:expr, $expected:expr) => {{
+            let expected: &[&str] = &$expected;
+            let pages = server
+                .get_pages_with_tags(wiki_id, &["scp"], $order)
+                .await
+                .expect("Unable to get pages from tags");
+
+            let slugs = pages.iter().map(|page| page.slug()).collect::<Vec<_>>();
+            assert_eq!(slugs, expected, "Page order doesn't match for {:?}", $order);
+        }};
+    }
+
+    check_order!(PageOrder::SlugAsc, ["scp-001", "scp-002", "scp-003"]);
+    check_order!(PageOrder::TitleAsc, ["scp-002", "scp-003", "scp-001"]);
+    check_order!(PageOrder::CreatedDesc, ["scp-003", "scp-002", "scp-001"]);
+    check_order!(PageOrder::ScoreDesc, ["scp-002", "scp-003", "scp-001"]);
+    check_order!(PageOrder::default(), ["scp-001", "scp-002", "scp-003"]);
+}
+
+#[tokio::test]
+async fn tag_change() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        () => {
+            PageCommit {
+                wiki_id,
+                slug: "tag-change",
+                message: "tag change test",
+                user: &user,
+            }
+        };
+    }
+
+    let (_page_id, create_revision_id) = server
+        .create_page(commit!(), "contents", &[], "Tag Change", "")
+        .await
+        .expect("Unable to create page");
+
+    let first_revision_id = server
+        .set_page_tags(commit!(), &["scp", "keter", "_image"])
+        .await
+        .expect("Unable to set page tags")
+        .expect("No tag revision created");
+
+    let second_revision_id = server
+        .set_page_tags(commit!(), &["scp", "euclid", "artifact"])
+        .await
+        .expect("Unable to set page tags")
+        .expect("No tag revision created");
+
+    let change = server
+        .get_tag_change(first_revision_id)
+        .await
+        .expect("Unable to get tag change")
+        .expect("No tag change found");
+
+    assert_eq!(change.revision_id(), first_revision_id);
+    assert_eq!(change.added_tags(), ["_image", "keter", "scp"]);
+    assert!(change.removed_tags().is_empty());
+
+    let change = server
+        .get_tag_change(second_revision_id)
+        .await
+        .expect("Unable to get tag change")
+        .expect("No tag change found");
+
+    assert_eq!(change.revision_id(), second_revision_id);
+    assert_eq!(change.added_tags(), ["artifact", "euclid"]);
+    assert_eq!(change.removed_tags(), ["_image", "keter"]);
+
+    let change = server
+        .get_tag_change(create_revision_id)
+        .await
+        .expect("Unable to get tag change");
+
+    assert!(change.is_none(), "Tag change found for non-tag revision");
+}
+
+#[tokio::test]
+async fn tag_counts() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "tag counts",
+                user: &user,
+            }
+        };
+    }
+
+    let pages: &[(&str, &[&str])] = &[
+        ("page-a", &["scp", "keter"]),
+        ("page-b", &["scp", "euclid"]),
+        ("page-c", &["tale"]),
+        ("page-d", &["tale", "humor"]),
+        ("page-e", &[]),
+        ("page-f", &["scp", "safe"]),
+    ];
+
+    for &(slug, tags) in pages {
+        server
+            .create_page(commit!(slug), "Contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+
+        server
+            .set_page_tags(commit!(slug), tags)
+            .await
+            .expect("Unable to set page tags");
+    }
+
+    // Deleted pages are never counted
+    server
+        .remove_page(commit!("page-f"))
+        .await
+        .expect("Unable to remove page");
+
+    let tag_counts: &[(&[&str], i64)] = &[
+        (&[], 0),
+        (&["scp"], 2),
+        (&["tale"], 2),
+        (&["scp", "keter"], 1),
+        (&["euclid", "safe"], 0),
+        (&["safe"], 0),
+        (&["humor"], 1),
+        (&["nonexistent"], 0),
+    ];
+
+    for &(tags, expected) in tag_counts {
+        let pages = server
+            .get_pages_with_tags(wiki_id, tags, PageOrder::default())
+            .await
+            .expect("Unable to get pages from tags");
+
+        let count = server
+            .count_pages_with_tags(wiki_id, tags)
+            .await
+            .expect("Unable to count pages from tags");
+
+        assert_eq!(count, expected, "Count mismatch for {:?}", tags);
+        assert_eq!(
+            pages.len(),
+            expected as usize,
+            "Page mismatch for {:?}",
+            tags
+        );
+    }
+}
+
+#[tokio::test]
+async fn tag_query() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "tag query",
+                user: &user,
+            }
+        };
+    }
+
+    let pages: &[(&str, &[&str])] = &[
+        ("page-a", &["scp", "keter"]),
+        ("page-b", &["scp", "euclid"]),
+        ("page-c", &["tale"]),
+        ("page-d", &["tale", "humor"]),
+        ("page-e", &[]),
+        ("page-f", &["scp", "safe"]),
+    ];
+
+    for &(slug, tags) in pages {
+        server
+            .create_page(commit!(slug), "Contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+
+        server
+            .set_page_tags(commit!(slug), tags)
+            .await
+            .expect("Unable to set page tags");
+    }
+
+    // Deleted pages are never returned
+    server
+        .remove_page(commit!("page-f"))
+        .await
+        .expect("Unable to remove page");
+
+    macro_rules! check {
+        ($required:expr, $excluded:expr, $any_of:expr, $expected:expr) => {{
+            let pages = server
+                .get_pages_by_tag_query(wiki_id, $required, $excluded, $any_of)
+                .await
+                .expect("Unable to get pages by tag query");
+
+            let slugs = pages.iter().map(|page| page.slug()).collect::<Vec<_>>();
+            let expected: &[&str] = $expected;
+            assert_eq!(slugs, expected);
+        }};
+    }
+
+    // Each list on its own
+    check!(
+        &[],
+        &[],
+        &[],
+        &["page-a", "page-b", "page-c", "page-d", "page-e"]
+    );
+    check!(&["scp"], &[], &[], &["page-a", "page-b"]);
+    check!(&[], &["scp"], &[], &["page-c", "page-d", "page-e"]);
+    check!(&[], &[], &["keter", "humor"], &["page-a", "page-d"]);
+
+    // Combinations
+    check!(&["scp"], &["keter"], &[], &["page-b"]);
+    check!(
+        &[],
+        &["tale"],
+        &["keter", "humor", "euclid"],
+        &["page-a", "page-b"]
+    );
+    check!(&["scp"], &[], &["keter", "humor"], &["page-a"]);
+    check!(&["scp"], &["euclid"], &["keter", "euclid"], &["page-a"]);
+    check!(&["tale"], &["tale"], &[], &[]);
+    check!(&["tale"], &["scp"], &["humor", "keter"], &["page-d"]);
+}
+
+#[tokio::test]
+async fn normalize_tags() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "tag normalization",
+                user: &user,
+            }
+        };
+    }
+
+    let pages: &[(&str, &[&str], &[&str])] = &[
+        ("page-a", &["SCP", "Keter"], &["keter", "scp"]),
+        ("page-b", &["scp", "euclid"], &["euclid", "scp"]),
+        ("page-c", &["Tale", "tale", " humor "], &["humor", "tale"]),
+        ("page-d", &[], &[]),
+    ];
+
+    for &(slug, tags, _) in pages {
+        server
+            .create_page(commit!(slug), "Contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+
+        server
+            .set_page_tags(commit!(slug), tags)
+            .await
+            .expect("Unable to set page tags");
+    }
+
+    let updated = server
+        .normalize_all_tags(wiki_id, &user)
+        .await
+        .expect("Unable to normalize tags");
+
+    assert_eq!(updated, 2, "Unexpected number of pages updated");
+
+    for &(slug, _, expected) in pages {
+        let (page, _, _) = server
+            .get_page(wiki_id, slug)
+            .await
+            .expect("Unable to get page")
+            .expect("No page found");
+
+        assert_eq!(page.tags(), expected, "Tags for {} don't match", slug);
+        assert!(
+            page.tags()
+                .iter()
+                .all(|tag| tag.chars().all(|c| !c.is_uppercase())),
+            "Tags for {} not lowercased",
+            slug,
+        );
+    }
+
+    // Already normalized, nothing to do
+    let updated = server
+        .normalize_all_tags(wiki_id, &user)
+        .await
+        .expect("Unable to normalize tags");
+
+    assert_eq!(updated, 0, "Pages updated on second run");
+}
+
+#[tokio::test]
+async fn tags_last_used() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "tags test",
+                user: &user,
+            }
+        };
+    }
+
+    for slug in &["old-page", "new-page"] {
+        server
+            .create_page(commit!(slug), "contents", &[], "", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    server
+        .set_page_tags(commit!("old-page"), &["stale", "shared"])
+        .await
+        .expect("Unable to set page tags");
+
+    let before = Utc::now();
+
+    server
+        .set_page_tags(commit!("new-page"), &["fresh", "shared"])
+        .await
+        .expect("Unable to set page tags");
+
+    let tags = server
+        .get_tags_last_used(wiki_id)
+        .await
+        .expect("Unable to get tags");
+
+    let names: Vec<_> = tags.iter().map(|(tag, _)| tag.as_str()).collect();
+    assert_eq!(names, vec!["stale", "fresh", "shared"]);
+
+    // Tags on the recently edited page report that edit
+    for (tag, last_used) in &tags[1..] {
+        assert!(
+            *last_used >= before - chrono::Duration::seconds(1),
+            "Tag '{}' was not recently used",
+            tag,
+        );
+    }
+
+    assert!(tags[0].1 <= tags[1].1);
+    assert_eq!(tags[1].1, tags[2].1);
+}
+
+#[tokio::test]
+async fn tag_history() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        () => {
+            PageCommit {
+                wiki_id,
+                slug: "tag-history",
+                message: "tag history test",
+                user: &user,
+            }
+        };
+    }
+
+    let (page_id, _) = server
+        .create_page(commit!(), "contents", &[], "Tag History", "")
+        .await
+        .expect("Unable to create page");
+
+    let history = server
+        .get_page_tag_history(page_id)
+        .await
+        .expect("Unable to get tag history");
+
+    assert!(history.is_empty());
+
+    let first_revision_id = server
+        .set_page_tags(commit!(), &["scp", "keter"])
+        .await
+        .expect("Unable to set page tags")
+        .expect("No tag revision created");
+
+    // Non-tag revisions don't appear
+    server
+        .edit_page(commit!(), Some("new contents"), None, None, false)
+        .await
+        .expect("Unable to edit page");
+
+    let second_revision_id = server
+        .set_page_tags(commit!(), &["scp", "euclid"])
+        .await
+        .expect("Unable to set page tags")
+        .expect("No tag revision created");
+
+    let history = server
+        .get_page_tag_history(page_id)
+        .await
+        .expect("Unable to get tag history");
+
+    assert_eq!(history.len(), 2);
+
+    assert_eq!(history[0].revision_id(), first_revision_id);
+    assert_eq!(history[0].user_id(), user.id());
+    assert_eq!(history[0].added_tags(), ["keter", "scp"]);
+    assert!(history[0].removed_tags().is_empty());
+
+    assert_eq!(history[1].revision_id(), second_revision_id);
+    assert_eq!(history[1].added_tags(), ["euclid"]);
+    assert_eq!(history[1].removed_tags(), ["keter"]);
+    assert!(history[0].created_at() <= history[1].created_at());
+}
+
+#[tokio::test]
+async fn remove_by_tag() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let other_user_id = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "spam cleanup test",
+                user: &user,
+            }
+        };
+    }
+
+    let mut page_ids = Vec::new();
+    for &(slug, tags) in &[
+        ("spam-a", &["_spam", "tale"][..]),
+        ("spam-b", &["_spam"][..]),
+        ("not-spam", &["tale"][..]),
+    ] {
+        let (page_id, _) = server
+            .create_page(commit!(slug), "contents", &[], "", "")
+            .await
+            .expect("Unable to create page");
+
+        server
+            .set_page_tags(commit!(slug), tags)
+            .await
+            .expect("Unable to set page tags");
+
+        page_ids.push(page_id);
+    }
+
+    // Locked by another user, nothing removed
+    server
+        .create_page_lock(wiki_id, "spam-b", other_user_id)
+        .await
+        .expect("Unable to create page lock");
+
+    let error = server
+        .remove_pages_with_tag(wiki_id, "_spam", &user, "Remove spam", false)
+        .await
+        .expect_err("Removed locked page");
+
+    match error {
+        Error::PageLocked(id) if id == other_user_id => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    assert!(server.check_page(wiki_id, "spam-a").await.unwrap());
+    assert!(server.check_page(wiki_id, "spam-b").await.unwrap());
+
+    // Bypass the lock
+    let removed = server
+        .remove_pages_with_tag(wiki_id, "_spam", &user, "Remove spam", true)
+        .await
+        .expect("Unable to remove pages by tag");
+
+    assert_eq!(removed, &page_ids[..2]);
+
+    for page_id in &page_ids[..2] {
+        let (page, _, _) = server
+            .get_page_by_id(*page_id)
+            .await
+            .expect("Unable to get page")
+            .expect("No page found");
+
+        assert!(!page.exists(), "Page {} not deleted", page.slug());
+    }
+
+    assert!(!server.check_page(wiki_id, "spam-a").await.unwrap());
+    assert!(!server.check_page(wiki_id, "spam-b").await.unwrap());
+    assert!(server.check_page(wiki_id, "not-spam").await.unwrap());
+
+    // Nothing left to remove
+    let removed = server
+        .remove_pages_with_tag(wiki_id, "_spam", &user, "Remove spam", false)
+        .await
+        .expect("Unable to remove pages by tag");
+
+    assert!(removed.is_empty());
 }
@@ -19,6 +19,9 @@
  */
 
 use super::prelude::*;
+use crate::package::user::UserStats;
+use crate::utils::rand_alphanum;
+use chrono::prelude::*;
 
 macro_rules! check_err {
     ($error:expr, $expected:pat) => {
@@ -253,3 +256,493 @@ async fn users_conflict() {
         .await
         .expect("Unable to edit user initially");
 }
+
+#[tokio::test]
+async fn users_canonical_email() {
+    let server = &create_server_with(|config| config.canonicalize_emails = true).await;
+
+    let local = {
+        let mut chars = rand_alphanum(12);
+        chars.make_ascii_lowercase();
+        chars
+    };
+
+    let email = format!("{}@gmail.com", local);
+    let name = format!("user_{}", local);
+    server
+        .create_user(&name, &email, "defaultpasswordhere2")
+        .await
+        .expect("Unable to create user");
+
+    macro_rules! check_conflict {
+        ($name:expr, $email:expr) => {{
+            let name = format!("{}_{}", $name, local);
+            let error = server
+                .create_user(&name, &$email, "defaultpasswordhere2")
+                .await
+                .expect_err("Created user with equivalent email");
+
+            check_err!(error, Error::UserEmailExists);
+        }};
+    }
+
+    check_conflict!("plus", format!("{}+deepwell@gmail.com", local));
+    check_conflict!("case", format!("{}@GMAIL.com", local.to_ascii_uppercase()));
+    check_conflict!(
+        "dots",
+        format!("{}.{}@googlemail.com", &local[..4], &local[4..])
+    );
+
+    // Without canonicalization these are distinct
+    let server = &create_server().await;
+    let email = format!("{}+other@gmail.com", local);
+    let name = format!("other_{}", local);
+    server
+        .create_user(&name, &email, "defaultpasswordhere2")
+        .await
+        .expect("Unable to create user");
+}
+
+#[tokio::test]
+async fn users_profile_validation() {
+    let server = &create_server().await;
+    let user_id = create_user(server).await;
+
+    macro_rules! check_invalid {
+        ($metadata:expr, $field:tt) => {{
+            let error = server
+                .edit_user(user_id, $metadata)
+                .await
+                .expect_err("Invalid profile field accepted");
+
+            check_err!(error, Error::InvalidProfileField($field));
+        }};
+    }
+
+    let about = "A".repeat(5000);
+    check_invalid!(
+        UserMetadata {
+            about: Some(&about),
+            ..Default::default()
+        },
+        "about"
+    );
+
+    check_invalid!(
+        UserMetadata {
+            website: Some("not a url"),
+            ..Default::default()
+        },
+        "website"
+    );
+
+    check_invalid!(
+        UserMetadata {
+            user_page: Some("ftp://www.scp-wiki.net/"),
+            ..Default::default()
+        },
+        "user_page"
+    );
+
+    let metadata = UserMetadata {
+        website: Some("  https://example.com/about  "),
+        location: Some("   \t "),
+        ..Default::default()
+    };
+
+    server
+        .edit_user(user_id, metadata)
+        .await
+        .expect("Unable to edit user");
+
+    let user = server
+        .get_user_from_id(user_id)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    assert_eq!(user.website(), "https://example.com/about");
+    assert_eq!(user.location(), "");
+}
+
+#[tokio::test]
+async fn users_anonymize() {
+    let server = &create_server().await;
+    let started = Utc::now();
+
+    let name = {
+        let mut chars = rand_alphanum(16);
+        chars.insert_str(0, "user_");
+        chars
+    };
+    let email = format!("{}@example.com", name);
+
+    // Attempt from before the account existed
+    server
+        .try_login(&name, "blackmoonhowls", Some("192.0.2.1"))
+        .await
+        .expect_err("Logged in as nonexistent user");
+
+    let user_id = server
+        .create_user(&name, &email, "blackmoonhowls")
+        .await
+        .expect("Unable to create user");
+
+    let wiki_id = create_wiki(server).await;
+
+    let metadata = UserMetadata {
+        about: Some("I write tales"),
+        location: Some("Site-19"),
+        ..Default::default()
+    };
+
+    server
+        .edit_user(user_id, metadata)
+        .await
+        .expect("Unable to edit user");
+
+    let user = server
+        .get_user_from_id(user_id)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "my-tale",
+        message: "new tale",
+        user: &user,
+    };
+
+    let (page_id, _revision_id) = server
+        .create_page(commit, "it was a dark and stormy night", &[], "My Tale", "")
+        .await
+        .expect("Unable to create page");
+
+    let session = server
+        .try_login_id(user_id, "blackmoonhowls", None)
+        .await
+        .expect("Unable to login");
+
+    server
+        .try_login(&email, "wrongpassword", Some("192.0.2.1"))
+        .await
+        .expect_err("Logged in with wrong password");
+
+    server
+        .new_verification_token(user_id)
+        .await
+        .expect("Unable to create verification token");
+
+    server
+        .anonymize_user(user_id)
+        .await
+        .expect("Unable to anonymize user");
+
+    // Personal information is gone
+    let user = server
+        .get_user_from_id(user_id)
+        .await
+        .expect("Unable to get user")
+        .expect("Anonymized user not found");
+
+    assert_eq!(user.name(), format!("deleted-user-{}", user_id));
+    assert_ne!(user.email(), email);
+    assert_eq!(user.about(), "");
+    assert_eq!(user.location(), "");
+    assert_eq!(user.is_active(), false);
+
+    let user = server
+        .get_user_from_name(&name)
+        .await
+        .expect("Unable to get user");
+
+    assert!(user.is_none());
+
+    let user = server
+        .get_user_from_email(&email)
+        .await
+        .expect("Unable to get user");
+
+    assert!(user.is_none());
+
+    let error = server
+        .check_session(session.session_id(), user_id)
+        .await
+        .expect_err("Session still valid");
+
+    check_err!(error, Error::InvalidSession);
+
+    // Password and login attempt details are gone
+    let error = server
+        .try_login_id(user_id, "blackmoonhowls", None)
+        .await
+        .expect_err("Logged in as anonymized user");
+
+    check_err!(error, Error::AuthenticationFailed);
+
+    let attempts = server
+        .get_login_attempts(user_id, started, true)
+        .await
+        .expect("Unable to get login attempts");
+
+    assert!(attempts.len() >= 2, "Login attempts were removed");
+
+    for attempt in &attempts {
+        assert_eq!(attempt.username_or_email(), None);
+        assert_eq!(attempt.remote_address(), None);
+    }
+
+    let attempts = server
+        .get_all_login_attempts(started, true)
+        .await
+        .expect("Unable to get login attempts");
+
+    assert!(
+        attempts
+            .iter()
+            .all(|attempt| attempt.username_or_email() != Some(name.as_str())),
+        "Login attempt by name not scrubbed",
+    );
+
+    // Content is preserved
+    let (page, _, _) = server
+        .get_page_by_id(page_id)
+        .await
+        .expect("Unable to get page")
+        .expect("Authored page not found");
+
+    assert!(page.exists());
+
+    let authors = server
+        .get_page_authors(Left(page_id))
+        .await
+        .expect("Unable to get authors");
+
+    assert_eq!(authors.len(), 1);
+    assert_eq!(authors[0].user_id(), user_id);
+}
+
+#[tokio::test]
+async fn users_resolve_names() {
+    let server = &create_server().await;
+    let (user_id_1, name_1, _) = create_user_full(server, "defaultpasswordhere2").await;
+    let (user_id_2, name_2, _) = create_user_full(server, "defaultpasswordhere2").await;
+    let name_2_upper = name_2.to_uppercase();
+
+    let names = [
+        name_1.as_str(),
+        "nonexistent-user-name",
+        name_2_upper.as_str(),
+        "",
+    ];
+
+    let ids = server
+        .resolve_usernames(&names)
+        .await
+        .expect("Unable to resolve usernames");
+
+    assert_eq!(ids.len(), 2);
+    assert_eq!(ids.get(&name_1), Some(&user_id_1));
+    assert_eq!(ids.get(&name_2_upper), Some(&user_id_2));
+    assert_eq!(ids.get("nonexistent-user-name"), None);
+}
+
+#[tokio::test]
+async fn users_default() {
+    let server = &create_server().await;
+
+    let user = server
+        .default_user()
+        .await
+        .expect("Unable to get default user");
+
+    assert_eq!(user.name(), "unknown");
+
+    let name = {
+        let mut chars = rand_alphanum(16);
+        chars.insert_str(0, "default_");
+        chars
+    };
+
+    let server = &create_server_with(|config| config.default_user_name = Some(name.as_str())).await;
+
+    let user = server
+        .default_user()
+        .await
+        .expect("Unable to get default user");
+
+    assert_eq!(user.name(), name);
+}
+
+#[tokio::test]
+async fn users_stats() {
+    let server = &create_server().await;
+    let wiki_id = create_wiki(server).await;
+
+    let user_id = create_user(server).await;
+    let user = server
+        .get_user_from_id(user_id)
+        .await
+        .expect("Unable to get user")
+        .expect("Created user not found");
+
+    let other_user = server.default_user().await.expect("Unable to get user");
+
+    let voter_1 = create_user(server).await;
+    let voter_2 = create_user(server).await;
+
+    // No activity
+    let (_, stats) = server
+        .get_user_with_stats(user_id)
+        .await
+        .expect("Unable to get user stats")
+        .expect("Created user not found");
+
+    assert_eq!(stats, UserStats::default());
+
+    // Create pages
+    let mut page_ids = Vec::new();
+    for (slug, author) in &[
+        ("page-a", &user),
+        ("page-b", &user),
+        ("page-c", &other_user),
+    ] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: author,
+        };
+
+        let (page_id, _) = server
+            .create_page(commit, "contents", &[], "Page", "")
+            .await
+            .expect("Unable to create page");
+
+        page_ids.push(page_id);
+    }
+
+    // Edit pages
+    for slug in &["page-a", "page-b", "page-c"] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "edit",
+            user: &user,
+        };
+
+        server
+            .edit_page(commit, Some("new contents"), None, None, false)
+            .await
+            .expect("Unable to edit page");
+    }
+
+    // Vote on pages
+    let votes = [
+        (page_ids[0], voter_1, 1),
+        (page_ids[1], voter_1, 1),
+        (page_ids[1], voter_2, -1),
+        (page_ids[0], voter_2, 1),
+        (page_ids[2], voter_1, 1),
+    ];
+
+    for &(page_id, voter_id, rating) in &votes {
+        server
+            .set_rating(page_id, voter_id, rating)
+            .await
+            .expect("Unable to set rating");
+    }
+
+    let (user, stats) = server
+        .get_user_with_stats(user_id)
+        .await
+        .expect("Unable to get user stats")
+        .expect("Created user not found");
+
+    assert_eq!(user.id(), user_id);
+    assert_eq!(stats.authored_pages, 2, "Authored page count doesn't match");
+    assert_eq!(stats.total_edits, 5, "Edit count doesn't match");
+    assert_eq!(stats.karma, 2, "Karma doesn't match");
+
+    // Missing user
+    let result = server
+        .get_user_with_stats(UserId::from_raw(i64::max_value()))
+        .await
+        .expect("Unable to get user stats");
+
+    assert!(result.is_none(), "Stats returned for missing user");
+}
+
+#[tokio::test]
+async fn users_created_between() {
+    let server = &create_server().await;
+
+    let mut users = Vec::new();
+    for _ in 0..3 {
+        let user_id = create_user(server).await;
+        let user = server
+            .get_user_from_id(user_id)
+            .await
+            .expect("Unable to get user")
+            .expect("Created user not found");
+
+        users.push(user);
+    }
+
+    let ids = |users: &[User]| users.iter().map(|user| user.id()).collect::<Vec<_>>();
+
+    // Other tests may create users concurrently, so only check ours
+    let from = users[0].created_at();
+    let to = users[2].created_at();
+
+    let found = server
+        .get_users_created_between(from, to, true, 100, 0)
+        .await
+        .expect("Unable to get users created between");
+
+    let found_ids = ids(&found);
+    assert!(found_ids.contains(&users[0].id()));
+    assert!(found_ids.contains(&users[1].id()));
+    assert!(!found_ids.contains(&users[2].id()));
+
+    for pair in found.windows(2) {
+        assert!(
+            pair[0].created_at() <= pair[1].created_at(),
+            "Users not in order"
+        );
+    }
+
+    // Exclude inactive users
+    server
+        .mark_user_inactive(users[1].id())
+        .await
+        .expect("Unable to mark user inactive");
+
+    let found = server
+        .get_users_created_between(from, to, false, 100, 0)
+        .await
+        .expect("Unable to get users created between");
+
+    let found_ids = ids(&found);
+    assert!(found_ids.contains(&users[0].id()));
+    assert!(!found_ids.contains(&users[1].id()));
+
+    let found = server
+        .get_users_created_between(from, to, true, 100, 0)
+        .await
+        .expect("Unable to get users created between");
+
+    assert!(ids(&found).contains(&users[1].id()));
+
+    // Limit can't exceed the maximum batch size
+    let error = server
+        .get_users_created_between(from, to, true, 101, 0)
+        .await
+        .expect_err("Allowed limit over maximum batch size");
+
+    match error {
+        Error::RequestTooLarge(101, 100) => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
@@ -75,3 +75,104 @@ async fn verify() {
 
     assert_eq!(user.is_verified(), true, "User is not verified after token");
 }
+
+#[tokio::test]
+async fn verify_email_change() {
+    let server = &create_server().await;
+    let password = "blackmoonhowls";
+    let (user_id, username, email) = create_user_full(server, password).await;
+    let (_, _, other_email) = create_user_full(server, password).await;
+    let new_email = format!("new-{}@example.net", username.to_ascii_lowercase());
+
+    server
+        .verify_user(user_id)
+        .await
+        .expect("Unable to verify user directly");
+
+    let old_token = server
+        .new_verification_token(user_id)
+        .await
+        .expect("Unable to generate verification token");
+
+    macro_rules! get_user {
+        () => {
+            server
+                .get_user_from_id(user_id)
+                .await
+                .expect("Couldn't find user")
+                .expect("Created user not found")
+        };
+    }
+
+    // Failed change leaves the old email in place
+    let error = server
+        .change_user_email(user_id, &other_email)
+        .await
+        .expect_err("Changed email to existing address");
+
+    match error {
+        Error::UserEmailExists => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    let user = get_user!();
+    assert_eq!(user.email(), email.to_ascii_lowercase());
+    assert_eq!(
+        user.is_verified(),
+        true,
+        "User unverified after failed change"
+    );
+
+    server
+        .try_login(&email, password, None)
+        .await
+        .expect("Unable to login with old email");
+
+    // Change email
+    let token = server
+        .change_user_email(user_id, &new_email)
+        .await
+        .expect("Unable to change email");
+
+    let user = get_user!();
+    assert_eq!(user.email(), new_email);
+    assert_eq!(
+        user.is_verified(),
+        false,
+        "User verified after email change"
+    );
+
+    let error = server
+        .try_login(&email, password, None)
+        .await
+        .expect_err("Logged in with old email");
+
+    match error {
+        Error::AuthenticationFailed => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    server
+        .try_login(&new_email, password, None)
+        .await
+        .expect("Unable to login with new email");
+
+    // Only the new token is valid
+    let error = server
+        .verify_token(&old_token)
+        .await
+        .expect_err("Verified with token for old email");
+
+    match error {
+        Error::InvalidVerificationToken => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    server
+        .verify_token(&token)
+        .await
+        .expect("Unable to verify user with token");
+
+    let user = get_user!();
+    assert_eq!(user.is_verified(), true, "User is not verified after token");
+}
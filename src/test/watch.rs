@@ -0,0 +1,100 @@
+/*
+ * test/watch.rs
+ *
+ * deepwell - Database management and migrations service
+ * Copyright (C) 2019-2020 Ammon Smith
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+
+#[tokio::test]
+async fn watch() {
+    let server = &create_server().await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let user_id_1 = create_user(server).await;
+    let user_id_2 = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "watched-page",
+        message: "new page",
+        user: &user,
+    };
+
+    let (page_id, _revision_id) = server
+        .create_page(commit, "contents", &[], "Watched Page", "")
+        .await
+        .expect("Unable to create page");
+
+    let watchers = server
+        .get_watchers(page_id)
+        .await
+        .expect("Unable to get watchers");
+
+    assert!(watchers.is_empty());
+
+    let added = server
+        .watch_page(user_id_2, page_id)
+        .await
+        .expect("Unable to watch page");
+
+    assert_eq!(added, true);
+
+    let added = server
+        .watch_page(user_id_1, page_id)
+        .await
+        .expect("Unable to watch page");
+
+    assert_eq!(added, true);
+
+    let added = server
+        .watch_page(user_id_1, page_id)
+        .await
+        .expect("Unable to watch page");
+
+    assert_eq!(added, false);
+
+    let watchers = server
+        .get_watchers(page_id)
+        .await
+        .expect("Unable to get watchers");
+
+    assert_eq!(watchers, vec![user_id_1, user_id_2]);
+
+    let removed = server
+        .unwatch_page(user_id_1, page_id)
+        .await
+        .expect("Unable to unwatch page");
+
+    assert_eq!(removed, true);
+
+    let removed = server
+        .unwatch_page(user_id_1, page_id)
+        .await
+        .expect("Unable to unwatch page");
+
+    assert_eq!(removed, false);
+
+    let watchers = server
+        .get_watchers(page_id)
+        .await
+        .expect("Unable to get watchers");
+
+    assert_eq!(watchers, vec![user_id_2]);
+}
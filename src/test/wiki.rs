@@ -19,6 +19,8 @@
  */
 
 use super::prelude::*;
+use chrono::prelude::*;
+use std::fs;
 
 #[tokio::test]
 async fn wikis() {
@@ -73,3 +75,684 @@ async fn wikis() {
         .await
         .expect("Unable to change domain");
 }
+
+#[tokio::test]
+async fn wikis_batch() {
+    let server = &create_server().await;
+    let wiki_id_1 = create_wiki(server).await;
+    let wiki_id_2 = create_wiki(server).await;
+    let invalid = WikiId::from_raw(-1);
+
+    let wikis = server
+        .get_wikis_by_ids(&[wiki_id_2, invalid, wiki_id_1])
+        .await
+        .expect("Unable to get multiple wikis");
+
+    let ids = wikis
+        .iter()
+        .map(|wiki| wiki.as_ref().map(|wiki| wiki.id()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(ids, vec![Some(wiki_id_2), None, Some(wiki_id_1)]);
+
+    let error = server
+        .get_wikis_by_ids(&vec![invalid; 101])
+        .await
+        .expect_err("Able to fetch over 100 wikis");
+
+    match error {
+        Error::RequestTooLarge(101, 100) => (),
+        _ => panic!("Error doesn't match"),
+    }
+}
+
+#[tokio::test]
+async fn batch_size_limit() {
+    let server = &create_server_with(|config| config.max_batch_size = 3).await;
+    let user_id = create_user(server).await;
+    let wiki_id = create_wiki(server).await;
+
+    macro_rules! check_limit {
+        ($result:expr, $len:expr) => {
+            match $result.expect_err("Able to exceed batch size") {
+                Error::RequestTooLarge($len, 3) => (),
+                _ => panic!("Error doesn't match"),
+            }
+        };
+    }
+
+    // At the limit
+    server
+        .get_users_from_ids(&[user_id; 3])
+        .await
+        .expect("Unable to get users at batch limit");
+
+    server
+        .get_wikis_by_ids(&[wiki_id; 3])
+        .await
+        .expect("Unable to get wikis at batch limit");
+
+    server
+        .get_user_ratings(user_id, &[PageId::from_raw(-1); 3])
+        .await
+        .expect("Unable to get ratings at batch limit");
+
+    // Over the limit
+    check_limit!(server.get_users_from_ids(&[user_id; 4]).await, 4);
+    check_limit!(server.get_wikis_by_ids(&[wiki_id; 5]).await, 5);
+    check_limit!(
+        server
+            .get_user_ratings(user_id, &[PageId::from_raw(-1); 4])
+            .await,
+        4
+    );
+    check_limit!(
+        server
+            .get_page_history_with_authors(PageId::from_raw(-1), 6, 0)
+            .await,
+        6
+    );
+    check_limit!(server.get_pages(wiki_id, &["page"; 4]).await, 4);
+    check_limit!(
+        server
+            .increment_page_views_many(&[PageId::from_raw(-1); 4])
+            .await,
+        4
+    );
+    check_limit!(
+        server
+            .check_sessions(&[(SessionId::from_raw(-1), user_id); 4])
+            .await,
+        4
+    );
+    check_limit!(server.get_last_logins(&[user_id; 4]).await, 4);
+    check_limit!(server.get_wiki_activity(wiki_id, 4, 0).await, 4);
+    check_limit!(
+        server
+            .get_page_history(PageId::from_raw(-1), 5, 0, true)
+            .await,
+        5
+    );
+    check_limit!(
+        server
+            .get_page_history(PageId::from_raw(-1), 4, 0, false)
+            .await,
+        4
+    );
+
+    let now = Utc::now();
+    check_limit!(
+        server.get_users_created_between(now, now, true, 4, 0).await,
+        4
+    );
+}
+
+#[tokio::test]
+async fn orphaned_repositories() {
+    let server = &create_server().await;
+    let (_, slug) = create_wiki_full(server).await;
+
+    let orphans = server
+        .find_orphaned_repositories()
+        .await
+        .expect("Unable to find orphaned repositories");
+
+    assert!(orphans.is_empty(), "Orphaned repositories found");
+
+    // Create stray directory
+    let stray = server.revisions_dir().join("wiki-deleted");
+    fs::create_dir(&stray).expect("Unable to create stray directory");
+
+    let orphans = server
+        .find_orphaned_repositories()
+        .await
+        .expect("Unable to find orphaned repositories");
+
+    assert_eq!(orphans, vec![stray], "Orphaned repositories don't match");
+    assert!(
+        !orphans.contains(&server.revisions_dir().join(&slug)),
+        "Wiki repository reported as orphaned",
+    );
+}
+
+#[tokio::test]
+async fn populated_wiki() {
+    let server = &create_server().await;
+    let (wiki_id, page_ids) = create_populated_wiki(server, 5).await;
+
+    assert_eq!(page_ids.len(), 5);
+
+    for (i, &page_id) in page_ids.iter().enumerate() {
+        let (page, votes, _) = server
+            .get_page_by_id(page_id)
+            .await
+            .expect("Unable to get page")
+            .expect("Populated page not found");
+
+        assert_eq!(page.wiki_id(), wiki_id);
+        assert_eq!(page.slug(), format!("page-{}", i));
+        assert_eq!(votes.count(), 1);
+    }
+}
+
+#[tokio::test]
+async fn reload_store() {
+    let server = &create_server().await;
+    let (wiki_id, _) = create_populated_wiki(server, 2).await;
+
+    let before = server
+        .get_page_contents(wiki_id, "page-0")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Populated page not found");
+
+    // Simulate a restart, where no stores are loaded
+    server.unload_wiki_store(wiki_id).await;
+
+    let after = server
+        .get_page_contents(wiki_id, "page-0")
+        .await
+        .expect("Unable to get page contents after unloading store")
+        .expect("Populated page not found after unloading store");
+
+    assert_eq!(before, after);
+
+    // Writes work too once reloaded
+    server.unload_wiki_store(wiki_id).await;
+
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page-1",
+        message: "edit",
+        user: &user,
+    };
+
+    server
+        .edit_page(commit, Some("Edited contents"), None, None, false)
+        .await
+        .expect("Unable to edit page after unloading store");
+
+    let contents = server
+        .get_page_contents(wiki_id, "page-1")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Edited page not found");
+
+    assert_eq!(contents, "Edited contents");
+}
+
+#[tokio::test]
+async fn wiki_info() {
+    let server = &create_server().await;
+    let (wiki_id, slug) = create_wiki_full(server).await;
+    let user = server.default_user().await.expect("Unable to get user");
+
+    let info = server
+        .get_wiki_info(wiki_id)
+        .await
+        .expect("Unable to get wiki info")
+        .expect("Wiki info not found");
+
+    assert_eq!(info.slug, slug);
+    assert_eq!(info.page_count, 0);
+    assert_eq!(info.first_page, None);
+
+    for slug in &["main", "about", "scp-series"] {
+        let commit = PageCommit {
+            wiki_id,
+            slug,
+            message: "new page",
+            user: &user,
+        };
+
+        server
+            .create_page(commit, "contents", &[], "", "")
+            .await
+            .expect("Unable to create page");
+    }
+
+    let (wiki, _) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    let info = server
+        .get_wiki_info(wiki_id)
+        .await
+        .expect("Unable to get wiki info")
+        .expect("Wiki info not found");
+
+    assert_eq!(info.name, wiki.name());
+    assert_eq!(info.slug, wiki.slug());
+    assert_eq!(info.domain, wiki.domain());
+    assert_eq!(info.created_at, wiki.created_at());
+    assert_eq!(info.page_count, 3);
+
+    let first_page = info.first_page.expect("No first page");
+    assert_eq!(first_page.slug(), "main");
+
+    let info = server
+        .get_wiki_info(WikiId::from_raw(i64::max_value()))
+        .await
+        .expect("Unable to get wiki info");
+
+    assert_eq!(info, None);
+}
+
+#[tokio::test]
+async fn deactivate_wiki() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let (wiki_id, slug) = create_wiki_full(server).await;
+
+    macro_rules! commit {
+        ($slug:expr) => {
+            PageCommit {
+                wiki_id,
+                slug: $slug,
+                message: "deactivation test",
+                user: &user,
+            }
+        };
+    }
+
+    macro_rules! check_err {
+        ($result:expr, $error:pat) => {
+            match $result.expect_err("Operation succeeded on inactive wiki") {
+                $error => (),
+                _ => panic!("Error doesn't match"),
+            }
+        };
+    }
+
+    let (page_id, revision_id) = server
+        .create_page(commit!("page"), "Contents", &[], "Page", "")
+        .await
+        .expect("Unable to create page");
+
+    let hash = server
+        .get_page_current_hash(wiki_id, "page")
+        .await
+        .expect("Unable to get page hash")
+        .expect("Page not found");
+
+    server
+        .deactivate_wiki(wiki_id)
+        .await
+        .expect("Unable to deactivate wiki");
+
+    // Only direct lookups still see it
+    let (wiki, _) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    assert!(!wiki.is_active());
+    assert!(wiki.deleted_at().is_some());
+
+    check_err!(server.get_wiki_by_slug(&slug).await, Error::WikiNotFound);
+
+    let info = server
+        .get_wiki_info(wiki_id)
+        .await
+        .expect("Unable to get wiki info");
+
+    assert!(info.is_none());
+
+    // Page operations are refused
+    check_err!(
+        server.get_page_contents(wiki_id, "page").await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server
+            .create_page(commit!("other"), "Contents", &[], "Other", "")
+            .await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server
+            .edit_page(commit!("page"), Some("Edited"), None, None, false)
+            .await,
+        Error::WikiInactive
+    );
+
+    // So are reads served from the database
+    check_err!(
+        server.check_page(wiki_id, "page").await,
+        Error::WikiInactive
+    );
+    check_err!(server.get_page(wiki_id, "page").await, Error::WikiInactive);
+
+    check_err!(
+        server
+            .get_pages_with_tags(wiki_id, &["tag"], PageOrder::SlugAsc)
+            .await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server.list_page_slugs(wiki_id, true).await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server.get_wiki_activity(wiki_id, 10, 0).await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server
+            .get_change_type_counts(wiki_id, wiki.created_at(), Utc::now())
+            .await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server.get_page_current_hash(wiki_id, "page").await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server
+            .get_page_contents_if_changed(wiki_id, "page", &hash)
+            .await,
+        Error::WikiInactive
+    );
+
+    // Including history looked up by page or revision ID
+    check_err!(
+        server.get_page_history(page_id, 10, 0, true).await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server.get_page_history_with_authors(page_id, 10, 0).await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server.get_page_title_history(page_id).await,
+        Error::WikiInactive
+    );
+
+    check_err!(
+        server.get_page_tag_history(page_id).await,
+        Error::WikiInactive
+    );
+
+    check_err!(server.get_page_views(page_id).await, Error::WikiInactive);
+    check_err!(server.get_revision(revision_id).await, Error::WikiInactive);
+
+    // Everything is available again once reactivated
+    server
+        .reactivate_wiki(wiki_id)
+        .await
+        .expect("Unable to reactivate wiki");
+
+    let exists = server
+        .check_page(wiki_id, "other")
+        .await
+        .expect("Unable to check page");
+
+    assert!(!exists, "Page creation wasn't rolled back");
+
+    let (wiki, _) = server
+        .get_wiki_by_slug(&slug)
+        .await
+        .expect("Unable to get wiki");
+
+    assert!(wiki.is_active());
+
+    let contents = server
+        .get_page_contents(wiki_id, "page")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page not found");
+
+    assert_eq!(contents, "Contents");
+
+    server
+        .create_page(commit!("other"), "Contents", &[], "Other", "")
+        .await
+        .expect("Unable to create page");
+
+    // Missing wikis can't be deactivated
+    check_err!(
+        server
+            .deactivate_wiki(WikiId::from_raw(i64::max_value()))
+            .await,
+        Error::WikiNotFound
+    );
+}
+
+#[tokio::test]
+async fn list_wikis() {
+    let server = &create_server().await;
+    let (first_id, _) = create_wiki_full(server).await;
+    let (second_id, _) = create_wiki_full(server).await;
+
+    server
+        .deactivate_wiki(second_id)
+        .await
+        .expect("Unable to deactivate wiki");
+
+    let wikis = server
+        .list_wikis(false)
+        .await
+        .expect("Unable to list wikis");
+
+    assert!(wikis.iter().any(|wiki| wiki.id() == first_id));
+    assert!(wikis.iter().all(|wiki| wiki.id() != second_id));
+    assert!(wikis.iter().all(|wiki| wiki.is_active()));
+    assert!(wikis
+        .windows(2)
+        .all(|pair| pair[0].slug() <= pair[1].slug()));
+
+    let wikis = server.list_wikis(true).await.expect("Unable to list wikis");
+
+    assert!(wikis.iter().any(|wiki| wiki.id() == first_id));
+    assert!(wikis.iter().any(|wiki| wiki.id() == second_id));
+    assert!(wikis
+        .windows(2)
+        .all(|pair| pair[0].slug() <= pair[1].slug()));
+}
+
+#[tokio::test]
+async fn change_wiki_slug() {
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let (wiki_id, old_slug) = create_wiki_full(server).await;
+    let (_, other_slug) = create_wiki_full(server).await;
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page",
+        message: "slug change test",
+        user: &user,
+    };
+
+    server
+        .create_page(commit, "Contents", &[], "Page", "")
+        .await
+        .expect("Unable to create page");
+
+    // Can't take another wiki's slug
+    let error = server
+        .change_wiki_slug(wiki_id, &other_slug)
+        .await
+        .expect_err("Took another wiki's slug");
+
+    match error {
+        Error::WikiExists => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    let new_slug = format!("{}-Moved", old_slug);
+    server
+        .change_wiki_slug(wiki_id, new_slug.as_str())
+        .await
+        .expect("Unable to change wiki slug");
+
+    let new_slug = new_slug.to_ascii_lowercase();
+    let (wiki, _) = server
+        .get_wiki_by_slug(&new_slug)
+        .await
+        .expect("Unable to get wiki by new slug");
+
+    assert_eq!(wiki.id(), wiki_id);
+    assert_eq!(wiki.slug(), new_slug);
+
+    match server.get_wiki_by_slug(&old_slug).await {
+        Err(Error::WikiNotFound) => (),
+        _ => panic!("Wiki still found by old slug"),
+    }
+
+    let contents = server
+        .get_page_contents(wiki_id, "page")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page not found");
+
+    assert_eq!(contents, "Contents");
+
+    // Also works after reloading the store from the new location
+    server.unload_wiki_store(wiki_id).await;
+
+    let contents = server
+        .get_page_contents(wiki_id, "page")
+        .await
+        .expect("Unable to get page contents")
+        .expect("Page not found");
+
+    assert_eq!(contents, "Contents");
+
+    let orphans = server
+        .find_orphaned_repositories()
+        .await
+        .expect("Unable to find orphaned repositories");
+
+    assert!(orphans.is_empty(), "Orphaned repositories found");
+}
+
+#[tokio::test]
+async fn change_wiki_slug_rollback() {
+    let server = &create_server().await;
+    let (wiki_id, old_slug) = create_wiki_full(server).await;
+    let new_slug = format!("{}-blocked", old_slug);
+
+    // Occupy the destination, so the repository can't be moved
+    let blocker = server.revisions_dir().join(&new_slug);
+    fs::create_dir(&blocker).expect("Unable to create directory");
+
+    server
+        .change_wiki_slug(wiki_id, new_slug.as_str())
+        .await
+        .expect_err("Changed slug despite existing directory");
+
+    // The database change was rolled back
+    {
+        use crate::schema::wikis;
+        use diesel::prelude::*;
+
+        let conn = create_connection();
+        let id: i64 = wiki_id.into();
+        let slug = wikis::table
+            .find(id)
+            .select(wikis::slug)
+            .first::<String>(&conn)
+            .expect("Unable to get wiki slug");
+
+        assert_eq!(slug, old_slug);
+    }
+
+    let (wiki, _) = server
+        .get_wiki_by_id(wiki_id)
+        .await
+        .expect("Unable to get wiki");
+
+    assert_eq!(wiki.slug(), old_slug);
+
+    server
+        .get_wiki_by_slug(&old_slug)
+        .await
+        .expect("Wiki not found by old slug");
+
+    fs::remove_dir_all(&blocker).expect("Unable to remove directory");
+}
+
+#[tokio::test]
+async fn store_unavailable() {
+    use std::process::Command;
+
+    let server = &create_server().await;
+    let user = server.default_user().await.expect("Unable to get user");
+    let (wiki_id, slug) = create_wiki_full(server).await;
+
+    // Replace the repository with one that has no initial commit
+    server.unload_wiki_store(wiki_id).await;
+
+    let repo = server.revisions_dir().join(&slug);
+    fs::remove_dir_all(&repo).expect("Unable to remove repository");
+    fs::create_dir(&repo).expect("Unable to create repository");
+
+    let status = Command::new("git")
+        .arg("init")
+        .current_dir(&repo)
+        .status()
+        .expect("Unable to run git");
+
+    assert!(status.success(), "Unable to initialize repository");
+
+    let commit = PageCommit {
+        wiki_id,
+        slug: "page",
+        message: "broken store",
+        user: &user,
+    };
+
+    let error = server
+        .create_page(commit, "Contents", &[], "Page", "")
+        .await
+        .expect_err("Committed to unusable store");
+
+    match error {
+        Error::WikiStoreUnavailable(id) if id == wiki_id => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    let exists = server
+        .check_page(wiki_id, "page")
+        .await
+        .expect("Unable to check page");
+
+    assert!(!exists, "Page creation wasn't rolled back");
+}
+
+#[tokio::test]
+async fn store_missing() {
+    let server = &create_server().await;
+    let (wiki_id, slug) = create_wiki_full(server).await;
+
+    server.unload_wiki_store(wiki_id).await;
+
+    let repo = server.revisions_dir().join(&slug);
+    fs::remove_dir_all(&repo).expect("Unable to remove repository");
+
+    // The repository is reported missing, not replaced with an empty one
+    let error = server
+        .get_page_contents(wiki_id, "page")
+        .await
+        .expect_err("Read from missing store");
+
+    match error {
+        Error::WikiStoreUnavailable(id) if id == wiki_id => (),
+        _ => panic!("Error doesn't match"),
+    }
+
+    assert!(!repo.exists(), "Missing repository was recreated");
+}